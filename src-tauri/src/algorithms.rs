@@ -0,0 +1,52 @@
+use serde::Serialize;
+use ssh2::{MethodType, Session};
+use std::ffi::{c_char, c_int, CStr};
+
+use crate::error::SshError;
+
+extern "C" {
+    // Not wrapped by libssh2-sys
+    fn libssh2_version(required_version: c_int) -> *const c_char;
+}
+
+// What the bundled libssh2 can negotiate, in its default preference order.
+// Compare against the server's offer when a connection fails with "no
+// matching cipher" and the like.
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportedAlgorithms {
+    pub library_version: String,
+    pub kex: Vec<&'static str>,
+    pub host_key: Vec<&'static str>,
+    pub ciphers: Vec<&'static str>,
+    pub macs: Vec<&'static str>,
+    pub compression: Vec<&'static str>,
+}
+
+fn library_version() -> String {
+    // SAFETY: with 0 as the minimum version libssh2 always returns its static version string
+    let version = unsafe { libssh2_version(0) };
+    if version.is_null() {
+        return "unknown".to_string();
+    }
+    unsafe { CStr::from_ptr(version) }.to_string_lossy().to_string()
+}
+
+pub fn supported_algorithms() -> Result<SupportedAlgorithms, SshError> {
+    // The lists don't depend on a connection, an unconnected session will do
+    let session = Session::new()?;
+
+    Ok(SupportedAlgorithms {
+        library_version: library_version(),
+        kex: session.supported_algs(MethodType::Kex)?,
+        host_key: session.supported_algs(MethodType::HostKey)?,
+        // Both directions support the same ciphers and MACs
+        ciphers: session.supported_algs(MethodType::CryptCs)?,
+        macs: session.supported_algs(MethodType::MacCs)?,
+        compression: session.supported_algs(MethodType::CompCs)?,
+    })
+}
+
+#[tauri::command]
+pub async fn list_supported_algorithms() -> Result<SupportedAlgorithms, SshError> {
+    supported_algorithms()
+}
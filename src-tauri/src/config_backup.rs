@@ -0,0 +1,331 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+use crate::favorites::{self, RemoteFavorite};
+use crate::keys::KeyManager;
+use crate::macros::{self, TerminalMacro};
+use crate::session_store::{self, CorruptEntry, StoreReport};
+use crate::settings::{self, AppSettings};
+use crate::templates::{self, SessionTemplate};
+use crate::{AppState, AuthMethod, Session};
+
+// Bumped whenever the layout changes; migrate() brings older exports up to date
+const CONFIG_VERSION: u32 = 1;
+const CONFIG_FORMAT: &str = "termnest-config";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSection {
+    Sessions,
+    Settings,
+    Macros,
+    Templates,
+    Favorites,
+    Keys,
+}
+
+const ALL_SECTIONS: &[ConfigSection] = &[
+    ConfigSection::Sessions,
+    ConfigSection::Settings,
+    ConfigSection::Macros,
+    ConfigSection::Templates,
+    ConfigSection::Favorites,
+    ConfigSection::Keys,
+];
+
+// Which private keys the sessions use and what they are. Only the paths are
+// exported, never the keys themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyMetadata {
+    pub path: String,
+    pub key_type: Option<String>,
+    pub encrypted: Option<bool>,
+    pub is_security_key: Option<bool>,
+    // Names of the sessions and templates using the key
+    pub used_by: Vec<String>,
+}
+
+// The export file. Every section is optional so a partial export (or one
+// from a version without some section) still imports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConfigBundle {
+    format: String,
+    version: u32,
+    app_version: String,
+    exported_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sessions: Option<Vec<Session>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    settings: Option<AppSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    macros: Option<Vec<TerminalMacro>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    templates: Option<Vec<SessionTemplate>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    favorites: Option<Vec<RemoteFavorite>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keys: Option<Vec<KeyMetadata>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub version: u32,
+    pub sections: BTreeMap<ConfigSection, usize>,
+    // Sessions whose proxy password was left out
+    pub secrets_removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    // Version the file was written with, before migration
+    pub version: u32,
+    pub sections: BTreeMap<ConfigSection, usize>,
+    // Session entries that didn't parse and were skipped
+    pub skipped_sessions: Vec<CorruptEntry>,
+    // Sessions whose password (keychain or proxy) has to be entered again
+    pub needs_password: Vec<String>,
+    // Keys the imported sessions use that aren't on this machine
+    pub missing_keys: Vec<String>,
+}
+
+fn key_path(auth_method: &AuthMethod) -> Option<&str> {
+    match auth_method {
+        AuthMethod::PublicKey { key_path } => Some(key_path),
+        _ => None,
+    }
+}
+
+fn key_metadata(sessions: &[Session], templates: &[SessionTemplate]) -> Vec<KeyMetadata> {
+    let mut used_by: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for session in sessions {
+        let methods = std::iter::once(&session.auth_method).chain(session.profiles.iter().map(|p| &p.auth_method));
+        for path in methods.filter_map(key_path) {
+            used_by.entry(path.to_string()).or_default().push(session.name.clone());
+        }
+    }
+    for template in templates {
+        if let Some(path) = key_path(&template.auth_method) {
+            used_by.entry(path.to_string()).or_default().push(format!("{} (template)", template.name));
+        }
+    }
+
+    used_by
+        .into_iter()
+        .map(|(path, mut used_by)| {
+            used_by.dedup();
+            let info = KeyManager::analyze_key(Path::new(&path)).ok();
+            KeyMetadata {
+                key_type: info.as_ref().map(|i| i.key_type.clone()),
+                encrypted: info.as_ref().map(|i| i.encrypted),
+                is_security_key: info.as_ref().map(|i| i.is_security_key),
+                path,
+                used_by,
+            }
+        })
+        .collect()
+}
+
+// Older exports are upgraded here one version at a time before parsing
+fn migrate(mut bundle: Value) -> Result<(Value, u32), String> {
+    if bundle.get("format").and_then(Value::as_str) != Some(CONFIG_FORMAT) {
+        return Err("Not a TermNest configuration export".to_string());
+    }
+    let version = bundle.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "This export is from a newer TermNest (format version {}, this one reads up to {})",
+            version, CONFIG_VERSION
+        ));
+    }
+    if version == 0 {
+        return Err("The export has no format version".to_string());
+    }
+
+    // No migrations yet: version 1 is the first format
+    bundle["version"] = Value::from(CONFIG_VERSION);
+    Ok((bundle, version))
+}
+
+fn sections_or_all(sections: Option<Vec<ConfigSection>>) -> Vec<ConfigSection> {
+    sections.unwrap_or_else(|| ALL_SECTIONS.to_vec())
+}
+
+// Writes the chosen sections (all by default) to one JSON file for moving a
+// setup to another machine. Passwords stay behind: keychain entries aren't
+// read and proxy passwords are blanked.
+#[tauri::command]
+pub async fn export_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    sections: Option<Vec<ConfigSection>>,
+) -> Result<ExportSummary, String> {
+    let sections = sections_or_all(sections);
+    let wants = |section| sections.contains(&section);
+
+    let mut sessions: Vec<Session> = state.sessions.lock().map_err(|e| e.to_string())?.values().cloned().collect();
+    sessions.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut secrets_removed = Vec::new();
+    for session in &mut sessions {
+        if let Some(password) = session.http_proxy.as_mut().and_then(|proxy| proxy.password.take()) {
+            if !password.is_empty() {
+                secrets_removed.push(session.name.clone());
+            }
+        }
+    }
+    let template_list = templates::load_templates(&app)?;
+
+    let bundle = ConfigBundle {
+        format: CONFIG_FORMAT.to_string(),
+        version: CONFIG_VERSION,
+        app_version: app.package_info().version.to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        keys: wants(ConfigSection::Keys).then(|| key_metadata(&sessions, &template_list)),
+        settings: if wants(ConfigSection::Settings) {
+            Some(state.settings.lock().map_err(|e| e.to_string())?.clone())
+        } else {
+            None
+        },
+        macros: if wants(ConfigSection::Macros) { Some(macros::load_macros(&app)?) } else { None },
+        favorites: if wants(ConfigSection::Favorites) { Some(favorites::load_favorites(&app)?) } else { None },
+        templates: wants(ConfigSection::Templates).then_some(template_list),
+        sessions: wants(ConfigSection::Sessions).then_some(sessions),
+    };
+
+    let mut counts = BTreeMap::new();
+    let mut count = |section, n: Option<usize>| {
+        if let Some(n) = n {
+            counts.insert(section, n);
+        }
+    };
+    count(ConfigSection::Sessions, bundle.sessions.as_ref().map(Vec::len));
+    count(ConfigSection::Settings, bundle.settings.as_ref().map(|_| 1));
+    count(ConfigSection::Macros, bundle.macros.as_ref().map(Vec::len));
+    count(ConfigSection::Templates, bundle.templates.as_ref().map(Vec::len));
+    count(ConfigSection::Favorites, bundle.favorites.as_ref().map(Vec::len));
+    count(ConfigSection::Keys, bundle.keys.as_ref().map(Vec::len));
+    if bundle.sessions.is_none() {
+        secrets_removed.clear();
+    }
+
+    let bytes = serde_json::to_vec_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    info!("Exported configuration to {}", path);
+    Ok(ExportSummary { path, version: CONFIG_VERSION, sections: counts, secrets_removed })
+}
+
+// Adds `incoming` to `existing`, replacing entries with the same key
+fn merge<T>(existing: &mut Vec<T>, incoming: Vec<T>, key: impl Fn(&T) -> String) {
+    for item in incoming {
+        match existing.iter().position(|e| key(e) == key(&item)) {
+            Some(i) => existing[i] = item,
+            None => existing.push(item),
+        }
+    }
+}
+
+// Restores the chosen sections (all that are in the file by default). Entries
+// are merged by id (macros by name) unless `replace` is set, which swaps each
+// chosen section out wholesale.
+#[tauri::command]
+pub async fn import_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    sections: Option<Vec<ConfigSection>>,
+    replace: Option<bool>,
+) -> Result<ImportReport, String> {
+    let sections = sections_or_all(sections);
+    let replace = replace.unwrap_or(false);
+
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let document: Value = serde_json::from_slice(&bytes).map_err(|e| format!("{} is not valid JSON: {}", path, e))?;
+    let (mut document, version) = migrate(document)?;
+
+    // Sessions are parsed one by one, like the session store, so one bad
+    // entry doesn't sink the import
+    let mut store_report = StoreReport::default();
+    let sessions_in_file = document.get("sessions").is_some();
+    let sessions = session_store::parse(&serde_json::to_vec(&document).map_err(|e| e.to_string())?, &mut store_report);
+    if let Some(e) = store_report.file_error {
+        return Err(format!("The sessions in the export are unreadable: {}", e));
+    }
+    if let Some(document) = document.as_object_mut() {
+        document.remove("sessions");
+    }
+    let bundle: ConfigBundle = serde_json::from_value(document).map_err(|e| format!("Invalid export: {}", e))?;
+
+    let mut report = ImportReport { version, skipped_sessions: store_report.corrupt_entries, ..Default::default() };
+
+    if sections.contains(&ConfigSection::Sessions) && sessions_in_file {
+        for session in &sessions {
+            let proxy_password = session.http_proxy.as_ref().is_some_and(|p| p.username.is_some() && p.password.is_none());
+            if session.store_password_in_keychain || proxy_password {
+                report.needs_password.push(session.name.clone());
+            }
+        }
+        report.missing_keys = key_metadata(&sessions, &[])
+            .into_iter()
+            .filter(|key| !Path::new(&key.path).exists())
+            .map(|key| key.path)
+            .collect();
+        report.sections.insert(ConfigSection::Sessions, sessions.len());
+
+        let all = {
+            let mut state_sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+            if replace {
+                state_sessions.clear();
+            }
+            state_sessions.extend(sessions.into_iter().map(|session| (session.id.clone(), session)));
+            state_sessions.values().cloned().collect()
+        };
+        session_store::save(&app, all)?;
+    }
+
+    if let Some(imported) = bundle.settings.filter(|_| sections.contains(&ConfigSection::Settings)) {
+        settings::apply(&app, &state, imported)?;
+        report.sections.insert(ConfigSection::Settings, 1);
+    }
+
+    if let Some(imported) = bundle.macros.filter(|_| sections.contains(&ConfigSection::Macros)) {
+        report.sections.insert(ConfigSection::Macros, imported.len());
+        let mut list = if replace { Vec::new() } else { macros::load_macros(&app)? };
+        merge(&mut list, imported, |m| m.name.clone());
+        macros::save_macros(&app, &list)?;
+    }
+
+    if let Some(imported) = bundle.templates.filter(|_| sections.contains(&ConfigSection::Templates)) {
+        report.sections.insert(ConfigSection::Templates, imported.len());
+        let mut list = if replace { Vec::new() } else { templates::load_templates(&app)? };
+        // Templates from before ids existed are matched by name
+        merge(&mut list, imported, |t| if t.id.is_empty() { t.name.clone() } else { t.id.clone() });
+        templates::save_templates(&app, &list)?;
+    }
+
+    if let Some(imported) = bundle.favorites.filter(|_| sections.contains(&ConfigSection::Favorites)) {
+        report.sections.insert(ConfigSection::Favorites, imported.len());
+        let mut list = if replace { Vec::new() } else { favorites::load_favorites(&app)? };
+        merge(&mut list, imported, |f| f.id.clone());
+        favorites::save_favorites(&app, &list)?;
+    }
+
+    // Key metadata is informational only: keys missing here are reported
+    if let Some(keys) = bundle.keys.filter(|_| sections.contains(&ConfigSection::Keys)) {
+        report.sections.insert(ConfigSection::Keys, keys.len());
+        for key in keys {
+            if !Path::new(&key.path).exists() && !report.missing_keys.contains(&key.path) {
+                report.missing_keys.push(key.path);
+            }
+        }
+    }
+
+    info!("Imported configuration from {} (format version {})", path, version);
+    Ok(report)
+}
@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedConnection {
+    pub username: Option<String>,
+    pub host: String,
+    pub port: u16,
+    pub identity_file: Option<String>,
+}
+
+fn is_valid_host(host: &str) -> bool {
+    if host.is_empty() || host.len() > 253 {
+        return false;
+    }
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return true;
+    }
+    host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        && !host.starts_with('-')
+        && !host.starts_with('.')
+}
+
+fn is_valid_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '$'))
+}
+
+// Splits `[user@]host[:port]`, accepting bracketed IPv6 literals (`[::1]:22`)
+fn parse_destination(destination: &str) -> Option<(Option<String>, String, Option<u16>)> {
+    let (username, rest) = match destination.rsplit_once('@') {
+        Some((user, rest)) => (Some(user.to_string()), rest),
+        None => (None, destination),
+    };
+
+    let (host, port) = if let Some(rest) = rest.strip_prefix('[') {
+        let (host, after) = rest.split_once(']')?;
+        let port = match after.strip_prefix(':') {
+            Some(port) => Some(port.parse().ok()?),
+            None if after.is_empty() => None,
+            None => return None,
+        };
+        (host.to_string(), port)
+    } else if rest.matches(':').count() == 1 {
+        let (host, port) = rest.split_once(':')?;
+        (host.to_string(), Some(port.parse().ok()?))
+    } else {
+        (rest.to_string(), None)
+    };
+
+    if !is_valid_host(&host) || !username.as_deref().map(is_valid_username).unwrap_or(true) {
+        return None;
+    }
+
+    Some((username, host, port))
+}
+
+fn parse_ssh_url(url: &str) -> Option<ParsedConnection> {
+    let rest = url.strip_prefix("ssh://")?;
+    let destination = rest.trim_end_matches('/');
+    let (username, host, port) = parse_destination(destination)?;
+
+    Some(ParsedConnection { username, host, port: port.unwrap_or(22), identity_file: None })
+}
+
+// Understands the common subset of an `ssh` command line: -p, -l, -i and a destination
+fn parse_ssh_command(command: &str) -> Option<ParsedConnection> {
+    let mut words = command.split_whitespace();
+    if words.next()? != "ssh" {
+        return None;
+    }
+
+    let mut destination = None;
+    let mut port = None;
+    let mut login = None;
+    let mut identity_file = None;
+
+    while let Some(word) = words.next() {
+        match word {
+            "-p" => port = Some(words.next()?.parse().ok()?),
+            "-l" => login = Some(words.next()?.to_string()),
+            "-i" => identity_file = Some(words.next()?.to_string()),
+            // Options with an argument we don't model, skip the value
+            "-o" | "-J" | "-F" | "-L" | "-R" | "-D" | "-b" | "-c" | "-m" | "-E" | "-W" => {
+                words.next()?;
+            }
+            flag if flag.starts_with('-') => {}
+            // Anything after the destination is a remote command
+            _ if destination.is_some() => break,
+            value => destination = Some(value),
+        }
+    }
+
+    let (username, host, dest_port) = parse_destination(destination?)?;
+
+    Some(ParsedConnection {
+        username: username.or(login),
+        host,
+        port: port.or(dest_port).unwrap_or(22),
+        identity_file,
+    })
+}
+
+// Recognizes `user@host[:port]`, `ssh [-p N] user@host` and `ssh://user@host:port`.
+// A bare word is not treated as a host since almost anything would match.
+pub fn parse_connection_string(input: &str) -> Option<ParsedConnection> {
+    let input = input.trim();
+    if input.is_empty() || input.lines().count() > 1 {
+        return None;
+    }
+
+    if input.starts_with("ssh://") {
+        return parse_ssh_url(input);
+    }
+    if input.starts_with("ssh ") {
+        return parse_ssh_command(input);
+    }
+
+    // `user@host -p 2222` is a common shorthand as well
+    let mut words = input.split_whitespace();
+    let destination = words.next()?;
+    let port = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => None,
+        (Some("-p"), Some(port), None) => Some(port.parse().ok()?),
+        _ => return None,
+    };
+
+    if !destination.contains('@') {
+        return None;
+    }
+
+    let (username, host, dest_port) = parse_destination(destination)?;
+
+    Some(ParsedConnection {
+        username,
+        host,
+        port: port.or(dest_port).unwrap_or(22),
+        identity_file: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(username: Option<&str>, host: &str, port: u16) -> Option<ParsedConnection> {
+        Some(ParsedConnection {
+            username: username.map(str::to_string),
+            host: host.to_string(),
+            port,
+            identity_file: None,
+        })
+    }
+
+    #[test]
+    fn parses_user_host_and_port() {
+        assert_eq!(parse_connection_string("deploy@example.com"), parsed(Some("deploy"), "example.com", 22));
+        assert_eq!(parse_connection_string("deploy@example.com:2222"), parsed(Some("deploy"), "example.com", 2222));
+        assert_eq!(parse_connection_string("  deploy@10.0.0.5 -p 2200 "), parsed(Some("deploy"), "10.0.0.5", 2200));
+        assert_eq!(parse_connection_string("ssh://deploy@example.com:2222/"), parsed(Some("deploy"), "example.com", 2222));
+    }
+
+    #[test]
+    fn parses_bracketed_ipv6() {
+        assert_eq!(parse_connection_string("root@[::1]:2222"), parsed(Some("root"), "::1", 2222));
+        assert_eq!(parse_connection_string("root@[fe80::1]"), parsed(Some("root"), "fe80::1", 22));
+        assert_eq!(parse_connection_string("ssh://[2001:db8::7]:22"), parsed(None, "2001:db8::7", 22));
+        assert_eq!(parse_connection_string("root@[::1]x"), None);
+        assert_eq!(parse_connection_string("root@[::1"), None);
+    }
+
+    #[test]
+    fn user_is_optional_only_where_the_input_is_clearly_ssh() {
+        assert_eq!(parse_connection_string("ssh example.com"), parsed(None, "example.com", 22));
+        assert_eq!(parse_connection_string("ssh://example.com"), parsed(None, "example.com", 22));
+        assert_eq!(parse_connection_string("ssh -l admin example.com"), parsed(Some("admin"), "example.com", 22));
+        // A bare word could be anything
+        assert_eq!(parse_connection_string("example.com"), None);
+        assert_eq!(parse_connection_string("example.com:22"), None);
+    }
+
+    #[test]
+    fn parses_ssh_command_lines() {
+        let connection = parse_connection_string("ssh -p 2222 -i ~/.ssh/id_ed25519 -o ServerAliveInterval=30 deploy@example.com uptime");
+        assert_eq!(
+            connection,
+            Some(ParsedConnection {
+                username: Some("deploy".to_string()),
+                host: "example.com".to_string(),
+                port: 2222,
+                identity_file: Some("~/.ssh/id_ed25519".to_string()),
+            })
+        );
+        // The user in the destination wins over -l
+        assert_eq!(parse_connection_string("ssh -l admin deploy@example.com"), parsed(Some("deploy"), "example.com", 22));
+        assert_eq!(parse_connection_string("ssh -p"), None);
+    }
+
+    #[test]
+    fn rejects_bad_ports() {
+        for input in [
+            "deploy@example.com:",
+            "deploy@example.com:ssh",
+            "deploy@example.com:65536",
+            "deploy@example.com:-1",
+            "deploy@example.com -p 70000",
+            "deploy@example.com -p",
+            "ssh -p abc example.com",
+            "ssh://deploy@[::1]:99999",
+        ] {
+            assert_eq!(parse_connection_string(input), None, "{} was accepted", input);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_hosts_and_users() {
+        for input in [
+            "",
+            "deploy@",
+            "@example.com",
+            "deploy@-example.com",
+            "deploy@.example.com",
+            "de ploy@example.com",
+            "deploy@exa_mple.com",
+            "deploy@example.com\nrm -rf /",
+            "deploy@example.com extra words",
+        ] {
+            assert_eq!(parse_connection_string(input), None, "{:?} was accepted", input);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+use crate::error::SshError;
+use crate::sftp::SftpClient;
+use crate::AppState;
+
+// Both sides are held in memory, and config files are far smaller than this
+const MAX_DIFF_FILE_SIZE: u64 = 2 * 1024 * 1024;
+// Lines of unchanged context around each change, as in `diff -u`
+const CONTEXT_LINES: usize = 3;
+// Myers keeps a snapshot per edit step, so beyond this many differing lines
+// the files are simply shown as replaced wholesale
+const MAX_EDIT_DISTANCE: usize = 4000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    pub identical: bool,
+    pub binary: bool,
+    // Unified diff from the remote file (---) to the local one (+++), or
+    // "Binary files differ"; empty when identical
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+// Same heuristic as git: a NUL byte early on means binary
+fn is_binary(data: &[u8]) -> bool {
+    data.iter().take(8000).any(|&b| b == 0) || std::str::from_utf8(data).is_err()
+}
+
+// Myers' O((N+M)D) diff over lines. `trace[d]` holds the furthest x reached
+// on diagonals -(d+1)..=d+1 before step d, which is all backtracking needs.
+fn myers(a: &[&str], b: &[&str]) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max as isize {
+        if d as usize > MAX_EDIT_DISTANCE {
+            return None;
+        }
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+            let mut x = if down { v[(offset + k + 1) as usize] } else { v[(offset + k - 1) as usize] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, snapshot) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| snapshot[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert(prev_y as usize));
+            } else {
+                edits.push(Edit::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    edits.reverse();
+    Some(edits)
+}
+
+fn line_edits(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    // Trimming the common ends keeps the search (and its memory) down to the
+    // part that actually changed
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let middle = myers(a_mid, b_mid).unwrap_or_else(|| {
+        (0..a_mid.len()).map(Edit::Delete).chain((0..b_mid.len()).map(Edit::Insert)).collect()
+    });
+
+    let shift = |edit: Edit| match edit {
+        Edit::Equal(i, j) => Edit::Equal(i + prefix, j + prefix),
+        Edit::Delete(i) => Edit::Delete(i + prefix),
+        Edit::Insert(j) => Edit::Insert(j + prefix),
+    };
+    (0..prefix)
+        .map(|i| Edit::Equal(i, i))
+        .chain(middle.into_iter().map(shift))
+        .chain((0..suffix).map(|i| Edit::Equal(a.len() - suffix + i, b.len() - suffix + i)))
+        .collect()
+}
+
+fn push_line(out: &mut String, marker: char, line: &str) {
+    out.push(marker);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+// "-start,count" for a hunk header; an empty range names the line before it
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    // Lines keep their newline so a missing one at the end shows up as a change
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = line_edits(&a, &b);
+
+    let changes: Vec<usize> = edits.iter().enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group changes whose context would overlap into one hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + 1 + CONTEXT_LINES).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks {
+        let hunk = &edits[start..end];
+        // Lines of each file that come before the hunk
+        let (old_before, new_before) = edits[..start].iter().fold((0, 0), |(o, n), edit| match edit {
+            Edit::Equal(..) => (o + 1, n + 1),
+            Edit::Delete(_) => (o + 1, n),
+            Edit::Insert(_) => (o, n + 1),
+        });
+        let old_count = hunk.iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_count = hunk.iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+
+        out.push_str(&format!("@@ -{} +{} @@\n", range(old_before, old_count), range(new_before, new_count)));
+        for edit in hunk {
+            match *edit {
+                Edit::Equal(i, _) => push_line(&mut out, ' ', a[i]),
+                Edit::Delete(i) => push_line(&mut out, '-', a[i]),
+                Edit::Insert(j) => push_line(&mut out, '+', b[j]),
+            }
+        }
+    }
+    out
+}
+
+pub fn diff_contents(old: &[u8], new: &[u8], old_name: &str, new_name: &str) -> FileDiff {
+    if old == new {
+        return FileDiff { identical: true, binary: is_binary(old), diff: String::new() };
+    }
+    if is_binary(old) || is_binary(new) {
+        return FileDiff { identical: false, binary: true, diff: "Binary files differ".to_string() };
+    }
+
+    // Both were checked to be valid UTF-8 above
+    let old = std::str::from_utf8(old).unwrap_or_default();
+    let new = std::str::from_utf8(new).unwrap_or_default();
+    FileDiff { identical: false, binary: false, diff: unified_diff(old, new, old_name, new_name) }
+}
+
+// Shows what uploading `local_path` over `remote_path` would change
+#[tauri::command]
+pub async fn diff_files(
+    state: State<'_, AppState>,
+    session_id: String,
+    remote_path: String,
+    local_path: String,
+    password: Option<String>,
+) -> Result<FileDiff, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+
+    let local_size = std::fs::metadata(&local_path)?.len();
+    if local_size > MAX_DIFF_FILE_SIZE {
+        return Err(SshError::Other(format!("{} is too large to diff ({} bytes)", local_path, local_size)));
+    }
+    let local = std::fs::read(Path::new(&local_path))?;
+
+    let client = SftpClient::connect(&session, password.as_deref())?;
+    let remote = client.read_file(&remote_path, MAX_DIFF_FILE_SIZE)?;
+
+    Ok(diff_contents(&remote, &local, &remote_path, &local_path))
+}
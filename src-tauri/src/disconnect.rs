@@ -0,0 +1,121 @@
+use libssh2_sys::LIBSSH2_SESSION;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_void};
+use std::sync::{LazyLock, Mutex};
+
+// libssh2 only reports the reason of a server DISCONNECT through a session
+// callback, which ssh2 doesn't wrap, so we register one ourselves
+const LIBSSH2_CALLBACK_DISCONNECT: c_int = 2;
+
+extern "C" {
+    fn libssh2_session_callback_set(session: *mut LIBSSH2_SESSION, cbtype: c_int, callback: *mut c_void) -> *mut c_void;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisconnectReason {
+    pub code: u32,
+    pub reason: &'static str,
+    pub message: String,
+}
+
+impl DisconnectReason {
+    pub fn describe(&self) -> String {
+        if self.message.is_empty() {
+            format!("Server closed connection ({})", self.reason)
+        } else {
+            format!("Server closed connection: {}", self.message)
+        }
+    }
+}
+
+// Reasons received so far, keyed by the address of the raw libssh2 session
+static REASONS: LazyLock<Mutex<HashMap<usize, DisconnectReason>>> = LazyLock::new(Default::default);
+
+// Reason codes from RFC 4253 section 11.1
+fn reason_name(code: u32) -> &'static str {
+    match code {
+        1 => "host not allowed to connect",
+        2 => "protocol error",
+        3 => "key exchange failed",
+        5 => "MAC error",
+        6 => "compression error",
+        7 => "service not available",
+        8 => "protocol version not supported",
+        9 => "host key not verifiable",
+        10 => "connection lost",
+        11 => "closed by application",
+        12 => "too many connections",
+        13 => "auth cancelled by user",
+        14 => "no more auth methods available",
+        15 => "illegal user name",
+        _ => "unknown reason",
+    }
+}
+
+extern "C" fn on_disconnect(
+    session: *mut LIBSSH2_SESSION,
+    reason: c_int,
+    message: *const c_char,
+    message_len: c_int,
+    _language: *const c_char,
+    _language_len: c_int,
+    _abstract: *mut *mut c_void,
+) {
+    let message = if message.is_null() || message_len <= 0 {
+        String::new()
+    } else {
+        // SAFETY: libssh2 passes a pointer/length pair into the packet it is parsing
+        let bytes = unsafe { std::slice::from_raw_parts(message as *const u8, message_len as usize) };
+        String::from_utf8_lossy(bytes).trim().to_string()
+    };
+
+    let code = reason as u32;
+    REASONS.lock().unwrap().insert(
+        session as usize,
+        DisconnectReason { code, reason: reason_name(code), message },
+    );
+}
+
+fn session_key(session: &ssh2::Session) -> usize {
+    &*session.raw() as *const LIBSSH2_SESSION as usize
+}
+
+// Forgets the session's reason when dropped, so one nobody took doesn't stay
+// in REASONS for the life of the app. It holds on to the session, which keeps
+// another one from being allocated at the same address in the meantime.
+#[must_use = "the reason is forgotten when the watch is dropped"]
+pub struct Watch {
+    session: ssh2::Session,
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        take(&self.session);
+    }
+}
+
+// Starts recording the disconnect reason for a session, until the returned
+// watch is dropped. Call before the handshake so a DISCONNECT during
+// authentication is captured too.
+pub fn watch(session: &ssh2::Session) -> Watch {
+    let key = session_key(session);
+
+    // A previous session may have lived at the same address
+    REASONS.lock().unwrap().remove(&key);
+
+    let mut raw = session.raw();
+    // SAFETY: the raw session outlives the registration and on_disconnect has the
+    // signature libssh2 expects for LIBSSH2_CALLBACK_DISCONNECT
+    unsafe {
+        libssh2_session_callback_set(&mut *raw, LIBSSH2_CALLBACK_DISCONNECT, on_disconnect as *mut c_void);
+    }
+
+    Watch { session: session.clone() }
+}
+
+// Returns (and forgets) the reason the server gave for disconnecting, if any
+pub fn take(session: &ssh2::Session) -> Option<DisconnectReason> {
+    let key = session_key(session);
+    REASONS.lock().unwrap().remove(&key)
+}
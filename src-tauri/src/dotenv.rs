@@ -0,0 +1,103 @@
+use log::info;
+
+use crate::exec::shell_quote;
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Value inside double quotes, with the escapes dotenv files commonly use
+fn unescape_double_quoted(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn parse_value(raw: &str, line_number: usize) -> Result<String, String> {
+    let raw = raw.trim();
+
+    for quote in ['\'', '"'] {
+        if let Some(rest) = raw.strip_prefix(quote) {
+            // Find the closing quote, skipping escaped ones inside double quotes
+            let mut escaped = false;
+            let end = rest.char_indices().find_map(|(i, c)| {
+                let closes = c == quote && !escaped;
+                escaped = quote == '"' && c == '\\' && !escaped;
+                closes.then_some(i)
+            });
+            let end = end.ok_or_else(|| format!("line {}: missing closing {}", line_number, quote))?;
+
+            let inner = &rest[..end];
+            return Ok(if quote == '"' { unescape_double_quoted(inner) } else { inner.to_string() });
+        }
+    }
+
+    // Unquoted: a " #" starts a comment
+    let value = match raw.find(" #") {
+        Some(comment) => &raw[..comment],
+        None => raw,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+// Parses dotenv syntax: KEY=value lines, blank lines and # comments, an
+// optional `export ` prefix, and single (literal) or double quoted values
+pub fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").map(str::trim_start).unwrap_or(line);
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("line {}: expected KEY=value", line_number))?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(format!("line {}: invalid variable name '{}'", line_number, key));
+        }
+
+        vars.push((key.to_string(), parse_value(value, line_number)?));
+    }
+
+    Ok(vars)
+}
+
+pub fn load_dotenv(path: &str) -> Result<Vec<(String, String)>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let vars = parse_dotenv(&content).map_err(|e| format!("{}: {}", path, e))?;
+
+    // Names only; the values are the user's business
+    let names: Vec<&str> = vars.iter().map(|(key, _)| key.as_str()).collect();
+    info!("Loaded {} variable(s) from {}: {}", vars.len(), path, names.join(", "));
+    Ok(vars)
+}
+
+// Shell input exporting the variables. The leading space keeps the line out
+// of the history in shells with HISTCONTROL=ignorespace; it still shows up
+// in the terminal like anything else typed.
+pub fn export_script(vars: &[(String, String)]) -> String {
+    let exports: Vec<String> = vars
+        .iter()
+        .map(|(key, value)| format!("export {}={}", key, shell_quote(value)))
+        .collect();
+    format!(" {}\n", exports.join("; "))
+}
@@ -0,0 +1,129 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use ssh2::ErrorCode;
+
+// Failure kinds shared by the SSH, SFTP and key code so callers (and the UI)
+// can tell an auth problem from a dropped connection
+#[derive(Debug, thiserror::Error)]
+pub enum SshError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+    #[error("Host key verification failed: {0}")]
+    HostKey(String),
+    #[error("SFTP error: {0}")]
+    Sftp(String),
+    #[error("Channel closed")]
+    ChannelClosed,
+    #[error("Operation timed out")]
+    Timeout,
+    #[error("Not connected")]
+    NotConnected,
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+    // The server turned down a new channel for lack of room, usually because
+    // the connection already has MaxSessions channels open
+    #[error("The server refused to open another channel ({0}); it may limit how many sessions one connection can have")]
+    ChannelLimitReached(String),
+    #[error("The server ran out of disk space while writing {path}{}", describe_available(.available_bytes))]
+    RemoteDiskFull { path: String, available_bytes: Option<u64> },
+    // Any other libssh2 failure
+    #[error("SSH error: {0}")]
+    Protocol(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+fn describe_available(available_bytes: &Option<u64>) -> String {
+    match available_bytes {
+        Some(bytes) => format!(" ({} bytes free)", bytes),
+        None => String::new(),
+    }
+}
+
+impl SshError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SshError::Io(_) => "io",
+            SshError::Auth(_) => "auth",
+            SshError::HostKey(_) => "host_key",
+            SshError::Sftp(_) => "sftp",
+            SshError::ChannelClosed => "channel_closed",
+            SshError::Timeout => "timeout",
+            SshError::NotConnected => "not_connected",
+            SshError::SessionNotFound(_) => "session_not_found",
+            SshError::RemoteDiskFull { .. } => "remote_disk_full",
+            SshError::ChannelLimitReached(_) => "channel_limit_reached",
+            SshError::Protocol(_) => "protocol",
+            SshError::Other(_) => "other",
+        }
+    }
+}
+
+// libssh2 reports a refused channel as "Channel open failure (<reason>)".
+// Of the reasons, administratively prohibited (OpenSSH's answer past
+// MaxSessions) and resource shortage mean the server is at its limit.
+fn channel_limit_reason(e: &ssh2::Error) -> Option<String> {
+    if e.code() != ErrorCode::Session(libssh2_sys::LIBSSH2_ERROR_CHANNEL_FAILURE) {
+        return None;
+    }
+    let reason = e.message().split_once('(')?.1.trim_end_matches(')');
+    matches!(reason, "administratively prohibited" | "resource shortage").then(|| reason.to_string())
+}
+
+// For the commands that report plain strings: a channel refused for being
+// over the server's limit says so, anything else is prefixed with `context`
+pub fn describe_channel_error(e: ssh2::Error, context: &str) -> String {
+    match channel_limit_reason(&e) {
+        Some(reason) => SshError::ChannelLimitReached(reason).to_string(),
+        None => format!("{}: {}", context, e),
+    }
+}
+
+impl From<ssh2::Error> for SshError {
+    fn from(e: ssh2::Error) -> Self {
+        use libssh2_sys::*;
+
+        if let Some(reason) = channel_limit_reason(&e) {
+            return SshError::ChannelLimitReached(reason);
+        }
+        let message = e.message().to_string();
+        match e.code() {
+            ErrorCode::SFTP(_) => SshError::Sftp(message),
+            ErrorCode::Session(code) => match code {
+                LIBSSH2_ERROR_AUTHENTICATION_FAILED
+                | LIBSSH2_ERROR_PUBLICKEY_UNVERIFIED
+                | LIBSSH2_ERROR_PASSWORD_EXPIRED
+                | LIBSSH2_ERROR_KEYFILE_AUTH_FAILED
+                | LIBSSH2_ERROR_FILE => SshError::Auth(message),
+                LIBSSH2_ERROR_HOSTKEY_INIT | LIBSSH2_ERROR_HOSTKEY_SIGN | LIBSSH2_ERROR_KNOWN_HOSTS => {
+                    SshError::HostKey(message)
+                }
+                LIBSSH2_ERROR_SFTP_PROTOCOL => SshError::Sftp(message),
+                LIBSSH2_ERROR_CHANNEL_CLOSED | LIBSSH2_ERROR_CHANNEL_EOF_SENT => SshError::ChannelClosed,
+                LIBSSH2_ERROR_TIMEOUT | LIBSSH2_ERROR_SOCKET_TIMEOUT => SshError::Timeout,
+                LIBSSH2_ERROR_SOCKET_DISCONNECT | LIBSSH2_ERROR_SOCKET_SEND | LIBSSH2_ERROR_SOCKET_RECV
+                | LIBSSH2_ERROR_BAD_SOCKET => SshError::NotConnected,
+                _ => SshError::Protocol(message),
+            },
+        }
+    }
+}
+
+// Commands that still report plain strings can keep using `?`
+impl From<SshError> for String {
+    fn from(e: SshError) -> Self {
+        e.to_string()
+    }
+}
+
+// Tauri commands hand this to the frontend as `{ kind, message }`
+impl Serialize for SshError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SshError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
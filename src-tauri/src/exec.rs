@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use log::{info, warn};
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::{describe_channel_error, SshError};
+use crate::remote::open_session;
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_status: i32,
+    // Set when the command was killed for running past its timeout
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+// How long blocking reads wait before we re-check the deadline
+const READ_POLL_MS: u32 = 250;
+
+// Grace period between TERM and KILL when a command times out
+const KILL_GRACE: Duration = Duration::from_secs(2);
+
+// exec_to_file keeps stderr in memory, so only this much of it
+const MAX_CAPTURED_STDERR: usize = 1024 * 1024;
+
+// Minimum time between exec_to_file progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecToFileOutput {
+    pub bytes_written: u64,
+    pub stderr: String,
+    // More stderr than MAX_CAPTURED_STDERR was produced; the rest was dropped
+    pub stderr_truncated: bool,
+    pub exit_status: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ExecToFileProgress {
+    session_id: String,
+    local_path: String,
+    bytes_written: u64,
+}
+
+// Quotes a value for safe interpolation into a POSIX shell command line
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Runs a single command on its own exec channel and collects its output
+pub fn run_command(sess: &ssh2::Session, command: &str) -> Result<ExecOutput, String> {
+    let mut channel = sess.channel_session()
+        .map_err(|e| describe_channel_error(e, "Failed to open exec channel"))?;
+
+    channel.exec(command)
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    // Both streams are read in one loop, since a command blocked writing to
+    // a full stderr window would otherwise never finish its stdout. The
+    // caller may have a timeout of its own set, so that one is put back.
+    let previous_timeout = sess.timeout();
+    sess.set_timeout(READ_POLL_MS);
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let drained = drain_to_eof(&mut channel, &mut stdout, &mut stderr);
+    sess.set_timeout(previous_timeout);
+    drained?;
+
+    channel.wait_close()
+        .map_err(|e| format!("Failed to close exec channel: {}", e))?;
+
+    let exit_status = channel.exit_status()
+        .map_err(|e| format!("Failed to get exit status: {}", e))?;
+
+    Ok(ExecOutput {
+        stdout: String::from_utf8_lossy(&stdout).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        exit_status,
+        timed_out: false,
+    })
+}
+
+// Reads whatever is available on one stream; false once it has hit EOF
+fn read_available(stream: &mut impl Read, out: &mut Vec<u8>) -> Result<bool, String> {
+    let mut buffer = [0u8; 8192];
+    match stream.read(&mut buffer) {
+        Ok(0) => Ok(false),
+        Ok(n) => {
+            out.extend_from_slice(&buffer[..n]);
+            Ok(true)
+        }
+        Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => Ok(true),
+        Err(e) => Err(format!("Failed to read command output: {}", e)),
+    }
+}
+
+// Retries a call on a non-blocking session until it stops asking to be
+// retried or the deadline passes
+fn until_ready<T>(deadline: Instant, context: &str, mut op: impl FnMut() -> Result<T, ssh2::Error>) -> Result<T, String> {
+    loop {
+        match op() {
+            Err(e) if crate::forwarding::is_would_block(&e) => {
+                if Instant::now() > deadline {
+                    return Err(format!("{}: timed out", context));
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            other => return other.map_err(|e| describe_channel_error(e, context)),
+        }
+    }
+}
+
+// run_command for the session of an interactive connection, which is
+// non-blocking, so a command can run alongside the shell without a login of
+// its own. Gives up on the command once `timeout` has passed.
+pub fn run_command_nonblocking(sess: &ssh2::Session, command: &str, timeout: Duration) -> Result<ExecOutput, String> {
+    let deadline = Instant::now() + timeout;
+    let mut channel = until_ready(deadline, "Failed to open exec channel", || sess.channel_session())?;
+    until_ready(deadline, "Failed to execute command", || channel.exec(command))?;
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    while !channel.eof() {
+        if Instant::now() > deadline {
+            let _ = channel.close();
+            return Err("Timed out waiting for the command to finish".to_string());
+        }
+        let read = stdout.len() + stderr.len();
+        read_available(&mut channel, &mut stdout)?;
+        read_available(&mut channel.stderr(), &mut stderr)?;
+        if stdout.len() + stderr.len() == read {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    until_ready(deadline, "Failed to close exec channel", || channel.close())?;
+    until_ready(deadline, "Failed to close exec channel", || channel.wait_close())?;
+    let exit_status = channel.exit_status()
+        .map_err(|e| format!("Failed to get exit status: {}", e))?;
+
+    Ok(ExecOutput {
+        stdout: String::from_utf8_lossy(&stdout).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        exit_status,
+        timed_out: false,
+    })
+}
+
+fn drain_to_eof(channel: &mut ssh2::Channel, stdout: &mut Vec<u8>, stderr: &mut Vec<u8>) -> Result<(), String> {
+    while !channel.eof() {
+        read_available(channel, stdout)?;
+        read_available(&mut channel.stderr(), stderr)?;
+    }
+    Ok(())
+}
+
+// Drains stdout/stderr until the channel closes or the deadline passes.
+// Returns whether the channel reached EOF in time.
+fn drain_until(channel: &mut ssh2::Channel, stdout: &mut Vec<u8>, stderr: &mut Vec<u8>, deadline: Instant) -> Result<bool, String> {
+    while Instant::now() < deadline {
+        read_available(channel, stdout)?;
+        read_available(&mut channel.stderr(), stderr)?;
+        if channel.eof() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn signal_process_group(sess: &ssh2::Session, pid: u32, signal: &str) {
+    // sshd starts the command in its own session, so the shell's pid is also
+    // the process group id and the whole tree can be signaled at once
+    if let Err(e) = run_command(sess, &format!("kill -{} -- -{} 2>/dev/null || kill -{} {}", signal, pid, signal, pid)) {
+        warn!("Failed to send {} to remote pid {}: {}", signal, pid, e);
+    }
+}
+
+// Like run_command, but kills the command (TERM, then KILL) if it runs past the
+// timeout and returns whatever output it produced so far. The command still
+// runs under the user's $SHELL; the line that reports its pid is the only
+// part the login shell has to read as POSIX.
+pub fn run_command_with_timeout(sess: &ssh2::Session, command: &str, timeout: Duration) -> Result<ExecOutput, String> {
+    let mut channel = sess.channel_session()
+        .map_err(|e| describe_channel_error(e, "Failed to open exec channel"))?;
+
+    // Print the shell's pid first so we know what to kill on timeout
+    channel.exec(&format!("echo $$; exec \"${{SHELL:-sh}}\" -c {}", shell_quote(command)))
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    let previous_timeout = sess.timeout();
+    sess.set_timeout(READ_POLL_MS);
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let finished = drain_until(&mut channel, &mut stdout, &mut stderr, Instant::now() + timeout);
+
+    let result = finished.and_then(|finished| {
+        let pid_line_end = stdout.iter().position(|&b| b == b'\n').map(|i| i + 1).unwrap_or(0);
+        let pid = String::from_utf8_lossy(&stdout[..pid_line_end]).trim().parse::<u32>().ok();
+        let output = stdout.split_off(pid_line_end);
+
+        if finished {
+            let _ = channel.wait_close();
+            let exit_status = channel.exit_status()
+                .map_err(|e| format!("Failed to get exit status: {}", e))?;
+            return Ok((output, exit_status, false));
+        }
+
+        let mut output = output;
+        if let Some(pid) = pid {
+            signal_process_group(sess, pid, "TERM");
+            if !drain_until(&mut channel, &mut output, &mut stderr, Instant::now() + KILL_GRACE)? {
+                signal_process_group(sess, pid, "KILL");
+            }
+        }
+        let _ = channel.close();
+        Ok((output, -1, true))
+    });
+
+    sess.set_timeout(previous_timeout);
+
+    let (output, exit_status, timed_out) = result?;
+    Ok(ExecOutput {
+        stdout: String::from_utf8_lossy(&output).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        exit_status,
+        timed_out,
+    })
+}
+
+// Streams the command's stdout into `out` as it arrives. stderr is read in
+// the same loop, since a command blocked writing to a full stderr window
+// would otherwise never finish its stdout.
+fn stream_command(
+    sess: &ssh2::Session,
+    command: &str,
+    out: &mut impl Write,
+    mut on_progress: impl FnMut(u64),
+) -> Result<ExecToFileOutput, SshError> {
+    let mut channel = sess.channel_session()?;
+    channel.exec(command)?;
+    let previous_timeout = sess.timeout();
+    sess.set_timeout(READ_POLL_MS);
+
+    let result = (|| {
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut stderr = Vec::new();
+        let mut stderr_truncated = false;
+        let mut bytes_written = 0u64;
+        let mut last_progress = Instant::now();
+
+        loop {
+            match channel.read(&mut buffer) {
+                Ok(0) => {}
+                Ok(n) => {
+                    out.write_all(&buffer[..n])?;
+                    bytes_written += n as u64;
+                    if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                        on_progress(bytes_written);
+                        last_progress = Instant::now();
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {}
+                Err(e) => return Err(SshError::Io(e)),
+            }
+
+            let mut chunk = Vec::new();
+            read_available(&mut channel.stderr(), &mut chunk).map_err(SshError::Other)?;
+            let room = MAX_CAPTURED_STDERR - stderr.len();
+            stderr_truncated |= chunk.len() > room;
+            stderr.extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+            if channel.eof() {
+                break;
+            }
+        }
+
+        out.flush()?;
+        on_progress(bytes_written);
+        let _ = channel.wait_close();
+        Ok(ExecToFileOutput {
+            bytes_written,
+            stderr: String::from_utf8_lossy(&stderr).to_string(),
+            stderr_truncated,
+            exit_status: channel.exit_status()?,
+        })
+    })();
+
+    sess.set_timeout(previous_timeout);
+    result
+}
+
+// Runs a command and writes its stdout straight to a local file, for output
+// too large to hold in memory (log dumps, database exports). Progress goes
+// out as `exec_to_file_progress` events. A failing command still leaves
+// whatever it printed in the file; check exit_status.
+#[tauri::command]
+pub async fn exec_to_file(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    command: String,
+    local_path: String,
+    password: Option<String>,
+) -> Result<ExecToFileOutput, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let sess = open_session(&session, password.as_deref())?;
+        let mut out = BufWriter::new(File::create(&local_path)?);
+
+        let output = stream_command(&sess, &command, &mut out, |bytes_written| {
+            let _ = app.emit("exec_to_file_progress", &ExecToFileProgress {
+                session_id: session_id.clone(),
+                local_path: local_path.clone(),
+                bytes_written,
+            });
+        })?;
+
+        info!("Wrote {} bytes of command output to {}", output.bytes_written, local_path);
+        Ok(output)
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn ssh_exec(
+    state: State<'_, AppState>,
+    session_id: String,
+    command: String,
+    password: Option<String>,
+    timeout_secs: Option<u64>,
+) -> Result<ExecOutput, String> {
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+
+    // No timeout by default, matching the behavior before timeouts existed
+    match timeout_secs {
+        Some(secs) if secs > 0 => run_command_with_timeout(&sess, &command, Duration::from_secs(secs)),
+        _ => run_command(&sess, &command),
+    }
+}
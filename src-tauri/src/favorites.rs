@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_store::StoreExt;
+use uuid::Uuid;
+
+use crate::{connect_with_config, ssh_config_for, AppState};
+
+// A remote directory bookmarked for quick access, across all sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteFavorite {
+    pub id: String,
+    pub session_id: String,
+    pub path: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FavoriteEntry {
+    #[serde(flatten)]
+    pub favorite: RemoteFavorite,
+    pub session_name: Option<String>,
+    pub host: Option<String>,
+    // The session it points to has been deleted
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OpenFileBrowserEvent {
+    session_id: String,
+    path: String,
+}
+
+pub(crate) fn load_favorites(app: &AppHandle) -> Result<Vec<RemoteFavorite>, String> {
+    let store = app.store("favorites.json").map_err(|e| e.to_string())?;
+
+    match store.get("favorites") {
+        Some(value) => serde_json::from_value(value).map_err(|e| e.to_string()),
+        None => Ok(vec![]),
+    }
+}
+
+pub(crate) fn save_favorites(app: &AppHandle, favorites: &[RemoteFavorite]) -> Result<(), String> {
+    let store = app.store("favorites.json").map_err(|e| e.to_string())?;
+    store.set("favorites", serde_json::to_value(favorites).map_err(|e| e.to_string())?);
+    store.save().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_remote_favorite(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    label: Option<String>,
+) -> Result<RemoteFavorite, String> {
+    state.get_session(&session_id)?;
+
+    let mut favorites = load_favorites(&app)?;
+    // Adding the same place twice just returns the existing entry
+    if let Some(existing) = favorites.iter().find(|f| f.session_id == session_id && f.path == path) {
+        return Ok(existing.clone());
+    }
+
+    let favorite = RemoteFavorite {
+        id: Uuid::new_v4().to_string(),
+        session_id,
+        path,
+        label,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    favorites.push(favorite.clone());
+    save_favorites(&app, &favorites)?;
+    Ok(favorite)
+}
+
+#[tauri::command]
+pub async fn list_remote_favorites(app: AppHandle, state: State<'_, AppState>) -> Result<Vec<FavoriteEntry>, String> {
+    let favorites = load_favorites(&app)?;
+    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+
+    Ok(favorites
+        .into_iter()
+        .map(|favorite| {
+            let session = sessions.get(&favorite.session_id);
+            FavoriteEntry {
+                session_name: session.map(|s| s.name.clone()),
+                host: session.map(|s| s.host.clone()),
+                stale: session.is_none(),
+                favorite,
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn remove_remote_favorite(app: AppHandle, favorite_id: String) -> Result<(), String> {
+    let mut favorites = load_favorites(&app)?;
+    favorites.retain(|f| f.id != favorite_id);
+    save_favorites(&app, &favorites)
+}
+
+// Connects the favorite's session unless it's already connected, then asks
+// the file browser to navigate to the path via an `open_file_browser` event
+#[tauri::command]
+pub async fn open_favorite(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    favorite_id: String,
+    password: Option<String>,
+) -> Result<RemoteFavorite, String> {
+    let favorite = load_favorites(&app)?
+        .into_iter()
+        .find(|f| f.id == favorite_id)
+        .ok_or_else(|| "Favorite not found".to_string())?;
+
+    let session = state.get_session(&favorite.session_id)
+        .map_err(|_| "The session for this favorite no longer exists".to_string())?;
+
+    if !state.ssh_manager.list_sessions().contains(&session.id) {
+        let config = ssh_config_for(&session, session.default_profile(), password);
+        connect_with_config(&state, app.clone(), session.id.clone(), config)?;
+    }
+
+    app.emit("open_file_browser", &OpenFileBrowserEvent {
+        session_id: favorite.session_id.clone(),
+        path: favorite.path.clone(),
+    })
+    .map_err(|e| e.to_string())?;
+
+    Ok(favorite)
+}
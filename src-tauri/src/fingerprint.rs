@@ -0,0 +1,63 @@
+use base64::engine::general_purpose::STANDARD_NO_PAD as BASE64_NO_PAD;
+use base64::Engine;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+// `ssh-keygen -l` style: SHA256:<unpadded base64> of the public key blob
+pub fn sha256_fingerprint(blob: &[u8]) -> String {
+    format!("SHA256:{}", BASE64_NO_PAD.encode(Sha256::digest(blob)))
+}
+
+// `ssh-keygen -l -E md5` style: MD5:aa:bb:... Only for the legacy fingerprints
+// older OpenSSH and many hosting panels still show.
+pub fn md5_fingerprint(blob: &[u8]) -> String {
+    let hex: Vec<String> = Md5::digest(blob).iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("MD5:{}", hex.join(":"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FingerprintAlgorithm {
+    Sha256,
+    Md5,
+}
+
+// Picks the fingerprint out of what the user pasted, which may be just the
+// hash or a whole `ssh-keygen -l` line ("256 SHA256:... comment (ED25519)").
+// Returns the algorithm and the fingerprint in canonical form.
+pub fn parse_fingerprint(text: &str) -> Option<(FingerprintAlgorithm, String)> {
+    let token = text
+        .split_whitespace()
+        .find(|word| word.starts_with("SHA256:") || word.starts_with("MD5:"))
+        .or_else(|| text.split_whitespace().next())?;
+
+    if let Some(hash) = token.strip_prefix("MD5:") {
+        return Some((FingerprintAlgorithm::Md5, format!("MD5:{}", hash.to_lowercase())));
+    }
+    // Bare colon-separated hex is an MD5 fingerprint
+    let is_hex_pairs = token.split(':').count() == 16
+        && token.split(':').all(|pair| pair.len() == 2 && pair.chars().all(|c| c.is_ascii_hexdigit()));
+    if is_hex_pairs {
+        return Some((FingerprintAlgorithm::Md5, format!("MD5:{}", token.to_lowercase())));
+    }
+
+    let hash = token.strip_prefix("SHA256:").unwrap_or(token).trim_end_matches('=');
+    // 32 bytes are 43 base64 characters without padding
+    let valid = hash.len() == 43 && hash.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/');
+    valid.then(|| (FingerprintAlgorithm::Sha256, format!("SHA256:{}", hash)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+
+    const ED25519_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl";
+
+    #[test]
+    fn matches_ssh_keygen() {
+        let blob = BASE64.decode(ED25519_KEY).unwrap();
+        assert_eq!(sha256_fingerprint(&blob), "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU");
+        assert_eq!(md5_fingerprint(&blob), "MD5:65:96:2d:fc:e8:d5:a9:11:64:0c:0f:ea:00:6e:5b:bd");
+    }
+}
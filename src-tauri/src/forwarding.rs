@@ -0,0 +1,479 @@
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use ssh2::{Channel, ErrorCode, Session};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long to keep retrying a channel open on the non-blocking session
+const CHANNEL_OPEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForwardKind {
+    Local,
+    Remote,
+    Dynamic,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardSpec {
+    pub kind: ForwardKind,
+    pub bind_address: String,
+    pub bind_port: u16,
+    // Unused for dynamic forwards, where the SOCKS client picks the destination
+    pub target_host: Option<String>,
+    pub target_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForwardInfo {
+    pub id: String,
+    pub kind: ForwardKind,
+    pub bind_address: String,
+    pub bind_port: u16,
+    pub target_host: Option<String>,
+    pub target_port: Option<u16>,
+    pub active_channels: usize,
+}
+
+pub struct Forward {
+    id: String,
+    spec: ForwardSpec,
+    shutdown: Arc<AtomicBool>,
+    active_channels: Arc<AtomicUsize>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Forward {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn info(&self) -> ForwardInfo {
+        ForwardInfo {
+            id: self.id.clone(),
+            kind: self.spec.kind,
+            bind_address: self.spec.bind_address.clone(),
+            bind_port: self.spec.bind_port,
+            target_host: self.spec.target_host.clone(),
+            target_port: self.spec.target_port,
+            active_channels: self.active_channels.load(Ordering::Relaxed),
+        }
+    }
+
+    // Matches the OpenSSH cancel syntax, which names a forward by its bind port
+    pub fn matches(&self, kind: ForwardKind, bind_port: u16) -> bool {
+        self.spec.kind == kind && self.spec.bind_port == bind_port
+    }
+
+    pub fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            if let Err(e) = handle.join() {
+                warn!("Forward {} thread join error: {:?}", self.id, e);
+            }
+        }
+    }
+}
+
+impl Drop for Forward {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Commands understood by the runtime command console, modelled on OpenSSH's `~C`
+#[derive(Debug, Clone)]
+pub enum SessionCommand {
+    Forward(ForwardSpec),
+    Cancel { kind: ForwardKind, bind_port: u16 },
+    KillForward(String),
+}
+
+// Splits on ':' while keeping bracketed IPv6 addresses together
+fn split_spec(spec: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_brackets = false;
+
+    for c in spec.chars() {
+        match c {
+            '[' if !in_brackets => in_brackets = true,
+            ']' if in_brackets => in_brackets = false,
+            ':' if !in_brackets => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+
+    if in_brackets {
+        return Err(format!("Unterminated '[' in forward specification: {}", spec));
+    }
+    parts.push(current);
+
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(format!("Empty field in forward specification: {}", spec));
+    }
+
+    Ok(parts)
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+    value.parse::<u16>().map_err(|_| format!("Invalid port: {}", value))
+}
+
+fn parse_forward(kind: ForwardKind, spec: &str) -> Result<ForwardSpec, String> {
+    let parts = split_spec(spec)?;
+
+    // Like ssh without GatewayPorts, listen on loopback unless told otherwise
+    let (bind_address, rest) = match (kind, parts.len()) {
+        (ForwardKind::Dynamic, 1) | (ForwardKind::Local | ForwardKind::Remote, 3) => {
+            ("127.0.0.1".to_string(), &parts[..])
+        }
+        (ForwardKind::Dynamic, 2) | (ForwardKind::Local | ForwardKind::Remote, 4) => {
+            (parts[0].clone(), &parts[1..])
+        }
+        _ => return Err(format!("Invalid forward specification: {}", spec)),
+    };
+
+    let bind_port = parse_port(&rest[0])?;
+    let (target_host, target_port) = if kind == ForwardKind::Dynamic {
+        (None, None)
+    } else {
+        (Some(rest[1].clone()), Some(parse_port(&rest[2])?))
+    };
+
+    Ok(ForwardSpec { kind, bind_address, bind_port, target_host, target_port })
+}
+
+pub fn parse_session_command(command: &str) -> Result<SessionCommand, String> {
+    let mut words = command.split_whitespace();
+    let verb = words.next().ok_or("Empty command")?;
+    let argument = words.next();
+
+    if words.next().is_some() {
+        return Err(format!("Too many arguments: {}", command));
+    }
+
+    let argument = || argument.ok_or_else(|| format!("Missing argument for {}", verb));
+
+    match verb {
+        "-L" => Ok(SessionCommand::Forward(parse_forward(ForwardKind::Local, argument()?)?)),
+        "-R" => Ok(SessionCommand::Forward(parse_forward(ForwardKind::Remote, argument()?)?)),
+        "-D" => Ok(SessionCommand::Forward(parse_forward(ForwardKind::Dynamic, argument()?)?)),
+        "-KL" | "-KR" | "-KD" => {
+            let kind = match verb {
+                "-KL" => ForwardKind::Local,
+                "-KR" => ForwardKind::Remote,
+                _ => ForwardKind::Dynamic,
+            };
+            // The bind address is irrelevant for cancellation, only the port is
+            let spec = argument()?;
+            let port = spec.rsplit(':').next().unwrap_or(spec);
+            Ok(SessionCommand::Cancel { kind, bind_port: parse_port(port)? })
+        }
+        "kill-forward" => Ok(SessionCommand::KillForward(argument()?.to_string())),
+        _ => Err(format!(
+            "Unknown command '{}'; supported: -L, -R, -D, -KL, -KR, -KD, kill-forward",
+            verb
+        )),
+    }
+}
+
+pub(crate) fn is_would_block(e: &ssh2::Error) -> bool {
+    e.code() == ErrorCode::Session(libssh2_sys::LIBSSH2_ERROR_EAGAIN)
+}
+
+// The interactive session runs non-blocking, so libssh2 calls on it may need
+// several attempts before they complete
+fn retry_would_block<T>(
+    shutdown: &AtomicBool,
+    mut op: impl FnMut() -> Result<T, ssh2::Error>,
+) -> Result<T, String> {
+    let deadline = Instant::now() + CHANNEL_OPEN_TIMEOUT;
+
+    loop {
+        match op() {
+            Err(e) if is_would_block(&e) => {
+                if shutdown.load(Ordering::Relaxed) {
+                    return Err("Forward is shutting down".to_string());
+                }
+                if Instant::now() > deadline {
+                    return Err("Timed out waiting for the SSH session".to_string());
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            other => return other.map_err(|e| e.to_string()),
+        }
+    }
+}
+
+fn write_all_retrying<W: Write>(writer: &mut W, mut data: &[u8], shutdown: &AtomicBool) -> std::io::Result<()> {
+    while !data.is_empty() {
+        if shutdown.load(Ordering::Relaxed) {
+            return Err(ErrorKind::Interrupted.into());
+        }
+        match writer.write(data) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => data = &data[n..],
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(1)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// Copies bytes both ways between a local socket and a channel until either side closes
+pub(crate) fn pump(mut channel: Channel, mut stream: TcpStream, shutdown: &AtomicBool) {
+    if let Err(e) = stream.set_nonblocking(true) {
+        warn!("Failed to make forwarded socket non-blocking: {}", e);
+        return;
+    }
+
+    let mut buffer = [0u8; 16384];
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let mut idle = true;
+
+        match stream.read(&mut buffer) {
+            Ok(0) => {
+                let _ = retry_would_block(shutdown, || channel.send_eof());
+                break;
+            }
+            Ok(n) => {
+                idle = false;
+                if write_all_retrying(&mut channel, &buffer[..n], shutdown).is_err() {
+                    break;
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(_) => break,
+        }
+
+        match channel.read(&mut buffer) {
+            Ok(0) => {
+                if channel.eof() {
+                    break;
+                }
+            }
+            Ok(n) => {
+                idle = false;
+                if write_all_retrying(&mut stream, &buffer[..n], shutdown).is_err() {
+                    break;
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(_) => break,
+        }
+
+        if idle {
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    let _ = retry_would_block(shutdown, || channel.close());
+}
+
+// Reads exactly `buf.len()` bytes from a blocking socket used during the SOCKS handshake
+fn socks_read(stream: &mut TcpStream, buf: &mut [u8]) -> Result<(), String> {
+    stream.read_exact(buf).map_err(|e| format!("SOCKS handshake failed: {}", e))
+}
+
+// Minimal SOCKS5 server side (no auth, CONNECT only). Returns the requested destination.
+fn socks5_handshake(stream: &mut TcpStream) -> Result<(String, u16), String> {
+    let mut header = [0u8; 2];
+    socks_read(stream, &mut header)?;
+    if header[0] != 0x05 {
+        return Err(format!("Unsupported SOCKS version {}", header[0]));
+    }
+
+    let mut methods = vec![0u8; header[1] as usize];
+    socks_read(stream, &mut methods)?;
+    if !methods.contains(&0x00) {
+        let _ = stream.write_all(&[0x05, 0xFF]);
+        return Err("SOCKS client does not offer the no-auth method".to_string());
+    }
+    stream.write_all(&[0x05, 0x00]).map_err(|e| e.to_string())?;
+
+    let mut request = [0u8; 4];
+    socks_read(stream, &mut request)?;
+    if request[1] != 0x01 {
+        // 0x07: command not supported
+        let _ = stream.write_all(&[0x05, 0x07, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+        return Err(format!("Unsupported SOCKS command {}", request[1]));
+    }
+
+    let host = match request[3] {
+        0x01 => {
+            let mut addr = [0u8; 4];
+            socks_read(stream, &mut addr)?;
+            std::net::Ipv4Addr::from(addr).to_string()
+        }
+        0x03 => {
+            let mut len = [0u8; 1];
+            socks_read(stream, &mut len)?;
+            let mut name = vec![0u8; len[0] as usize];
+            socks_read(stream, &mut name)?;
+            String::from_utf8(name).map_err(|_| "Invalid SOCKS domain name".to_string())?
+        }
+        0x04 => {
+            let mut addr = [0u8; 16];
+            socks_read(stream, &mut addr)?;
+            std::net::Ipv6Addr::from(addr).to_string()
+        }
+        other => {
+            // 0x08: address type not supported
+            let _ = stream.write_all(&[0x05, 0x08, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+            return Err(format!("Unsupported SOCKS address type {}", other));
+        }
+    };
+
+    let mut port = [0u8; 2];
+    socks_read(stream, &mut port)?;
+
+    Ok((host, u16::from_be_bytes(port)))
+}
+
+fn handle_local_connection(
+    session: &Session,
+    spec: &ForwardSpec,
+    mut stream: TcpStream,
+    shutdown: &AtomicBool,
+) -> Result<(), String> {
+    let (host, port) = match spec.kind {
+        ForwardKind::Dynamic => {
+            stream.set_read_timeout(Some(Duration::from_secs(10))).map_err(|e| e.to_string())?;
+            let destination = socks5_handshake(&mut stream)?;
+            stream.set_read_timeout(None).map_err(|e| e.to_string())?;
+            destination
+        }
+        _ => (
+            spec.target_host.clone().unwrap_or_default(),
+            spec.target_port.unwrap_or_default(),
+        ),
+    };
+
+    let opened = retry_would_block(shutdown, || session.channel_direct_tcpip(&host, port, None));
+
+    if spec.kind == ForwardKind::Dynamic {
+        // 0x00 succeeded, 0x05 connection refused; the bound address is not meaningful here
+        let status = if opened.is_ok() { 0x00 } else { 0x05 };
+        stream
+            .write_all(&[0x05, status, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+            .map_err(|e| e.to_string())?;
+    }
+
+    let channel = opened.map_err(|e| format!("Failed to open channel to {}:{}: {}", host, port, e))?;
+    debug!("Forwarding connection to {}:{}", host, port);
+    pump(channel, stream, shutdown);
+    Ok(())
+}
+
+fn spawn_connection_thread(
+    active_channels: &Arc<AtomicUsize>,
+    shutdown: &Arc<AtomicBool>,
+    work: impl FnOnce(&AtomicBool) -> Result<(), String> + Send + 'static,
+) {
+    let active_channels = active_channels.clone();
+    let shutdown = shutdown.clone();
+
+    active_channels.fetch_add(1, Ordering::Relaxed);
+    thread::spawn(move || {
+        if let Err(e) = work(&shutdown) {
+            warn!("Forwarded connection failed: {}", e);
+        }
+        active_channels.fetch_sub(1, Ordering::Relaxed);
+    });
+}
+
+// Binds the listening side of a forward and starts serving it on a background thread
+pub fn start_forward(session: &Session, mut spec: ForwardSpec) -> Result<Forward, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let active_channels = Arc::new(AtomicUsize::new(0));
+
+    let handle = match spec.kind {
+        ForwardKind::Local | ForwardKind::Dynamic => {
+            let listener = TcpListener::bind((spec.bind_address.as_str(), spec.bind_port)).map_err(|e| match e.kind() {
+                ErrorKind::AddrInUse => format!("{}:{} is already in use", spec.bind_address, spec.bind_port),
+                _ => format!("Failed to bind {}:{}: {}", spec.bind_address, spec.bind_port, e),
+            })?;
+            listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+            // Report the real port when the caller asked for an ephemeral one
+            if let Ok(addr) = listener.local_addr() {
+                spec.bind_port = addr.port();
+            }
+
+            let session = session.clone();
+            let spec = spec.clone();
+            let shutdown = shutdown.clone();
+            let active_channels = active_channels.clone();
+
+            thread::spawn(move || {
+                while !shutdown.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let session = session.clone();
+                            let spec = spec.clone();
+                            spawn_connection_thread(&active_channels, &shutdown, move |shutdown| {
+                                handle_local_connection(&session, &spec, stream, shutdown)
+                            });
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(50));
+                        }
+                        Err(e) => {
+                            warn!("Forward listener error: {}", e);
+                            break;
+                        }
+                    }
+                }
+            })
+        }
+        ForwardKind::Remote => {
+            let never = AtomicBool::new(false);
+            let (mut listener, bound_port) = retry_would_block(&never, || {
+                session.channel_forward_listen(spec.bind_port, Some(&spec.bind_address), None)
+            })
+            .map_err(|e| format!("Server refused remote forward on port {}: {}", spec.bind_port, e))?;
+            spec.bind_port = bound_port;
+
+            let target_host = spec.target_host.clone().unwrap_or_default();
+            let target_port = spec.target_port.unwrap_or_default();
+            let shutdown = shutdown.clone();
+            let active_channels = active_channels.clone();
+
+            thread::spawn(move || {
+                while !shutdown.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok(channel) => {
+                            let target_host = target_host.clone();
+                            spawn_connection_thread(&active_channels, &shutdown, move |shutdown| {
+                                let stream = TcpStream::connect((target_host.as_str(), target_port))
+                                    .map_err(|e| format!("Failed to connect to {}:{}: {}", target_host, target_port, e))?;
+                                pump(channel, stream, shutdown);
+                                Ok(())
+                            });
+                        }
+                        Err(e) if is_would_block(&e) => thread::sleep(Duration::from_millis(50)),
+                        Err(e) => {
+                            warn!("Remote forward listener error: {}", e);
+                            break;
+                        }
+                    }
+                }
+            })
+        }
+    };
+
+    Ok(Forward {
+        id,
+        spec,
+        shutdown,
+        active_channels,
+        handle: Some(handle),
+    })
+}
@@ -0,0 +1,172 @@
+use log::info;
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::{close_session, connect_with_config, ssh_config_for, AppState, Session};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupMemberStatus {
+    Connected,
+    Disconnected,
+    // Nothing to do: already connected, or not connected for a disconnect
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMemberResult {
+    pub session_id: String,
+    pub name: String,
+    pub status: GroupMemberStatus,
+    pub error: Option<String>,
+}
+
+// Emitted as "group_progress" after each session, for "4/10 connected"
+#[derive(Debug, Clone, Serialize)]
+struct GroupProgressEvent {
+    group: String,
+    session_id: String,
+    status: GroupMemberStatus,
+    done: usize,
+    total: usize,
+    failed: usize,
+}
+
+fn group_members(state: &AppState, group: &str) -> Result<Vec<Session>, String> {
+    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    let mut members: Vec<Session> = sessions.values()
+        .filter(|session| session.group.as_deref() == Some(group))
+        .cloned()
+        .collect();
+    if members.is_empty() {
+        return Err(format!("No sessions in group {}", group));
+    }
+    members.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(members)
+}
+
+// Runs `action` over the group's sessions on up to `limit` threads, emitting
+// progress as each one finishes. Results come back in the group's order.
+fn run_for_group<F>(app: &AppHandle, group: &str, members: Vec<Session>, limit: usize, action: F) -> Vec<GroupMemberResult>
+where
+    F: Fn(&Session) -> (GroupMemberStatus, Option<String>) + Sync,
+{
+    let total = members.len();
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<GroupMemberResult>>> = Mutex::new(vec![None; total]);
+    let progress = Mutex::new((0, 0));
+
+    thread::scope(|scope| {
+        for _ in 0..limit.clamp(1, total) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(session) = members.get(index) else {
+                    break;
+                };
+
+                let (status, error) = action(session);
+                let (done, failed) = {
+                    let mut progress = progress.lock().unwrap();
+                    progress.0 += 1;
+                    if status == GroupMemberStatus::Failed {
+                        progress.1 += 1;
+                    }
+                    *progress
+                };
+                let _ = app.emit("group_progress", &GroupProgressEvent {
+                    group: group.to_string(),
+                    session_id: session.id.clone(),
+                    status,
+                    done,
+                    total,
+                    failed,
+                });
+
+                results.lock().unwrap()[index] = Some(GroupMemberResult {
+                    session_id: session.id.clone(),
+                    name: session.name.clone(),
+                    status,
+                    error,
+                });
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+// Connects every session in the group that isn't connected yet, at most
+// group_connect_limit at a time. `password` is tried for sessions that need
+// one and have none stored.
+#[tauri::command]
+pub async fn connect_group(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    group: String,
+    password: Option<String>,
+) -> Result<Vec<GroupMemberResult>, String> {
+    let members = group_members(&state, &group)?;
+    let limit = state.settings.lock().map_err(|e| e.to_string())?.group_connect_limit;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let connected = state.ssh_manager.list_sessions();
+
+        let results = run_for_group(&app, &group, members, limit, |session| {
+            if connected.contains(&session.id) {
+                return (GroupMemberStatus::Skipped, None);
+            }
+            let config = ssh_config_for(session, session.default_profile(), password.clone());
+            match connect_with_config(&state, app.clone(), session.id.clone(), config) {
+                Ok(()) => (GroupMemberStatus::Connected, None),
+                Err(e) => (GroupMemberStatus::Failed, Some(e)),
+            }
+        });
+
+        let count = |status| results.iter().filter(|r| r.status == status).count();
+        info!(
+            "Connected group {}: {} connected, {} already connected, {} failed",
+            group,
+            count(GroupMemberStatus::Connected),
+            count(GroupMemberStatus::Skipped),
+            count(GroupMemberStatus::Failed)
+        );
+        Ok(results)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn disconnect_group(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    group: String,
+) -> Result<Vec<GroupMemberResult>, String> {
+    let members = group_members(&state, &group)?;
+    let limit = state.settings.lock().map_err(|e| e.to_string())?.group_connect_limit;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let connected = state.ssh_manager.list_sessions();
+
+        let results = run_for_group(&app, &group, members, limit, |session| {
+            if !connected.contains(&session.id) {
+                return (GroupMemberStatus::Skipped, None);
+            }
+            match close_session(&state, &app, &session.id) {
+                Ok(()) => (GroupMemberStatus::Disconnected, None),
+                Err(e) => (GroupMemberStatus::Failed, Some(e)),
+            }
+        });
+
+        info!("Disconnected group {}", group);
+        Ok(results)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,93 @@
+use base64::Engine;
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::error::SshError;
+use crate::exec::shell_quote;
+use crate::ssh_new::SshManager;
+
+const DELIMITER: &str = "TERMNEST_EOF";
+
+// Terminals cap the length of a line in canonical mode (4096 on Linux), so
+// anything longer is sent base64 encoded in short lines instead
+const MAX_PLAIN_LINE: usize = 1024;
+const BASE64_LINE: usize = 76;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeredocEncoding {
+    Plain,
+    Base64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SentFile {
+    pub remote_path: String,
+    pub bytes: usize,
+    pub encoding: HeredocEncoding,
+}
+
+// Whether the content survives being typed into an interactive shell as is.
+// Tabs would trigger completion, `!` history expansion and other control
+// characters are eaten by the line discipline. A heredoc always ends with a
+// newline, so content without one is only reproduced exactly via base64.
+fn is_plain_safe(content: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(content) else {
+        return false;
+    };
+
+    (text.is_empty() || text.ends_with('\n'))
+        && text.lines().all(|line| line.len() <= MAX_PLAIN_LINE)
+        && !text.chars().any(|c| c == '!' || (c.is_control() && c != '\n'))
+}
+
+// Picks a terminator that doesn't appear as a line of the content
+fn unique_delimiter(body: &str) -> String {
+    let mut delimiter = DELIMITER.to_string();
+    let mut n = 0;
+    while body.lines().any(|line| line == delimiter) {
+        n += 1;
+        delimiter = format!("{}_{}", DELIMITER, n);
+    }
+    delimiter
+}
+
+// Builds the shell input that recreates `content` at `remote_path`
+pub fn build_heredoc(content: &[u8], remote_path: &str) -> (String, HeredocEncoding) {
+    let path = shell_quote(remote_path);
+
+    if is_plain_safe(content) {
+        let body = String::from_utf8_lossy(content);
+        let delimiter = unique_delimiter(&body);
+        let script = format!("cat > {} <<'{}'\n{}{}\n", path, delimiter, body, delimiter);
+        return (script, HeredocEncoding::Plain);
+    }
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(content);
+    let mut body = String::with_capacity(encoded.len() + encoded.len() / BASE64_LINE + 1);
+    for chunk in encoded.as_bytes().chunks(BASE64_LINE) {
+        body.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        body.push('\n');
+    }
+
+    // Base64 output can never contain the delimiter
+    let script = format!("base64 -d > {} <<'{}'\n{}{}\n", path, DELIMITER, body, DELIMITER);
+    (script, HeredocEncoding::Base64)
+}
+
+// Writes a small file by typing a heredoc into the interactive shell, for
+// devices that have a shell but no SFTP. This goes through whatever is running
+// in the terminal, so it only works at a normal shell prompt, not inside an
+// editor or another full-screen program.
+#[tauri::command]
+pub async fn send_text_as_file(
+    session_id: String,
+    content: Vec<u8>,
+    remote_path: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<SentFile, SshError> {
+    let (script, encoding) = build_heredoc(&content, &remote_path);
+    state.send_input(&session_id, &script)?;
+
+    Ok(SentFile { remote_path, bytes: content.len(), encoding })
+}
@@ -0,0 +1,351 @@
+use base64::engine::general_purpose::{STANDARD as BASE64, STANDARD_NO_PAD as BASE64_NO_PAD};
+use base64::Engine;
+use log::{info, warn};
+use serde::Serialize;
+use ssh2::{HashType, HostKeyType};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::SshError;
+use crate::known_hosts::{append_known_host, check_known_host, format_host, KnownHostMatch};
+use crate::settings::{HashKnownHosts, HostKeyTrust};
+use crate::AppState;
+
+struct Defaults {
+    trust: HostKeyTrust,
+    hashing: HashKnownHosts,
+}
+
+// Mirrors AppSettings::host_key_trust and hash_known_hosts so connects don't
+// need the app state
+static DEFAULTS: Mutex<Defaults> = Mutex::new(Defaults {
+    trust: HostKeyTrust::Ask,
+    hashing: HashKnownHosts::Auto,
+});
+
+#[derive(Debug, Clone)]
+struct PinnedKey {
+    key_type: &'static str,
+    key: Vec<u8>,
+    fingerprint: String,
+    first_seen: String,
+}
+
+// Keys pinned in Memory mode, by known_hosts style host ([host]:port). Gone
+// when the app quits.
+static PINNED: Mutex<BTreeMap<String, PinnedKey>> = Mutex::new(BTreeMap::new());
+
+struct UnknownKey {
+    key_type: &'static str,
+    key_base64: String,
+    fingerprint: String,
+}
+
+// Keys of hosts refused in Ask mode because known_hosts doesn't list them,
+// waiting for accept_host_key. Only the last key each host presented is kept.
+static UNKNOWN: Mutex<BTreeMap<String, UnknownKey>> = Mutex::new(BTreeMap::new());
+
+// Set at startup so refused keys can be reported without every connect path
+// passing the app handle down to verify()
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+// Payload of the host_key_unknown and host_key_mismatch events
+#[derive(Clone, Serialize)]
+struct HostKeyEvent {
+    host: String,
+    port: u16,
+    key_type: String,
+    fingerprint: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PinnedHostKey {
+    pub host: String,
+    pub key_type: String,
+    pub fingerprint: String,
+    pub first_seen: String,
+}
+
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+fn emit(event: &str, host: &str, port: u16, key_type: &str, fingerprint: &str) {
+    if let Some(app) = APP.get() {
+        let _ = app.emit(event, HostKeyEvent {
+            host: host.to_string(),
+            port,
+            key_type: key_type.to_string(),
+            fingerprint: fingerprint.to_string(),
+        });
+    }
+}
+
+pub fn set_defaults(trust: HostKeyTrust, hashing: HashKnownHosts) {
+    *DEFAULTS.lock().unwrap() = Defaults { trust, hashing };
+}
+
+fn key_type_name(key_type: HostKeyType) -> &'static str {
+    match key_type {
+        HostKeyType::Rsa => "ssh-rsa",
+        HostKeyType::Dss => "ssh-dss",
+        HostKeyType::Ecdsa256 => "ecdsa-sha2-nistp256",
+        HostKeyType::Ecdsa384 => "ecdsa-sha2-nistp384",
+        HostKeyType::Ecdsa521 => "ecdsa-sha2-nistp521",
+        HostKeyType::Ed25519 => "ssh-ed25519",
+        HostKeyType::Unknown => "unknown",
+    }
+}
+
+// As `ssh-keygen -l` prints it
+fn fingerprint(sess: &ssh2::Session) -> String {
+    match sess.host_key_hash(HashType::Sha256) {
+        Some(hash) => format!("SHA256:{}", BASE64_NO_PAD.encode(hash)),
+        None => "unknown".to_string(),
+    }
+}
+
+// Pins the host's key the first time it's seen, and after that accepts only
+// that key. Another key type counts as a mismatch too: otherwise a server in
+// the middle could get past the pin by offering an algorithm that wasn't
+// pinned. Returns the pinned key when the presented one isn't it.
+fn check_pin(
+    pinned: &mut BTreeMap<String, PinnedKey>,
+    host_field: &str,
+    key_type: &'static str,
+    key: &[u8],
+    fingerprint: &str,
+) -> Result<(), PinnedKey> {
+    match pinned.get(host_field) {
+        Some(pin) if pin.key_type == key_type && pin.key == key => Ok(()),
+        Some(pin) => Err(pin.clone()),
+        None => {
+            pinned.insert(
+                host_field.to_string(),
+                PinnedKey {
+                    key_type,
+                    key: key.to_vec(),
+                    fingerprint: fingerprint.to_string(),
+                    first_seen: chrono::Utc::now().to_rfc3339(),
+                },
+            );
+            Ok(())
+        }
+    }
+}
+
+// Checks the server's key right after the handshake, before any credentials
+// are sent. `trust` is the session's own mode; None follows the settings.
+pub fn verify(sess: &ssh2::Session, host: &str, port: u16, trust: Option<HostKeyTrust>) -> Result<(), SshError> {
+    let (trust, hashing) = {
+        let defaults = DEFAULTS.lock().unwrap();
+        (trust.unwrap_or(defaults.trust), defaults.hashing)
+    };
+    if trust == HostKeyTrust::Off {
+        return Ok(());
+    }
+
+    let (key, key_type) = sess
+        .host_key()
+        .ok_or_else(|| SshError::HostKey(format!("{} sent no host key", host)))?;
+    let key_type = key_type_name(key_type);
+    let host_field = format_host(host, port);
+
+    match trust {
+        HostKeyTrust::Off => Ok(()),
+        HostKeyTrust::Memory => {
+            let now = fingerprint(sess);
+            let result = check_pin(&mut PINNED.lock().unwrap(), &host_field, key_type, key, &now);
+            result.map_err(|pin| {
+                emit("host_key_mismatch", host, port, key_type, &now);
+                if pin.key_type == key_type {
+                    SshError::HostKey(format!(
+                        "the {} key of {} changed since it was first seen this session (was {}, now {})",
+                        key_type, host_field, pin.fingerprint, now
+                    ))
+                } else {
+                    SshError::HostKey(format!(
+                        "{} presented a {} key ({}), but its {} key ({}) was pinned this session",
+                        host_field, key_type, now, pin.key_type, pin.fingerprint
+                    ))
+                }
+            })
+        }
+        HostKeyTrust::Disk | HostKeyTrust::Ask => {
+            let key_base64 = BASE64.encode(key);
+            match check_known_host(host, port, key_type, &key_base64) {
+                KnownHostMatch::Match => Ok(()),
+                KnownHostMatch::Mismatch => {
+                    emit("host_key_mismatch", host, port, key_type, &fingerprint(sess));
+                    Err(SshError::HostKey(format!(
+                        "the {} key of {} ({}) doesn't match the one in known_hosts",
+                        key_type,
+                        host_field,
+                        fingerprint(sess)
+                    )))
+                }
+                KnownHostMatch::Revoked => {
+                    Err(SshError::HostKey(format!("the {} key of {} has been revoked", key_type, host_field)))
+                }
+                KnownHostMatch::NotFound if trust == HostKeyTrust::Ask => {
+                    let fingerprint = fingerprint(sess);
+                    emit("host_key_unknown", host, port, key_type, &fingerprint);
+                    let message = format!(
+                        "{} is not in known_hosts; accept its {} key ({}) to connect",
+                        host_field, key_type, fingerprint
+                    );
+                    UNKNOWN.lock().unwrap().insert(host_field, UnknownKey { key_type, key_base64, fingerprint });
+                    Err(SshError::HostKey(message))
+                }
+                KnownHostMatch::NotFound => {
+                    info!("Adding {} key of {} ({}) to known_hosts", key_type, host_field, fingerprint(sess));
+                    // Failing to remember the key doesn't make this connection unsafe
+                    if let Err(e) = append_known_host(host, port, key_type, &key_base64, hashing) {
+                        warn!("{}", e);
+                    }
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+// The session's mode (its own or the global one), or the global one without
+// a session
+#[tauri::command]
+pub async fn get_host_key_trust_mode(
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+) -> Result<HostKeyTrust, String> {
+    let global = state.settings.lock().map_err(|e| e.to_string())?.host_key_trust;
+    match session_id {
+        Some(id) => Ok(state.get_session(&id)?.host_key_trust.unwrap_or(global)),
+        None => Ok(global),
+    }
+}
+
+// Sets a session's mode, where None goes back to the global one, or the
+// global mode itself without a session. Returns the mode now in effect.
+#[tauri::command]
+pub async fn set_host_key_trust_mode(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+    mode: Option<HostKeyTrust>,
+) -> Result<HostKeyTrust, String> {
+    let Some(id) = session_id else {
+        let mode = mode.ok_or("The global mode can't be unset")?;
+        let mut settings = state.settings.lock().map_err(|e| e.to_string())?.clone();
+        settings.host_key_trust = mode;
+        crate::settings::apply(&app, &state, settings)?;
+        return Ok(mode);
+    };
+
+    {
+        let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+        let session = sessions.get_mut(&id).ok_or("Session not found")?;
+        session.host_key_trust = mode;
+    }
+    crate::save_sessions_to_store(app, state.clone()).await?;
+
+    let global = state.settings.lock().map_err(|e| e.to_string())?.host_key_trust;
+    Ok(mode.unwrap_or(global))
+}
+
+#[tauri::command]
+pub async fn list_pinned_host_keys() -> Result<Vec<PinnedHostKey>, String> {
+    Ok(PINNED
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|(host, pin)| PinnedHostKey {
+            host: host.clone(),
+            key_type: pin.key_type.to_string(),
+            fingerprint: pin.fingerprint.clone(),
+            first_seen: pin.first_seen.clone(),
+        })
+        .collect())
+}
+
+// Forgets a host's pinned key so the next connect pins whatever it presents,
+// after a rebuild. Without a host every pin goes. Returns how many were removed.
+#[tauri::command]
+pub async fn forget_pinned_host_key(host: Option<String>, port: Option<u16>) -> Result<usize, String> {
+    let mut pinned = PINNED.lock().map_err(|e| e.to_string())?;
+    let Some(host) = host else {
+        let count = pinned.len();
+        pinned.clear();
+        return Ok(count);
+    };
+    Ok(pinned.remove(&format_host(&host, port.unwrap_or(22))).map_or(0, |_| 1))
+}
+
+// Trusts the key an unknown host presented when Ask mode refused it, by adding
+// it to known_hosts. `fingerprint` is the one the user was shown, so a
+// different key the host presented since isn't accepted in its place.
+#[tauri::command]
+pub async fn accept_host_key(
+    state: State<'_, AppState>,
+    host: String,
+    port: Option<u16>,
+    fingerprint: String,
+) -> Result<(), String> {
+    let port = port.unwrap_or(22);
+    let host_field = format_host(&host, port);
+    let hashing = state.settings.lock().map_err(|e| e.to_string())?.hash_known_hosts;
+
+    let mut unknown = UNKNOWN.lock().map_err(|e| e.to_string())?;
+    let key = unknown
+        .get(&host_field)
+        .ok_or(format!("No key of {} is waiting to be accepted", host_field))?;
+    if key.fingerprint != fingerprint {
+        return Err(format!("{} now presents a different key ({})", host_field, key.fingerprint));
+    }
+    append_known_host(&host, port, key.key_type, &key.key_base64, hashing)?;
+    info!("Accepted {} key of {} ({})", key.key_type, host_field, key.fingerprint);
+    unknown.remove(&host_field);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_the_first_key_and_accepts_it_again() {
+        let mut pinned = BTreeMap::new();
+        assert!(check_pin(&mut pinned, "example.com", "ssh-ed25519", b"key", "SHA256:a").is_ok());
+        assert!(check_pin(&mut pinned, "example.com", "ssh-ed25519", b"key", "SHA256:a").is_ok());
+        assert_eq!(pinned.len(), 1);
+    }
+
+    #[test]
+    fn refuses_a_changed_key() {
+        let mut pinned = BTreeMap::new();
+        check_pin(&mut pinned, "example.com", "ssh-ed25519", b"key", "SHA256:a").unwrap();
+
+        let pin = check_pin(&mut pinned, "example.com", "ssh-ed25519", b"other", "SHA256:b").unwrap_err();
+        assert_eq!(pin.fingerprint, "SHA256:a");
+    }
+
+    #[test]
+    fn refuses_a_key_of_another_type() {
+        let mut pinned = BTreeMap::new();
+        check_pin(&mut pinned, "example.com", "ssh-ed25519", b"key", "SHA256:a").unwrap();
+
+        let pin = check_pin(&mut pinned, "example.com", "ecdsa-sha2-nistp256", b"ecdsa", "SHA256:b").unwrap_err();
+        assert_eq!(pin.key_type, "ssh-ed25519");
+        // The refused key doesn't replace the pin
+        assert!(check_pin(&mut pinned, "example.com", "ecdsa-sha2-nistp256", b"ecdsa", "SHA256:b").is_err());
+        assert!(check_pin(&mut pinned, "example.com", "ssh-ed25519", b"key", "SHA256:a").is_ok());
+    }
+
+    #[test]
+    fn pins_hosts_and_ports_separately() {
+        let mut pinned = BTreeMap::new();
+        check_pin(&mut pinned, "example.com", "ssh-ed25519", b"key", "SHA256:a").unwrap();
+        assert!(check_pin(&mut pinned, "[example.com]:2222", "ecdsa-sha2-nistp256", b"ecdsa", "SHA256:b").is_ok());
+        assert!(check_pin(&mut pinned, "example.org", "ssh-rsa", b"rsa", "SHA256:c").is_ok());
+    }
+}
@@ -0,0 +1,125 @@
+use log::{info, warn};
+use ssh2::Session;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tauri::AppHandle;
+
+use crate::error::SshError;
+use crate::ssh_new::{authenticate, emit_connection_status, SshConfig};
+
+// The sessions to each jump host and the threads carrying the next hop's
+// traffic over them. Everything stops when it's dropped.
+pub struct JumpChain {
+    sessions: Vec<Session>,
+    shutdown: Arc<AtomicBool>,
+    pumps: Vec<thread::JoinHandle<()>>,
+}
+
+impl JumpChain {
+    pub fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        for pump in self.pumps.drain(..) {
+            if let Err(e) = pump.join() {
+                warn!("Jump host tunnel thread join error: {:?}", e);
+            }
+        }
+        // Furthest hop first, each one's traffic runs through the one before
+        while let Some(session) = self.sessions.pop() {
+            session.set_blocking(true);
+            session.set_timeout(2000);
+            let _ = session.disconnect(None, "Closed", None);
+        }
+    }
+}
+
+impl Drop for JumpChain {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// Opens a direct-tcpip channel to host:port on the jump host's session and
+// returns a local socket carrying it: libssh2 only runs a session over a real
+// socket, so the channel is pumped into a loopback connection
+fn tunnel(
+    session: &Session,
+    host: &str,
+    port: u16,
+    shutdown: &Arc<AtomicBool>,
+) -> Result<(TcpStream, thread::JoinHandle<()>), SshError> {
+    let channel = session.channel_direct_tcpip(host, port, None).map_err(|e| {
+        SshError::Other(format!("The jump host could not open a connection to {}:{}: {}", host, port, e))
+    })?;
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let stream = TcpStream::connect(listener.local_addr()?)?;
+    let expected = stream.local_addr()?;
+    let tunnel_end = loop {
+        let (accepted, peer) = listener.accept()?;
+        // Anything else on this machine could connect to the port first
+        if peer == expected {
+            break accepted;
+        }
+        warn!("Dropped an unexpected connection from {} to a jump host tunnel", peer);
+    };
+
+    // The pump expects the session's calls to return instead of blocking
+    session.set_blocking(false);
+    let shutdown = shutdown.clone();
+    let pump = thread::spawn(move || crate::forwarding::pump(channel, tunnel_end, &shutdown));
+    Ok((stream, pump))
+}
+
+// Connects to each of `config.jump_hosts` in turn, every one reached through
+// the one before, and returns a socket reaching `config`'s own host through
+// the last, like OpenSSH's ProxyJump. Each hop authenticates with its own
+// method and has its key checked; the hops' own jump_hosts are ignored, and
+// `config`'s connect timeout applies to all of them. The returned address is
+// the first hop's.
+pub fn connect_via_jump(
+    config: &SshConfig,
+    app_handle: &AppHandle,
+    session_id: &str,
+) -> Result<(TcpStream, SocketAddr, JumpChain), SshError> {
+    let first = config.jump_hosts.first().ok_or_else(|| SshError::Other("No jump hosts configured".to_string()))?;
+    let timeout = config.connect_timeout();
+    let (mut stream, first_addr) = crate::net::connect_with_timeout(
+        &first.host,
+        first.port,
+        first.http_proxy.as_ref(),
+        first.bind_address.as_deref(),
+        timeout,
+    )?;
+
+    let mut chain = JumpChain {
+        sessions: Vec::new(),
+        shutdown: Arc::new(AtomicBool::new(false)),
+        pumps: Vec::new(),
+    };
+    let next_hops = config.jump_hosts[1..]
+        .iter()
+        .map(|hop| (hop.host.as_str(), hop.port))
+        .chain([(config.host.as_str(), config.port)]);
+
+    for (hop, (next_host, next_port)) in config.jump_hosts.iter().zip(next_hops) {
+        info!("Connecting through jump host {}@{}:{}", hop.username, hop.host, hop.port);
+        emit_connection_status(app_handle, session_id, "connecting", &format!("Connecting through {}", hop.host));
+
+        let mut session = Session::new()?;
+        session.set_tcp_stream(stream);
+        session.set_timeout(timeout.as_millis() as u32);
+        session.handshake()?;
+        session.set_timeout(0);
+        crate::host_keys::verify(&session, &hop.host, hop.port, hop.host_key_trust)?;
+        authenticate(&session, hop, None, app_handle, session_id)?;
+
+        let (next, pump) = tunnel(&session, next_host, next_port, &chain.shutdown)?;
+        chain.sessions.push(session);
+        chain.pumps.push(pump);
+        stream = next;
+    }
+
+    Ok((stream, first_addr, chain))
+}
@@ -0,0 +1,226 @@
+use log::{debug, info, warn};
+use serde::Serialize;
+use ssh2::{KeyboardInteractivePrompt, Prompt};
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+// How long the user gets to answer a prompt before authentication gives up
+const PROMPT_ANSWER_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// Out-of-band approvals (Duo push, phone call) leave the server silent until
+// the user reacts; Duo itself gives up after about a minute
+pub const MFA_APPROVAL_TIMEOUT: Duration = Duration::from_secs(3 * 60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptField {
+    pub text: String,
+    pub echo: bool,
+}
+
+// A numbered option from a second factor menu, e.g. " 1. Duo Push to XXX-XXX-1234"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MfaChoice {
+    pub value: String,
+    pub label: String,
+    // Approved on another device rather than by typing a code
+    pub out_of_band: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyboardInteractiveEvent {
+    pub request_id: String,
+    pub session_id: String,
+    pub instructions: String,
+    pub prompts: Vec<PromptField>,
+    // Non-empty when the round looks like a "passcode or option" menu
+    pub choices: Vec<MfaChoice>,
+}
+
+// Tells the UI to show "Check your phone…" instead of an input box; it lasts
+// until the connect call returns
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaWaitingEvent {
+    pub session_id: String,
+    pub message: String,
+    pub timeout_secs: u64,
+}
+
+// Keyboard-interactive prompts waiting for the user, keyed by request id
+pub struct PromptResponder {
+    pending: Mutex<HashMap<String, mpsc::Sender<Vec<String>>>>,
+}
+
+impl PromptResponder {
+    pub fn new() -> Self {
+        PromptResponder {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Blocks until respond_keyboard_interactive is called; None on timeout
+    fn ask(&self, app_handle: &AppHandle, mut event: KeyboardInteractiveEvent) -> Option<Vec<String>> {
+        let (tx, rx) = mpsc::channel();
+        event.request_id = Uuid::new_v4().to_string();
+        self.pending.lock().unwrap().insert(event.request_id.clone(), tx);
+
+        let _ = app_handle.emit("keyboard_interactive_prompt", &event);
+        let answer = rx.recv_timeout(PROMPT_ANSWER_TIMEOUT).ok();
+        if answer.is_none() {
+            warn!("No answer to keyboard-interactive prompt for {}", event.session_id);
+        }
+
+        self.pending.lock().unwrap().remove(&event.request_id);
+        answer
+    }
+}
+
+fn is_password_prompt(text: &str) -> bool {
+    let text = text.to_lowercase();
+    text.contains("password") && !text.contains("new password") && !text.contains("passcode")
+}
+
+fn is_out_of_band(label: &str) -> bool {
+    let label = label.to_lowercase();
+    ["push", "phone call", "approve", "approval"].iter().any(|word| label.contains(word))
+}
+
+// Picks the numbered options out of a second factor menu. Duo sends them in
+// the instructions or in the prompt text itself depending on the server.
+pub fn parse_choices(text: &str) -> Vec<MfaChoice> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (number, label) = line.split_once(['.', ')'])?;
+            if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let label = label.trim();
+            (!label.is_empty()).then(|| MfaChoice {
+                value: number.to_string(),
+                label: label.to_string(),
+                out_of_band: is_out_of_band(label),
+            })
+        })
+        .collect()
+}
+
+// Answers keyboard-interactive rounds for one connect: password prompts are
+// filled from the saved password (once, so a second factor isn't sent it),
+// anything else goes to the UI
+pub struct InteractivePrompter<'a> {
+    app_handle: &'a AppHandle,
+    session_id: &'a str,
+    password: Option<&'a str>,
+}
+
+impl<'a> InteractivePrompter<'a> {
+    pub fn new(app_handle: &'a AppHandle, session_id: &'a str, password: Option<&'a str>) -> Self {
+        InteractivePrompter {
+            app_handle,
+            session_id,
+            password: password.filter(|password| !password.is_empty()),
+        }
+    }
+
+    // A password for the "password" method when the server asks for one after
+    // partial success, e.g. key then password. The saved password is used if
+    // a keyboard-interactive round hasn't taken it already.
+    pub fn ask_password(&mut self, username: &str) -> Option<String> {
+        if let Some(password) = self.password.take() {
+            return Some(password.to_string());
+        }
+
+        self.ask_secret("The server also requires a password", &format!("Password for {}: ", username))
+    }
+
+    // Asks the UI for one hidden value, e.g. a token's PIN; None if the user
+    // gave nothing
+    pub fn ask_secret(&self, instructions: &str, prompt: &str) -> Option<String> {
+        let event = KeyboardInteractiveEvent {
+            request_id: String::new(),
+            session_id: self.session_id.to_string(),
+            instructions: instructions.to_string(),
+            prompts: vec![PromptField { text: prompt.to_string(), echo: false }],
+            choices: Vec::new(),
+        };
+        let responses = self.app_handle.state::<PromptResponder>().ask(self.app_handle, event)?;
+        responses.into_iter().next().filter(|secret| !secret.is_empty())
+    }
+
+    fn emit_waiting(&self, message: &str) {
+        let message = if message.trim().is_empty() { "Waiting for approval on your other device" } else { message.trim() };
+        info!("SSH session {} is waiting for an out-of-band approval", self.session_id);
+        let _ = self.app_handle.emit("mfa_waiting", &MfaWaitingEvent {
+            session_id: self.session_id.to_string(),
+            message: message.to_string(),
+            timeout_secs: MFA_APPROVAL_TIMEOUT.as_secs(),
+        });
+    }
+}
+
+impl KeyboardInteractivePrompt for InteractivePrompter<'_> {
+    fn prompt<'b>(&mut self, _username: &str, instructions: &str, prompts: &[Prompt<'b>]) -> Vec<String> {
+        debug!("Keyboard-interactive round for {} with {} prompt(s)", self.session_id, prompts.len());
+
+        // A round with nothing to answer is purely informational, which is
+        // how servers announce "push sent" before blocking on the approval
+        if prompts.is_empty() {
+            self.emit_waiting(instructions);
+            return Vec::new();
+        }
+
+        if let [prompt] = prompts {
+            if !prompt.echo && is_password_prompt(&prompt.text) {
+                if let Some(password) = self.password.take() {
+                    return vec![password.to_string()];
+                }
+            }
+        }
+
+        let menu = prompts.iter().fold(instructions.to_string(), |text, prompt| text + "\n" + &prompt.text);
+        let choices = parse_choices(&menu);
+        let event = KeyboardInteractiveEvent {
+            request_id: String::new(),
+            session_id: self.session_id.to_string(),
+            instructions: instructions.to_string(),
+            prompts: prompts.iter().map(|p| PromptField { text: p.text.to_string(), echo: p.echo }).collect(),
+            choices: choices.clone(),
+        };
+
+        let Some(mut responses) = self.app_handle.state::<PromptResponder>().ask(self.app_handle, event) else {
+            return vec![String::new(); prompts.len()];
+        };
+        responses.resize(prompts.len(), String::new());
+
+        // The server goes quiet until the push is approved, so say so now
+        if let [answer] = responses.as_slice() {
+            let answer = answer.trim();
+            let choice = choices.iter().find(|choice| choice.value == answer);
+            if let Some(choice) = choice.filter(|choice| choice.out_of_band) {
+                self.emit_waiting(&format!("{}. Approve it to continue.", choice.label));
+            } else if is_out_of_band(answer) {
+                self.emit_waiting("Approve the sign-in request to continue.");
+            }
+        }
+
+        responses
+    }
+}
+
+// Answers a `keyboard_interactive_prompt` event, one response per prompt
+#[tauri::command]
+pub async fn respond_keyboard_interactive(
+    responder: State<'_, PromptResponder>,
+    request_id: String,
+    responses: Vec<String>,
+) -> Result<(), String> {
+    let sender = responder.pending.lock().unwrap()
+        .remove(&request_id)
+        .ok_or_else(|| "This prompt is no longer pending".to_string())?;
+
+    sender.send(responses).map_err(|_| "The connection attempt was cancelled".to_string())
+}
@@ -0,0 +1,63 @@
+use keyring::Entry;
+
+// All TermNest secrets live under one service name, keyed by session id
+const KEYCHAIN_SERVICE: &str = "TermNest";
+
+fn entry(session_id: &str) -> Result<Entry, String> {
+    Entry::new(KEYCHAIN_SERVICE, session_id)
+        .map_err(|e| format!("Failed to access keychain: {}", e))
+}
+
+// Returns None when nothing has been stored for the session yet
+pub fn load_password(session_id: &str) -> Result<Option<String>, String> {
+    match entry(session_id)?.get_password() {
+        Ok(password) => Ok(Some(password)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read password from keychain: {}", e)),
+    }
+}
+
+pub fn require_password(session_id: &str) -> Result<String, String> {
+    load_password(session_id)?
+        .ok_or_else(|| "No password stored in the keychain for this session".to_string())
+}
+
+fn save_password(account: &str, password: &str) -> Result<(), String> {
+    entry(account)?
+        .set_password(password)
+        .map_err(|e| format!("Failed to save password to keychain: {}", e))
+}
+
+// A session's HTTP proxy password has its own entry next to the session's
+fn proxy_account(session_id: &str) -> String {
+    format!("{}/proxy", session_id)
+}
+
+pub fn load_proxy_password(session_id: &str) -> Result<Option<String>, String> {
+    load_password(&proxy_account(session_id))
+}
+
+pub fn save_proxy_password(session_id: &str, password: &str) -> Result<(), String> {
+    save_password(&proxy_account(session_id), password)
+}
+
+pub fn delete_proxy_password(session_id: &str) -> Result<(), String> {
+    delete_password(&proxy_account(session_id))
+}
+
+pub fn delete_password(session_id: &str) -> Result<(), String> {
+    match entry(session_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete password from keychain: {}", e)),
+    }
+}
+
+#[tauri::command]
+pub async fn save_password_to_keychain(session_id: String, password: String) -> Result<(), String> {
+    save_password(&session_id, &password)
+}
+
+#[tauri::command]
+pub async fn delete_password_from_keychain(session_id: String) -> Result<(), String> {
+    delete_password(&session_id)
+}
@@ -0,0 +1,313 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::State;
+
+use crate::error::SshError;
+use crate::local_terminal::LocalTerminalManager;
+use crate::macros::MacroRecorder;
+use crate::ssh_new::SshManager;
+use crate::AppState;
+
+// Keys are identified like "Ctrl+Alt+ArrowLeft": modifiers in that order,
+// then a DOM KeyboardEvent.key name or a single character
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Modifiers {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+}
+
+impl Modifiers {
+    // xterm's modifier parameter, as in ESC [ 1 ; 3 D for Alt+Left
+    fn param(self) -> u8 {
+        1 + self.shift as u8 + 2 * self.alt as u8 + 4 * self.ctrl as u8
+    }
+
+    fn any(self) -> bool {
+        self.ctrl || self.alt || self.shift
+    }
+}
+
+const NAMED_KEYS: &[(&str, &[&str])] = &[
+    ("ArrowUp", &["up"]),
+    ("ArrowDown", &["down"]),
+    ("ArrowLeft", &["left"]),
+    ("ArrowRight", &["right"]),
+    ("Home", &[]),
+    ("End", &[]),
+    ("PageUp", &["pgup"]),
+    ("PageDown", &["pgdn"]),
+    ("Insert", &["ins"]),
+    ("Delete", &["del"]),
+    ("Enter", &["return"]),
+    ("Tab", &[]),
+    ("Backspace", &[]),
+    ("Escape", &["esc"]),
+    ("Space", &[]),
+    ("F1", &[]), ("F2", &[]), ("F3", &[]), ("F4", &[]), ("F5", &[]), ("F6", &[]),
+    ("F7", &[]), ("F8", &[]), ("F9", &[]), ("F10", &[]), ("F11", &[]), ("F12", &[]),
+];
+
+fn parse_key(id: &str) -> Result<(Modifiers, String), String> {
+    let mut mods = Modifiers::default();
+    let mut rest = id.trim();
+
+    // The key itself may be "+", so only split while something follows
+    while let Some((modifier, tail)) = rest.split_once('+').filter(|(_, tail)| !tail.is_empty()) {
+        match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => mods.ctrl = true,
+            "alt" | "meta" | "option" => mods.alt = true,
+            "shift" => mods.shift = true,
+            _ => return Err(format!("Unknown modifier {:?} in key {:?}", modifier, id)),
+        }
+        rest = tail;
+    }
+
+    let key = match rest {
+        " " => "Space".to_string(),
+        c if c.chars().count() == 1 => c.to_string(),
+        name => NAMED_KEYS
+            .iter()
+            .find(|(canonical, aliases)| {
+                canonical.eq_ignore_ascii_case(name) || aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+            .map(|(canonical, _)| canonical.to_string())
+            .ok_or_else(|| format!("Unknown key {:?}", id))?,
+    };
+    Ok((mods, key))
+}
+
+// Canonical form of a key identifier, so "alt+left" and "Alt+ArrowLeft" are
+// the same binding
+fn normalize_key(id: &str) -> Result<String, String> {
+    let (mods, key) = parse_key(id)?;
+    let mut parts = Vec::new();
+    if mods.ctrl {
+        parts.push("Ctrl".to_string());
+    }
+    if mods.alt {
+        parts.push("Alt".to_string());
+    }
+    if mods.shift {
+        parts.push("Shift".to_string());
+    }
+    parts.push(key);
+    Ok(parts.join("+"))
+}
+
+fn ctrl_char(c: char) -> Option<char> {
+    match c {
+        'a'..='z' | 'A'..='Z' => Some(((c.to_ascii_lowercase() as u8) & 0x1f) as char),
+        '@' | ' ' | '2' => Some('\0'),
+        '[' | '3' => Some('\x1b'),
+        '\\' | '4' => Some('\x1c'),
+        ']' | '5' => Some('\x1d'),
+        '^' | '6' => Some('\x1e'),
+        '_' | '-' | '7' => Some('\x1f'),
+        '?' | '8' => Some('\x7f'),
+        _ => None,
+    }
+}
+
+// What xterm sends for a key. `app_cursor` is DECCKM (application cursor keys),
+// which full-screen programs like vim turn on.
+fn default_sequence(mods: Modifiers, key: &str, app_cursor: bool) -> Option<String> {
+    let m = mods.param();
+    let alt = if mods.alt { "\x1b" } else { "" };
+
+    let cursor = match key {
+        "ArrowUp" => Some('A'),
+        "ArrowDown" => Some('B'),
+        "ArrowRight" => Some('C'),
+        "ArrowLeft" => Some('D'),
+        "Home" => Some('H'),
+        "End" => Some('F'),
+        _ => None,
+    };
+    if let Some(c) = cursor {
+        return Some(match (mods.any(), app_cursor) {
+            (true, _) => format!("\x1b[1;{}{}", m, c),
+            (false, true) => format!("\x1bO{}", c),
+            (false, false) => format!("\x1b[{}", c),
+        });
+    }
+
+    let tilde = match key {
+        "Insert" => Some(2),
+        "Delete" => Some(3),
+        "PageUp" => Some(5),
+        "PageDown" => Some(6),
+        "F5" => Some(15),
+        "F6" => Some(17),
+        "F7" => Some(18),
+        "F8" => Some(19),
+        "F9" => Some(20),
+        "F10" => Some(21),
+        "F11" => Some(23),
+        "F12" => Some(24),
+        _ => None,
+    };
+    if let Some(n) = tilde {
+        return Some(if mods.any() { format!("\x1b[{};{}~", n, m) } else { format!("\x1b[{}~", n) });
+    }
+
+    let sequence = match key {
+        "F1" | "F2" | "F3" | "F4" => {
+            let c = (b'P' + key[1..].parse::<u8>().ok()? - 1) as char;
+            return Some(if mods.any() { format!("\x1b[1;{}{}", m, c) } else { format!("\x1bO{}", c) });
+        }
+        "Tab" if mods.shift => return Some("\x1b[Z".to_string()),
+        "Tab" => "\t".to_string(),
+        "Enter" => "\r".to_string(),
+        "Escape" => "\x1b".to_string(),
+        "Backspace" if mods.ctrl => "\x08".to_string(),
+        "Backspace" => "\x7f".to_string(),
+        "Space" if mods.ctrl => "\0".to_string(),
+        "Space" => " ".to_string(),
+        c => {
+            let c = c.chars().next().filter(|_| c.chars().count() == 1)?;
+            if mods.ctrl {
+                ctrl_char(c)?.to_string()
+            } else {
+                c.to_string()
+            }
+        }
+    };
+    Some(format!("{}{}", alt, sequence))
+}
+
+// Sequences are written with escapes: \e (or \x1b), \xNN, \r, \n, \t, \0, \\
+fn decode_sequence(value: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('e') => out.push('\x1b'),
+            Some('r') => out.push('\r'),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('0') => out.push('\0'),
+            Some('\\') => out.push('\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 2)
+                    .ok_or_else(|| format!("Invalid \\x escape in {:?}", value))?;
+                if byte > 0x7f {
+                    return Err(format!("\\x{} in {:?} is not an ASCII character", hex, value));
+                }
+                out.push(byte as char);
+            }
+            other => {
+                return Err(format!("Unknown escape \\{} in {:?}", other.map(String::from).unwrap_or_default(), value))
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn encode_sequence(sequence: &str) -> String {
+    sequence
+        .chars()
+        .map(|c| match c {
+            '\x1b' => "\\e".to_string(),
+            '\r' => "\\r".to_string(),
+            '\n' => "\\n".to_string(),
+            '\t' => "\\t".to_string(),
+            '\0' => "\\0".to_string(),
+            '\\' => "\\\\".to_string(),
+            c if c.is_ascii_control() => format!("\\x{:02x}", c as u8),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+// The session's overrides keyed by canonical key id. Sessions that aren't
+// saved (quick connects) just get the defaults.
+fn session_overrides(state: &AppState, session_id: &str) -> Result<HashMap<String, String>, String> {
+    let keymap = state.get_session(session_id).map(|session| session.keymap).unwrap_or_default();
+    keymap
+        .iter()
+        .map(|(key, value)| {
+            let sequence = decode_sequence(value).map_err(|e| format!("Keymap entry {}: {}", key, e))?;
+            Ok((normalize_key(key)?, sequence))
+        })
+        .collect()
+}
+
+fn resolve(overrides: &HashMap<String, String>, key: &str, app_cursor: bool) -> Result<String, String> {
+    let (mods, name) = parse_key(key)?;
+    if let Some(sequence) = overrides.get(&normalize_key(key)?) {
+        return Ok(sequence.clone());
+    }
+    default_sequence(mods, &name, app_cursor).ok_or_else(|| format!("No sequence for key {}", key))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyBinding {
+    pub key: String,
+    // Escaped, in the same notation the keymap is written in
+    pub sequence: String,
+    pub overridden: bool,
+}
+
+// Named keys (with and without modifiers) and what they send for this
+// session, followed by overrides for anything else, for a keymap editor
+#[tauri::command]
+pub async fn get_keymap(
+    state: State<'_, AppState>,
+    session_id: String,
+    app_cursor: Option<bool>,
+) -> Result<Vec<KeyBinding>, String> {
+    let overrides = session_overrides(&state, &session_id)?;
+    let app_cursor = app_cursor.unwrap_or(false);
+
+    let mut bindings = Vec::new();
+    for (name, _) in NAMED_KEYS {
+        for prefix in ["", "Shift+", "Alt+", "Ctrl+"] {
+            let key = format!("{}{}", prefix, name);
+            bindings.push(KeyBinding {
+                sequence: encode_sequence(&resolve(&overrides, &key, app_cursor)?),
+                overridden: overrides.contains_key(&key),
+                key,
+            });
+        }
+    }
+
+    let mut extra: Vec<_> = overrides.iter().filter(|(key, _)| !bindings.iter().any(|b| &b.key == *key)).collect();
+    extra.sort();
+    bindings.extend(extra.into_iter().map(|(key, sequence)| KeyBinding {
+        key: key.clone(),
+        sequence: encode_sequence(sequence),
+        overridden: true,
+    }));
+    Ok(bindings)
+}
+
+// Sends a key by its logical name, translated through the session's keymap,
+// so special keys reach the server the same way whatever the frontend does
+#[tauri::command]
+pub async fn send_key(
+    state: State<'_, AppState>,
+    ssh: State<'_, Arc<SshManager>>,
+    local: State<'_, LocalTerminalManager>,
+    recorder: State<'_, MacroRecorder>,
+    session_id: String,
+    key: String,
+    app_cursor: Option<bool>,
+) -> Result<(), SshError> {
+    let overrides = session_overrides(&state, &session_id).map_err(SshError::Other)?;
+    let input = resolve(&overrides, &key, app_cursor.unwrap_or(false)).map_err(SshError::Other)?;
+
+    recorder.record(&session_id, &input);
+    if local.contains(&session_id) {
+        return local.write(&session_id, &input);
+    }
+    ssh.send_input(&session_id, &input)
+}
@@ -0,0 +1,271 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::settings::HashKnownHosts;
+
+// Prefix of a hashed host field: |1|base64(salt)|base64(hmac)
+const HASH_MAGIC: &str = "|1|";
+
+pub fn known_hosts_path() -> Option<PathBuf> {
+    crate::get_ssh_dir().map(|dir| dir.join("known_hosts"))
+}
+
+// known_hosts only brackets the host when a non-default port is used
+pub fn format_host(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+fn hmac_sha1(salt: &[u8], host: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(salt).expect("HMAC accepts any key length");
+    mac.update(host.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Same scheme as `ssh-keygen -H`: HMAC-SHA1 of the host keyed with a random salt
+pub fn hash_host(host: &str) -> String {
+    let salt: [u8; 20] = rand::random();
+    format!("{}{}|{}", HASH_MAGIC, BASE64.encode(salt), BASE64.encode(hmac_sha1(&salt, host)))
+}
+
+// Checks a hashed host field against a formatted host the way `ssh-keygen -F` does
+pub fn hashed_host_matches(field: &str, host: &str) -> bool {
+    let Some((salt, hash)) = field.strip_prefix(HASH_MAGIC).and_then(|rest| rest.split_once('|')) else {
+        return false;
+    };
+
+    match (BASE64.decode(salt), BASE64.decode(hash)) {
+        (Ok(salt), Ok(hash)) => hmac_sha1(&salt, host) == hash,
+        _ => false,
+    }
+}
+
+// Matches the first field of a known_hosts line, which is either a hashed host
+// or a comma separated list of plain ones
+pub fn host_field_matches(field: &str, host: &str) -> bool {
+    if field.starts_with(HASH_MAGIC) {
+        hashed_host_matches(field, host)
+    } else {
+        field.split(',').any(|candidate| candidate == host)
+    }
+}
+
+// What known_hosts says about a host's key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownHostMatch {
+    Match,
+    // The host is listed with a different key of the same type
+    Mismatch,
+    // Marked @revoked
+    Revoked,
+    NotFound,
+}
+
+// Looks a host key up in known_hosts. Keys of another type don't count as a
+// mismatch, the same way OpenSSH only compares keys of the negotiated type.
+pub fn check_known_host(host: &str, port: u16, key_type: &str, key_base64: &str) -> KnownHostMatch {
+    match known_hosts_path().and_then(|path| std::fs::read_to_string(path).ok()) {
+        Some(contents) => match_known_host(&contents, host, port, key_type, key_base64),
+        None => KnownHostMatch::NotFound,
+    }
+}
+
+fn match_known_host(contents: &str, host: &str, port: u16, key_type: &str, key_base64: &str) -> KnownHostMatch {
+    let host_field = format_host(host, port);
+
+    let mut result = KnownHostMatch::NotFound;
+    for line in contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let mut fields = line.split_whitespace().peekable();
+        let marker = fields.next_if(|field| field.starts_with('@'));
+        let (Some(field), Some(kt), Some(key)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if !host_field_matches(field, &host_field) {
+            continue;
+        }
+        match marker {
+            Some("@revoked") if kt == key_type && key == key_base64 => return KnownHostMatch::Revoked,
+            // Certificate authorities and other markers aren't host keys
+            Some(_) => {}
+            None if kt == key_type && key == key_base64 => result = KnownHostMatch::Match,
+            None if kt == key_type && result == KnownHostMatch::NotFound => result = KnownHostMatch::Mismatch,
+            None => {}
+        }
+    }
+    result
+}
+
+// A file "uses hashing" if any existing entry is hashed
+fn file_uses_hashing(contents: &str) -> bool {
+    contents.lines().any(|line| line.trim_start().starts_with(HASH_MAGIC))
+}
+
+// Appends a host key entry, hashing the host name according to the setting
+pub fn append_known_host(
+    host: &str,
+    port: u16,
+    key_type: &str,
+    key_base64: &str,
+    hashing: HashKnownHosts,
+) -> Result<(), String> {
+    let path = known_hosts_path().ok_or("Could not determine the home directory")?;
+
+    if let Some(dir) = path.parent() {
+        if !dir.exists() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700));
+            }
+        }
+    }
+
+    let existing = std::fs::read_to_string(&path).unwrap_or_default();
+    let host_field = format_host(host, port);
+
+    let already_known = existing.lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        matches!(
+            (fields.next(), fields.next(), fields.next()),
+            (Some(field), Some(kt), Some(key)) if kt == key_type && key == key_base64 && host_field_matches(field, &host_field)
+        )
+    });
+    if already_known {
+        return Ok(());
+    }
+
+    let hash = match hashing {
+        HashKnownHosts::Yes => true,
+        HashKnownHosts::No => false,
+        HashKnownHosts::Auto => file_uses_hashing(&existing),
+    };
+
+    let host_field = if hash { hash_host(&host_field) } else { host_field };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    // Don't glue our entry onto a last line that lacks a newline
+    let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+    writeln!(file, "{}{} {} {}", separator, host_field, key_type, key_base64)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[tauri::command]
+pub async fn add_known_host(
+    state: tauri::State<'_, crate::AppState>,
+    host: String,
+    port: u16,
+    key_type: String,
+    key: String,
+) -> Result<(), String> {
+    let hashing = state.settings.lock().map_err(|e| e.to_string())?.hash_known_hosts;
+    append_known_host(&host, port, &key_type, &key, hashing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ED25519_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl";
+    const OTHER_ED25519_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIBcVUV8hPP4Xv3CKnU8mHT4fMvLrDI0FMOM4v3CcNxsK";
+    // `ssh-keygen -H` output for example.com
+    const HASHED_EXAMPLE_COM: &str = "|1|LropkTMWid+GFEFXzRoY+AiJGdE=|I6AunClIE94xcOB/wXMGZZP5beU=";
+
+    #[test]
+    fn formats_non_default_ports_in_brackets() {
+        assert_eq!(format_host("example.com", 22), "example.com");
+        assert_eq!(format_host("example.com", 2222), "[example.com]:2222");
+    }
+
+    #[test]
+    fn matches_hosts_hashed_by_ssh_keygen() {
+        assert!(hashed_host_matches(HASHED_EXAMPLE_COM, "example.com"));
+        assert!(!hashed_host_matches(HASHED_EXAMPLE_COM, "example.org"));
+        assert!(!hashed_host_matches(HASHED_EXAMPLE_COM, "[example.com]:2222"));
+        assert!(!hashed_host_matches("|1|not base64|I6AunClIE94xcOB/wXMGZZP5beU=", "example.com"));
+        assert!(!hashed_host_matches("|1|LropkTMWid+GFEFXzRoY+AiJGdE=", "example.com"));
+    }
+
+    #[test]
+    fn hashed_hosts_round_trip() {
+        let field = hash_host("[10.0.0.5]:2222");
+        assert!(field.starts_with(HASH_MAGIC));
+        assert!(host_field_matches(&field, "[10.0.0.5]:2222"));
+        assert!(!host_field_matches(&field, "10.0.0.5"));
+    }
+
+    #[test]
+    fn matches_plain_host_lists() {
+        assert!(host_field_matches("example.com,192.0.2.1", "192.0.2.1"));
+        assert!(host_field_matches("example.com,192.0.2.1", "example.com"));
+        assert!(!host_field_matches("example.com,192.0.2.1", "example"));
+    }
+
+    #[test]
+    fn parses_entries_skipping_comments_and_malformed_lines() {
+        let contents = format!(
+            "# comment\n\nexample.org ssh-ed25519\n  example.com ssh-ed25519 {} user@host  \n",
+            ED25519_KEY
+        );
+        assert_eq!(match_known_host(&contents, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::Match);
+        assert_eq!(match_known_host(&contents, "example.org", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::NotFound);
+        assert_eq!(match_known_host(&contents, "example.com", 2222, "ssh-ed25519", ED25519_KEY), KnownHostMatch::NotFound);
+    }
+
+    #[test]
+    fn finds_hashed_entries() {
+        let contents = format!("{} ssh-ed25519 {}\n", HASHED_EXAMPLE_COM, ED25519_KEY);
+        assert_eq!(match_known_host(&contents, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::Match);
+        assert_eq!(match_known_host(&contents, "example.net", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::NotFound);
+    }
+
+    #[test]
+    fn detects_a_changed_key() {
+        let contents = format!("example.com ssh-ed25519 {}\n", OTHER_ED25519_KEY);
+        assert_eq!(match_known_host(&contents, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::Mismatch);
+
+        let hashed = format!("{} ssh-ed25519 {}\n", HASHED_EXAMPLE_COM, OTHER_ED25519_KEY);
+        assert_eq!(match_known_host(&hashed, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::Mismatch);
+    }
+
+    #[test]
+    fn keys_of_another_type_are_not_a_mismatch() {
+        let contents = "example.com ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTY=\n";
+        assert_eq!(match_known_host(contents, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::NotFound);
+    }
+
+    #[test]
+    fn a_matching_entry_wins_over_an_earlier_stale_one() {
+        let contents = format!(
+            "example.com ssh-ed25519 {}\nexample.com ssh-ed25519 {}\n",
+            OTHER_ED25519_KEY, ED25519_KEY
+        );
+        assert_eq!(match_known_host(&contents, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::Match);
+    }
+
+    #[test]
+    fn revoked_keys_are_reported_and_markers_skipped() {
+        let contents = format!(
+            "example.com ssh-ed25519 {key}\n@revoked example.com ssh-ed25519 {key}\n",
+            key = ED25519_KEY
+        );
+        assert_eq!(match_known_host(&contents, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::Revoked);
+
+        let ca = format!("@cert-authority example.com ssh-ed25519 {}\n", OTHER_ED25519_KEY);
+        assert_eq!(match_known_host(&ca, "example.com", 22, "ssh-ed25519", ED25519_KEY), KnownHostMatch::NotFound);
+    }
+}
@@ -0,0 +1,98 @@
+use log::debug;
+use serde::Serialize;
+use ssh2::Session;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::ssh_new::probe_session;
+
+pub const DEFAULT_LATENCY_INTERVAL: Duration = Duration::from_secs(2);
+const MIN_LATENCY_INTERVAL: Duration = Duration::from_millis(500);
+
+// Enough samples for a sparkline covering a couple of minutes
+const HISTORY_LEN: usize = 60;
+
+// One sample. `rtt_ms` is None when the server didn't answer in time, which
+// the UI shows differently from a (LAN-fast) 0ms
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySample {
+    pub at: String,
+    pub rtt_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LatencyEvent {
+    session_id: String,
+    rtt_ms: Option<u64>,
+    error: Option<String>,
+}
+
+// Periodically times a round trip to the server and emits a `latency` event.
+// libssh2's keepalive doesn't tell us when the reply arrives, so this times
+// opening a channel instead, the same probe probe_channel uses.
+pub struct LatencyMonitor {
+    history: Arc<Mutex<VecDeque<LatencySample>>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl LatencyMonitor {
+    pub fn start(session_id: String, session: Session, interval: Duration, app_handle: AppHandle) -> Self {
+        let interval = interval.max(MIN_LATENCY_INTERVAL);
+        let history = Arc::new(Mutex::new(VecDeque::with_capacity(HISTORY_LEN)));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread_history = history.clone();
+        let thread_shutdown = shutdown.clone();
+        let handle = thread::spawn(move || {
+            while !thread_shutdown.load(Ordering::Relaxed) {
+                let started = Instant::now();
+                let result = probe_session(&session);
+                let rtt_ms = result.as_ref().ok().map(|elapsed| elapsed.as_millis() as u64);
+
+                {
+                    let mut history = thread_history.lock().unwrap();
+                    if history.len() == HISTORY_LEN {
+                        history.pop_front();
+                    }
+                    history.push_back(LatencySample { at: chrono::Utc::now().to_rfc3339(), rtt_ms });
+                }
+
+                let _ = app_handle.emit("latency", &LatencyEvent {
+                    session_id: session_id.clone(),
+                    rtt_ms,
+                    error: result.err(),
+                });
+
+                // Sleep in small steps so stopping doesn't wait a whole interval
+                while started.elapsed() < interval && !thread_shutdown.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(50));
+                }
+            }
+            debug!("Latency monitor for {} stopped", session_id);
+        });
+
+        LatencyMonitor { history, shutdown, handle: Some(handle) }
+    }
+
+    pub fn history(&self) -> Vec<LatencySample> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for LatencyMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
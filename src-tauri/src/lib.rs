@@ -4,8 +4,68 @@ use std::sync::Mutex;
 use tauri::{AppHandle, Manager, State};
 use uuid::Uuid;
 
+mod algorithms;
+mod config_backup;
+mod connection_string;
+mod diff;
+mod disconnect;
+mod dotenv;
+mod error;
+mod exec;
+mod favorites;
+mod fingerprint;
+mod forwarding;
+mod groups;
+mod heredoc;
+mod host_keys;
+mod jump;
+mod keyboard_interactive;
+mod key_convert;
+mod keys;
+mod keychain;
+mod keymap;
+mod known_hosts;
+mod latency;
+mod local_terminal;
+mod logging;
+mod logins;
+mod ls_listing;
+mod macros;
+mod metrics;
+mod net;
+mod paste;
+mod pkcs11;
+mod port_scan;
+mod processes;
+mod reconnect;
+mod remote;
+mod remote_platform;
+mod remote_user;
+mod runbook;
+mod scp;
+mod secrets;
+mod settings;
+mod session_logs;
+mod session_report;
+mod session_store;
+mod sftp;
+mod sftp_glob;
+mod shell_cwd;
+mod ssh_config;
 mod ssh_new;
+mod sweeper;
+mod temp_files;
+mod templates;
+mod text_check;
+mod thumbnails;
+mod transfer_history;
+mod transfer_hooks;
+mod transfers;
+mod xattrs;
+use error::describe_channel_error;
+use settings::AppSettings;
 use ssh_new::SshManager;
+use temp_files::TempFileRegistry;
 
 // Session data structures
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +79,92 @@ pub struct Session {
     pub protocol: Protocol,
     pub created_at: String,
     pub last_used: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    // The password itself lives in the OS keychain, never in sessions.json
+    #[serde(default)]
+    pub store_password_in_keychain: bool,
+    // Alternative username/auth combos for the same host
+    #[serde(default)]
+    pub profiles: Vec<SessionProfile>,
+    // Tunnel the connection through an HTTP CONNECT proxy
+    #[serde(default)]
+    pub http_proxy: Option<net::ProxyConfig>,
+    // Local .env file exported into the remote shell on connect. Meant for
+    // non-secret config: the values are sent to the server in plain text.
+    #[serde(default)]
+    pub env_file: Option<String>,
+    // Skip the "this is your own machine" warning on connect
+    #[serde(default)]
+    pub allow_loopback: bool,
+    #[serde(default)]
+    pub terminal_modes: ssh_new::TerminalModes,
+    #[serde(default)]
+    pub reconnect: Option<settings::ReconnectPolicy>,
+    // Like OpenSSH's IdentitiesOnly: authenticate only with the configured
+    // method, without falling back to keyboard-interactive when it fails
+    #[serde(default)]
+    pub strict_identities: bool,
+    // Local address outgoing connections are bound to, on machines with
+    // several interfaces (OpenSSH's BindAddress)
+    #[serde(default)]
+    pub bind_address: Option<String>,
+    // Key id (e.g. "Alt+ArrowLeft") to the sequence it sends, written with
+    // \e-style escapes, for keys this host expects something unusual for
+    #[serde(default)]
+    pub keymap: HashMap<String, String>,
+    // Overrides the global host key trust mode
+    #[serde(default)]
+    pub host_key_trust: Option<settings::HostKeyTrust>,
+    // Let the UI answer sudo prompts with the session's cached password
+    #[serde(default)]
+    pub sudo_autofill: bool,
+    // Bastions to connect through first, as in OpenSSH's ProxyJump:
+    // comma-separated [user@]host[:port], where a host may be an alias from
+    // ~/.ssh/config
+    #[serde(default)]
+    pub proxy_jump: Option<String>,
+    // Seconds between keepalives; None uses the default, 0 turns them off
+    #[serde(default)]
+    pub keepalive_interval_secs: Option<u64>,
+    // Extra `Key=Value` options for the system ssh fallback, each passed as
+    // its own -o. See local_terminal::check_ssh_option.
+    #[serde(default)]
+    pub ssh_options: Vec<String>,
+    // Seconds to wait for the server to answer; None uses the default
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+}
+
+// The session's own username and auth method act as this profile
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionProfile {
+    pub name: String,
+    pub username: String,
+    pub auth_method: AuthMethod,
+}
+
+impl Session {
+    pub fn default_profile(&self) -> SessionProfile {
+        SessionProfile {
+            name: DEFAULT_PROFILE.to_string(),
+            username: self.username.clone(),
+            auth_method: self.auth_method.clone(),
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> Option<SessionProfile> {
+        if name == DEFAULT_PROFILE {
+            return Some(self.default_profile());
+        }
+        self.profiles.iter().find(|p| p.name == name).cloned()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +172,8 @@ pub enum AuthMethod {
     Password,
     PublicKey { key_path: String },
     Agent,
+    // A smartcard or token's keys through its PKCS#11 module
+    Pkcs11 { provider_path: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +199,33 @@ pub struct FileItem {
     pub size: u64,
     pub is_directory: bool,
     pub modified: String,
+    #[serde(default)]
+    pub is_symlink: bool,
+    // Where a symlink points, as returned by readlink
+    #[serde(default)]
+    pub link_target: Option<String>,
+    #[serde(default)]
+    pub link_target_type: Option<LinkTargetType>,
+    // Number of hard links. SFTP listings leave it out, see get_directory_details.
+    #[serde(default)]
+    pub nlink: Option<u64>,
+    // Mode string like "drwxr-xr-x"
+    #[serde(default)]
+    pub permissions: Option<String>,
+    // SELinux label, also from get_directory_details
+    #[serde(default)]
+    pub selinux_context: Option<String>,
+}
+
+// What a symlink resolves to, so the UI knows whether opening it enters a directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkTargetType {
+    Directory,
+    File,
+    Other,
+    // Dangling link, or a target we are not allowed to stat
+    Missing,
 }
 
 // Application state
@@ -58,16 +233,27 @@ pub struct AppState {
     pub sessions: Mutex<HashMap<String, Session>>,
     pub active_connections: Mutex<HashMap<String, ConnectionStatus>>,
     pub ssh_manager: std::sync::Arc<SshManager>,
+    pub temp_files: TempFileRegistry,
+    pub settings: Mutex<AppSettings>,
 }
 
 impl AppState {
-    pub fn new(_app_handle: AppHandle) -> Self {
+    pub fn new(app_handle: AppHandle, ssh_manager: std::sync::Arc<SshManager>) -> Self {
         Self {
+            settings: Mutex::new(settings::load(&app_handle)),
             sessions: Mutex::new(HashMap::new()),
             active_connections: Mutex::new(HashMap::new()),
-            ssh_manager: std::sync::Arc::new(SshManager::new()),
+            ssh_manager,
+            temp_files: TempFileRegistry::new(),
         }
     }
+
+    pub fn get_session(&self, session_id: &str) -> Result<Session, String> {
+        let sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions.get(session_id)
+            .cloned()
+            .ok_or_else(|| "Session not found".to_string())
+    }
 }
 
 // Tauri commands
@@ -84,37 +270,59 @@ async fn list_sessions(state: State<'_, AppState>) -> Result<Vec<Session>, Strin
 
 #[tauri::command]
 async fn load_sessions_from_store(app: AppHandle, state: State<'_, AppState>) -> Result<Vec<Session>, String> {
-    use tauri_plugin_store::StoreExt;
-    
-    let store = app.store("sessions.json").map_err(|e| e.to_string())?;
-    
-    if let Some(sessions_value) = store.get("sessions") {
-        let sessions: Vec<Session> = serde_json::from_value(sessions_value.clone())
-            .map_err(|e| e.to_string())?;
-        
-        // Load into state
-        let mut state_sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-        for session in &sessions {
-            state_sessions.insert(session.id.clone(), session.clone());
+    // Damaged entries are skipped; verify_session_store reports them
+    let (mut sessions, _) = session_store::load(&app)?;
+
+    // Proxy passwords come from the keychain. One still in the file, from
+    // before they were kept there, is moved over and the file rewritten.
+    let mut migrated = false;
+    for session in &mut sessions {
+        let Some(proxy) = session.http_proxy.as_mut() else { continue };
+        match proxy.password.as_deref() {
+            Some(password) if !password.is_empty() => match keychain::save_proxy_password(&session.id, password) {
+                Ok(()) => migrated = true,
+                Err(e) => log::warn!("{}", e),
+            },
+            _ if proxy.username.is_some() => {
+                proxy.password = keychain::load_proxy_password(&session.id).unwrap_or_else(|e| {
+                    log::warn!("{}", e);
+                    None
+                });
+            }
+            _ => {}
         }
-        
-        Ok(sessions)
-    } else {
-        Ok(vec![])
     }
+    if migrated {
+        session_store::save(&app, sessions.clone())?;
+        if let Err(e) = session_store::scrub_proxy_passwords(&app) {
+            log::warn!("{}", e);
+        }
+    }
+    
+    // Load into state
+    let mut state_sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    for session in &sessions {
+        state_sessions.insert(session.id.clone(), session.clone());
+    }
+    
+    Ok(sessions)
 }
 
-async fn save_sessions_to_store(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
-    use tauri_plugin_store::StoreExt;
-    
+pub(crate) async fn save_sessions_to_store(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
     let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
     let sessions_vec: Vec<Session> = sessions.values().cloned().collect();
     
-    let store = app.store("sessions.json").map_err(|e| e.to_string())?;
-    store.set("sessions", serde_json::to_value(sessions_vec).map_err(|e| e.to_string())?);
-    store.save().map_err(|e| e.to_string())?;
-    
-    Ok(())
+    session_store::save(&app, sessions_vec)
+}
+
+pub(crate) fn get_ssh_dir() -> Option<std::path::PathBuf> {
+    #[cfg(target_os = "windows")]
+    let home = std::env::var("USERPROFILE");
+
+    #[cfg(not(target_os = "windows"))]
+    let home = std::env::var("HOME");
+
+    home.ok().map(|home| std::path::Path::new(&home).join(".ssh"))
 }
 
 fn get_default_ssh_key_path() -> String {
@@ -163,6 +371,25 @@ async fn create_session(
         auth_method: AuthMethod::PublicKey { key_path: default_key_path },
         created_at: chrono::Utc::now().to_rfc3339(),
         last_used: None,
+        group: None,
+        tags: Vec::new(),
+        color: None,
+        store_password_in_keychain: false,
+        profiles: Vec::new(),
+        http_proxy: None,
+        env_file: None,
+        allow_loopback: false,
+        terminal_modes: Default::default(),
+        reconnect: None,
+        strict_identities: false,
+        bind_address: None,
+        keymap: Default::default(),
+        host_key_trust: None,
+        sudo_autofill: false,
+        proxy_jump: None,
+        keepalive_interval_secs: None,
+        ssh_options: Vec::new(),
+        connect_timeout_secs: None,
     };
 
     // Insert session and drop guard before await
@@ -175,15 +402,84 @@ async fn create_session(
     Ok(session)
 }
 
+// Reads the clipboard and, if it holds something like `ssh -p 2222 user@host`,
+// returns an unsaved session ready to connect
+#[tauri::command]
+async fn parse_clipboard_connection(app: AppHandle) -> Result<Option<Session>, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    // An empty or non-text clipboard simply isn't a connection string
+    let Ok(text) = app.clipboard().read_text() else {
+        return Ok(None);
+    };
+
+    let Some(parsed) = connection_string::parse_connection_string(&text) else {
+        return Ok(None);
+    };
+
+    let username = parsed.username
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_default();
+
+    let key_path = parsed.identity_file.unwrap_or_else(get_default_ssh_key_path);
+
+    Ok(Some(Session {
+        id: Uuid::new_v4().to_string(),
+        name: format!("{}@{}", username, parsed.host),
+        host: parsed.host,
+        port: parsed.port,
+        username,
+        protocol: Protocol::SSH,
+        auth_method: AuthMethod::PublicKey { key_path },
+        created_at: chrono::Utc::now().to_rfc3339(),
+        last_used: None,
+        group: None,
+        tags: Vec::new(),
+        color: None,
+        store_password_in_keychain: false,
+        profiles: Vec::new(),
+        http_proxy: None,
+        env_file: None,
+        allow_loopback: false,
+        terminal_modes: Default::default(),
+        reconnect: None,
+        strict_identities: false,
+        bind_address: None,
+        keymap: Default::default(),
+        host_key_trust: None,
+        sudo_autofill: false,
+        proxy_jump: None,
+        keepalive_interval_secs: None,
+        ssh_options: Vec::new(),
+        connect_timeout_secs: None,
+    }))
+}
+
 #[tauri::command]
 async fn update_session(
     state: State<'_, AppState>,
     app: AppHandle,
-    session: Session,
+    mut session: Session,
 ) -> Result<Session, String> {
+    for option in &session.ssh_options {
+        local_terminal::check_ssh_option(option)?;
+    }
+
     // Update session and drop guard before await
     {
         let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+        if let Some(proxy) = session.http_proxy.as_mut() {
+            match proxy.password.as_deref() {
+                Some(password) if !password.is_empty() => keychain::save_proxy_password(&session.id, password)?,
+                // The UI never gets the password, so it doesn't send it back
+                _ => {
+                    proxy.password = sessions
+                        .get(&session.id)
+                        .and_then(|old| old.http_proxy.as_ref()?.password.clone())
+                }
+            }
+        }
         sessions.insert(session.id.clone(), session.clone());
     }
 
@@ -198,9 +494,21 @@ async fn delete_session(
     #[allow(non_snake_case)] sessionId: String,
 ) -> Result<(), String> {
     // Remove session and drop guard before await
-    {
+    let removed = {
         let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-        sessions.remove(&sessionId);
+        sessions.remove(&sessionId)
+    };
+
+    // Don't leave an orphaned password behind in the OS keychain
+    if removed.as_ref().is_some_and(|s| s.store_password_in_keychain) {
+        if let Err(e) = keychain::delete_password(&sessionId) {
+            log::warn!("{}", e);
+        }
+    }
+    if removed.is_some_and(|s| s.http_proxy.is_some_and(|proxy| proxy.password.is_some())) {
+        if let Err(e) = keychain::delete_proxy_password(&sessionId) {
+            log::warn!("{}", e);
+        }
     }
 
     save_sessions_to_store(app, state).await?;
@@ -218,47 +526,118 @@ async fn connect_ssh(
         sessions.get(&sessionId).cloned().ok_or("Session not found")?
     };
 
+    let config = ssh_config_for(&session, session.default_profile(), None);
+    connect_with_config(&state, app, sessionId, config)
+}
+
+// Convert a session (as one of its profiles) to SSH config
+pub(crate) fn ssh_config_for(session: &Session, profile: SessionProfile, password: Option<String>) -> ssh_new::SshConfig {
+    // The keychain entry belongs to the session's own credentials
+    let use_keychain = session.store_password_in_keychain && profile.name == DEFAULT_PROFILE;
+
+    ssh_new::SshConfig {
+        host: session.host.clone(),
+        port: session.port,
+        username: profile.username,
+        http_proxy: session.http_proxy.clone(),
+        env_file: session.env_file.clone(),
+        allow_loopback: session.allow_loopback,
+        terminal_modes: session.terminal_modes.clone(),
+        reconnect: session.reconnect.clone(),
+        strict_identities: session.strict_identities,
+        bind_address: session.bind_address.clone(),
+        host_key_trust: session.host_key_trust,
+        sudo_autofill: session.sudo_autofill,
+        jump_hosts: crate::ssh_config::jump_hosts(session),
+        keepalive_interval_secs: session.keepalive_interval_secs,
+        connect_timeout_secs: session.connect_timeout_secs,
+        auth_method: match (profile.auth_method, password) {
+            (_, Some(password)) => ssh_new::AuthMethod::Password { password },
+            (AuthMethod::Password, None) if use_keychain => ssh_new::AuthMethod::Keychain { session_id: session.id.clone() },
+            (AuthMethod::Password, None) => ssh_new::AuthMethod::Password { password: String::new() },
+            (AuthMethod::PublicKey { key_path }, None) => ssh_new::AuthMethod::PublicKey { private_key_path: key_path },
+            (AuthMethod::Agent, None) => ssh_new::AuthMethod::Agent,
+            (AuthMethod::Pkcs11 { provider_path }, None) => ssh_new::AuthMethod::Pkcs11 { provider_path },
+        },
+    }
+}
+
+pub(crate) fn connect_with_config(
+    state: &State<'_, AppState>,
+    app: AppHandle,
+    session_id: String,
+    config: ssh_new::SshConfig,
+) -> Result<(), String> {
     // Update connection status
     {
         let mut connections = state.active_connections.lock().map_err(|e| e.to_string())?;
-        connections.insert(sessionId.clone(), ConnectionStatus::Connecting);
+        connections.insert(session_id.clone(), ConnectionStatus::Connecting);
     }
 
-    // Convert session to SSH config
-    let config = ssh_new::SshConfig {
-        host: session.host,
-        port: session.port,
-        username: session.username,
-        auth_method: match session.auth_method {
-            AuthMethod::Password => ssh_new::AuthMethod::Password { password: String::new() },
-            AuthMethod::PublicKey { key_path } => ssh_new::AuthMethod::PublicKey { private_key_path: key_path },
-            AuthMethod::Agent => ssh_new::AuthMethod::Agent,
-        },
-    };
-
-    match state.ssh_manager.connect(sessionId.clone(), config, app) {
+    match state.ssh_manager.connect(session_id.clone(), config, app.clone()) {
         Ok(_) => {
+            remote_user::prime(&app, session_id.clone());
+            app.state::<reconnect::RecentDrops>().remove(&session_id);
             let mut connections = state.active_connections.lock().map_err(|e| e.to_string())?;
-            connections.insert(sessionId, ConnectionStatus::Connected);
+            connections.insert(session_id, ConnectionStatus::Connected);
             Ok(())
         }
         Err(e) => {
             let mut connections = state.active_connections.lock().map_err(|e| e.to_string())?;
-            connections.insert(sessionId, ConnectionStatus::Error(e.to_string()));
+            connections.insert(session_id, ConnectionStatus::Error(e.to_string()));
             Err(e.to_string())
         }
     }
 }
 
+#[tauri::command]
+async fn connect_with_profile(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    session_id: String,
+    profile_name: String,
+    password: Option<String>,
+) -> Result<(), String> {
+    let session = state.get_session(&session_id)?;
+    let profile = session.profile(&profile_name)
+        .ok_or_else(|| format!("Profile not found: {}", profile_name))?;
+
+    let config = ssh_config_for(&session, profile, password);
+    connect_with_config(&state, app, session_id, config)
+}
+
+// The default profile always comes first
+#[tauri::command]
+async fn list_profiles(state: State<'_, AppState>, session_id: String) -> Result<Vec<SessionProfile>, String> {
+    let session = state.get_session(&session_id)?;
+
+    let mut profiles = vec![session.default_profile()];
+    profiles.extend(session.profiles);
+    Ok(profiles)
+}
+
 #[tauri::command]
 async fn disconnect_session(
     state: State<'_, AppState>,
+    app: AppHandle,
     #[allow(non_snake_case)] sessionId: String,
 ) -> Result<(), String> {
-    state.ssh_manager.disconnect(&sessionId).map_err(|e| e.to_string())?;
+    close_session(&state, &app, &sessionId)
+}
+
+// Disconnects a session and drops everything that belongs to the connection
+pub(crate) fn close_session(state: &AppState, app: &AppHandle, session_id: &str) -> Result<(), String> {
+    app.state::<reconnect::Reconnector>().cancel(session_id);
+    app.state::<reconnect::RecentDrops>().remove(session_id);
+    state.ssh_manager.disconnect(session_id).map_err(|e| e.to_string())?;
+    state.temp_files.cleanup_session(session_id);
+    app.state::<metrics::MetricsMonitors>().cancel(session_id);
+    if let Some(logger) = app.try_state::<session_logs::SessionLogger>() {
+        logger.stop(session_id);
+    }
     
     let mut connections = state.active_connections.lock().map_err(|e| e.to_string())?;
-    connections.insert(sessionId, ConnectionStatus::Disconnected);
+    connections.insert(session_id.to_string(), ConnectionStatus::Disconnected);
     
     Ok(())
 }
@@ -273,6 +652,25 @@ async fn send_terminal_input(
     state.ssh_manager.send_input(&sessionId, &input).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn set_log_level(app: AppHandle, level: String) -> Result<(), String> {
+    use tauri_plugin_store::StoreExt;
+
+    logging::set_level(&level)?;
+
+    // Persist so the level survives a restart (TERMNEST_LOG still overrides it)
+    let store = app.store("settings.json").map_err(|e| e.to_string())?;
+    store.set("log_level", serde_json::Value::String(level.to_lowercase()));
+    store.save().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_recent_logs(limit: Option<usize>) -> Result<Vec<String>, String> {
+    Ok(logging::recent_logs(limit))
+}
+
 #[tauri::command]
 async fn browse_ssh_key(app: AppHandle) -> Result<Option<String>, String> {
     use tauri_plugin_dialog::DialogExt;
@@ -322,6 +720,8 @@ async fn list_remote_directory(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] session_id: String,
     path: String,
+    follow_symlinks: Option<bool>,
+    options: Option<settings::FileListOptions>,
 ) -> Result<Vec<FileItem>, String> {
     // Get the session configuration and clone it to avoid lifetime issues
     let session = {
@@ -330,9 +730,15 @@ async fn list_remote_directory(
             .ok_or_else(|| "Session not found".to_string())?
             .clone()
     };
+    let options = match options {
+        Some(options) => options,
+        None => state.settings.lock().map_err(|e| e.to_string())?.file_list,
+    };
     
     // Create SFTP connection using the session's configuration
-    list_directory_sftp(&session.host, session.port, &session.username, &session.auth_method, &path).await
+    let mut files = list_directory_sftp(&session, &session.auth_method, &path, follow_symlinks.unwrap_or(false)).await?;
+    sftp::apply_list_options(&mut files, &options);
+    Ok(files)
 }
 
 #[tauri::command]
@@ -341,6 +747,8 @@ async fn list_remote_directory_with_password(
     #[allow(non_snake_case)] session_id: String,
     path: String,
     password: String,
+    follow_symlinks: Option<bool>,
+    options: Option<settings::FileListOptions>,
 ) -> Result<Vec<FileItem>, String> {
     // Get the session configuration and clone it to avoid lifetime issues
     let session = {
@@ -349,25 +757,29 @@ async fn list_remote_directory_with_password(
             .ok_or_else(|| "Session not found".to_string())?
             .clone()
     };
+    let options = match options {
+        Some(options) => options,
+        None => state.settings.lock().map_err(|e| e.to_string())?.file_list,
+    };
     
     // Use password authentication for SFTP
     let auth_method = AuthMethod::Password;
-    list_directory_sftp_with_password(&session.host, session.port, &session.username, &auth_method, &path, &password).await
+    let mut files = list_directory_sftp_with_password(&session, &auth_method, &path, &password, follow_symlinks.unwrap_or(false)).await?;
+    sftp::apply_list_options(&mut files, &options);
+    Ok(files)
 }
 
 async fn list_directory_sftp(
-    host: &str,
-    port: u16,
-    username: &str,
+    target: &Session,
     auth_method: &AuthMethod,
     path: &str,
+    follow_symlinks: bool,
 ) -> Result<Vec<FileItem>, String> {
     use ssh2::{Session};
-    use std::net::TcpStream;
     use std::path::Path;
     
     // Connect to SSH server
-    let tcp = TcpStream::connect(format!("{}:{}", host, port))
+    let (tcp, _) = crate::net::connect(&target.host, target.port, target.http_proxy.as_ref(), target.bind_address.as_deref())
         .map_err(|e| format!("Failed to connect: {}", e))?;
     
     let mut sess = Session::new()
@@ -376,6 +788,7 @@ async fn list_directory_sftp(
     sess.set_tcp_stream(tcp);
     sess.handshake()
         .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    crate::host_keys::verify(&sess, &target.host, target.port, target.host_key_trust)?;
     
     // Authenticate
     match auth_method {
@@ -383,13 +796,17 @@ async fn list_directory_sftp(
             return Err("Password authentication requires interactive input for SFTP".to_string());
         }
         AuthMethod::PublicKey { key_path } => {
-            sess.userauth_pubkey_file(username, None, Path::new(key_path), None)
+            sess.userauth_pubkey_file(&target.username, None, Path::new(key_path), None)
                 .map_err(|e| format!("Public key authentication failed: {}", e))?;
         }
         AuthMethod::Agent => {
-            sess.userauth_agent(username)
+            sess.userauth_agent(&target.username)
                 .map_err(|e| format!("Agent authentication failed: {}", e))?;
         }
+        AuthMethod::Pkcs11 { provider_path } => {
+            crate::pkcs11::authenticate(&sess, &target.username, provider_path, || None)
+                .map_err(|e| e.to_string())?;
+        }
     }
     
     if !sess.authenticated() {
@@ -398,76 +815,22 @@ async fn list_directory_sftp(
     
     // Create SFTP channel
     let sftp = sess.sftp()
-        .map_err(|e| format!("Failed to create SFTP channel: {}", e))?;
-    
-    // Read directory
-    let remote_path = std::path::Path::new(path);
-    let dir_entries = sftp.readdir(remote_path)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
-    
-    let mut files = Vec::new();
-    
-    for (path_buf, stat) in dir_entries {
-        let name = path_buf.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        
-        let full_path = path_buf.to_str().unwrap_or("").to_string();
-        let is_directory = stat.is_dir();
-        let size = if is_directory { 0 } else { stat.size.unwrap_or(0) };
-        
-        // Format modification time
-        let modified = if let Some(mtime) = stat.mtime {
-            let datetime = chrono::DateTime::from_timestamp(mtime as i64, 0)
-                .unwrap_or_else(|| chrono::Utc::now());
-            datetime.format("%Y-%m-%d %H:%M").to_string()
-        } else {
-            "unknown".to_string()
-        };
-        
-        files.push(FileItem {
-            name,
-            path: full_path,
-            size,
-            is_directory,
-            modified,
-        });
-    }
-    
-    // Add parent directory entry if we're not at root
-    if path != "/" && path != "" {
-        let parent_path = std::path::Path::new(path)
-            .parent()
-            .and_then(|p| p.to_str())
-            .unwrap_or("/")
-            .to_string();
-        
-        files.insert(0, FileItem {
-            name: "..".to_string(),
-            path: parent_path,
-            size: 0,
-            is_directory: true,
-            modified: "".to_string(),
-        });
-    }
+        .map_err(|e| describe_channel_error(e, "Failed to create SFTP channel"))?;
     
-    Ok(files)
+    crate::sftp::read_directory(&sftp, path, follow_symlinks)
 }
 
 async fn list_directory_sftp_with_password(
-    host: &str,
-    port: u16,
-    username: &str,
+    target: &Session,
     _auth_method: &AuthMethod,
     path: &str,
     password: &str,
+    follow_symlinks: bool,
 ) -> Result<Vec<FileItem>, String> {
     use ssh2::Session;
-    use std::net::TcpStream;
     
     // Connect to SSH server
-    let tcp = TcpStream::connect(format!("{}:{}", host, port))
+    let (tcp, _) = crate::net::connect(&target.host, target.port, target.http_proxy.as_ref(), target.bind_address.as_deref())
         .map_err(|e| format!("Failed to connect: {}", e))?;
     
     let mut sess = Session::new()
@@ -476,9 +839,10 @@ async fn list_directory_sftp_with_password(
     sess.set_tcp_stream(tcp);
     sess.handshake()
         .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    crate::host_keys::verify(&sess, &target.host, target.port, target.host_key_trust)?;
     
     // Authenticate with password
-    sess.userauth_password(username, password)
+    sess.userauth_password(&target.username, password)
         .map_err(|e| format!("Password authentication failed: {}", e))?;
     
     if !sess.authenticated() {
@@ -487,75 +851,21 @@ async fn list_directory_sftp_with_password(
     
     // Create SFTP channel
     let sftp = sess.sftp()
-        .map_err(|e| format!("Failed to create SFTP channel: {}", e))?;
-    
-    // Read directory
-    let remote_path = std::path::Path::new(path);
-    let dir_entries = sftp.readdir(remote_path)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
-    
-    let mut files = Vec::new();
-    
-    for (path_buf, stat) in dir_entries {
-        let name = path_buf.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-        
-        let full_path = path_buf.to_str().unwrap_or("").to_string();
-        let is_directory = stat.is_dir();
-        let size = if is_directory { 0 } else { stat.size.unwrap_or(0) };
-        
-        // Format modification time
-        let modified = if let Some(mtime) = stat.mtime {
-            let datetime = chrono::DateTime::from_timestamp(mtime as i64, 0)
-                .unwrap_or_else(|| chrono::Utc::now());
-            datetime.format("%Y-%m-%d %H:%M").to_string()
-        } else {
-            "unknown".to_string()
-        };
-        
-        files.push(FileItem {
-            name,
-            path: full_path,
-            size,
-            is_directory,
-            modified,
-        });
-    }
-    
-    // Add parent directory entry if we're not at root
-    if path != "/" && path != "" {
-        let parent_path = std::path::Path::new(path)
-            .parent()
-            .and_then(|p| p.to_str())
-            .unwrap_or("/")
-            .to_string();
-        
-        files.insert(0, FileItem {
-            name: "..".to_string(),
-            path: parent_path,
-            size: 0,
-            is_directory: true,
-            modified: "".to_string(),
-        });
-    }
+        .map_err(|e| describe_channel_error(e, "Failed to create SFTP channel"))?;
     
-    Ok(files)
+    crate::sftp::read_directory(&sftp, path, follow_symlinks)
 }
 
 async fn download_file_sftp_with_password(
-    host: &str,
-    port: u16,
-    username: &str,
+    target: &Session,
     remote_path: &str,
     local_path: &str,
     password: &str,
+    buffer_size: usize,
 ) -> Result<String, String> {
-    use std::net::TcpStream;
     use ssh2::Session;
     
-    let tcp = TcpStream::connect(format!("{}:{}", host, port))
+    let (tcp, _) = crate::net::connect(&target.host, target.port, target.http_proxy.as_ref(), target.bind_address.as_deref())
         .map_err(|e| format!("Failed to connect: {}", e))?;
     
     let mut sess = Session::new()
@@ -564,8 +874,9 @@ async fn download_file_sftp_with_password(
     sess.set_tcp_stream(tcp);
     sess.handshake()
         .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    crate::host_keys::verify(&sess, &target.host, target.port, target.host_key_trust)?;
     
-    sess.userauth_password(username, password)
+    sess.userauth_password(&target.username, password)
         .map_err(|e| format!("SSH authentication failed: {}", e))?;
     
     if !sess.authenticated() {
@@ -573,7 +884,7 @@ async fn download_file_sftp_with_password(
     }
     
     let sftp = sess.sftp()
-        .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        .map_err(|e| describe_channel_error(e, "Failed to create SFTP session"))?;
     
     let mut remote_file = sftp.open(std::path::Path::new(remote_path))
         .map_err(|e| format!("Failed to open remote file: {}", e))?;
@@ -581,23 +892,20 @@ async fn download_file_sftp_with_password(
     let mut local_file = std::fs::File::create(local_path)
         .map_err(|e| format!("Failed to create local file: {}", e))?;
     
-    std::io::copy(&mut remote_file, &mut local_file)
+    crate::sftp::copy_with_buffer(&mut remote_file, &mut local_file, buffer_size)
         .map_err(|e| format!("Failed to copy file: {}", e))?;
     
     Ok(format!("File downloaded successfully to: {}", local_path))
 }
 
 async fn delete_file_sftp_with_password(
-    host: &str,
-    port: u16,
-    username: &str,
+    target: &Session,
     remote_path: &str,
     password: &str,
 ) -> Result<String, String> {
-    use std::net::TcpStream;
     use ssh2::Session;
     
-    let tcp = TcpStream::connect(format!("{}:{}", host, port))
+    let (tcp, _) = crate::net::connect(&target.host, target.port, target.http_proxy.as_ref(), target.bind_address.as_deref())
         .map_err(|e| format!("Failed to connect: {}", e))?;
     
     let mut sess = Session::new()
@@ -606,8 +914,9 @@ async fn delete_file_sftp_with_password(
     sess.set_tcp_stream(tcp);
     sess.handshake()
         .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    crate::host_keys::verify(&sess, &target.host, target.port, target.host_key_trust)?;
     
-    sess.userauth_password(username, password)
+    sess.userauth_password(&target.username, password)
         .map_err(|e| format!("SSH authentication failed: {}", e))?;
     
     if !sess.authenticated() {
@@ -615,7 +924,7 @@ async fn delete_file_sftp_with_password(
     }
     
     let sftp = sess.sftp()
-        .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+        .map_err(|e| describe_channel_error(e, "Failed to create SFTP session"))?;
     
     sftp.unlink(std::path::Path::new(remote_path))
         .map_err(|e| format!("Failed to delete file: {}", e))?;
@@ -625,6 +934,7 @@ async fn delete_file_sftp_with_password(
 
 #[tauri::command]
 async fn download_remote_file(
+    app: AppHandle,
     state: State<'_, AppState>,
     #[allow(non_snake_case)] session_id: String,
     remote_path: String,
@@ -638,11 +948,22 @@ async fn download_remote_file(
             .clone()
     };
     
-    download_file_sftp(&session.host, session.port, &session.username, &session.auth_method, &remote_path, &local_path).await
+    let buffer_size = state.settings.lock().map_err(|e| e.to_string())?.sftp_buffer_size;
+    let started = std::time::Instant::now();
+    let message = download_file_sftp(&session, &session.auth_method, &remote_path, &local_path, buffer_size).await?;
+    record_download(&app, &session, &remote_path, &local_path, started);
+    Ok(message)
+}
+
+fn record_download(app: &AppHandle, session: &Session, remote_path: &str, local_path: &str, started: std::time::Instant) {
+    let bytes = std::fs::metadata(local_path).map(|metadata| metadata.len()).unwrap_or(0);
+    let record = transfer_history::TransferRecord::new(session, transfer_history::TransferDirection::Download, bytes, started.elapsed());
+    transfer_history::record(app, record.with_paths(local_path, remote_path));
 }
 
 #[tauri::command]
 async fn download_remote_file_with_password(
+    app: AppHandle,
     state: State<'_, AppState>,
     #[allow(non_snake_case)] session_id: String,
     remote_path: String,
@@ -657,25 +978,26 @@ async fn download_remote_file_with_password(
             .clone()
     };
     
-    download_file_sftp_with_password(&session.host, session.port, &session.username, &remote_path, &local_path, &password).await
+    let buffer_size = state.settings.lock().map_err(|e| e.to_string())?.sftp_buffer_size;
+    let started = std::time::Instant::now();
+    let message = download_file_sftp_with_password(&session, &remote_path, &local_path, &password, buffer_size).await?;
+    record_download(&app, &session, &remote_path, &local_path, started);
+    Ok(message)
 }
 
 async fn download_file_sftp(
-    host: &str,
-    port: u16,
-    username: &str,
+    target: &Session,
     auth_method: &AuthMethod,
     remote_path: &str,
     local_path: &str,
+    buffer_size: usize,
 ) -> Result<String, String> {
     use ssh2::Session;
     use std::fs::File;
-    use std::io::copy;
-    use std::net::TcpStream;
     use std::path::Path;
     
     // Connect to SSH server
-    let tcp = TcpStream::connect(format!("{}:{}", host, port))
+    let (tcp, _) = crate::net::connect(&target.host, target.port, target.http_proxy.as_ref(), target.bind_address.as_deref())
         .map_err(|e| format!("Failed to connect: {}", e))?;
     
     let mut sess = Session::new()
@@ -684,6 +1006,7 @@ async fn download_file_sftp(
     sess.set_tcp_stream(tcp);
     sess.handshake()
         .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    crate::host_keys::verify(&sess, &target.host, target.port, target.host_key_trust)?;
     
     // Authenticate
     match auth_method {
@@ -691,13 +1014,17 @@ async fn download_file_sftp(
             return Err("Password authentication requires interactive input for SFTP".to_string());
         }
         AuthMethod::PublicKey { key_path } => {
-            sess.userauth_pubkey_file(username, None, Path::new(key_path), None)
+            sess.userauth_pubkey_file(&target.username, None, Path::new(key_path), None)
                 .map_err(|e| format!("Public key authentication failed: {}", e))?;
         }
         AuthMethod::Agent => {
-            sess.userauth_agent(username)
+            sess.userauth_agent(&target.username)
                 .map_err(|e| format!("Agent authentication failed: {}", e))?;
         }
+        AuthMethod::Pkcs11 { provider_path } => {
+            crate::pkcs11::authenticate(&sess, &target.username, provider_path, || None)
+                .map_err(|e| e.to_string())?;
+        }
     }
     
     if !sess.authenticated() {
@@ -706,7 +1033,7 @@ async fn download_file_sftp(
     
     // Create SFTP channel
     let sftp = sess.sftp()
-        .map_err(|e| format!("Failed to create SFTP channel: {}", e))?;
+        .map_err(|e| describe_channel_error(e, "Failed to create SFTP channel"))?;
     
     // Open remote file
     let mut remote_file = sftp.open(Path::new(remote_path))
@@ -717,7 +1044,7 @@ async fn download_file_sftp(
         .map_err(|e| format!("Failed to create local file: {}", e))?;
     
     // Copy data
-    let bytes_copied = copy(&mut remote_file, &mut local_file)
+    let bytes_copied = crate::sftp::copy_with_buffer(&mut remote_file, &mut local_file, buffer_size)
         .map_err(|e| format!("Failed to copy data: {}", e))?;
     
     Ok(format!("Downloaded {} bytes to {}", bytes_copied, local_path))
@@ -737,7 +1064,7 @@ async fn delete_remote_file(
             .clone()
     };
     
-    delete_file_sftp(&session.host, session.port, &session.username, &session.auth_method, &remote_path).await
+    delete_file_sftp(&session, &session.auth_method, &remote_path).await
 }
 
 #[tauri::command]
@@ -755,22 +1082,19 @@ async fn delete_remote_file_with_password(
             .clone()
     };
     
-    delete_file_sftp_with_password(&session.host, session.port, &session.username, &remote_path, &password).await
+    delete_file_sftp_with_password(&session, &remote_path, &password).await
 }
 
 async fn delete_file_sftp(
-    host: &str,
-    port: u16,
-    username: &str,
+    target: &Session,
     auth_method: &AuthMethod,
     remote_path: &str,
 ) -> Result<String, String> {
     use ssh2::Session;
-    use std::net::TcpStream;
     use std::path::Path;
     
     // Connect to SSH server
-    let tcp = TcpStream::connect(format!("{}:{}", host, port))
+    let (tcp, _) = crate::net::connect(&target.host, target.port, target.http_proxy.as_ref(), target.bind_address.as_deref())
         .map_err(|e| format!("Failed to connect: {}", e))?;
     
     let mut sess = Session::new()
@@ -779,6 +1103,7 @@ async fn delete_file_sftp(
     sess.set_tcp_stream(tcp);
     sess.handshake()
         .map_err(|e| format!("SSH handshake failed: {}", e))?;
+    crate::host_keys::verify(&sess, &target.host, target.port, target.host_key_trust)?;
     
     // Authenticate
     match auth_method {
@@ -786,13 +1111,17 @@ async fn delete_file_sftp(
             return Err("Password authentication requires interactive input for SFTP".to_string());
         }
         AuthMethod::PublicKey { key_path } => {
-            sess.userauth_pubkey_file(username, None, Path::new(key_path), None)
+            sess.userauth_pubkey_file(&target.username, None, Path::new(key_path), None)
                 .map_err(|e| format!("Public key authentication failed: {}", e))?;
         }
         AuthMethod::Agent => {
-            sess.userauth_agent(username)
+            sess.userauth_agent(&target.username)
                 .map_err(|e| format!("Agent authentication failed: {}", e))?;
         }
+        AuthMethod::Pkcs11 { provider_path } => {
+            crate::pkcs11::authenticate(&sess, &target.username, provider_path, || None)
+                .map_err(|e| e.to_string())?;
+        }
     }
     
     if !sess.authenticated() {
@@ -801,7 +1130,7 @@ async fn delete_file_sftp(
     
     // Create SFTP channel
     let sftp = sess.sftp()
-        .map_err(|e| format!("Failed to create SFTP channel: {}", e))?;
+        .map_err(|e| describe_channel_error(e, "Failed to create SFTP channel"))?;
     
     // Check if it's a directory or file
     let stat = sftp.stat(Path::new(remote_path))
@@ -822,23 +1151,91 @@ async fn delete_file_sftp(
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // ssh-keygen runs the app as its askpass program during key conversion
+    if key_convert::answer_askpass() {
+        return;
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .setup(|app| {
+            use tauri_plugin_store::StoreExt;
+
+            let stored_level = app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get("log_level"))
+                .and_then(|value| value.as_str().map(str::to_string));
+            logging::init(stored_level.as_deref());
+
             let app_handle = app.handle().clone();
+            // Share one manager between AppState and the ssh_* commands so both
+            // see the same live connections
             let ssh_manager = std::sync::Arc::new(ssh_new::SshManager::new());
             
-            app.manage(AppState::new(app_handle));
+            app.manage(AppState::new(app_handle.clone(), ssh_manager.clone()));
+            sweeper::start(app_handle.clone(), ssh_manager.clone());
+            sweeper::watch_for_resume(app_handle.clone(), ssh_manager.clone());
+            host_keys::init(app_handle);
             app.manage(ssh_manager);
+            app.manage(local_terminal::LocalTerminalManager::new());
+            app.manage(macros::MacroRecorder::new());
+            app.manage(transfers::ConflictResolver::new());
+            app.manage(keyboard_interactive::PromptResponder::new());
+            app.manage(transfer_history::TransferHistory::new());
+            app.manage(session_logs::SessionLogger::new());
+            app.manage(reconnect::Reconnector::new());
+            app.manage(reconnect::RecentDrops::new());
+            app.manage(remote_user::RemoteUserCache::new());
+            app.manage(remote_platform::RemotePlatformCache::new());
+            app.manage(metrics::MetricsMonitors::new());
             Ok(())
         })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Focused(false) = event {
+                let app_handle = window.app_handle();
+                let enabled = app_handle.try_state::<AppState>()
+                    .and_then(|state| state.settings.lock().ok().map(|s| s.clear_secrets_when_unfocused))
+                    .unwrap_or(false);
+                if enabled {
+                    secrets::clear(app_handle);
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
+            secrets::clear_secret_cache,
+            algorithms::list_supported_algorithms,
+            config_backup::export_config,
+            config_backup::import_config,
+            favorites::add_remote_favorite,
+            favorites::list_remote_favorites,
+            favorites::remove_remote_favorite,
+            favorites::open_favorite,
+            groups::connect_group,
+            groups::disconnect_group,
+            keymap::get_keymap,
+            keymap::send_key,
+            session_logs::start_session_log,
+            session_logs::stop_session_log,
+            session_logs::list_session_logs,
+            session_logs::open_session_log,
+            macros::start_macro_record,
+            macros::stop_macro_record,
+            macros::list_macros,
+            macros::delete_macro,
+            macros::play_macro,
+            transfers::upload_files,
+            transfers::download_files,
+            transfers::resolve_transfer_conflict,
             list_sessions,
             load_sessions_from_store,
+            session_store::verify_session_store,
+            session_store::restore_sessions_backup,
             create_session,
             update_session,
+            parse_clipboard_connection,
             delete_session,
             connect_ssh,
             disconnect_session,
@@ -850,13 +1247,122 @@ pub fn run() {
             delete_remote_file,
             delete_remote_file_with_password,
             browse_ssh_key,
+            set_log_level,
+            settings::get_app_settings,
+            settings::update_app_settings,
+            settings::get_file_list_options,
+            settings::set_file_list_options,
+            known_hosts::add_known_host,
+            host_keys::get_host_key_trust_mode,
+            host_keys::set_host_key_trust_mode,
+            host_keys::list_pinned_host_keys,
+            host_keys::forget_pinned_host_key,
+            host_keys::accept_host_key,
+            keys::verify_key_access,
+            templates::save_template,
+            templates::list_templates,
+            templates::delete_template,
+            templates::create_session_from_template,
+            get_recent_logs,
+            sftp::open_remote_with_default_app,
+            exec::ssh_exec,
+            pkcs11::list_pkcs11_keys,
+            pkcs11::add_pkcs11_to_agent,
+            temp_files::secure_delete_temp,
+            runbook::run_script,
+            exec::exec_to_file,
+            paste::check_paste,
+            paste::send_paste,
+            processes::list_remote_processes,
+            processes::kill_remote_process,
+            logins::list_remote_logins,
+            logins::terminate_remote_login,
+            sftp_glob::delete_remote_glob,
+            sftp_glob::download_remote_glob,
+            xattrs::get_xattrs,
+            xattrs::set_xattr,
+            xattrs::set_selinux_context,
+            keychain::save_password_to_keychain,
+            keychain::delete_password_from_keychain,
+            connect_with_profile,
+            list_profiles,
             ssh_new::ssh_connect,
             ssh_new::ssh_connect_with_password,
             ssh_new::ssh_send_input,
             ssh_new::ssh_resize_terminal,
+            ssh_new::widen_terminal,
+            ssh_new::restore_terminal_width,
             ssh_new::ssh_disconnect,
-            ssh_new::ssh_list_sessions
+            ssh_new::restart_session,
+            ssh_new::send_eof,
+            ssh_new::answer_sudo_prompt,
+            local_terminal::connect_via_system_ssh,
+            keys::analyze_key,
+            keys::verify_key_fingerprint,
+            key_convert::convert_key,
+            ssh_new::probe_channel,
+            ssh_new::start_latency_monitor,
+            ssh_new::stop_latency_monitor,
+            ssh_new::get_latency_history,
+            net::check_loopback_target,
+            net::check_bind_address,
+            port_scan::scan_host_ports,
+            keyboard_interactive::respond_keyboard_interactive,
+            transfer_history::get_transfer_history,
+            transfer_history::clear_transfer_history,
+            session_report::generate_session_report,
+            ssh_new::pause_output,
+            reconnect::cancel_reconnect,
+            reconnect::list_recent_drops,
+            reconnect::reconnect_drop,
+            remote_user::get_remote_user_info,
+            remote_platform::detect_remote_platform,
+            remote_platform::remote_service_command,
+            metrics::get_server_metrics,
+            metrics::start_metrics_monitor,
+            metrics::stop_metrics_monitor,
+            ssh_new::resume_output,
+            ssh_new::open_sftp,
+            ssh_new::close_sftp,
+            ssh_new::list_session_channels,
+            ssh_new::close_channel,
+            shell_cwd::get_suggested_sftp_start,
+            heredoc::send_text_as_file,
+            sftp::create_hard_link,
+            sftp::get_directory_details,
+            sftp::get_transfer_parameters,
+            sftp::realpath,
+            diff::diff_files,
+            text_check::analyze_text_file,
+            thumbnails::get_remote_thumbnail,
+            sftp::get_remote_free_space,
+            sftp::check_writable,
+            ssh_config::preview_ssh_config_import,
+            ssh_config::import_ssh_config,
+            ssh_config::resolve_host_alias,
+            sftp::upload_file_compressed,
+            sftp::download_file_compressed,
+            scp::download_file_with_method,
+            scp::upload_file_with_method,
+            scp::benchmark_transfer_methods,
+            ls_listing::list_remote_directory_via_shell,
+            ssh_new::ssh_list_sessions,
+            ssh_new::list_active_connections,
+            ssh_new::get_multiplexing_info,
+            ssh_new::session_command,
+            ssh_new::start_local_forward,
+            ssh_new::stop_local_forward,
+            ssh_new::start_socks_proxy,
+            ssh_new::stop_socks_proxy
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            // Downloaded copies of remote files shouldn't outlive the app
+            if let tauri::RunEvent::Exit = event {
+                if let Some(state) = app_handle.try_state::<AppState>() {
+                    state.temp_files.cleanup_all();
+                }
+            }
+        });
 }
@@ -0,0 +1,338 @@
+use log::{debug, error, info};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, State};
+
+use crate::error::SshError;
+use crate::ssh_new::{emit_connection_status, emit_terminal_data};
+use crate::{AppState, AuthMethod};
+
+// A local process running in a pty, wired to the same terminal events as an
+// SSH session so the terminal UI doesn't care which one it talks to
+pub struct LocalTerminal {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    reader_handle: Option<thread::JoinHandle<()>>,
+}
+
+fn pty_size(cols: u32, rows: u32) -> PtySize {
+    PtySize { rows: rows as u16, cols: cols as u16, pixel_width: 0, pixel_height: 0 }
+}
+
+impl LocalTerminal {
+    pub fn spawn(
+        session_id: &str,
+        command: CommandBuilder,
+        (cols, rows): (u32, u32),
+        app_handle: AppHandle,
+    ) -> Result<Self, SshError> {
+        let pair = native_pty_system()
+            .openpty(pty_size(cols, rows))
+            .map_err(|e| SshError::Other(format!("Failed to open pty: {}", e)))?;
+
+        let child = pair.slave
+            .spawn_command(command)
+            .map_err(|e| SshError::Other(format!("Failed to start process: {}", e)))?;
+        // The child holds its own copy; keeping ours would stop EOF from arriving
+        drop(pair.slave);
+
+        let mut reader = pair.master
+            .try_clone_reader()
+            .map_err(|e| SshError::Other(e.to_string()))?;
+        let writer = pair.master
+            .take_writer()
+            .map_err(|e| SshError::Other(e.to_string()))?;
+
+        let session_id = session_id.to_string();
+        let reader_handle = thread::spawn(move || {
+            let mut buffer = [0u8; 4096];
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let data = String::from_utf8_lossy(&buffer[..n]).to_string();
+                        emit_terminal_data(&app_handle, &session_id, data);
+                    }
+                    Err(e) => {
+                        // Linux reports EIO once the child side of the pty closes
+                        debug!("Local terminal {} read ended: {}", session_id, e);
+                        break;
+                    }
+                }
+            }
+
+            emit_connection_status(&app_handle, &session_id, "disconnected", "Process exited");
+        });
+
+        Ok(LocalTerminal { master: pair.master, writer, child, reader_handle: Some(reader_handle) })
+    }
+
+    pub fn write(&mut self, input: &str) -> Result<(), SshError> {
+        self.writer.write_all(input.as_bytes())?;
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn resize(&self, cols: u32, rows: u32) -> Result<(), SshError> {
+        self.master
+            .resize(pty_size(cols, rows))
+            .map_err(|e| SshError::Other(format!("Resize failed: {}", e)))
+    }
+
+    pub fn close(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            if let Err(e) = self.child.kill() {
+                error!("Failed to kill local process: {}", e);
+            }
+        }
+        let _ = self.child.wait();
+
+        if let Some(handle) = self.reader_handle.take() {
+            if let Err(e) = handle.join() {
+                error!("Local terminal reader join error: {:?}", e);
+            }
+        }
+    }
+}
+
+impl Drop for LocalTerminal {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+pub struct LocalTerminalManager {
+    terminals: Mutex<HashMap<String, LocalTerminal>>,
+}
+
+impl LocalTerminalManager {
+    pub fn new() -> Self {
+        LocalTerminalManager {
+            terminals: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn insert(&self, session_id: String, terminal: LocalTerminal) {
+        let previous = self.terminals.lock().unwrap().insert(session_id, terminal);
+        // Close a replaced terminal outside of the lock
+        drop(previous);
+    }
+
+    pub fn contains(&self, session_id: &str) -> bool {
+        self.terminals.lock().unwrap().contains_key(session_id)
+    }
+
+    pub fn write(&self, session_id: &str, input: &str) -> Result<(), SshError> {
+        let mut terminals = self.terminals.lock().unwrap();
+        let terminal = terminals.get_mut(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        terminal.write(input)
+    }
+
+    pub fn resize(&self, session_id: &str, cols: u32, rows: u32) -> Result<(), SshError> {
+        let terminals = self.terminals.lock().unwrap();
+        let terminal = terminals.get(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        terminal.resize(cols, rows)
+    }
+
+    pub fn close(&self, session_id: &str) -> Result<(), SshError> {
+        let terminal = self.terminals.lock().unwrap().remove(session_id);
+        match terminal {
+            Some(mut terminal) => {
+                terminal.close();
+                Ok(())
+            }
+            None => Err(SshError::SessionNotFound(session_id.to_string())),
+        }
+    }
+}
+
+pub(crate) fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let binary = if cfg!(windows) { format!("{}.exe", binary) } else { binary.to_string() };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&binary))
+        .find(|path| path.is_file())
+}
+
+// The ssh options a session may set, lowercased. Sessions can be restored
+// from a backup someone else made, so anything that runs a local command,
+// loads a library (PKCS11Provider, SecurityKeyProvider), pulls in another
+// config file, hands the server local resources (agent, X11, forwards) or
+// changes which host key is trusted (StrictHostKeyChecking, known hosts files,
+// HostKeyAlias, VerifyHostKeyDNS) is left out; those belong in ~/.ssh/config.
+const ALLOWED_OPTIONS: [&str; 52] = [
+    "addkeystoagent",
+    "addressfamily",
+    "batchmode",
+    "bindaddress",
+    "bindinterface",
+    "canonicaldomains",
+    "canonicalizefallbacklocal",
+    "canonicalizehostname",
+    "canonicalizemaxdots",
+    "canonicalizepermittedcnames",
+    "casignaturealgorithms",
+    "certificatefile",
+    "checkhostip",
+    "ciphers",
+    "compression",
+    "connectionattempts",
+    "connecttimeout",
+    "escapechar",
+    "fingerprinthash",
+    "gssapiauthentication",
+    "hashknownhosts",
+    "hostbasedacceptedalgorithms",
+    "hostbasedauthentication",
+    "hostkeyalgorithms",
+    "hostname",
+    "identitiesonly",
+    "identityfile",
+    "ipqos",
+    "kbdinteractiveauthentication",
+    "kbdinteractivedevices",
+    "kexalgorithms",
+    "loglevel",
+    "macs",
+    "nohostauthenticationforlocalhost",
+    "numberofpasswordprompts",
+    "obscurekeystroketiming",
+    "passwordauthentication",
+    "port",
+    "preferredauthentications",
+    "pubkeyacceptedalgorithms",
+    "pubkeyacceptedkeytypes",
+    "pubkeyauthentication",
+    "rekeylimit",
+    "requesttty",
+    "requiredrsasize",
+    "sendenv",
+    "serveralivecountmax",
+    "serveraliveinterval",
+    "setenv",
+    "tcpkeepalive",
+    "updatehostkeys",
+    "user",
+];
+
+// A session's extra ssh option must be a single `Key=Value` with a key from
+// ALLOWED_OPTIONS, so it can't smuggle in another argument or run anything
+pub fn check_ssh_option(option: &str) -> Result<(), String> {
+    let (key, value) = option
+        .split_once('=')
+        .ok_or_else(|| format!("SSH option `{}` is not in the form Key=Value", option))?;
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("`{}` is not an ssh option name", key));
+    }
+    if value.trim().is_empty() || value.chars().any(char::is_control) {
+        return Err(format!("SSH option {} has an empty or invalid value", key));
+    }
+    if !ALLOWED_OPTIONS.contains(&key.to_ascii_lowercase().as_str()) {
+        return Err(format!("{} can't be set on a session; set it in ~/.ssh/config instead", key));
+    }
+    Ok(())
+}
+
+// Escape hatch for setups we can't handle natively yet (FIDO2 keys, GSSAPI,
+// ProxyCommand...): run the OS ssh client, which also honors ~/.ssh/config
+#[tauri::command]
+pub async fn connect_via_system_ssh(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    terminals: State<'_, LocalTerminalManager>,
+    session_id: String,
+    cols: Option<u32>,
+    rows: Option<u32>,
+) -> Result<(), SshError> {
+    let session = state.get_session(&session_id)
+        .map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+
+    let ssh = find_in_path("ssh")
+        .ok_or_else(|| SshError::Other("No ssh client found on PATH".to_string()))?;
+
+    let mut command = CommandBuilder::new(&ssh);
+    command.args(["-p", &session.port.to_string(), "-l", &session.username]);
+    match &session.auth_method {
+        AuthMethod::PublicKey { key_path } => command.args(["-i", key_path]),
+        // ssh loads the module itself and asks for the PIN in the terminal
+        AuthMethod::Pkcs11 { provider_path } => command.args(["-I", provider_path]),
+        _ => {}
+    }
+    for option in &session.ssh_options {
+        check_ssh_option(option).map_err(SshError::Other)?;
+        command.args(["-o", option]);
+    }
+    // A host from an imported backup or ssh_config could start with `-`
+    command.args(["--", &session.host]);
+    command.env("TERM", "xterm-256color");
+
+    info!("Starting {} for session {}", ssh.display(), session_id);
+    let terminal = LocalTerminal::spawn(&session_id, command, (cols.unwrap_or(80), rows.unwrap_or(24)), app_handle.clone())?;
+    terminals.insert(session_id.clone(), terminal);
+
+    emit_connection_status(&app_handle, &session_id, "connected", "Connected via system ssh");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_allowed_options_in_any_case() {
+        assert!(check_ssh_option("PubkeyAcceptedAlgorithms=+ssh-rsa").is_ok());
+        assert!(check_ssh_option("macs=hmac-sha2-256").is_ok());
+        assert!(check_ssh_option("ServerAliveInterval=30").is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_options() {
+        assert!(check_ssh_option("Ciphers aes128-ctr").is_err());
+        assert!(check_ssh_option("=aes128-ctr").is_err());
+        assert!(check_ssh_option("-oCiphers=aes128-ctr").is_err());
+        assert!(check_ssh_option("Ciphers=").is_err());
+        assert!(check_ssh_option("Ciphers=  ").is_err());
+        assert!(check_ssh_option("Ciphers=aes128-ctr\nProxyCommand=sh").is_err());
+    }
+
+    #[test]
+    fn rejects_options_that_run_local_code() {
+        for option in [
+            "ProxyCommand=nc %h %p",
+            "LocalCommand=touch /tmp/x",
+            "PermitLocalCommand=yes",
+            "KnownHostsCommand=/bin/true",
+            "PKCS11Provider=/tmp/evil.so",
+            "SecurityKeyProvider=/tmp/evil.so",
+            "Include=/tmp/other_config",
+            "proxyjump=bastion",
+        ] {
+            assert!(check_ssh_option(option).is_err(), "{} was accepted", option);
+        }
+    }
+
+    #[test]
+    fn rejects_options_that_weaken_host_key_checking() {
+        for option in [
+            "StrictHostKeyChecking=no",
+            "stricthostkeychecking=accept-new",
+            "UserKnownHostsFile=/dev/null",
+            "GlobalKnownHostsFile=/tmp/known_hosts",
+            "HostKeyAlias=trusted.example.com",
+            "VerifyHostKeyDNS=yes",
+        ] {
+            assert!(check_ssh_option(option).is_err(), "{} was accepted", option);
+        }
+    }
+
+    #[test]
+    fn allowlist_is_lowercase_and_sorted() {
+        assert!(ALLOWED_OPTIONS.iter().all(|option| *option == option.to_ascii_lowercase()));
+        assert!(ALLOWED_OPTIONS.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -0,0 +1,89 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+// Number of formatted log lines kept in memory for bug reports
+const RECENT_LOG_CAPACITY: usize = 1000;
+
+// Environment variable that overrides the configured log level
+const LOG_LEVEL_ENV: &str = "TERMNEST_LOG";
+
+struct TermNestLogger {
+    recent: Mutex<VecDeque<String>>,
+}
+
+static LOGGER: TermNestLogger = TermNestLogger {
+    recent: Mutex::new(VecDeque::new()),
+};
+
+impl Log for TermNestLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        // Only our own crate's messages; dependencies are far too chatty at debug
+        if !self.enabled(record.metadata()) || !record.target().starts_with("termnest") {
+            return;
+        }
+
+        let line = format!(
+            "{} {:<5} [{}] {}",
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        if record.level() <= Level::Warn {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == RECENT_LOG_CAPACITY {
+                recent.pop_front();
+            }
+            recent.push_back(line);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn default_level() -> LevelFilter {
+    if cfg!(debug_assertions) {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Warn
+    }
+}
+
+// Installs the logger once. The env var wins over the stored setting so a user
+// can always turn on verbose logs for a single run.
+pub fn init(stored_level: Option<&str>) {
+    let level = std::env::var(LOG_LEVEL_ENV)
+        .ok()
+        .and_then(|value| LevelFilter::from_str(&value).ok())
+        .or_else(|| stored_level.and_then(|value| LevelFilter::from_str(value).ok()))
+        .unwrap_or_else(default_level);
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+pub fn set_level(level: &str) -> Result<(), String> {
+    let level = LevelFilter::from_str(level)
+        .map_err(|_| format!("Invalid log level: {}", level))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+pub fn recent_logs(limit: Option<usize>) -> Vec<String> {
+    let recent = LOGGER.recent.lock().unwrap();
+    let skip = limit.map(|limit| recent.len().saturating_sub(limit)).unwrap_or(0);
+    recent.iter().skip(skip).cloned().collect()
+}
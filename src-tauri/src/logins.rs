@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::exec::run_command;
+use crate::processes::{normalize_signal, KillOutcome};
+use crate::remote::open_session;
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteLogin {
+    pub user: String,
+    pub tty: String,
+    // Remote host, or None for a local login
+    pub from: Option<String>,
+    pub login_time: String,
+    // Not known when only `who` was available
+    pub idle: Option<String>,
+    pub what: Option<String>,
+}
+
+// Plain `w` rather than `w -h`: the header says which columns this
+// implementation prints (procps adds JCPU/PCPU and may leave out FROM, BSD
+// has neither). `who` is the fallback on systems without w.
+const LOGINS_COMMAND: &str = "w 2>/dev/null || who";
+
+fn optional(value: &str) -> Option<String> {
+    (!value.is_empty() && value != "-").then(|| value.to_string())
+}
+
+// `w` output: an uptime line, a header, then one row per login. Every column
+// but the last (WHAT) is a single word.
+fn parse_w_output(output: &str) -> Option<Vec<RemoteLogin>> {
+    let mut lines = output.lines().skip_while(|line| !line.trim_start().starts_with("USER"));
+    let columns: Vec<&str> = lines.next()?.split_whitespace().collect();
+    if columns.last() != Some(&"WHAT") {
+        return None;
+    }
+    let column = |name: &str| columns.iter().position(|c| *c == name);
+    let (tty, from, login, idle) = (column("TTY")?, column("FROM"), column("LOGIN@")?, column("IDLE")?);
+
+    let logins = lines
+        .filter_map(|line| {
+            let mut fields: Vec<&str> = Vec::with_capacity(columns.len());
+            let mut rest = line.trim_start();
+            while fields.len() < columns.len() - 1 {
+                let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if field.is_empty() {
+                    return None;
+                }
+                fields.push(field);
+                rest = tail.trim_start();
+            }
+
+            Some(RemoteLogin {
+                user: fields[0].to_string(),
+                tty: fields[tty].to_string(),
+                from: from.and_then(|i| optional(fields[i])),
+                login_time: fields[login].to_string(),
+                idle: optional(fields[idle]),
+                what: optional(rest.trim_end()),
+            })
+        })
+        .collect();
+    Some(logins)
+}
+
+// `who` output: "user tty <time, several words> (host)" with no header
+fn parse_who_output(output: &str) -> Vec<RemoteLogin> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let user = fields.next()?.to_string();
+            let tty = fields.next()?.to_string();
+            let rest: Vec<&str> = fields.collect();
+
+            let (time, from) = match rest.split_last() {
+                Some((last, time)) if last.starts_with('(') && last.ends_with(')') => {
+                    (time, optional(&last[1..last.len() - 1]))
+                }
+                _ => (rest.as_slice(), None),
+            };
+
+            Some(RemoteLogin { user, tty, from, login_time: time.join(" "), idle: None, what: None })
+        })
+        .collect()
+}
+
+fn parse_logins(output: &str) -> Vec<RemoteLogin> {
+    parse_w_output(output).unwrap_or_else(|| parse_who_output(output))
+}
+
+// Accepts `pts/3`, `/dev/pts/3` or `ttys001` and rejects anything else so the
+// value can be interpolated into the remote command safely
+fn normalize_tty(tty: &str) -> Result<String, String> {
+    let tty = tty.trim();
+    let tty = tty.strip_prefix("/dev/").unwrap_or(tty);
+
+    let valid = !tty.is_empty()
+        && !tty.starts_with('/')
+        && !tty.split('/').any(|part| part.is_empty() || part == "." || part == "..")
+        && tty.chars().all(|c| c.is_ascii_alphanumeric() || c == '/');
+    if !valid {
+        return Err(format!("Invalid tty: {}", tty));
+    }
+    Ok(tty.to_string())
+}
+
+#[tauri::command]
+pub async fn list_remote_logins(
+    state: State<'_, AppState>,
+    session_id: String,
+    password: Option<String>,
+) -> Result<Vec<RemoteLogin>, String> {
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+
+    let output = run_command(&sess, LOGINS_COMMAND)?;
+    if output.exit_status != 0 {
+        return Err(format!("Listing logins failed: {}", output.stderr.trim()));
+    }
+
+    Ok(parse_logins(&output.stdout))
+}
+
+// Kills every process on the login's terminal, ending the session. Other
+// users' sessions need root on the remote side.
+#[tauri::command]
+pub async fn terminate_remote_login(
+    state: State<'_, AppState>,
+    session_id: String,
+    tty: String,
+    signal: Option<String>,
+    password: Option<String>,
+) -> Result<KillOutcome, String> {
+    let tty = normalize_tty(&tty)?;
+    let signal = normalize_signal(signal.as_deref().unwrap_or("KILL"))?;
+
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+
+    // pkill exits 0 once it has signalled anything, so a partial permission
+    // failure only shows up on stderr
+    let output = run_command(&sess, &format!("pkill -{} -t {}", signal, tty))?;
+    let stderr = output.stderr.trim().to_string();
+    if stderr.contains("Operation not permitted") || stderr.contains("Permission denied") {
+        return Ok(KillOutcome::PermissionDenied { stderr });
+    }
+
+    match output.exit_status {
+        0 => Ok(KillOutcome::Killed),
+        1 => Ok(KillOutcome::Failed { stderr: format!("No processes on {}", tty), exit_status: 1 }),
+        exit_status => Ok(KillOutcome::Failed { stderr, exit_status }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROCPS_W: &str = " 10:15:01 up 3 days,  2:03,  3 users,  load average: 0.00, 0.01, 0.05
+USER     TTY      FROM             LOGIN@   IDLE   JCPU   PCPU WHAT
+alice    pts/0    192.168.1.10     09:12    1.00s  0.05s  0.00s w
+bob      tty1     -                Mon08    3days  0.10s  0.10s -bash
+carol    pts/1    10.0.0.2         10:01    2:00   0.20s  0.01s vim  notes with spaces.txt
+";
+
+    #[test]
+    fn parses_procps_w() {
+        let logins = parse_logins(PROCPS_W);
+        assert_eq!(logins.len(), 3);
+
+        assert_eq!(logins[0].user, "alice");
+        assert_eq!(logins[0].tty, "pts/0");
+        assert_eq!(logins[0].from.as_deref(), Some("192.168.1.10"));
+        assert_eq!(logins[0].login_time, "09:12");
+        assert_eq!(logins[0].idle.as_deref(), Some("1.00s"));
+        assert_eq!(logins[0].what.as_deref(), Some("w"));
+
+        assert_eq!(logins[1].tty, "tty1");
+        assert_eq!(logins[1].from, None);
+        assert_eq!(logins[1].what.as_deref(), Some("-bash"));
+
+        assert_eq!(logins[2].what.as_deref(), Some("vim  notes with spaces.txt"));
+    }
+
+    #[test]
+    fn parses_w_without_from_column() {
+        let output = " 10:15:01 up 1 day,  1 user,  load average: 0.00, 0.00, 0.00
+USER     TTY       LOGIN@   IDLE   JCPU   PCPU WHAT
+alice    pts/2     09:12    0.00s  0.01s  0.00s top -d 5
+";
+        let logins = parse_logins(output);
+        assert_eq!(logins.len(), 1);
+        assert_eq!(logins[0].tty, "pts/2");
+        assert_eq!(logins[0].from, None);
+        assert_eq!(logins[0].login_time, "09:12");
+        assert_eq!(logins[0].what.as_deref(), Some("top -d 5"));
+    }
+
+    #[test]
+    fn parses_bsd_w() {
+        let output = "10:15  up 3 days, 14:02, 2 users, load averages: 1.00 1.10 1.20
+USER     TTY      FROM              LOGIN@  IDLE WHAT
+alice    console  -                Mon09   3days -
+alice    s001     10.0.0.5          9:12       - ssh build-host uptime
+";
+        let logins = parse_logins(output);
+        assert_eq!(logins.len(), 2);
+        assert_eq!(logins[0].tty, "console");
+        assert_eq!(logins[0].what, None);
+        assert_eq!(logins[1].tty, "s001");
+        assert_eq!(logins[1].from.as_deref(), Some("10.0.0.5"));
+        assert_eq!(logins[1].idle, None);
+        assert_eq!(logins[1].what.as_deref(), Some("ssh build-host uptime"));
+    }
+
+    #[test]
+    fn skips_short_w_rows() {
+        let output = "USER     TTY      FROM             LOGIN@   IDLE   JCPU   PCPU WHAT
+alice    pts/0
+";
+        assert!(parse_logins(output).is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_who() {
+        let output = "alice    pts/0        2024-05-01 09:12 (192.168.1.10)
+bob      tty1         2024-05-01 08:00
+";
+        let logins = parse_logins(output);
+        assert_eq!(logins.len(), 2);
+        assert_eq!(logins[0].tty, "pts/0");
+        assert_eq!(logins[0].login_time, "2024-05-01 09:12");
+        assert_eq!(logins[0].from.as_deref(), Some("192.168.1.10"));
+        assert_eq!(logins[0].idle, None);
+        assert_eq!(logins[1].tty, "tty1");
+        assert_eq!(logins[1].from, None);
+    }
+
+    #[test]
+    fn normalizes_ttys() {
+        for (input, tty) in [("pts/3", "pts/3"), ("/dev/pts/3", "pts/3"), ("tty1", "tty1"), (" ttys001 ", "ttys001")] {
+            assert_eq!(normalize_tty(input).unwrap(), tty);
+        }
+    }
+
+    #[test]
+    fn rejects_unsafe_ttys() {
+        for input in ["", "/dev/", "pts/", "/etc/passwd", "/dev//pts/1", "pts/../1", "pts/1; reboot", "tty 1", "-tty1"] {
+            assert!(normalize_tty(input).is_err(), "{} was accepted", input);
+        }
+    }
+}
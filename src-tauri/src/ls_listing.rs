@@ -0,0 +1,213 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use tauri::State;
+
+use crate::exec::{run_command, shell_quote};
+use crate::remote::open_session;
+use crate::settings::FileListOptions;
+use crate::sftp::{apply_list_options, parent_entry};
+use crate::{AppState, FileItem, LinkTargetType};
+
+// Operations that need SFTP and are unavailable when browsing through `ls`
+const SHELL_BROWSING_LIMITATIONS: &[&str] = &["chmod", "rename", "upload", "download", "hard_link", "xattrs"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShellDirectoryListing {
+    pub files: Vec<FileItem>,
+    // Always true; lets the UI show "shell-based browsing, limited operations"
+    pub shell_based: bool,
+    pub unsupported_operations: Vec<&'static str>,
+}
+
+// Splits off the next whitespace separated field, returning it and the rest
+// of the line after the single space that follows it
+fn next_field(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start_matches(' ');
+    if line.is_empty() {
+        return None;
+    }
+    match line.find(' ') {
+        Some(end) => Some((&line[..end], &line[end + 1..])),
+        None => Some((line, "")),
+    }
+}
+
+// Undoes `ls -b` (GNU, every odd character escaped) or `ls -B` (BSD, only
+// octal escapes for non-printable bytes). Names come back as raw bytes in
+// the C locale, so non-ASCII names are decoded as UTF-8 at the end.
+fn unescape(name: &str, gnu: bool) -> String {
+    let bytes = name.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let octal = bytes.get(i + 1..i + 4)
+            .filter(|digits| digits.iter().all(|d| (b'0'..=b'7').contains(d)))
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok());
+        if let Some(byte) = octal {
+            out.push(byte);
+            i += 4;
+            continue;
+        }
+
+        let escaped = match bytes[i + 1] {
+            _ if !gnu => None,
+            b'n' => Some(b'\n'),
+            b't' => Some(b'\t'),
+            b'r' => Some(b'\r'),
+            b'a' => Some(0x07),
+            b'b' => Some(0x08),
+            b'f' => Some(0x0c),
+            b'v' => Some(0x0b),
+            other => Some(other),
+        };
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 2;
+            }
+            None => {
+                out.push(b'\\');
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    MONTHS.iter().position(|m| *m == name).map(|i| i as u32 + 1)
+}
+
+// BSD style dates only show the time for recent files and leave out the
+// year, which is then this year unless that would put it in the future
+fn bsd_datetime(month: &str, day: &str, time_or_year: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let month = month_number(month)?;
+    let day: u32 = day.parse().ok()?;
+
+    if let Some((hour, minute)) = time_or_year.split_once(':') {
+        let (hour, minute) = (hour.parse().ok()?, minute.parse().ok()?);
+        let this_year = NaiveDate::from_ymd_opt(now.year(), month, day)?.and_hms_opt(hour, minute, 0)?;
+        if this_year > now + chrono::Duration::days(1) {
+            return NaiveDate::from_ymd_opt(now.year() - 1, month, day)?.and_hms_opt(hour, minute, 0);
+        }
+        Some(this_year)
+    } else {
+        NaiveDate::from_ymd_opt(time_or_year.parse().ok()?, month, day)?.and_hms_opt(0, 0, 0)
+    }
+}
+
+// Parses one line of `ls -l`. Handles GNU with --time-style=long-iso and the
+// BSD/BusyBox default date format, device files ("major, minor" instead of a
+// size) and permission strings with ACL/SELinux markers.
+pub fn parse_ls_line(line: &str, dir: &str, now: NaiveDateTime) -> Option<FileItem> {
+    let (mode, rest) = next_field(line)?;
+    let file_type = mode.chars().next()?;
+    if mode.len() < 10 || !"-dlcbps".contains(file_type) {
+        return None;
+    }
+
+    let (links, rest) = next_field(rest)?;
+    let nlink = links.parse::<u64>().ok()?;
+    let (_owner, rest) = next_field(rest)?;
+    let (_group, rest) = next_field(rest)?;
+
+    let (size_field, mut rest) = next_field(rest)?;
+    let size = if size_field.ends_with(',') {
+        // Device file: skip the minor number, there's no size
+        rest = next_field(rest)?.1;
+        0
+    } else {
+        size_field.parse().ok()?
+    };
+
+    let (date, rest) = next_field(rest)?;
+    let (gnu, modified, name) = if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() {
+        let (time, name) = next_field(rest)?;
+        let modified = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").ok();
+        (true, modified, name)
+    } else {
+        let (day, rest) = next_field(rest)?;
+        let (time_or_year, name) = next_field(rest)?;
+        (false, bsd_datetime(date, day, time_or_year, now), name)
+    };
+
+    // With GNU's escaping a literal " -> " can only be the link separator
+    let (name, link_target) = match (file_type, name.split_once(" -> ")) {
+        ('l', Some((name, target))) => (unescape(name, gnu), Some(unescape(target, gnu))),
+        _ => (unescape(name, gnu), None),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+
+    let is_directory = file_type == 'd';
+    let is_symlink = file_type == 'l';
+    Some(FileItem {
+        path: format!("{}/{}", dir.trim_end_matches('/'), name),
+        name,
+        size: if is_directory { 0 } else { size },
+        is_directory,
+        modified: modified.map(|m| m.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "unknown".to_string()),
+        is_symlink,
+        link_target,
+        // Telling what a link points to would take a stat per link
+        link_target_type: is_symlink.then_some(LinkTargetType::Other),
+        nlink: Some(nlink),
+        permissions: Some(mode[..10].to_string()),
+        // `ls -l` only shows it with -Z, which not every ls has
+        selinux_context: None,
+    })
+}
+
+pub fn parse_ls_output(output: &str, dir: &str) -> Vec<FileItem> {
+    let now = chrono::Local::now().naive_local();
+    output.lines().filter_map(|line| parse_ls_line(line, dir, now)).collect()
+}
+
+// A degraded directory listing for hosts without an SFTP subsystem
+// (appliances, restricted shells), built from `ls` run over exec. The C
+// locale keeps month names parseable and escapes every non-ASCII byte.
+#[tauri::command]
+pub async fn list_remote_directory_via_shell(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+    options: Option<FileListOptions>,
+) -> Result<ShellDirectoryListing, String> {
+    let session = state.get_session(&session_id)?;
+    let options = match options {
+        Some(options) => options,
+        None => state.settings.lock().map_err(|e| e.to_string())?.file_list,
+    };
+    let sess = open_session(&session, password.as_deref())?;
+
+    let quoted = shell_quote(&path);
+    let command = format!(
+        "LC_ALL=C ls -lab --time-style=long-iso -- {0} 2>/dev/null || LC_ALL=C ls -laB -- {0} 2>/dev/null || LC_ALL=C ls -la -- {0}",
+        quoted
+    );
+    let output = run_command(&sess, &command)?;
+    if output.exit_status != 0 {
+        return Err(format!("Failed to list {}: {}", path, output.stderr.trim()));
+    }
+
+    let mut files: Vec<FileItem> = parent_entry(&path).into_iter().collect();
+    files.extend(parse_ls_output(&output.stdout, &path));
+    apply_list_options(&mut files, &options);
+
+    Ok(ShellDirectoryListing {
+        files,
+        shell_based: true,
+        unsupported_operations: SHELL_BROWSING_LIMITATIONS.to_vec(),
+    })
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::error::SshError;
+use crate::local_terminal::LocalTerminalManager;
+use crate::ssh_new::SshManager;
+
+// Recorded keystrokes, one entry per chunk the terminal sent (a key press, an
+// escape sequence or a paste)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalMacro {
+    pub name: String,
+    pub inputs: Vec<String>,
+}
+
+struct Recording {
+    name: String,
+    inputs: Vec<String>,
+}
+
+// Sessions currently recording a macro
+pub struct MacroRecorder {
+    recordings: Mutex<HashMap<String, Recording>>,
+}
+
+impl MacroRecorder {
+    pub fn new() -> Self {
+        MacroRecorder {
+            recordings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Called for every bit of input sent to a session
+    pub fn record(&self, session_id: &str, input: &str) {
+        if let Some(recording) = self.recordings.lock().unwrap().get_mut(session_id) {
+            recording.inputs.push(input.to_string());
+        }
+    }
+}
+
+pub(crate) fn load_macros(app: &AppHandle) -> Result<Vec<TerminalMacro>, String> {
+    let store = app.store("macros.json").map_err(|e| e.to_string())?;
+
+    match store.get("macros") {
+        Some(value) => serde_json::from_value(value).map_err(|e| e.to_string()),
+        None => Ok(vec![]),
+    }
+}
+
+pub(crate) fn save_macros(app: &AppHandle, macros: &[TerminalMacro]) -> Result<(), String> {
+    let store = app.store("macros.json").map_err(|e| e.to_string())?;
+    store.set("macros", serde_json::to_value(macros).map_err(|e| e.to_string())?);
+    store.save().map_err(|e| e.to_string())
+}
+
+// Starts capturing what is typed into the session. Anything typed while
+// recording ends up in the macro, passwords included.
+#[tauri::command]
+pub async fn start_macro_record(
+    recorder: State<'_, MacroRecorder>,
+    session_id: String,
+    name: String,
+) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Macro name cannot be empty".to_string());
+    }
+
+    recorder.recordings.lock().unwrap().insert(session_id, Recording { name, inputs: Vec::new() });
+    Ok(())
+}
+
+// Stops recording and saves the macro, replacing one with the same name
+#[tauri::command]
+pub async fn stop_macro_record(
+    app: AppHandle,
+    recorder: State<'_, MacroRecorder>,
+    session_id: String,
+) -> Result<TerminalMacro, String> {
+    let recording = recorder.recordings.lock().unwrap()
+        .remove(&session_id)
+        .ok_or_else(|| "This session is not recording a macro".to_string())?;
+
+    if recording.inputs.is_empty() {
+        return Err("Nothing was recorded".to_string());
+    }
+
+    let recorded = TerminalMacro { name: recording.name, inputs: recording.inputs };
+    let mut macros = load_macros(&app)?;
+    macros.retain(|m| m.name != recorded.name);
+    macros.push(recorded.clone());
+    save_macros(&app, &macros)?;
+
+    Ok(recorded)
+}
+
+#[tauri::command]
+pub async fn list_macros(app: AppHandle) -> Result<Vec<TerminalMacro>, String> {
+    load_macros(&app)
+}
+
+#[tauri::command]
+pub async fn delete_macro(app: AppHandle, name: String) -> Result<(), String> {
+    let mut macros = load_macros(&app)?;
+    macros.retain(|m| m.name != name);
+    save_macros(&app, &macros)
+}
+
+// Sends a macro's keystrokes to the session. Some appliances drop input that
+// arrives faster than a person types, so a delay can be put between keystrokes.
+#[tauri::command]
+pub async fn play_macro(
+    app: AppHandle,
+    state: State<'_, Arc<SshManager>>,
+    local: State<'_, LocalTerminalManager>,
+    session_id: String,
+    name: String,
+    delay_ms: Option<u64>,
+) -> Result<(), SshError> {
+    let recorded = load_macros(&app).map_err(SshError::Other)?
+        .into_iter()
+        .find(|m| m.name == name)
+        .ok_or_else(|| SshError::Other(format!("Macro '{}' not found", name)))?;
+
+    let delay = Duration::from_millis(delay_ms.unwrap_or(0));
+    for (i, input) in recorded.inputs.iter().enumerate() {
+        if i > 0 && !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+
+        if local.contains(&session_id) {
+            local.write(&session_id, input)?;
+        } else {
+            state.send_input(&session_id, input)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,382 @@
+use log::debug;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::SshError;
+use crate::exec::{run_command, shell_quote};
+use crate::remote::open_session;
+use crate::{AppState, Session};
+
+const DEFAULT_METRICS_INTERVAL: Duration = Duration::from_secs(5);
+// A sample takes a second of its own (the CPU delta), so polling faster
+// would just queue them up
+const MIN_METRICS_INTERVAL: Duration = Duration::from_secs(2);
+// Failed samples back off, doubling the wait up to this
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+// Everything is gathered in one exec, each section after an @@name marker.
+// Every command may be missing: Linux has /proc and free, the BSDs and macOS
+// sysctl, minimal containers maybe neither. CPU usage is the difference
+// between two samples of the counters a second apart.
+const METRICS_PROBE: &str = r#"echo @@df; df -Pk 2>/dev/null
+echo @@free; free -b 2>/dev/null
+echo @@meminfo; cat /proc/meminfo 2>/dev/null
+echo @@physmem; sysctl -n hw.memsize 2>/dev/null || sysctl -n hw.physmem 2>/dev/null
+echo @@loadavg; cat /proc/loadavg 2>/dev/null || sysctl -n vm.loadavg 2>/dev/null
+echo @@uptime; uptime 2>/dev/null
+echo @@procuptime; cat /proc/uptime 2>/dev/null
+echo @@boottime; sysctl -n kern.boottime 2>/dev/null && date +%s
+echo @@cpu1; head -n 1 /proc/stat 2>/dev/null || sysctl -n kern.cp_time 2>/dev/null
+sleep 1
+echo @@cpu2; head -n 1 /proc/stat 2>/dev/null || sysctl -n kern.cp_time 2>/dev/null"#;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsage {
+    pub filesystem: String,
+    pub mount: String,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub used_percent: f64,
+}
+
+// Only the total is known on the BSDs and macOS
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryUsage {
+    pub total_bytes: u64,
+    pub used_bytes: Option<u64>,
+    pub free_bytes: Option<u64>,
+    // Free plus what the kernel can reclaim (caches)
+    pub available_bytes: Option<u64>,
+    pub swap_total_bytes: Option<u64>,
+    pub swap_used_bytes: Option<u64>,
+}
+
+// Whatever the server could tell us; fields it couldn't are None (or empty)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServerMetrics {
+    pub sampled_at: String,
+    pub disks: Vec<DiskUsage>,
+    pub memory: Option<MemoryUsage>,
+    // 1, 5 and 15 minutes
+    pub load_average: Option<[f64; 3]>,
+    pub cpu_percent: Option<f64>,
+    pub uptime_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct MetricsEvent {
+    session_id: String,
+    metrics: Option<ServerMetrics>,
+    error: Option<String>,
+}
+
+fn sections(output: &str) -> HashMap<&str, Vec<&str>> {
+    let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut current = None;
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("@@") {
+            current = Some(name.trim());
+            sections.entry(name.trim()).or_default();
+        } else if let Some(name) = current {
+            sections.entry(name).or_default().push(line);
+        }
+    }
+    sections
+}
+
+// df -Pk: filesystem, 1024-blocks, used, available, capacity, mount point
+// (which may contain spaces). Pseudo filesystems with no size are left out.
+fn parse_df(lines: &[&str]) -> Vec<DiskUsage> {
+    lines
+        .iter()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 6 {
+                return None;
+            }
+            let total_bytes = fields[1].parse::<u64>().ok()? * 1024;
+            let used_bytes = fields[2].parse::<u64>().ok()? * 1024;
+            let available_bytes = fields[3].parse::<u64>().ok()? * 1024;
+            if total_bytes == 0 {
+                return None;
+            }
+            // What df itself shows: used out of what the user could have
+            let usable = used_bytes + available_bytes;
+            Some(DiskUsage {
+                filesystem: fields[0].to_string(),
+                mount: fields[5..].join(" "),
+                total_bytes,
+                used_bytes,
+                available_bytes,
+                used_percent: if usable == 0 { 0.0 } else { used_bytes as f64 * 100.0 / usable as f64 },
+            })
+        })
+        .collect()
+}
+
+// free -b, whose columns differ between procps versions and BusyBox, so
+// they're looked up by the header
+fn parse_free(lines: &[&str]) -> Option<MemoryUsage> {
+    let header: Vec<&str> = lines.first()?.split_whitespace().collect();
+    let row = |label: &str| -> Option<Vec<u64>> {
+        let line = lines.iter().find(|line| line.starts_with(label))?;
+        Some(line.split_whitespace().skip(1).filter_map(|v| v.parse().ok()).collect())
+    };
+    let column = |values: &[u64], name: &str| header.iter().position(|h| *h == name).and_then(|i| values.get(i).copied());
+
+    let mem = row("Mem:")?;
+    let swap = row("Swap:");
+    Some(MemoryUsage {
+        total_bytes: column(&mem, "total")?,
+        used_bytes: column(&mem, "used"),
+        free_bytes: column(&mem, "free"),
+        available_bytes: column(&mem, "available"),
+        swap_total_bytes: swap.as_ref().and_then(|swap| swap.first().copied()),
+        swap_used_bytes: swap.as_ref().and_then(|swap| swap.get(1).copied()),
+    })
+}
+
+// /proc/meminfo, for containers without free. Values are in kB.
+fn parse_meminfo(lines: &[&str]) -> Option<MemoryUsage> {
+    let value = |key: &str| -> Option<u64> {
+        let line = lines.iter().find(|line| line.split(':').next() == Some(key))?;
+        line.split_whitespace().nth(1)?.parse::<u64>().ok().map(|kb| kb * 1024)
+    };
+
+    let total = value("MemTotal")?;
+    let free = value("MemFree");
+    let available = value("MemAvailable").or_else(|| Some(free? + value("Buffers")? + value("Cached")?));
+    let swap_total = value("SwapTotal");
+    Some(MemoryUsage {
+        total_bytes: total,
+        used_bytes: available.map(|available| total.saturating_sub(available)),
+        free_bytes: free,
+        available_bytes: available,
+        swap_total_bytes: swap_total,
+        swap_used_bytes: swap_total.zip(value("SwapFree")).map(|(total, free)| total.saturating_sub(free)),
+    })
+}
+
+fn parse_load(text: &str) -> Option<[f64; 3]> {
+    let numbers: Vec<f64> = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '{' || c == '}')
+        .filter_map(|word| word.parse().ok())
+        .take(3)
+        .collect();
+    numbers.try_into().ok()
+}
+
+// "... load average: 0.00, 0.01, 0.05" (Linux) or "load averages: 1.2 1.1 1.0" (macOS)
+fn parse_uptime_load(line: &str) -> Option<[f64; 3]> {
+    let (_, rest) = line.split_once("load average")?;
+    parse_load(rest.split_once(':')?.1)
+}
+
+// "{ sec = 1700000000, usec = 0 } Tue Nov 14 ..." followed by the remote's
+// current time, so clock differences with this machine don't matter
+fn parse_boottime(lines: &[&str]) -> Option<u64> {
+    let boot: u64 = lines.first()?.split("sec =").nth(1)?.split(',').next()?.trim().parse().ok()?;
+    let now: u64 = lines.get(1)?.trim().parse().ok()?;
+    now.checked_sub(boot)
+}
+
+// (busy, total) from a /proc/stat cpu line or kern.cp_time. In /proc/stat
+// idle and iowait are the 4th and 5th counters; guest time is already
+// counted in user, so only the first 8 add up to the total.
+fn cpu_counters(line: &str) -> Option<(u64, u64)> {
+    let linux = line.starts_with("cpu");
+    let values: Vec<u64> = line.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+    if linux {
+        let values = &values[..values.len().min(8)];
+        let idle = values.get(3)? + values.get(4).copied().unwrap_or(0);
+        let total: u64 = values.iter().sum();
+        Some((total - idle, total))
+    } else {
+        // user nice sys intr idle
+        let idle = *values.get(4)?;
+        let total: u64 = values.iter().take(5).sum();
+        Some((total - idle, total))
+    }
+}
+
+fn cpu_percent(first: &str, second: &str) -> Option<f64> {
+    let (busy1, total1) = cpu_counters(first)?;
+    let (busy2, total2) = cpu_counters(second)?;
+    let total = total2.checked_sub(total1).filter(|total| *total > 0)?;
+    Some(busy2.saturating_sub(busy1) as f64 * 100.0 / total as f64)
+}
+
+fn parse_metrics(output: &str) -> ServerMetrics {
+    let sections = sections(output);
+    let section = |name: &str| sections.get(name).map(Vec::as_slice).unwrap_or_default();
+    let first = |name: &str| section(name).first().copied();
+
+    let memory = parse_free(section("free"))
+        .or_else(|| parse_meminfo(section("meminfo")))
+        .or_else(|| {
+            let total = first("physmem")?.trim().parse().ok()?;
+            Some(MemoryUsage {
+                total_bytes: total,
+                used_bytes: None,
+                free_bytes: None,
+                available_bytes: None,
+                swap_total_bytes: None,
+                swap_used_bytes: None,
+            })
+        });
+
+    ServerMetrics {
+        sampled_at: chrono::Utc::now().to_rfc3339(),
+        disks: parse_df(section("df")),
+        memory,
+        load_average: first("loadavg").and_then(parse_load).or_else(|| first("uptime").and_then(parse_uptime_load)),
+        cpu_percent: first("cpu1").zip(first("cpu2")).and_then(|(a, b)| cpu_percent(a, b)),
+        uptime_secs: first("procuptime")
+            .and_then(|line| line.split_whitespace().next()?.parse::<f64>().ok())
+            .map(|secs| secs as u64)
+            .or_else(|| parse_boottime(section("boottime"))),
+    }
+}
+
+fn collect(sess: &ssh2::Session) -> Result<ServerMetrics, String> {
+    let output = run_command(sess, &format!("sh -c {}", shell_quote(METRICS_PROBE)))?;
+    Ok(parse_metrics(&output.stdout))
+}
+
+// Disk, memory, load and CPU usage in one go, for a server health panel
+#[tauri::command]
+pub async fn get_server_metrics(
+    state: State<'_, AppState>,
+    session_id: String,
+    password: Option<String>,
+) -> Result<ServerMetrics, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let sess = open_session(&session, password.as_deref())?;
+        collect(&sess).map_err(SshError::Other)
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+struct MetricsMonitor {
+    shutdown: Arc<AtomicBool>,
+    handle: thread::JoinHandle<()>,
+}
+
+// Sessions with a live dashboard open, each polled on its own connection
+pub struct MetricsMonitors {
+    monitors: Mutex<HashMap<String, MetricsMonitor>>,
+}
+
+impl MetricsMonitors {
+    pub fn new() -> Self {
+        MetricsMonitors {
+            monitors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn stop(&self, session_id: &str) -> bool {
+        let Some(monitor) = self.monitors.lock().unwrap().remove(session_id) else {
+            return false;
+        };
+        monitor.shutdown.store(true, Ordering::Relaxed);
+        let _ = monitor.handle.join();
+        true
+    }
+
+    // For when the session closes: the monitor is told to stop but not waited
+    // for, since it may be in the middle of a sample
+    pub fn cancel(&self, session_id: &str) {
+        if let Some(monitor) = self.monitors.lock().unwrap().remove(session_id) {
+            monitor.shutdown.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn run_monitor(session_id: String, session: Session, password: Option<String>, interval: Duration, shutdown: Arc<AtomicBool>, app_handle: AppHandle) {
+    // Kept between samples and opened again after a failure
+    let mut sess = None;
+    let mut failures = 0;
+    while !shutdown.load(Ordering::Relaxed) {
+        let started = Instant::now();
+
+        let open = match sess.take() {
+            Some(open) => Ok(open),
+            None => open_session(&session, password.as_deref()),
+        };
+        // Trying the same credentials again would only pile up failed logins
+        if let Err(e @ SshError::Auth(_)) = &open {
+            let _ = app_handle.emit("server_metrics", &MetricsEvent {
+                session_id: session_id.clone(),
+                metrics: None,
+                error: Some(format!("{}; monitoring stopped", e)),
+            });
+            break;
+        }
+        let result = open
+            .map_err(|e| e.to_string())
+            .and_then(|open| collect(&open).map(|metrics| (open, metrics)));
+
+        let event = match result {
+            Ok((open, metrics)) => {
+                sess = Some(open);
+                failures = 0;
+                MetricsEvent { session_id: session_id.clone(), metrics: Some(metrics), error: None }
+            }
+            Err(e) => {
+                failures += 1;
+                MetricsEvent { session_id: session_id.clone(), metrics: None, error: Some(e) }
+            }
+        };
+        let _ = app_handle.emit("server_metrics", &event);
+
+        let wait = if failures == 0 {
+            interval
+        } else {
+            interval.saturating_mul(1 << failures.min(8)).min(MAX_RETRY_DELAY.max(interval))
+        };
+        while started.elapsed() < wait && !shutdown.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+    debug!("Metrics monitor for {} stopped", session_id);
+}
+
+// Emits `server_metrics` events for the session every `interval_ms` until
+// stopped. Starting it again just changes the interval.
+#[tauri::command]
+pub async fn start_metrics_monitor(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    monitors: State<'_, MetricsMonitors>,
+    session_id: String,
+    interval_ms: Option<u64>,
+    password: Option<String>,
+) -> Result<(), SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let interval = interval_ms.map(Duration::from_millis).unwrap_or(DEFAULT_METRICS_INTERVAL).max(MIN_METRICS_INTERVAL);
+    monitors.stop(&session_id);
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let thread_shutdown = shutdown.clone();
+    let thread_session_id = session_id.clone();
+    let handle = thread::spawn(move || run_monitor(thread_session_id, session, password, interval, thread_shutdown, app_handle));
+
+    monitors.monitors.lock().unwrap().insert(session_id, MetricsMonitor { shutdown, handle });
+    Ok(())
+}
+
+// Returns whether a monitor was running
+#[tauri::command]
+pub async fn stop_metrics_monitor(monitors: State<'_, MetricsMonitors>, session_id: String) -> Result<bool, SshError> {
+    Ok(monitors.stop(&session_id))
+}
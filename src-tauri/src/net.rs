@@ -0,0 +1,300 @@
+use base64::Engine;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+use crate::settings::AddressFamily;
+
+// Per-address limit, so a dead record doesn't eat the whole connect attempt
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+// How long the proxy gets to answer a CONNECT
+const PROXY_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+// A CONNECT response is a status line and a few headers; anything bigger is
+// not a proxy we understand
+const MAX_PROXY_RESPONSE: usize = 16 * 1024;
+
+// An HTTP proxy that SSH connections are tunnelled through with CONNECT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    pub host: String,
+    pub port: u16,
+    // Basic auth credentials, sent only when a username is set
+    #[serde(default)]
+    pub username: Option<String>,
+    // Kept in the OS keychain for saved sessions, never written to disk
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+}
+
+// Mirrors AppSettings::address_family so connects don't need the app state
+static ADDRESS_FAMILY: AtomicU8 = AtomicU8::new(AddressFamily::Any as u8);
+
+pub fn set_address_family(family: AddressFamily) {
+    ADDRESS_FAMILY.store(family as u8, Ordering::Relaxed);
+}
+
+fn address_family() -> AddressFamily {
+    match ADDRESS_FAMILY.load(Ordering::Relaxed) {
+        x if x == AddressFamily::PreferIpv4 as u8 => AddressFamily::PreferIpv4,
+        x if x == AddressFamily::PreferIpv6 as u8 => AddressFamily::PreferIpv6,
+        _ => AddressFamily::Any,
+    }
+}
+
+// Keeps the resolver's order within each family, like `ssh` does
+fn order_addresses(mut addrs: Vec<SocketAddr>, family: AddressFamily) -> Vec<SocketAddr> {
+    match family {
+        AddressFamily::Any => {}
+        AddressFamily::PreferIpv4 => addrs.sort_by_key(|addr| !addr.is_ipv4()),
+        AddressFamily::PreferIpv6 => addrs.sort_by_key(|addr| !addr.is_ipv6()),
+    }
+    addrs
+}
+
+pub fn parse_bind_address(bind_address: &str) -> io::Result<IpAddr> {
+    bind_address.trim().parse().map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("Bind address {} is not an IP address", bind_address))
+    })
+}
+
+// A socket bound to `bind` (any port), which fails unless the address belongs
+// to one of this machine's interfaces
+fn bound_socket(bind: IpAddr) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(SocketAddr::new(bind, 0)), Type::STREAM, Some(Protocol::TCP))?;
+    socket.bind(&SocketAddr::new(bind, 0).into()).map_err(|e| match e.kind() {
+        io::ErrorKind::AddrNotAvailable => {
+            io::Error::new(e.kind(), format!("Bind address {} is not an address of this machine", bind))
+        }
+        _ => io::Error::new(e.kind(), format!("Failed to bind to {}: {}", bind, e)),
+    })?;
+    Ok(socket)
+}
+
+fn connect_addr(addr: &SocketAddr, bind: Option<IpAddr>, timeout: Duration) -> io::Result<TcpStream> {
+    let connected = match bind {
+        None => TcpStream::connect_timeout(addr, timeout),
+        Some(bind) => {
+            let socket = bound_socket(bind)?;
+            socket.connect_timeout(&(*addr).into(), timeout).map(|_| socket.into())
+        }
+    };
+    connected.map_err(|e| match e.kind() {
+        io::ErrorKind::TimedOut => io::Error::new(e.kind(), format!("Connection timed out after {}s", timeout.as_secs())),
+        _ => e,
+    })
+}
+
+// Resolves every A/AAAA record and tries them in turn, so a host with a dead
+// IPv6 address but a working IPv4 one still connects. Returns the address
+// that worked.
+//
+// With a bind address (OpenSSH's BindAddress) the connection leaves from that
+// local address, so only the host's addresses of the same family are tried.
+pub fn connect_host(
+    host: &str,
+    port: u16,
+    bind_address: Option<&str>,
+    timeout: Duration,
+) -> io::Result<(TcpStream, SocketAddr)> {
+    let bind = bind_address.filter(|address| !address.trim().is_empty()).map(parse_bind_address).transpose()?;
+    let mut addrs = order_addresses((host, port).to_socket_addrs()?.collect(), address_family());
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve to any address", host)));
+    }
+    if let Some(bind) = bind {
+        addrs.retain(|addr| addr.is_ipv4() == bind.is_ipv4());
+        if addrs.is_empty() {
+            let family = if bind.is_ipv4() { "IPv4" } else { "IPv6" };
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} has no {} address to reach from {}", host, family, bind),
+            ));
+        }
+    }
+
+    let mut last_error = None;
+    for addr in addrs {
+        match connect_addr(&addr, bind, timeout) {
+            Ok(stream) => {
+                info!("Connected to {} via {}", host, addr);
+                return Ok((stream, addr));
+            }
+            // Binding fails the same way for every address
+            Err(e) if e.kind() == io::ErrorKind::AddrNotAvailable => return Err(e),
+            Err(e) => {
+                debug!("Connecting to {} via {} failed: {}", host, addr, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| io::ErrorKind::NotConnected.into()))
+}
+
+// Host part of a CONNECT target; IPv6 literals need brackets
+fn authority(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+// Reads the response head byte by byte, so nothing the target sends after the
+// blank line (the SSH banner) is consumed here
+fn read_response_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Proxy closed the connection"));
+        }
+        head.push(byte[0]);
+        if head.len() > MAX_PROXY_RESPONSE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Proxy response is too large"));
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&head).to_string())
+}
+
+// Asks the proxy to open a tunnel to host:port over an established stream
+fn http_connect(stream: &mut TcpStream, proxy: &ProxyConfig, host: &str, port: u16) -> io::Result<()> {
+    let target = authority(host, port);
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(username) = &proxy.username {
+        let credentials = format!("{}:{}", username, proxy.password.as_deref().unwrap_or(""));
+        let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", encoded));
+    }
+    request.push_str("\r\n");
+
+    stream.set_read_timeout(Some(PROXY_HANDSHAKE_TIMEOUT))?;
+    stream.write_all(request.as_bytes())?;
+    let head = read_response_head(stream)?;
+    stream.set_read_timeout(None)?;
+
+    let status_line = head.lines().next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unexpected proxy response: {}", status_line)))?;
+
+    match status {
+        200..=299 => Ok(()),
+        407 if proxy.username.is_some() => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Proxy authentication failed, check the proxy username and password",
+        )),
+        407 => Err(io::Error::new(io::ErrorKind::PermissionDenied, "Proxy requires authentication")),
+        _ => Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("Proxy refused to connect to {}: {}", target, status_line.trim()),
+        )),
+    }
+}
+
+// Connects to host:port, through the HTTP proxy when one is configured. The
+// returned address is the proxy's in that case.
+// Addresses of this machine we can find without platform interface APIs:
+// the source address the OS routes outbound traffic from (connecting a UDP
+// socket sends nothing) and whatever our own hostname resolves to. Secondary
+// interfaces that are neither of those aren't detected.
+fn local_addresses() -> Vec<IpAddr> {
+    let mut addrs = Vec::new();
+
+    for (bind, probe) in [("0.0.0.0:0", "192.0.2.1:9"), ("[::]:0", "[2001:db8::1]:9")] {
+        let local = UdpSocket::bind(bind)
+            .and_then(|socket| socket.connect(probe).and_then(|_| socket.local_addr()));
+        if let Ok(local) = local {
+            addrs.push(local.ip());
+        }
+    }
+
+    let hostname = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .or_else(|_| std::fs::read_to_string("/etc/hostname").map(|name| name.trim().to_string()));
+    if let Ok(hostname) = hostname.as_deref() {
+        if let Ok(resolved) = (hostname, 0).to_socket_addrs() {
+            addrs.extend(resolved.map(|addr| addr.ip()));
+        }
+    }
+
+    addrs.retain(|ip| !ip.is_unspecified());
+    addrs
+}
+
+fn is_local_ip(ip: IpAddr, local: &[IpAddr]) -> bool {
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    };
+    ip.is_loopback() || ip.is_unspecified() || local.contains(&ip)
+}
+
+// Returns the address `host` resolves to when that is this machine itself
+// (loopback or one of our own addresses), which is usually a typo'd or
+// half-edited session rather than intended
+pub fn local_target(host: &str, port: u16) -> io::Result<Option<SocketAddr>> {
+    let resolved: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
+    let local = local_addresses();
+    Ok(resolved.into_iter().find(|addr| is_local_ip(addr.ip(), &local)))
+}
+
+// Whether a connected stream goes to this machine: either end on loopback,
+// or both ends on the same address, which also catches our other interfaces
+// without listing them
+pub fn is_self_connection(stream: &TcpStream) -> bool {
+    match (stream.local_addr(), stream.peer_addr()) {
+        (Ok(local), Ok(peer)) => peer.ip().is_loopback() || local.ip() == peer.ip(),
+        _ => false,
+    }
+}
+
+pub fn connect(
+    host: &str,
+    port: u16,
+    proxy: Option<&ProxyConfig>,
+    bind_address: Option<&str>,
+) -> io::Result<(TcpStream, SocketAddr)> {
+    connect_with_timeout(host, port, proxy, bind_address, CONNECT_TIMEOUT)
+}
+
+// Like connect, with `timeout` for each address instead of CONNECT_TIMEOUT
+pub fn connect_with_timeout(
+    host: &str,
+    port: u16,
+    proxy: Option<&ProxyConfig>,
+    bind_address: Option<&str>,
+    timeout: Duration,
+) -> io::Result<(TcpStream, SocketAddr)> {
+    let Some(proxy) = proxy else {
+        return connect_host(host, port, bind_address, timeout);
+    };
+
+    let (mut stream, addr) = connect_host(&proxy.host, proxy.port, bind_address, timeout)?;
+    http_connect(&mut stream, proxy, host, port)?;
+    info!("Tunnelled to {}:{} through proxy {}", host, port, addr);
+    Ok((stream, addr))
+}
+
+// Lets the session editor reject a bind address that isn't on this machine
+#[tauri::command]
+pub async fn check_bind_address(address: String) -> Result<(), String> {
+    let ip = parse_bind_address(&address).map_err(|e| e.to_string())?;
+    bound_socket(ip).map(|_| ()).map_err(|e| e.to_string())
+}
+
+// Lets the session editor warn about a loopback host before it's saved
+#[tauri::command]
+pub async fn check_loopback_target(host: String, port: Option<u16>) -> Result<Option<String>, String> {
+    let address = local_target(&host, port.unwrap_or(22)).map_err(|e| format!("Failed to resolve {}: {}", host, e))?;
+    Ok(address.map(|addr| addr.ip().to_string()))
+}
@@ -0,0 +1,191 @@
+use log::warn;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::State;
+
+use crate::error::SshError;
+use crate::local_terminal::LocalTerminalManager;
+use crate::settings::PasteGuard;
+use crate::ssh_new::SshManager;
+use crate::AppState;
+
+const BRACKETED_PASTE_START: &str = "\x1b[200~";
+const BRACKETED_PASTE_END: &str = "\x1b[201~";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PasteReport {
+    // Something was stripped or escaped
+    pub modified: bool,
+    // What was found, one entry per kind, e.g. "title change (OSC 0)"
+    pub found: Vec<String>,
+    // Line breaks that reach the shell as Enter presses, i.e. commands that
+    // run straight away. Always 0 for a bracketed paste.
+    pub line_breaks: usize,
+}
+
+fn is_control(c: char) -> bool {
+    (c < ' ' && !matches!(c, '\t' | '\n' | '\r')) || ('\u{7f}'..='\u{9f}').contains(&c)
+}
+
+// Caret notation for C0 and DEL, the code point for C1
+fn visible(c: char) -> String {
+    match c {
+        '\u{7f}' => "^?".to_string(),
+        c if c < ' ' => format!("^{}", (c as u8 + 0x40) as char),
+        c if is_control(c) => format!("<U+{:04X}>", c as u32),
+        c => c.to_string(),
+    }
+}
+
+// Index just past the string terminator (BEL, ESC \ or ST) of an OSC, DCS,
+// SOS, PM or APC string starting at `from`. An unterminated string runs to the
+// end of the text, which is how a terminal would swallow it too.
+fn string_end(chars: &[char], from: usize) -> usize {
+    let mut i = from;
+    while i < chars.len() {
+        match chars[i] {
+            '\x07' | '\u{9c}' => return i + 1,
+            '\x1b' if chars.get(i + 1) == Some(&'\\') => return i + 2,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+fn describe_osc(body: &[char]) -> String {
+    let number: String = body.iter().take_while(|c| c.is_ascii_digit()).collect();
+    match number.as_str() {
+        "0" | "1" | "2" => format!("title change (OSC {})", number),
+        "52" => "clipboard access (OSC 52)".to_string(),
+        "" => "OSC sequence".to_string(),
+        _ => format!("OSC {} sequence", number),
+    }
+}
+
+// The control sequence starting at `start` (which is a control character):
+// where it ends and what it is
+fn sequence_at(chars: &[char], start: usize) -> (usize, String) {
+    // 7-bit introducers are ESC plus a letter, 8-bit ones a single C1 character
+    let (kind, body) = match chars[start] {
+        '\x1b' => match chars.get(start + 1) {
+            Some('[') => ('[', start + 2),
+            Some(']') => (']', start + 2),
+            Some(&c @ ('P' | 'X' | '^' | '_')) => (c, start + 2),
+            Some(_) => return (start + 2, "escape sequence".to_string()),
+            None => return (start + 1, "escape character".to_string()),
+        },
+        '\u{9b}' => ('[', start + 1),
+        '\u{9d}' => (']', start + 1),
+        '\u{90}' => ('P', start + 1),
+        '\u{98}' => ('X', start + 1),
+        '\u{9e}' => ('^', start + 1),
+        '\u{9f}' => ('_', start + 1),
+        c => return (start + 1, format!("control character {}", visible(c))),
+    };
+
+    match kind {
+        '[' => {
+            // Parameters and intermediates up to a final byte in @..~
+            let end = chars[body..].iter()
+                .position(|c| ('@'..='~').contains(c))
+                .map_or(chars.len(), |i| body + i + 1);
+            let sequence: String = chars[body..end].iter().collect();
+            let description = if sequence == "200~" || sequence == "201~" {
+                "bracketed paste marker".to_string()
+            } else {
+                "CSI sequence".to_string()
+            };
+            (end, description)
+        }
+        ']' => {
+            let end = string_end(chars, body);
+            (end, describe_osc(&chars[body..end]))
+        }
+        'P' => (string_end(chars, body), "device control string".to_string()),
+        _ => (string_end(chars, body), "application control string".to_string()),
+    }
+}
+
+// Applies the paste guard and turns line breaks into the carriage returns a
+// terminal sends for Enter
+pub fn sanitize(text: &str, guard: PasteGuard) -> (String, PasteReport) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut report = PasteReport::default();
+
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\r' if chars.get(i + 1) == Some(&'\n') => {
+                out.push('\r');
+                i += 2;
+            }
+            '\n' | '\r' => {
+                out.push('\r');
+                i += 1;
+            }
+            c if is_control(c) => {
+                let (end, description) = sequence_at(&chars, i);
+                if !report.found.contains(&description) {
+                    report.found.push(description);
+                }
+                match guard {
+                    PasteGuard::Off => out.extend(&chars[i..end]),
+                    PasteGuard::Strip => {}
+                    PasteGuard::Escape => out.extend(chars[i..end].iter().map(|&c| visible(c))),
+                }
+                i = end;
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    report.modified = guard != PasteGuard::Off && !report.found.is_empty();
+    report.line_breaks = out.matches('\r').count();
+    (out, report)
+}
+
+fn prepare(state: &AppState, text: &str, bracketed: bool) -> (String, PasteReport) {
+    let guard = state.settings.lock().map(|settings| settings.paste_guard).unwrap_or_default();
+    let (text, mut report) = sanitize(text, guard);
+    if bracketed {
+        report.line_breaks = 0;
+        return (format!("{}{}{}", BRACKETED_PASTE_START, text, BRACKETED_PASTE_END), report);
+    }
+    (text, report)
+}
+
+// What send_paste would change, so the UI can ask before a multi-line or
+// suspicious paste goes out. `bracketed` is whether the remote program has
+// turned bracketed paste mode on.
+#[tauri::command]
+pub async fn check_paste(state: State<'_, AppState>, text: String, bracketed: bool) -> Result<PasteReport, SshError> {
+    Ok(prepare(&state, &text, bracketed).1)
+}
+
+// Sends clipboard text to the terminal after the paste_guard setting has
+// dealt with any control sequences hidden in it
+#[tauri::command]
+pub async fn send_paste(
+    state: State<'_, AppState>,
+    ssh: State<'_, Arc<SshManager>>,
+    local: State<'_, LocalTerminalManager>,
+    session_id: String,
+    text: String,
+    bracketed: bool,
+) -> Result<PasteReport, SshError> {
+    let (input, report) = prepare(&state, &text, bracketed);
+    if report.modified {
+        warn!("Paste into {} contained {}", session_id, report.found.join(", "));
+    }
+
+    if local.contains(&session_id) {
+        local.write(&session_id, &input)?;
+    } else {
+        ssh.send_input(&session_id, &input)?;
+    }
+    Ok(report)
+}
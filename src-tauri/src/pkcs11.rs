@@ -0,0 +1,154 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use log::{debug, info};
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use zeroize::Zeroizing;
+
+use crate::error::SshError;
+use crate::fingerprint::sha256_fingerprint;
+use crate::local_terminal::find_in_path;
+
+// libssh2 can't load a PKCS#11 module itself, so smartcards and tokens go
+// through OpenSSH's tools: `ssh-keygen -D` lists the token's public keys and
+// `ssh-add -s` loads the token into ssh-agent, which then does the signing.
+// The system ssh fallback passes the provider to `ssh -I` instead.
+
+// A key on the token, as `ssh-keygen -D` lists it (no PIN needed)
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenKey {
+    pub key_type: String,
+    pub fingerprint: String,
+    // The token's label for the key, when it has one
+    pub comment: Option<String>,
+    // An authorized_keys line for the key
+    pub public_key: String,
+    #[serde(skip)]
+    blob: Vec<u8>,
+}
+
+fn tool(name: &str) -> Result<std::path::PathBuf, String> {
+    find_in_path(name).ok_or_else(|| format!("{} was not found on PATH; it is needed for PKCS#11 tokens", name))
+}
+
+fn parse_token_keys(output: &str) -> Vec<TokenKey> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (key_type, key) = (fields.next()?, fields.next()?);
+            let blob = BASE64.decode(key).ok()?;
+            let comment = fields.collect::<Vec<_>>().join(" ");
+            Some(TokenKey {
+                key_type: key_type.to_string(),
+                fingerprint: sha256_fingerprint(&blob),
+                comment: (!comment.is_empty()).then_some(comment),
+                public_key: line.trim().to_string(),
+                blob,
+            })
+        })
+        .collect()
+}
+
+pub fn token_keys(provider: &str) -> Result<Vec<TokenKey>, String> {
+    let output = Command::new(tool("ssh-keygen")?)
+        .arg("-D")
+        .arg(provider)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run ssh-keygen: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to read the keys of {}: {}",
+            provider,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_token_keys(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Loads the token's keys into ssh-agent. ssh-add reads the PIN from stdin
+// when it has no terminal or askpass program to ask with.
+pub fn add_to_agent(provider: &str, pin: &str) -> Result<(), String> {
+    let mut child = Command::new(tool("ssh-add")?)
+        .arg("-s")
+        .arg(provider)
+        .env_remove("SSH_ASKPASS")
+        .env_remove("SSH_ASKPASS_REQUIRE")
+        .env_remove("DISPLAY")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run ssh-add: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let line = Zeroizing::new(format!("{}\n", pin));
+        stdin.write_all(line.as_bytes()).map_err(|e| format!("Failed to pass the PIN to ssh-add: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| format!("ssh-add failed: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ssh-add could not load {}: {}",
+            provider,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    info!("Loaded the keys of {} into ssh-agent", provider);
+    Ok(())
+}
+
+// Authenticates with the token's keys through ssh-agent, offering only those
+// keys and not whatever else the agent holds. When the agent doesn't have
+// them yet, `ask_pin` is asked for the PIN to load them with.
+pub fn authenticate(
+    sess: &ssh2::Session,
+    username: &str,
+    provider: &str,
+    ask_pin: impl FnOnce() -> Option<String>,
+) -> Result<(), SshError> {
+    let keys = token_keys(provider).map_err(SshError::Auth)?;
+    if keys.is_empty() {
+        return Err(SshError::Auth(format!("{} has no keys", provider)));
+    }
+    let on_token = |identity: &ssh2::PublicKey| keys.iter().any(|key| key.blob == identity.blob());
+
+    let mut agent = sess.agent()?;
+    agent.connect()?;
+    agent.list_identities()?;
+    if !agent.identities()?.iter().any(on_token) {
+        let pin = Zeroizing::new(ask_pin().ok_or_else(|| {
+            SshError::Auth("The token's keys are not in ssh-agent and no PIN was given to load them".to_string())
+        })?);
+        add_to_agent(provider, &pin).map_err(SshError::Auth)?;
+        agent.list_identities()?;
+    }
+
+    for identity in agent.identities()?.iter().filter(|identity| on_token(identity)) {
+        debug!("Trying token key {} for {}", identity.comment(), username);
+        if agent.userauth(username, identity).is_ok() {
+            return Ok(());
+        }
+    }
+    Err(SshError::Auth("The server accepted none of the token's keys".to_string()))
+}
+
+// The keys on a token, to pick the provider in the session editor and to
+// check which key the server needs in authorized_keys
+#[tauri::command]
+pub async fn list_pkcs11_keys(provider_path: String) -> Result<Vec<TokenKey>, String> {
+    tauri::async_runtime::spawn_blocking(move || token_keys(&provider_path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Unlocks a token into ssh-agent ahead of time, for the SFTP and exec
+// connections that can't stop to ask for the PIN
+#[tauri::command]
+pub async fn add_pkcs11_to_agent(provider_path: String, pin: String) -> Result<(), String> {
+    let pin = Zeroizing::new(pin);
+    tauri::async_runtime::spawn_blocking(move || add_to_agent(&provider_path, &pin))
+        .await
+        .map_err(|e| e.to_string())?
+}
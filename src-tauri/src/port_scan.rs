@@ -0,0 +1,132 @@
+use serde::Serialize;
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+// Where sshd usually is, or what a host that only has a web server answers on
+const DEFAULT_PORTS: [u16; 4] = [22, 2222, 80, 443];
+// This is a diagnostic for one host, not a scanner
+const MAX_PORTS: usize = 16;
+const MAX_PARALLEL: usize = 8;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(2000);
+const MAX_TIMEOUT: Duration = Duration::from_secs(10);
+// SSH servers speak first; anything else stays silent and is left alone
+const BANNER_TIMEOUT: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortState {
+    Open,
+    // Refused: the host is up but nothing listens there
+    Closed,
+    // No answer at all, usually a firewall dropping the packets
+    Filtered,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortScanResult {
+    pub port: u16,
+    pub state: PortState,
+    // First line the server sent, e.g. "SSH-2.0-OpenSSH_9.6"
+    pub banner: Option<String>,
+    pub ssh: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortScan {
+    // The address that was scanned
+    pub address: String,
+    pub ports: Vec<PortScanResult>,
+    // The first port an SSH server answered on, for a "try port 2222" hint
+    pub suggested_port: Option<u16>,
+}
+
+fn read_banner(stream: &mut TcpStream) -> Option<String> {
+    stream.set_read_timeout(Some(BANNER_TIMEOUT)).ok()?;
+    let mut buf = [0u8; 256];
+    let n = stream.read(&mut buf).ok().filter(|n| *n > 0)?;
+    let text = String::from_utf8_lossy(&buf[..n]);
+    let line = text.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+fn probe(addr: SocketAddr, timeout: Duration) -> PortScanResult {
+    let (state, banner, error) = match TcpStream::connect_timeout(&addr, timeout) {
+        Ok(mut stream) => (PortState::Open, read_banner(&mut stream), None),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => (PortState::Closed, None, None),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => (PortState::Filtered, None, None),
+        Err(e) => (PortState::Error, None, Some(e.to_string())),
+    };
+
+    PortScanResult {
+        port: addr.port(),
+        state,
+        ssh: banner.as_deref().is_some_and(|banner| banner.starts_with("SSH-")),
+        banner,
+        error,
+    }
+}
+
+// The default ports plus the requested ones, without duplicates
+fn ports_to_scan(requested: &[u16]) -> Result<Vec<u16>, String> {
+    let mut ports = DEFAULT_PORTS.to_vec();
+    for port in requested {
+        if *port == 0 {
+            return Err("Port 0 can't be scanned".to_string());
+        }
+        if !ports.contains(port) {
+            ports.push(*port);
+        }
+    }
+    if ports.len() > MAX_PORTS {
+        return Err(format!("At most {} ports can be checked at once", MAX_PORTS));
+    }
+    Ok(ports)
+}
+
+// Checks which of a few ports accept connections, to find out whether (and
+// where) SSH is listening when connecting fails
+#[tauri::command]
+pub async fn scan_host_ports(host: String, ports: Option<Vec<u16>>, timeout_ms: Option<u64>) -> Result<PortScan, String> {
+    let ports = ports_to_scan(&ports.unwrap_or_default())?;
+    let timeout = timeout_ms.map(Duration::from_millis).unwrap_or(DEFAULT_TIMEOUT).clamp(Duration::from_millis(100), MAX_TIMEOUT);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        // Every port is tried on the same address, whichever the host resolves to first
+        let ip = (host.as_str(), 0)
+            .to_socket_addrs()
+            .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+            .next()
+            .ok_or_else(|| format!("{} did not resolve to any address", host))?
+            .ip();
+
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<PortScanResult>>> = Mutex::new(vec![None; ports.len()]);
+        thread::scope(|scope| {
+            for _ in 0..MAX_PARALLEL.min(ports.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(port) = ports.get(index) else {
+                        break;
+                    };
+                    let result = probe(SocketAddr::new(ip, *port), timeout);
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+
+        let ports: Vec<PortScanResult> = results.into_inner().unwrap().into_iter().flatten().collect();
+        Ok(PortScan {
+            address: ip.to_string(),
+            suggested_port: ports.iter().find(|result| result.ssh).map(|result| result.port),
+            ports,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
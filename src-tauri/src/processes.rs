@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::exec::run_command;
+use crate::remote::open_session;
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteProcess {
+    pub pid: u32,
+    pub user: String,
+    pub cpu: f32,
+    pub mem: f32,
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum KillOutcome {
+    Killed,
+    PermissionDenied { stderr: String },
+    Failed { stderr: String, exit_status: i32 },
+}
+
+// `ps -eo` with trailing `=` suppresses the header and is understood by both
+// procps (Linux) and the BSD/macOS ps
+const PS_COMMAND: &str = "ps -eo pid=,user=,pcpu=,pmem=,args=";
+
+fn parse_ps_output(output: &str) -> Vec<RemoteProcess> {
+    output
+        .lines()
+        .filter_map(|line| {
+            // The command line is the rest of the row, spacing included
+            let mut rest = line.trim_start();
+            let mut field = || {
+                let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                rest = tail.trim_start();
+                Some(field).filter(|field| !field.is_empty())
+            };
+            let pid = field()?.parse().ok()?;
+            let user = field()?.to_string();
+            let cpu = field()?.parse().unwrap_or(0.0);
+            let mem = field()?.parse().unwrap_or(0.0);
+            let command = rest.trim_end().to_string();
+
+            Some(RemoteProcess { pid, user, cpu, mem, command })
+        })
+        .collect()
+}
+
+// Accepts `TERM`, `SIGTERM` or a signal number and rejects anything else so the
+// value can be interpolated into the remote command safely
+pub(crate) fn normalize_signal(signal: &str) -> Result<String, String> {
+    let signal = signal.trim();
+    let signal = signal.strip_prefix("SIG").unwrap_or(signal);
+
+    if signal.is_empty() || !signal.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid signal: {}", signal));
+    }
+
+    Ok(signal.to_ascii_uppercase())
+}
+
+#[tauri::command]
+pub async fn list_remote_processes(
+    state: State<'_, AppState>,
+    session_id: String,
+    password: Option<String>,
+) -> Result<Vec<RemoteProcess>, String> {
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+
+    let output = run_command(&sess, PS_COMMAND)?;
+    if output.exit_status != 0 {
+        return Err(format!("ps failed: {}", output.stderr.trim()));
+    }
+
+    Ok(parse_ps_output(&output.stdout))
+}
+
+#[tauri::command]
+pub async fn kill_remote_process(
+    state: State<'_, AppState>,
+    session_id: String,
+    pid: u32,
+    signal: Option<String>,
+    password: Option<String>,
+) -> Result<KillOutcome, String> {
+    let signal = normalize_signal(signal.as_deref().unwrap_or("TERM"))?;
+
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+
+    let output = run_command(&sess, &format!("kill -{} {}", signal, pid))?;
+    if output.exit_status == 0 {
+        return Ok(KillOutcome::Killed);
+    }
+
+    let stderr = output.stderr.trim().to_string();
+    if stderr.contains("Operation not permitted") || stderr.contains("Permission denied") {
+        Ok(KillOutcome::PermissionDenied { stderr })
+    } else {
+        Ok(KillOutcome::Failed { stderr, exit_status: output.exit_status })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ps_rows() {
+        let processes = parse_ps_output("    1 root      0.0  0.1 /sbin/init splash\n  812 alice    12.5  3.4 vim\n");
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].pid, 1);
+        assert_eq!(processes[0].user, "root");
+        assert_eq!(processes[0].command, "/sbin/init splash");
+        assert_eq!(processes[1].pid, 812);
+        assert_eq!(processes[1].cpu, 12.5);
+        assert_eq!(processes[1].mem, 3.4);
+        assert_eq!(processes[1].command, "vim");
+    }
+
+    #[test]
+    fn skips_header_and_malformed_lines() {
+        let output = "  PID USER     %CPU %MEM ARGS\n\n  42 bob 0.0 0.0 sleep 60\nnot a process\n  43\n";
+        let processes = parse_ps_output(output);
+        assert_eq!(processes.len(), 1);
+        assert_eq!(processes[0].pid, 42);
+        assert_eq!(processes[0].command, "sleep 60");
+    }
+
+    #[test]
+    fn keeps_spacing_inside_args() {
+        let processes = parse_ps_output("  7 carol  0.3  0.0 sh -c echo  'two  spaces'   \n");
+        assert_eq!(processes[0].command, "sh -c echo  'two  spaces'");
+    }
+
+    #[test]
+    fn allows_rows_without_args() {
+        let processes = parse_ps_output("  2 root 0.0 0.0\n");
+        assert_eq!(processes[0].pid, 2);
+        assert_eq!(processes[0].command, "");
+    }
+
+    #[test]
+    fn normalizes_signals() {
+        for (input, signal) in [("TERM", "TERM"), ("SIGKILL", "KILL"), ("hup", "HUP"), (" 9 ", "9")] {
+            assert_eq!(normalize_signal(input).unwrap(), signal);
+        }
+        for input in ["", "SIG", "TERM; rm -rf /", "-9", "KILL TERM"] {
+            assert!(normalize_signal(input).is_err(), "{} was accepted", input);
+        }
+    }
+}
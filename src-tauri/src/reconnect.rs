@@ -0,0 +1,368 @@
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::disconnect::DisconnectReason;
+use crate::error::SshError;
+use crate::settings::ReconnectPolicy;
+use crate::ssh_new::{emit_connection_status, probe_session, SshConfig, SshManager};
+use crate::{AppState, ConnectionStatus};
+
+// Granularity of the backoff sleep, so a cancel takes effect promptly
+const CANCEL_POLL: Duration = Duration::from_millis(250);
+// How long a dropped session is offered for reconnecting, and how many
+const DROP_EXPIRY: Duration = Duration::from_secs(60 * 60);
+const MAX_RECENT_DROPS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconnectTrigger {
+    Eof,
+    ReadError,
+    KeepaliveFailure,
+    Resume,
+}
+
+impl ReconnectTrigger {
+    fn enabled_in(self, policy: &ReconnectPolicy) -> bool {
+        match self {
+            ReconnectTrigger::Eof => policy.on_eof,
+            ReconnectTrigger::ReadError => policy.on_read_error,
+            ReconnectTrigger::KeepaliveFailure => policy.on_keepalive_failure,
+            ReconnectTrigger::Resume => policy.on_resume,
+        }
+    }
+}
+
+// Sent before each attempt so the UI can show "Reconnecting (2/5) in 4s…"
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconnectAttemptEvent {
+    pub session_id: String,
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub next_delay_ms: u64,
+    pub trigger: ReconnectTrigger,
+}
+
+// Sessions with a reconnect in progress, each with its cancel flag
+pub struct Reconnector {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl Reconnector {
+    pub fn new() -> Self {
+        Reconnector {
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // None when the session is already being reconnected
+    fn begin(&self, session_id: &str) -> Option<Arc<AtomicBool>> {
+        let mut running = self.running.lock().unwrap();
+        if running.contains_key(session_id) {
+            return None;
+        }
+        let cancelled = Arc::new(AtomicBool::new(false));
+        running.insert(session_id.to_string(), cancelled.clone());
+        Some(cancelled)
+    }
+
+    fn finish(&self, session_id: &str) {
+        self.running.lock().unwrap().remove(session_id);
+    }
+
+    fn is_running(&self, session_id: &str) -> bool {
+        self.running.lock().unwrap().contains_key(session_id)
+    }
+
+    pub fn cancel(&self, session_id: &str) -> bool {
+        match self.running.lock().unwrap().get(session_id) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// A session whose connection ended without the user closing it
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentDrop {
+    pub session_id: String,
+    pub host: String,
+    pub username: String,
+    pub trigger: ReconnectTrigger,
+    pub message: String,
+    // Set when the server said why it closed the connection
+    pub disconnect: Option<DisconnectReason>,
+    pub dropped_at: String,
+    // Auto-reconnect is still trying
+    pub reconnecting: bool,
+}
+
+struct DropEntry {
+    drop: RecentDrop,
+    since: Instant,
+    // What reconnect_drop needs, the connection itself may be gone by then
+    config: SshConfig,
+    pty_size: (u32, u32),
+}
+
+// Recently dropped sessions, so the UI can offer "3 sessions dropped,
+// reconnect?" once the network is back. Entries go once the session is
+// reconnected or closed, or after DROP_EXPIRY.
+pub struct RecentDrops {
+    entries: Mutex<HashMap<String, DropEntry>>,
+}
+
+impl RecentDrops {
+    pub fn new() -> Self {
+        RecentDrops {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(
+        &self,
+        session_id: &str,
+        trigger: ReconnectTrigger,
+        message: String,
+        disconnect: Option<DisconnectReason>,
+        config: &SshConfig,
+        pty_size: (u32, u32),
+    ) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.since.elapsed() < DROP_EXPIRY);
+        if entries.len() >= MAX_RECENT_DROPS && !entries.contains_key(session_id) {
+            let oldest = entries.iter().min_by_key(|(_, entry)| entry.since).map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(session_id.to_string(), DropEntry {
+            drop: RecentDrop {
+                session_id: session_id.to_string(),
+                host: config.host.clone(),
+                username: config.username.clone(),
+                trigger,
+                message,
+                disconnect,
+                dropped_at: chrono::Utc::now().to_rfc3339(),
+                reconnecting: false,
+            },
+            since: Instant::now(),
+            config: config.clone(),
+            pty_size,
+        });
+    }
+
+    pub fn remove(&self, session_id: &str) {
+        self.entries.lock().unwrap().remove(session_id);
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<RecentDrop> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(session_id).filter(|entry| entry.since.elapsed() < DROP_EXPIRY).map(|entry| entry.drop.clone())
+    }
+
+    // Newest first
+    fn list(&self, reconnector: &Reconnector) -> Vec<RecentDrop> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.since.elapsed() < DROP_EXPIRY);
+
+        let mut drops: Vec<(Instant, RecentDrop)> = entries
+            .values()
+            .map(|entry| {
+                let mut drop = entry.drop.clone();
+                drop.reconnecting = reconnector.is_running(&drop.session_id);
+                (entry.since, drop)
+            })
+            .collect();
+        drops.sort_by_key(|(since, _)| std::cmp::Reverse(*since));
+        drops.into_iter().map(|(_, drop)| drop).collect()
+    }
+
+    fn reconnect_info(&self, session_id: &str) -> Option<(SshConfig, (u32, u32))> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(session_id).filter(|entry| entry.since.elapsed() < DROP_EXPIRY)?;
+        Some((entry.config.clone(), entry.pty_size))
+    }
+}
+
+// Delay before `attempt` (1-based): the base delay doubled per failure, capped
+fn backoff(policy: &ReconnectPolicy, attempt: u32) -> Duration {
+    let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+    Duration::from_millis(policy.base_delay_ms.saturating_mul(factor).min(policy.max_delay_ms))
+}
+
+fn policy_for(app_handle: &AppHandle, config: &SshConfig) -> ReconnectPolicy {
+    config.reconnect.clone().unwrap_or_else(|| {
+        app_handle.state::<AppState>().settings.lock()
+            .map(|settings| settings.reconnect.clone())
+            .unwrap_or_default()
+    })
+}
+
+// Called from a session's reader thread when the connection ends. The rest
+// happens on a new thread: replacing the connection joins the reader.
+pub fn on_disconnect(
+    app_handle: &AppHandle,
+    session_id: &str,
+    trigger: ReconnectTrigger,
+    message: String,
+    disconnect: Option<DisconnectReason>,
+) {
+    let app_handle = app_handle.clone();
+    let session_id = session_id.to_string();
+
+    let spawned = thread::Builder::new().name(format!("reconnect-{}", session_id)).spawn(move || {
+        let manager = app_handle.state::<Arc<SshManager>>().inner().clone();
+        let Some((config, pty_size, session)) = manager.reconnect_info(&session_id) else {
+            return;
+        };
+
+        // A shell that exited on its own leaves the transport answering;
+        // only a connection that really dropped is worth reconnecting
+        if trigger == ReconnectTrigger::Eof && probe_session(&session).is_ok() {
+            return;
+        }
+        drop(session);
+
+        app_handle.state::<RecentDrops>().record(&session_id, trigger, message, disconnect, &config, pty_size);
+        start(&app_handle, &session_id, trigger, config, pty_size);
+    });
+
+    if let Err(e) = spawned {
+        error!("Failed to start a reconnect thread: {}", e);
+    }
+}
+
+// Retries the connection following the session's policy, blocking until it
+// succeeds, gives up or is cancelled. Does nothing if the policy is off or
+// doesn't cover `trigger`.
+pub fn start(app_handle: &AppHandle, session_id: &str, trigger: ReconnectTrigger, config: SshConfig, pty_size: (u32, u32)) {
+    let policy = policy_for(app_handle, &config);
+    if !policy.enabled || policy.max_attempts == 0 || !trigger.enabled_in(&policy) {
+        return;
+    }
+
+    let reconnector = app_handle.state::<Reconnector>();
+    let Some(cancelled) = reconnector.begin(session_id) else {
+        return;
+    };
+    let manager = app_handle.state::<Arc<SshManager>>();
+    info!("Reconnecting SSH session {} after {:?}", session_id, trigger);
+
+    let mut last_error = String::new();
+    for attempt in 1..=policy.max_attempts {
+        let delay = backoff(&policy, attempt);
+        let _ = app_handle.emit("reconnect_attempt", &ReconnectAttemptEvent {
+            session_id: session_id.to_string(),
+            attempt,
+            max_attempts: policy.max_attempts,
+            next_delay_ms: delay.as_millis() as u64,
+            trigger,
+        });
+
+        let mut waited = Duration::ZERO;
+        while waited < delay && !cancelled.load(Ordering::SeqCst) {
+            thread::sleep(CANCEL_POLL.min(delay - waited));
+            waited += CANCEL_POLL;
+        }
+        if cancelled.load(Ordering::SeqCst) {
+            info!("Reconnect of {} cancelled", session_id);
+            reconnector.finish(session_id);
+            emit_connection_status(app_handle, session_id, "disconnected", "Reconnect cancelled");
+            return;
+        }
+
+        set_status(app_handle, session_id, ConnectionStatus::Connecting);
+        match manager.reconnect(session_id, config.clone(), pty_size, app_handle.clone()) {
+            Ok(()) => {
+                info!("Reconnected SSH session {} on attempt {}", session_id, attempt);
+                reconnector.finish(session_id);
+                app_handle.state::<RecentDrops>().remove(session_id);
+                set_status(app_handle, session_id, ConnectionStatus::Connected);
+                return;
+            }
+            // Retrying with the same credentials would only lock the account
+            Err(SshError::Auth(e)) => {
+                last_error = format!("Authentication failed: {}", e);
+                break;
+            }
+            Err(e) => {
+                warn!("Reconnect attempt {} for {} failed: {}", attempt, session_id, e);
+                last_error = e.to_string();
+            }
+        }
+    }
+
+    reconnector.finish(session_id);
+    set_status(app_handle, session_id, ConnectionStatus::Disconnected);
+    emit_connection_status(app_handle, session_id, "disconnected", &format!("Gave up reconnecting: {}", last_error));
+}
+
+fn set_status(app_handle: &AppHandle, session_id: &str, status: ConnectionStatus) {
+    if let Ok(mut connections) = app_handle.state::<AppState>().active_connections.lock() {
+        connections.insert(session_id.to_string(), status);
+    }
+}
+
+// Stops a reconnect in progress; false when there was none
+#[tauri::command]
+pub async fn cancel_reconnect(reconnector: State<'_, Reconnector>, session_id: String) -> Result<bool, String> {
+    Ok(reconnector.cancel(&session_id))
+}
+
+#[tauri::command]
+pub async fn list_recent_drops(
+    drops: State<'_, RecentDrops>,
+    reconnector: State<'_, Reconnector>,
+) -> Result<Vec<RecentDrop>, String> {
+    Ok(drops.list(&reconnector))
+}
+
+// One-click way back for a dropped session, whether auto-reconnect is off
+// or gave up
+#[tauri::command]
+pub async fn reconnect_drop(
+    app_handle: AppHandle,
+    drops: State<'_, RecentDrops>,
+    reconnector: State<'_, Reconnector>,
+    session_id: String,
+) -> Result<(), SshError> {
+    let (config, pty_size) = drops
+        .reconnect_info(&session_id)
+        .ok_or_else(|| SshError::Other("The session is not among the recent drops".to_string()))?;
+    if reconnector.is_running(&session_id) {
+        return Err(SshError::Other("The session is already being reconnected".to_string()));
+    }
+
+    set_status(&app_handle, &session_id, ConnectionStatus::Connecting);
+    let manager = app_handle.state::<Arc<SshManager>>().inner().clone();
+    let handle = app_handle.clone();
+    let id = session_id.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || manager.reconnect(&id, config, pty_size, handle))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?;
+
+    match result {
+        Ok(()) => {
+            info!("Reconnected dropped SSH session {}", session_id);
+            drops.remove(&session_id);
+            set_status(&app_handle, &session_id, ConnectionStatus::Connected);
+            Ok(())
+        }
+        Err(e) => {
+            set_status(&app_handle, &session_id, ConnectionStatus::Disconnected);
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use ssh2::{KeyboardInteractivePrompt, Prompt};
+use std::path::Path;
+
+use crate::error::SshError;
+use crate::{AuthMethod, Session};
+
+// One authentication method open_session can try
+enum AuthStep<'a> {
+    Password(&'a str),
+    Key(&'a str),
+    Agent,
+    // The token's keys must already be in ssh-agent: there's no one to ask
+    // for the PIN here
+    Token(&'a str),
+}
+
+impl AuthStep<'_> {
+    // An empty list means the server wouldn't say, so everything is worth a try
+    fn offered(&self, methods: &str) -> bool {
+        let offers = |method| methods.is_empty() || methods.split(',').any(|offered| offered == method);
+        match self {
+            AuthStep::Password(_) => offers("password") || offers("keyboard-interactive"),
+            AuthStep::Key(_) | AuthStep::Agent | AuthStep::Token(_) => offers("publickey"),
+        }
+    }
+
+    fn run(&self, sess: &ssh2::Session, username: &str, methods: &str) -> Result<(), SshError> {
+        let result = match self {
+            // Servers that only do keyboard-interactive get the password through it
+            AuthStep::Password(password) if !methods.is_empty() && !methods.split(',').any(|m| m == "password") => {
+                sess.userauth_keyboard_interactive(username, &mut PasswordPrompter(password))
+            }
+            AuthStep::Password(password) => sess.userauth_password(username, password),
+            AuthStep::Key(key_path) => sess.userauth_pubkey_file(username, None, Path::new(key_path), None),
+            AuthStep::Agent => sess.userauth_agent(username),
+            AuthStep::Token(provider) => return crate::pkcs11::authenticate(sess, username, provider, || None),
+        };
+        Ok(result?)
+    }
+}
+
+// Answers a keyboard-interactive round that only asks for the password
+struct PasswordPrompter<'a>(&'a str);
+
+impl KeyboardInteractivePrompt for PasswordPrompter<'_> {
+    fn prompt<'b>(&mut self, _username: &str, _instructions: &str, prompts: &[Prompt<'b>]) -> Vec<String> {
+        prompts.iter().map(|prompt| if prompt.echo { String::new() } else { self.0.to_string() }).collect()
+    }
+}
+
+// Opens a fresh, authenticated SSH session for a stored session. An explicit
+// password always wins over the stored auth method. Servers that want more
+// than one method (AuthenticationMethods publickey,password) get the key and
+// then the password, if there is one.
+pub fn open_session(session: &Session, password: Option<&str>) -> Result<ssh2::Session, SshError> {
+    let (tcp, _) = crate::net::connect(&session.host, session.port, session.http_proxy.as_ref(), session.bind_address.as_deref())?;
+
+    let mut sess = ssh2::Session::new()?;
+
+    sess.set_tcp_stream(tcp);
+    sess.handshake()?;
+    crate::host_keys::verify(&sess, &session.host, session.port, session.host_key_trust)?;
+
+    // Asking for the methods tries "none" first, which some servers accept
+    let mut methods = sess.auth_methods(&session.username).map(str::to_string).unwrap_or_default();
+    if sess.authenticated() {
+        return Ok(sess);
+    }
+
+    let keychain_password = match (&session.auth_method, password) {
+        (_, Some(_)) => None,
+        (AuthMethod::Password, None) if session.store_password_in_keychain => {
+            Some(crate::keychain::require_password(&session.id).map_err(SshError::Auth)?)
+        }
+        (AuthMethod::Password, None) => {
+            return Err(SshError::Auth("Password authentication requires interactive input".to_string()));
+        }
+        // Only needed if the server asks for a password after the key
+        (_, None) if session.store_password_in_keychain => crate::keychain::require_password(&session.id).ok(),
+        (_, None) => None,
+    };
+
+    let mut steps = Vec::new();
+    if let Some(password) = password.or(keychain_password.as_deref()) {
+        steps.push(AuthStep::Password(password));
+    }
+    match &session.auth_method {
+        AuthMethod::PublicKey { key_path } => steps.push(AuthStep::Key(key_path)),
+        AuthMethod::Agent => steps.push(AuthStep::Agent),
+        AuthMethod::Pkcs11 { provider_path } => steps.push(AuthStep::Token(provider_path)),
+        AuthMethod::Password => {}
+    }
+
+    // Each step either finishes authentication, fails outright (the method
+    // list stays the same) or succeeds partially and leads to the next one
+    let mut last_error = None;
+    while !sess.authenticated() {
+        let Some(index) = steps.iter().position(|step| step.offered(&methods)) else {
+            if last_error.is_none() && !methods.is_empty() {
+                return Err(SshError::Auth(format!("The server requires authentication by {}", methods)));
+            }
+            break;
+        };
+        let step = steps.remove(index);
+        if let Err(e) = step.run(&sess, &session.username, &methods) {
+            last_error = Some(e);
+        }
+        if sess.authenticated() {
+            break;
+        }
+
+        let remaining = sess.auth_methods(&session.username).map(str::to_string).unwrap_or_else(|_| methods.clone());
+        if remaining == methods {
+            break;
+        }
+        log::debug!("Partial authentication success for {}, remaining: {}", session.id, remaining);
+        methods = remaining;
+        last_error = None;
+    }
+
+    if !sess.authenticated() {
+        return Err(match last_error {
+            Some(e) => e,
+            None => SshError::Auth("server rejected the credentials".to_string()),
+        });
+    }
+
+    Ok(sess)
+}
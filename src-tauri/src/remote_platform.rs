@@ -0,0 +1,210 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::error::SshError;
+use crate::exec::{run_command, shell_quote};
+use crate::remote::open_session;
+use crate::AppState;
+
+// One `key=value` per line. Run under sh explicitly like the user probe, and
+// with every check optional: minimal containers have no os-release, no
+// package manager and no init system to speak of.
+const PLATFORM_PROBE: &str = r#"echo "kernel=$(uname -s 2>/dev/null)"
+if [ -r /etc/os-release ]; then (. /etc/os-release; echo "os_id=${ID:-}"; echo "os_name=${PRETTY_NAME:-}"); fi
+for m in apt-get dnf yum apk pacman zypper brew pkg; do command -v "$m" >/dev/null 2>&1 && echo "pkg=$m"; done
+if [ -d /run/systemd/system ]; then echo init=systemd
+elif command -v rc-service >/dev/null 2>&1; then echo init=openrc
+elif command -v launchctl >/dev/null 2>&1; then echo init=launchd
+elif command -v service >/dev/null 2>&1 || [ -d /etc/init.d ]; then echo init=sysvinit
+fi"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageManager {
+    Apt,
+    Dnf,
+    Yum,
+    Apk,
+    Pacman,
+    Zypper,
+    Brew,
+    // FreeBSD's pkg
+    Pkg,
+}
+
+impl PackageManager {
+    fn from_command(command: &str) -> Option<Self> {
+        Some(match command {
+            "apt-get" => PackageManager::Apt,
+            "dnf" => PackageManager::Dnf,
+            "yum" => PackageManager::Yum,
+            "apk" => PackageManager::Apk,
+            "pacman" => PackageManager::Pacman,
+            "zypper" => PackageManager::Zypper,
+            "brew" => PackageManager::Brew,
+            "pkg" => PackageManager::Pkg,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitSystem {
+    Systemd,
+    Openrc,
+    Launchd,
+    // Plain init scripts, driven through `service`
+    Sysvinit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceAction {
+    Start,
+    Stop,
+    Restart,
+    Status,
+}
+
+impl ServiceAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            ServiceAction::Start => "start",
+            ServiceAction::Stop => "stop",
+            ServiceAction::Restart => "restart",
+            ServiceAction::Status => "status",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RemotePlatform {
+    // uname -s: Linux, Darwin, FreeBSD...
+    pub kernel: Option<String>,
+    // os-release ID and PRETTY_NAME, Linux only
+    pub os_id: Option<String>,
+    pub os_name: Option<String>,
+    // Every one found, the preferred one first (dnf before yum)
+    pub package_managers: Vec<PackageManager>,
+    pub init_system: Option<InitSystem>,
+}
+
+impl RemotePlatform {
+    // The command for a service action, or None without an init system to
+    // go through
+    pub fn service_command(&self, service: &str, action: ServiceAction) -> Option<String> {
+        let service = shell_quote(service);
+        Some(match self.init_system? {
+            InitSystem::Systemd => format!("systemctl {} {}", action.as_str(), service),
+            InitSystem::Openrc => format!("rc-service {} {}", service, action.as_str()),
+            InitSystem::Sysvinit => format!("service {} {}", service, action.as_str()),
+            // launchd services are addressed by label in the system domain
+            InitSystem::Launchd => match action {
+                ServiceAction::Start => format!("launchctl kickstart system/{}", service),
+                ServiceAction::Stop => format!("launchctl kill TERM system/{}", service),
+                ServiceAction::Restart => format!("launchctl kickstart -k system/{}", service),
+                ServiceAction::Status => format!("launchctl print system/{}", service),
+            },
+        })
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn parse_probe(output: &str) -> RemotePlatform {
+    let mut platform = RemotePlatform::default();
+    for (key, value) in output.lines().filter_map(|line| line.split_once('=')) {
+        match key {
+            "kernel" => platform.kernel = non_empty(value),
+            "os_id" => platform.os_id = non_empty(value),
+            "os_name" => platform.os_name = non_empty(value),
+            "pkg" => platform.package_managers.extend(PackageManager::from_command(value.trim())),
+            "init" => {
+                platform.init_system = match value.trim() {
+                    "systemd" => Some(InitSystem::Systemd),
+                    "openrc" => Some(InitSystem::Openrc),
+                    "launchd" => Some(InitSystem::Launchd),
+                    "sysvinit" => Some(InitSystem::Sysvinit),
+                    _ => None,
+                }
+            }
+            _ => {}
+        }
+    }
+    platform
+}
+
+// Looked up on first use, keyed by session id
+pub struct RemotePlatformCache {
+    entries: Mutex<HashMap<String, RemotePlatform>>,
+}
+
+impl RemotePlatformCache {
+    pub fn new() -> Self {
+        RemotePlatformCache {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn lookup(
+    state: &AppState,
+    cache: &RemotePlatformCache,
+    session_id: &str,
+    password: Option<&str>,
+    refresh: bool,
+) -> Result<RemotePlatform, SshError> {
+    if !refresh {
+        if let Some(platform) = cache.entries.lock().unwrap().get(session_id) {
+            return Ok(platform.clone());
+        }
+    }
+
+    let session = state.get_session(session_id).map_err(|_| SshError::SessionNotFound(session_id.to_string()))?;
+    let sess = open_session(&session, password)?;
+    let output = run_command(&sess, &format!("sh -c {}", shell_quote(PLATFORM_PROBE))).map_err(SshError::Other)?;
+    if output.exit_status != 0 {
+        debug!("Platform probe for {} exited with {}: {}", session_id, output.exit_status, output.stderr.trim());
+    }
+
+    let platform = parse_probe(&output.stdout);
+    cache.entries.lock().unwrap().insert(session_id.to_string(), platform.clone());
+    Ok(platform)
+}
+
+// Which package managers and init system the server has, so quick actions
+// can send the right command. Cached unless `refresh` is set.
+#[tauri::command]
+pub async fn detect_remote_platform(
+    state: State<'_, AppState>,
+    cache: State<'_, RemotePlatformCache>,
+    session_id: String,
+    password: Option<String>,
+    refresh: Option<bool>,
+) -> Result<RemotePlatform, SshError> {
+    lookup(&state, &cache, &session_id, password.as_deref(), refresh.unwrap_or(false))
+}
+
+// The command that starts/stops/restarts a service on this server, e.g.
+// `systemctl restart nginx` or `service nginx restart`
+#[tauri::command]
+pub async fn remote_service_command(
+    state: State<'_, AppState>,
+    cache: State<'_, RemotePlatformCache>,
+    session_id: String,
+    service: String,
+    action: ServiceAction,
+    password: Option<String>,
+) -> Result<String, SshError> {
+    let platform = lookup(&state, &cache, &session_id, password.as_deref(), false)?;
+    platform
+        .service_command(&service, action)
+        .ok_or_else(|| SshError::Other("No service manager found on the server".to_string()))
+}
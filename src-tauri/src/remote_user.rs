@@ -0,0 +1,155 @@
+use log::{debug, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::SshError;
+use crate::exec::{run_command, shell_quote, ExecOutput};
+use crate::remote::open_session;
+use crate::ssh_new::SshManager;
+use crate::{AppState, Session};
+
+// Run under sh explicitly: exec commands go through the login shell, which
+// may be csh or fish. getent is missing on macOS and some BusyBox systems,
+// hence the fallback to $SHELL.
+const USER_PROBE: &str = r#"u=$(id -un 2>/dev/null || whoami); echo "$u"; echo "$HOME"; s=$(getent passwd "$u" 2>/dev/null | cut -d: -f7); echo "${s:-$SHELL}""#;
+
+// The probe is a few builtins, but shares the connection with the shell
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+// The remote account's context. Each field is None when the server wouldn't
+// tell us, e.g. an SFTP-only account has a home but no shell access.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RemoteUserInfo {
+    pub user: Option<String>,
+    pub home: Option<String>,
+    pub shell: Option<String>,
+}
+
+// Looked up once per connect, keyed by session id
+pub struct RemoteUserCache {
+    entries: Mutex<HashMap<String, RemoteUserInfo>>,
+}
+
+impl RemoteUserCache {
+    pub fn new() -> Self {
+        RemoteUserCache {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<RemoteUserInfo> {
+        self.entries.lock().unwrap().get(session_id).cloned()
+    }
+
+    fn insert(&self, session_id: &str, info: RemoteUserInfo) {
+        self.entries.lock().unwrap().insert(session_id.to_string(), info);
+    }
+
+    pub fn invalidate(&self, session_id: &str) {
+        self.entries.lock().unwrap().remove(session_id);
+    }
+}
+
+fn non_empty(line: Option<&str>) -> Option<String> {
+    line.map(str::trim).filter(|line| !line.is_empty()).map(str::to_string)
+}
+
+fn parse_probe(session_id: &str, output: Result<ExecOutput, String>) -> RemoteUserInfo {
+    match output {
+        Ok(output) if output.exit_status == 0 => {
+            let mut lines = output.stdout.lines();
+            RemoteUserInfo {
+                user: non_empty(lines.next()),
+                home: non_empty(lines.next()),
+                shell: non_empty(lines.next()),
+            }
+        }
+        Ok(output) => {
+            debug!("User probe for {} exited with {}: {}", session_id, output.exit_status, output.stderr.trim());
+            RemoteUserInfo::default()
+        }
+        Err(e) => {
+            debug!("User probe for {} failed: {}", session_id, e);
+            RemoteUserInfo::default()
+        }
+    }
+}
+
+fn probe_command() -> String {
+    format!("sh -c {}", shell_quote(USER_PROBE))
+}
+
+// Over the terminal's own connection, when the session has one
+fn lookup_connected(manager: &SshManager, session_id: &str) -> Result<RemoteUserInfo, SshError> {
+    let output = manager.exec(session_id, &probe_command(), PROBE_TIMEOUT)?;
+    Ok(parse_probe(session_id, Ok(output)))
+}
+
+fn lookup(session: &Session, password: Option<&str>) -> Result<RemoteUserInfo, SshError> {
+    let sess = open_session(session, password)?;
+
+    // SFTP's realpath works even where the account has no shell
+    let sftp_home = sess.sftp()
+        .and_then(|sftp| sftp.realpath(Path::new(".")))
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| debug!("SFTP realpath failed for {}: {}", session.id, e))
+        .ok();
+
+    let mut info = parse_probe(&session.id, run_command(&sess, &probe_command()));
+    if sftp_home.is_some() {
+        info.home = sftp_home;
+    }
+    Ok(info)
+}
+
+// Looks the account up in the background after a connect, over the new
+// connection, so the file browser and command features have it ready.
+// Failures only mean a later lookup happens on demand.
+pub fn prime(app_handle: &AppHandle, session_id: String) {
+    let app_handle = app_handle.clone();
+    app_handle.state::<RemoteUserCache>().invalidate(&session_id);
+
+    thread::spawn(move || {
+        let manager = app_handle.state::<AppState>().ssh_manager.clone();
+        match lookup_connected(&manager, &session_id) {
+            Ok(info) => app_handle.state::<RemoteUserCache>().insert(&session_id, info),
+            Err(e) => warn!("Could not look up the remote user for {}: {}", session_id, e),
+        }
+    });
+}
+
+// Home directory and default shell of the remote account, from the cache
+// unless `refresh` is set
+#[tauri::command]
+pub async fn get_remote_user_info(
+    state: State<'_, AppState>,
+    cache: State<'_, RemoteUserCache>,
+    session_id: String,
+    password: Option<String>,
+    refresh: Option<bool>,
+) -> Result<RemoteUserInfo, SshError> {
+    if !refresh.unwrap_or(false) {
+        if let Some(info) = cache.get(&session_id) {
+            return Ok(info);
+        }
+    }
+
+    // A connected terminal is used as is; otherwise a connection is opened
+    // for the lookup
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let manager = state.ssh_manager.clone();
+    let info = tauri::async_runtime::spawn_blocking(move || match lookup_connected(&manager, &session.id) {
+        Err(SshError::SessionNotFound(_)) => lookup(&session, password.as_deref()),
+        result => result,
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))??;
+
+    cache.insert(&session_id, info.clone());
+    Ok(info)
+}
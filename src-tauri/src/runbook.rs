@@ -0,0 +1,213 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::SshError;
+use crate::exec::{run_command, run_command_with_timeout, shell_quote, ExecOutput};
+use crate::remote::open_session;
+use crate::AppState;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptStep {
+    // May use {{name}} for a variable, which expands to its value quoted for
+    // the shell
+    pub command: String,
+    // Text the step's stdout or stderr must contain for it to pass
+    #[serde(default)]
+    pub expect: Option<String>,
+    // None runs the step for as long as it takes
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    // Sets this variable to the step's stdout, without trailing newlines the
+    // way $(...) does, for the steps after it
+    #[serde(default)]
+    pub capture: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    // Not run because an earlier step failed
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub index: usize,
+    // With variables expanded
+    pub command: String,
+    pub status: StepStatus,
+    pub output: Option<ExecOutput>,
+    // Whether `expect` was found, when the step has one
+    pub expect_matched: Option<bool>,
+    // Why the step failed, or couldn't run at all
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptResult {
+    pub success: bool,
+    pub steps: Vec<StepResult>,
+    // The variables as the last step left them
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Clone, Serialize)]
+struct ScriptStepEvent<'a> {
+    session_id: &'a str,
+    total: usize,
+    #[serde(flatten)]
+    step: &'a StepResult,
+}
+
+// Replaces each {{name}} with the shell-quoted value of the variable
+fn expand(command: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut expanded = String::with_capacity(command.len());
+    let mut rest = command;
+    while let Some(start) = rest.find("{{") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or_else(|| format!("Unclosed {{{{ in `{}`", command))?;
+        let name = after[..end].trim();
+        let value = variables.get(name).ok_or_else(|| format!("Unknown variable {} in `{}`", name, command))?;
+        expanded.push_str(&shell_quote(value));
+        rest = &after[end + 2..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Catches a misspelled variable before anything runs rather than halfway
+// through the script
+fn check_steps(steps: &[ScriptStep], variables: &HashMap<String, String>) -> Result<(), String> {
+    let mut known = variables.clone();
+    for (index, step) in steps.iter().enumerate() {
+        if step.command.trim().is_empty() {
+            return Err(format!("Step {} has no command", index + 1));
+        }
+        expand(&step.command, &known).map_err(|e| format!("Step {}: {}", index + 1, e))?;
+        if let Some(name) = &step.capture {
+            if !valid_name(name) {
+                return Err(format!("Step {}: {} is not a valid variable name", index + 1, name));
+            }
+            known.insert(name.clone(), String::new());
+        }
+    }
+    Ok(())
+}
+
+fn run_step(sess: &ssh2::Session, index: usize, step: &ScriptStep, variables: &mut HashMap<String, String>) -> StepResult {
+    let mut result = StepResult {
+        index,
+        command: step.command.clone(),
+        status: StepStatus::Failed,
+        output: None,
+        expect_matched: None,
+        error: None,
+    };
+    let command = match expand(&step.command, variables) {
+        Ok(command) => command,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+    result.command = command;
+
+    let output = match step.timeout_secs {
+        Some(secs) if secs > 0 => run_command_with_timeout(sess, &result.command, Duration::from_secs(secs)),
+        _ => run_command(sess, &result.command),
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+
+    result.expect_matched = step
+        .expect
+        .as_deref()
+        .map(|expect| output.stdout.contains(expect) || output.stderr.contains(expect));
+    result.error = if output.timed_out {
+        Some("Timed out".to_string())
+    } else if output.exit_status != 0 {
+        Some(format!("Exited with status {}", output.exit_status))
+    } else if result.expect_matched == Some(false) {
+        Some(format!("The output doesn't contain `{}`", step.expect.as_deref().unwrap_or_default()))
+    } else {
+        None
+    };
+    if result.error.is_none() {
+        result.status = StepStatus::Passed;
+        if let Some(name) = &step.capture {
+            variables.insert(name.clone(), output.stdout.trim_end_matches(['\n', '\r']).to_string());
+        }
+    }
+    result.output = Some(output);
+    result
+}
+
+// Runs `steps` one after the other, each on its own exec channel of a
+// connection opened for the script, so shell state like `cd` doesn't carry
+// over; variables do. Stops at the first failed step unless
+// `continue_on_failure` is set. Each finished step is also sent as a
+// `script_step` event.
+#[tauri::command]
+pub async fn run_script(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    steps: Vec<ScriptStep>,
+    variables: Option<HashMap<String, String>>,
+    continue_on_failure: Option<bool>,
+    password: Option<String>,
+) -> Result<ScriptResult, SshError> {
+    let mut variables = variables.unwrap_or_default();
+    check_steps(&steps, &variables).map_err(SshError::Other)?;
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let continue_on_failure = continue_on_failure.unwrap_or(false);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let sess = open_session(&session, password.as_deref())?;
+        let mut results = Vec::with_capacity(steps.len());
+        let mut failed = false;
+
+        for (index, step) in steps.iter().enumerate() {
+            let result = if failed && !continue_on_failure {
+                StepResult {
+                    index,
+                    command: step.command.clone(),
+                    status: StepStatus::Skipped,
+                    output: None,
+                    expect_matched: None,
+                    error: None,
+                }
+            } else {
+                run_step(&sess, index, step, &mut variables)
+            };
+            failed |= result.status == StepStatus::Failed;
+
+            let _ = app.emit("script_step", &ScriptStepEvent {
+                session_id: &session_id,
+                total: steps.len(),
+                step: &result,
+            });
+            results.push(result);
+        }
+
+        info!("Ran a {} step script on {}: {}", steps.len(), session_id, if failed { "failed" } else { "succeeded" });
+        Ok(ScriptResult { success: !failed, steps: results, variables })
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))?
+}
@@ -0,0 +1,268 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+
+use crate::error::SshError;
+use crate::exec::{run_command, shell_quote};
+use crate::remote::open_session;
+use crate::sftp::{check_upload_mode, copy_with_buffer, SftpClient};
+use crate::transfer_history::{self, TransferDirection, TransferRecord};
+use crate::transfer_hooks::{self, HookTransfer, TransferStatus};
+use crate::AppState;
+
+// Auto picks SCP from this size up. SCP streams a single file with no
+// per-request round trips, which is where it beats SFTP on high-latency links.
+const AUTO_SCP_THRESHOLD: u64 = 64 * 1024 * 1024;
+const DEFAULT_BENCHMARK_SAMPLE: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferMethod {
+    #[default]
+    Sftp,
+    Scp,
+    // SCP for large files or when the SFTP subsystem is unavailable, SFTP otherwise
+    Auto,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodTransfer {
+    pub method: TransferMethod,
+    pub bytes: u64,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodBenchmark {
+    pub sample_bytes: u64,
+    // Bytes per second, None when the method isn't usable on this server
+    pub sftp_bytes_per_sec: Option<u64>,
+    pub scp_bytes_per_sec: Option<u64>,
+    pub sftp_error: Option<String>,
+    pub scp_error: Option<String>,
+    pub recommended: TransferMethod,
+}
+
+// Finishes an SCP channel the way the protocol expects so the remote scp exits cleanly
+fn close_scp_channel(mut channel: ssh2::Channel) -> Result<(), SshError> {
+    channel.send_eof()?;
+    channel.wait_eof()?;
+    channel.close()?;
+    channel.wait_close()?;
+    Ok(())
+}
+
+pub fn scp_download(sess: &ssh2::Session, remote_path: &str, local_path: &Path, buffer_size: usize) -> Result<u64, SshError> {
+    let (channel, stat) = sess.scp_recv(Path::new(remote_path))?;
+    let mut local_file = File::create(local_path)?;
+
+    // The channel carries the file followed by the protocol's trailing status byte
+    let mut reader = channel.take(stat.size());
+    let bytes = copy_with_buffer(&mut reader, &mut local_file, buffer_size)?;
+    if bytes < stat.size() {
+        return Err(SshError::ChannelClosed);
+    }
+
+    close_scp_channel(reader.into_inner())?;
+    Ok(bytes)
+}
+
+// Without `mode` a new file gets the local file's permissions
+pub fn scp_upload(
+    sess: &ssh2::Session,
+    local_path: &Path,
+    remote_path: &str,
+    buffer_size: usize,
+    mode: Option<u32>,
+) -> Result<u64, SshError> {
+    let mut local_file = File::open(local_path)?;
+    let metadata = local_file.metadata()?;
+
+    #[cfg(unix)]
+    let local_mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) as i32 & 0o777;
+    #[cfg(not(unix))]
+    let local_mode = 0o644;
+    let mode = mode.map_or(local_mode, |mode| mode as i32);
+
+    let mut channel = sess.scp_send(Path::new(remote_path), mode, metadata.len(), None)?;
+    let bytes = copy_with_buffer(&mut local_file, &mut channel, buffer_size)?;
+
+    close_scp_channel(channel)?;
+    Ok(bytes)
+}
+
+// scp only uses the mode when it creates the file, so an overwritten one is
+// chmod-ed over exec, there being no SFTP to setstat with
+fn chmod_over_exec(sess: &ssh2::Session, remote_path: &str, mode: u32) -> Result<(), SshError> {
+    let output = run_command(sess, &format!("chmod {:o} -- {}", mode, shell_quote(remote_path))).map_err(SshError::Other)?;
+    if output.exit_status != 0 {
+        return Err(SshError::Other(format!("Failed to set the mode of {}: {}", remote_path, output.stderr.trim())));
+    }
+    Ok(())
+}
+
+fn resolve_method(method: TransferMethod, size: Option<u64>, sftp: &Result<SftpClient, SshError>) -> TransferMethod {
+    match method {
+        TransferMethod::Auto if sftp.is_err() => TransferMethod::Scp,
+        TransferMethod::Auto if size.is_some_and(|size| size >= AUTO_SCP_THRESHOLD) => TransferMethod::Scp,
+        TransferMethod::Auto => TransferMethod::Sftp,
+        method => method,
+    }
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+fn run_hook(app: &AppHandle, session_id: &str, command: Option<&str>, local_path: &str, remote_path: &str, succeeded: bool) {
+    let status = if succeeded { TransferStatus::Success } else { TransferStatus::Failed };
+    let transfer = HookTransfer { local_path: local_path.to_string(), remote_path: remote_path.to_string(), status };
+    transfer_hooks::run(app, session_id, command, vec![transfer]);
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_file_with_method(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    remote_path: String,
+    local_path: String,
+    method: TransferMethod,
+    password: Option<String>,
+    on_complete_command: Option<String>,
+) -> Result<MethodTransfer, SshError> {
+    let on_complete_command = transfer_hooks::check(&state, on_complete_command).map_err(SshError::Other)?;
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let buffer_size = state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?.sftp_buffer_size;
+
+    let sess = open_session(&session, password.as_deref())?;
+    let sftp = SftpClient::from_session(sess.clone()).map(|client| client.with_buffer_size(buffer_size));
+    // Only Auto needs the size up front, and only SFTP can stat cheaply
+    let size = sftp.as_ref().ok().and_then(|client| client.stat(&remote_path).ok()).and_then(|stat| stat.size);
+
+    let method = resolve_method(method, size, &sftp);
+    let started = Instant::now();
+    let downloaded = match method {
+        TransferMethod::Scp => scp_download(&sess, &remote_path, Path::new(&local_path), buffer_size),
+        _ => sftp.and_then(|client| client.download_file(&remote_path, Path::new(&local_path))),
+    };
+    run_hook(&app, &session_id, on_complete_command.as_deref(), &local_path, &remote_path, downloaded.is_ok());
+    let bytes = downloaded?;
+
+    info!("Downloaded {} ({} bytes) using {:?}", remote_path, bytes, method);
+    let record = TransferRecord::new(&session, TransferDirection::Download, bytes, started.elapsed());
+    transfer_history::record(&app, record.with_paths(&local_path, &remote_path));
+    Ok(MethodTransfer { method, bytes, elapsed_ms: elapsed_ms(started) })
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_file_with_method(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    local_path: String,
+    remote_path: String,
+    method: TransferMethod,
+    password: Option<String>,
+    upload_mode: Option<u32>,
+    on_complete_command: Option<String>,
+) -> Result<MethodTransfer, SshError> {
+    let upload_mode = check_upload_mode(upload_mode)?;
+    let on_complete_command = transfer_hooks::check(&state, on_complete_command).map_err(SshError::Other)?;
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let buffer_size = state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?.sftp_buffer_size;
+
+    let sess = open_session(&session, password.as_deref())?;
+    let sftp = SftpClient::from_session(sess.clone()).map(|client| client.with_buffer_size(buffer_size));
+    let size = std::fs::metadata(&local_path).ok().map(|metadata| metadata.len());
+
+    let method = resolve_method(method, size, &sftp);
+    let started = Instant::now();
+    let uploaded = match method {
+        TransferMethod::Scp => scp_upload(&sess, Path::new(&local_path), &remote_path, buffer_size, upload_mode).and_then(|bytes| {
+            if let Some(mode) = upload_mode {
+                chmod_over_exec(&sess, &remote_path, mode)?;
+            }
+            Ok(bytes)
+        }),
+        _ => sftp.and_then(|client| {
+            let bytes = client.upload_file(Path::new(&local_path), &remote_path)?;
+            if let Some(mode) = upload_mode {
+                client.set_mode(&remote_path, mode)?;
+            }
+            Ok(bytes)
+        }),
+    };
+    run_hook(&app, &session_id, on_complete_command.as_deref(), &local_path, &remote_path, uploaded.is_ok());
+    let bytes = uploaded?;
+
+    info!("Uploaded {} ({} bytes) using {:?}", local_path, bytes, method);
+    let record = TransferRecord::new(&session, TransferDirection::Upload, bytes, started.elapsed());
+    transfer_history::record(&app, record.with_paths(&local_path, &remote_path));
+    Ok(MethodTransfer { method, bytes, elapsed_ms: elapsed_ms(started) })
+}
+
+fn bytes_per_sec(bytes: u64, elapsed: Duration) -> u64 {
+    (bytes as f64 / elapsed.as_secs_f64().max(0.001)) as u64
+}
+
+// Reads up to `limit` bytes and throws them away, returning the throughput
+fn time_read(reader: &mut impl Read, limit: u64, buffer_size: usize) -> std::io::Result<u64> {
+    let started = Instant::now();
+    let read = copy_with_buffer(&mut reader.take(limit), &mut std::io::sink(), buffer_size)?;
+    Ok(bytes_per_sec(read, started.elapsed()))
+}
+
+// Downloads the start of a remote file with both methods and recommends the
+// faster one. The sample should be large enough to get past TCP slow start.
+#[tauri::command]
+pub async fn benchmark_transfer_methods(
+    state: State<'_, AppState>,
+    session_id: String,
+    remote_path: String,
+    sample_bytes: Option<u64>,
+    password: Option<String>,
+) -> Result<MethodBenchmark, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let buffer_size = state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?.sftp_buffer_size;
+    let sample = sample_bytes.unwrap_or(DEFAULT_BENCHMARK_SAMPLE);
+
+    let sess = open_session(&session, password.as_deref())?;
+
+    let sftp_result = sess.sftp()
+        .and_then(|sftp| sftp.open(Path::new(&remote_path)))
+        .map_err(SshError::from)
+        .and_then(|mut file| Ok(time_read(&mut file, sample, buffer_size)?));
+
+    let scp_result = sess.scp_recv(Path::new(&remote_path))
+        .map_err(SshError::from)
+        .and_then(|(mut channel, stat)| {
+            let speed = time_read(&mut channel, sample.min(stat.size()), buffer_size)?;
+            // Stop partway through the file
+            let _ = channel.close();
+            Ok(speed)
+        });
+
+    let recommended = match (&sftp_result, &scp_result) {
+        (Ok(sftp), Ok(scp)) if scp > sftp => TransferMethod::Scp,
+        (Err(_), Ok(_)) => TransferMethod::Scp,
+        _ => TransferMethod::Sftp,
+    };
+    if let (Err(sftp), Err(scp)) = (&sftp_result, &scp_result) {
+        warn!("Neither SFTP ({}) nor SCP ({}) could read {}", sftp, scp, remote_path);
+    }
+
+    Ok(MethodBenchmark {
+        sample_bytes: sample,
+        sftp_bytes_per_sec: sftp_result.as_ref().ok().copied(),
+        scp_bytes_per_sec: scp_result.as_ref().ok().copied(),
+        sftp_error: sftp_result.err().map(|e| e.to_string()),
+        scp_error: scp_result.err().map(|e| e.to_string()),
+        recommended,
+    })
+}
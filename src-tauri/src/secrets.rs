@@ -0,0 +1,40 @@
+use log::info;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::ssh_new::SshManager;
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretsCleared {
+    pub passwords: usize,
+    pub watchers: usize,
+}
+
+// Wipes every credential the backend keeps in memory:
+// - passwords held by live SSH connections so they can be restarted
+// - passwords held by "edit with default app" watchers for re-uploading
+// The frontend drops its own copy when it sees the `secrets_cleared` event.
+// Passwords in the OS keychain are not in memory and are left alone. After
+// this, reconnecting or opening SFTP asks for the password again.
+pub fn clear(app_handle: &AppHandle) -> SecretsCleared {
+    let passwords = app_handle
+        .try_state::<Arc<SshManager>>()
+        .map(|manager| manager.clear_cached_secrets())
+        .unwrap_or(0);
+    let watchers = app_handle
+        .try_state::<AppState>()
+        .map(|state| state.temp_files.stop_secret_watchers())
+        .unwrap_or(0);
+
+    info!("Cleared {} cached password(s) and stopped {} watcher(s)", passwords, watchers);
+    let cleared = SecretsCleared { passwords, watchers };
+    let _ = app_handle.emit("secrets_cleared", &cleared);
+    cleared
+}
+
+#[tauri::command]
+pub async fn clear_secret_cache(app_handle: AppHandle) -> Result<SecretsCleared, String> {
+    Ok(clear(&app_handle))
+}
@@ -0,0 +1,216 @@
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager, State};
+
+use crate::settings::AppSettings;
+use crate::AppState;
+
+const LOG_EXTENSION: &str = "log";
+
+// Terminal output of sessions being logged, one file per session
+pub struct SessionLogger {
+    active: Mutex<HashMap<String, ActiveLog>>,
+}
+
+struct ActiveLog {
+    path: PathBuf,
+    file: File,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionLogInfo {
+    // The file name, which is what open_session_log takes
+    pub id: String,
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<String>,
+    // Still being written by a connected session
+    pub active: bool,
+}
+
+impl SessionLogger {
+    pub fn new() -> Self {
+        SessionLogger {
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Appends terminal output if the session is being logged
+    pub fn write(&self, session_id: &str, data: &str) {
+        let mut active = self.active.lock().unwrap();
+        if let Some(log) = active.get_mut(session_id) {
+            if let Err(e) = log.file.write_all(data.as_bytes()) {
+                warn!("Stopped logging session {}: {}", session_id, e);
+                active.remove(session_id);
+            }
+        }
+    }
+
+    // Returns whether the session was being logged
+    pub fn stop(&self, session_id: &str) -> bool {
+        self.active.lock().unwrap().remove(session_id).is_some()
+    }
+
+    fn active_paths(&self) -> Vec<PathBuf> {
+        self.active.lock().unwrap().values().map(|log| log.path.clone()).collect()
+    }
+}
+
+pub fn log_dir(app: &AppHandle, settings: &AppSettings) -> Result<PathBuf, String> {
+    match &settings.session_log_dir {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => Ok(app.path().app_data_dir().map_err(|e| e.to_string())?.join("session-logs")),
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+// host_YYYYmmdd-HHMMSS_xxxxxxxx.log. The random suffix keeps two sessions to
+// the same host started in the same second apart, and create_new makes sure
+// an existing file is never reused.
+fn create_log_file(dir: &Path, host: &str) -> std::io::Result<(PathBuf, File)> {
+    std::fs::create_dir_all(dir)?;
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+
+    loop {
+        let suffix = &uuid::Uuid::new_v4().simple().to_string()[..8];
+        let path = dir.join(format!("{}_{}_{}.{}", sanitize(host), timestamp, suffix, LOG_EXTENSION));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn log_files(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<_> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == LOG_EXTENSION))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| (entry.path(), metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+        })
+        .collect();
+
+    // Newest first
+    files.sort_by_key(|file| std::cmp::Reverse(file.2));
+    files
+}
+
+// Deletes the oldest logs beyond `keep` files or `max_bytes` in total (0
+// disables either limit). Logs that are still being written are never deleted,
+// so several sessions can share the directory.
+fn rotate(dir: &Path, keep: usize, max_bytes: u64, active: &[PathBuf]) {
+    let mut kept = 0usize;
+    let mut total = 0u64;
+
+    for (path, size, _) in log_files(dir) {
+        let is_active = active.contains(&path);
+        let over_count = keep > 0 && kept >= keep;
+        let over_size = max_bytes > 0 && total + size > max_bytes;
+
+        if !is_active && (over_count || over_size) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => info!("Rotated out session log {}", path.display()),
+                Err(e) => warn!("Failed to delete session log {}: {}", path.display(), e),
+            }
+            continue;
+        }
+
+        kept += 1;
+        total += size;
+    }
+}
+
+fn log_info(path: &Path, size: u64, modified: SystemTime, active: bool) -> SessionLogInfo {
+    SessionLogInfo {
+        id: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+        path: path.to_string_lossy().to_string(),
+        size,
+        modified: Some(chrono::DateTime::<chrono::Local>::from(modified).to_rfc3339()),
+        active,
+    }
+}
+
+// Starts writing the session's terminal output to a new file in the log
+// directory, rotating out old logs first
+#[tauri::command]
+pub async fn start_session_log(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    logger: State<'_, SessionLogger>,
+    session_id: String,
+) -> Result<SessionLogInfo, String> {
+    let session = state.get_session(&session_id)?;
+    let settings = state.settings.lock().map_err(|e| e.to_string())?.clone();
+    let dir = log_dir(&app, &settings)?;
+
+    let (path, file) = create_log_file(&dir, &session.host)
+        .map_err(|e| format!("Failed to create session log in {}: {}", dir.display(), e))?;
+    let info = log_info(&path, 0, SystemTime::now(), true);
+
+    // A session already being logged moves on to the new file
+    logger.active.lock().unwrap().insert(session_id, ActiveLog { path, file });
+    rotate(&dir, settings.session_log_keep, settings.session_log_max_mb * 1024 * 1024, &logger.active_paths());
+
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn stop_session_log(logger: State<'_, SessionLogger>, session_id: String) -> Result<(), String> {
+    if logger.stop(&session_id) {
+        Ok(())
+    } else {
+        Err("This session is not being logged".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn list_session_logs(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    logger: State<'_, SessionLogger>,
+) -> Result<Vec<SessionLogInfo>, String> {
+    let settings = state.settings.lock().map_err(|e| e.to_string())?.clone();
+    let active = logger.active_paths();
+
+    Ok(log_files(&log_dir(&app, &settings)?)
+        .into_iter()
+        .map(|(path, size, modified)| {
+            let is_active = active.contains(&path);
+            log_info(&path, size, modified, is_active)
+        })
+        .collect())
+}
+
+// Opens a log in the default application for .log files
+#[tauri::command]
+pub async fn open_session_log(app: AppHandle, state: State<'_, AppState>, id: String) -> Result<(), String> {
+    // Only bare file names from list_session_logs, nothing outside the directory
+    if id.contains(['/', '\\']) || id.starts_with('.') {
+        return Err("Invalid session log id".to_string());
+    }
+
+    let settings = state.settings.lock().map_err(|e| e.to_string())?.clone();
+    let path = log_dir(&app, &settings)?.join(&id);
+    if !path.is_file() {
+        return Err("Session log not found".to_string());
+    }
+
+    tauri_plugin_opener::open_path(&path, None::<&str>).map_err(|e| e.to_string())
+}
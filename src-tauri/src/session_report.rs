@@ -0,0 +1,254 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, State};
+
+use crate::latency::LatencySample;
+use crate::reconnect::{RecentDrop, RecentDrops};
+use crate::ssh_new::{ActiveConnectionInfo, SshManager};
+use crate::transfer_history::{TransferDirection, TransferHistory, TransferHistoryFilter, TransferRecord};
+use crate::{AppState, Protocol};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    // The timeline only, one row per event
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    Connected,
+    Dropped,
+    Upload,
+    Download,
+    Latency,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEvent {
+    pub at: String,
+    pub kind: TimelineEventKind,
+    pub detail: Option<String>,
+    pub bytes: Option<u64>,
+    pub rtt_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransferSummary {
+    pub uploads: usize,
+    pub downloads: usize,
+    pub bytes_uploaded: u64,
+    pub bytes_downloaded: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencySummary {
+    pub samples: usize,
+    // Probes the server didn't answer in time
+    pub timeouts: usize,
+    pub min_ms: Option<u64>,
+    pub avg_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+// What the app already knows about a session, put together for a support
+// ticket or a usage log. Nothing is measured for the report itself, so the
+// latency part is only there if the latency monitor was running.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionReport {
+    pub session_id: String,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub protocol: Protocol,
+    pub generated_at: String,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    // The current connection, with its uptime and traffic counters
+    pub connection: Option<ActiveConnectionInfo>,
+    // The last unexpected disconnect, if it's still remembered
+    pub last_drop: Option<RecentDrop>,
+    pub transfers: TransferSummary,
+    pub latency: LatencySummary,
+    // Oldest first
+    pub timeline: Vec<TimelineEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionReportExport {
+    pub format: ReportFormat,
+    pub report: SessionReport,
+    // The report in the requested format, ready to be saved
+    pub content: String,
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|time| time.with_timezone(&Utc))
+}
+
+// Unparseable timestamps are kept rather than silently left out
+fn in_range(at: &str, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> bool {
+    match parse_time(at) {
+        Some(at) => since.is_none_or(|since| at >= since) && until.is_none_or(|until| at <= until),
+        None => true,
+    }
+}
+
+fn summarize_transfers(transfers: &[TransferRecord]) -> TransferSummary {
+    let mut summary = TransferSummary::default();
+    for record in transfers {
+        match record.direction {
+            TransferDirection::Upload => {
+                summary.uploads += 1;
+                summary.bytes_uploaded += record.bytes;
+            }
+            TransferDirection::Download => {
+                summary.downloads += 1;
+                summary.bytes_downloaded += record.bytes;
+            }
+        }
+    }
+    summary
+}
+
+fn summarize_latency(samples: &[LatencySample]) -> LatencySummary {
+    let answered: Vec<u64> = samples.iter().filter_map(|sample| sample.rtt_ms).collect();
+    LatencySummary {
+        samples: samples.len(),
+        timeouts: samples.len() - answered.len(),
+        min_ms: answered.iter().min().copied(),
+        avg_ms: (!answered.is_empty()).then(|| answered.iter().sum::<u64>() / answered.len() as u64),
+        max_ms: answered.iter().max().copied(),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn timeline_csv(timeline: &[TimelineEvent]) -> String {
+    let mut csv = String::from("at,event,detail,bytes,rtt_ms\n");
+    for event in timeline {
+        let kind = serde_json::to_value(event.kind).ok().and_then(|value| value.as_str().map(str::to_string));
+        let row = [
+            csv_field(&event.at),
+            kind.unwrap_or_default(),
+            csv_field(event.detail.as_deref().unwrap_or("")),
+            event.bytes.map(|bytes| bytes.to_string()).unwrap_or_default(),
+            event.rtt_ms.map(|rtt| rtt.to_string()).unwrap_or_default(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// Builds a report of the session from its connection stats, transfer
+// history, latency samples and last drop. `since`/`until` (RFC 3339) limit
+// the transfers, samples and timeline to a time range.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_session_report(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    manager: State<'_, Arc<SshManager>>,
+    history: State<'_, TransferHistory>,
+    drops: State<'_, RecentDrops>,
+    session_id: String,
+    format: Option<ReportFormat>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<SessionReportExport, String> {
+    let session = state.get_session(&session_id)?;
+    let since_time = since.as_deref().map(|value| parse_time(value).ok_or(format!("Invalid time: {}", value))).transpose()?;
+    let until_time = until.as_deref().map(|value| parse_time(value).ok_or(format!("Invalid time: {}", value))).transpose()?;
+
+    let connection = manager.list_active_connections().into_iter().find(|info| info.session_id == session_id);
+    let last_drop = drops.get(&session_id).filter(|drop| in_range(&drop.dropped_at, since_time, until_time));
+    let transfers: Vec<TransferRecord> = history
+        .matching(&app, &TransferHistoryFilter {
+            session_id: Some(session_id.clone()),
+            ..Default::default()
+        })?
+        .into_iter()
+        .filter(|record| in_range(&record.at, since_time, until_time))
+        .collect();
+    // Only kept while the session is connected and being monitored
+    let samples: Vec<LatencySample> = manager
+        .latency_history(&session_id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|sample| in_range(&sample.at, since_time, until_time))
+        .collect();
+
+    let mut timeline = Vec::new();
+    if let Some(info) = connection.as_ref().filter(|info| in_range(&info.connected_at, since_time, until_time)) {
+        timeline.push(TimelineEvent {
+            at: info.connected_at.clone(),
+            kind: TimelineEventKind::Connected,
+            detail: Some(format!("{}@{} ({})", info.username, info.host, info.remote_addr)),
+            bytes: None,
+            rtt_ms: None,
+        });
+    }
+    if let Some(drop) = &last_drop {
+        timeline.push(TimelineEvent {
+            at: drop.dropped_at.clone(),
+            kind: TimelineEventKind::Dropped,
+            detail: Some(drop.message.clone()),
+            bytes: None,
+            rtt_ms: None,
+        });
+    }
+    timeline.extend(transfers.iter().map(|record| TimelineEvent {
+        at: record.at.clone(),
+        kind: match record.direction {
+            TransferDirection::Upload => TimelineEventKind::Upload,
+            TransferDirection::Download => TimelineEventKind::Download,
+        },
+        detail: record.remote_path.clone(),
+        bytes: Some(record.bytes),
+        rtt_ms: None,
+    }));
+    timeline.extend(samples.iter().map(|sample| TimelineEvent {
+        at: sample.at.clone(),
+        kind: TimelineEventKind::Latency,
+        detail: None,
+        bytes: None,
+        rtt_ms: sample.rtt_ms,
+    }));
+    timeline.sort_by_key(|event| parse_time(&event.at));
+
+    let report = SessionReport {
+        session_id,
+        name: session.name,
+        host: session.host,
+        port: session.port,
+        username: session.username,
+        protocol: session.protocol,
+        generated_at: Utc::now().to_rfc3339(),
+        since,
+        until,
+        connection,
+        last_drop,
+        transfers: summarize_transfers(&transfers),
+        latency: summarize_latency(&samples),
+        timeline,
+    };
+
+    let format = format.unwrap_or_default();
+    let content = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?,
+        ReportFormat::Csv => timeline_csv(&report.timeline),
+    };
+    Ok(SessionReportExport { format, report, content })
+}
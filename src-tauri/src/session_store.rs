@@ -0,0 +1,235 @@
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::{AppState, Session};
+
+// sessions.json is read and written here rather than through the store
+// plugin, which can neither skip a bad entry nor write atomically. The file
+// keeps the plugin's layout (`{"sessions": [...]}` in app data) so existing
+// libraries load unchanged.
+const STORE_FILE: &str = "sessions.json";
+const SESSIONS_KEY: &str = "sessions";
+// Rolling copies of the last stores that loaded cleanly, newest first
+const BACKUP_COUNT: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CorruptEntry {
+    // Position in the stored list
+    pub index: usize,
+    pub name: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StoreReport {
+    pub path: String,
+    pub loaded: usize,
+    pub corrupt_entries: Vec<CorruptEntry>,
+    // The file couldn't be parsed at all
+    pub file_error: Option<String>,
+    // Where the damaged file was copied before anything overwrote it
+    pub damaged_copy: Option<String>,
+    pub backups: Vec<String>,
+}
+
+impl StoreReport {
+    fn is_clean(&self) -> bool {
+        self.corrupt_entries.is_empty() && self.file_error.is_none()
+    }
+}
+
+fn store_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_data_dir().map_err(|e| e.to_string())
+}
+
+fn backup_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("sessions.backup-{}.json", n))
+}
+
+fn existing_backups(dir: &Path) -> Vec<PathBuf> {
+    (1..=BACKUP_COUNT).map(|n| backup_path(dir, n)).filter(|path| path.exists()).collect()
+}
+
+// Parses a store file entry by entry, so one bad session doesn't take the
+// rest of the library down with it
+pub(crate) fn parse(bytes: &[u8], report: &mut StoreReport) -> Vec<Session> {
+    let document: Value = match serde_json::from_slice(bytes) {
+        Ok(document) => document,
+        Err(e) => {
+            report.file_error = Some(e.to_string());
+            return Vec::new();
+        }
+    };
+
+    let entries = match document.get(SESSIONS_KEY) {
+        None | Some(Value::Null) => return Vec::new(),
+        Some(Value::Array(entries)) => entries,
+        Some(_) => {
+            report.file_error = Some("\"sessions\" is not a list".to_string());
+            return Vec::new();
+        }
+    };
+
+    let mut sessions = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        match serde_json::from_value::<Session>(entry.clone()) {
+            Ok(session) => sessions.push(session),
+            Err(e) => report.corrupt_entries.push(CorruptEntry {
+                index,
+                name: entry.get("name").and_then(Value::as_str).map(str::to_string),
+                error: e.to_string(),
+            }),
+        }
+    }
+    report.loaded = sessions.len();
+    sessions
+}
+
+fn read(path: &Path) -> Result<(Vec<Session>, StoreReport), String> {
+    let mut report = StoreReport { path: path.display().to_string(), ..Default::default() };
+    let sessions = match fs::read(path) {
+        Ok(bytes) => parse(&bytes, &mut report),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    Ok((sessions, report))
+}
+
+// Writes to a temp file next to the target and renames it over, so a crash
+// leaves either the old store or the new one, never half of each
+fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+// Pushes a copy of a cleanly loaded store onto the backups, unless it's the
+// same as the newest one (the store is loaded on every start)
+fn rotate_backups(dir: &Path, store: &Path) -> std::io::Result<()> {
+    let current = fs::read(store)?;
+    if fs::read(backup_path(dir, 1)).is_ok_and(|newest| newest == current) {
+        return Ok(());
+    }
+
+    for n in (1..BACKUP_COUNT).rev() {
+        let from = backup_path(dir, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(dir, n + 1))?;
+        }
+    }
+    write_atomically(&backup_path(dir, 1), &current)
+}
+
+// Loads the stored sessions, skipping entries that don't parse. A damaged
+// file is copied aside first, since the next save will replace it.
+pub fn load(app: &AppHandle) -> Result<(Vec<Session>, StoreReport), String> {
+    let dir = store_dir(app)?;
+    let path = dir.join(STORE_FILE);
+    let (sessions, mut report) = read(&path)?;
+
+    if report.is_clean() {
+        if path.exists() {
+            if let Err(e) = rotate_backups(&dir, &path) {
+                warn!("Failed to back up the session store: {}", e);
+            }
+        }
+    } else {
+        let damaged = dir.join(format!("sessions.damaged-{}.json", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        match fs::copy(&path, &damaged) {
+            Ok(_) => report.damaged_copy = Some(damaged.display().to_string()),
+            Err(e) => warn!("Failed to keep a copy of the damaged session store: {}", e),
+        }
+        warn!(
+            "Session store {} is damaged, {} entries skipped{}",
+            path.display(),
+            report.corrupt_entries.len(),
+            report.file_error.as_ref().map(|e| format!(", {}", e)).unwrap_or_default()
+        );
+    }
+
+    report.backups = existing_backups(&dir).iter().map(|p| p.display().to_string()).collect();
+    Ok((sessions, report))
+}
+
+pub fn save(app: &AppHandle, sessions: Vec<Session>) -> Result<(), String> {
+    let dir = store_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut document = Map::new();
+    document.insert(SESSIONS_KEY.to_string(), serde_json::to_value(sessions).map_err(|e| e.to_string())?);
+    let bytes = serde_json::to_vec_pretty(&document).map_err(|e| e.to_string())?;
+    write_atomically(&dir.join(STORE_FILE), &bytes).map_err(|e| format!("Failed to save sessions: {}", e))
+}
+
+// Drops the HTTP proxy passwords that stores from before they moved to the
+// keychain have in plain text, from the backups of them too
+pub fn scrub_proxy_passwords(app: &AppHandle) -> Result<(), String> {
+    for path in existing_backups(&store_dir(app)?) {
+        // A backup that doesn't parse has nothing we could scrub either
+        let document = fs::read(&path).ok().and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+        let Some(mut document) = document else { continue };
+        let mut scrubbed = false;
+        if let Some(Value::Array(entries)) = document.get_mut(SESSIONS_KEY) {
+            for entry in entries {
+                if let Some(Value::Object(proxy)) = entry.get_mut("http_proxy") {
+                    scrubbed |= proxy.remove("password").is_some();
+                }
+            }
+        }
+        if scrubbed {
+            let bytes = serde_json::to_vec_pretty(&document).map_err(|e| e.to_string())?;
+            write_atomically(&path, &bytes).map_err(|e| format!("Failed to rewrite {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+// Checks sessions.json without changing anything
+#[tauri::command]
+pub async fn verify_session_store(app: AppHandle) -> Result<StoreReport, String> {
+    let dir = store_dir(&app)?;
+    let (_, mut report) = read(&dir.join(STORE_FILE))?;
+    report.backups = existing_backups(&dir).iter().map(|p| p.display().to_string()).collect();
+    Ok(report)
+}
+
+// Replaces the session library with a backup, 1 being the newest, and
+// returns the restored sessions
+#[tauri::command]
+pub async fn restore_sessions_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    backup: Option<usize>,
+) -> Result<Vec<Session>, String> {
+    let n = backup.unwrap_or(1);
+    if !(1..=BACKUP_COUNT).contains(&n) {
+        return Err(format!("There are at most {} backups", BACKUP_COUNT));
+    }
+
+    let dir = store_dir(&app)?;
+    let backup_file = backup_path(&dir, n);
+    if !backup_file.exists() {
+        return Err(format!("Backup {} doesn't exist", n));
+    }
+    let (sessions, report) = read(&backup_file)?;
+    if let Some(e) = report.file_error {
+        return Err(format!("Backup {} is unreadable: {}", n, e));
+    }
+
+    save(&app, sessions.clone())?;
+    let mut state_sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    state_sessions.clear();
+    state_sessions.extend(sessions.iter().map(|session| (session.id.clone(), session.clone())));
+
+    info!("Restored {} session(s) from {}", sessions.len(), backup_file.display());
+    Ok(sessions)
+}
@@ -0,0 +1,304 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::AppState;
+
+// Mirrors OpenSSH's HashKnownHosts; Auto follows whatever the existing file does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashKnownHosts {
+    #[default]
+    Auto,
+    Yes,
+    No,
+}
+
+// How a server's host key is checked when connecting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HostKeyTrust {
+    // Pinned in ~/.ssh/known_hosts. Unknown hosts are added on first connect,
+    // like OpenSSH's StrictHostKeyChecking=accept-new.
+    Disk,
+    // Pinned for as long as the app runs and never written anywhere, for
+    // ephemeral hosts whose keys legitimately change between rebuilds
+    Memory,
+    // Pinned in known_hosts like Disk, but an unknown host is refused and
+    // reported with host_key_unknown until the user accepts its key
+    #[default]
+    Ask,
+    // Not checked
+    Off,
+}
+
+// What happens to shell output while the terminal is paused
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputPauseMode {
+    // Keep reading into a buffer and flush it on resume. Once the buffer is
+    // full it falls back to flow control rather than dropping output.
+    #[default]
+    Buffer,
+    // Stop reading the channel straight away. The SSH window fills up and the
+    // remote program blocks on its next write until the terminal resumes,
+    // which costs no memory but stalls whatever is producing the output.
+    FlowControl,
+}
+
+// What send_paste does with control characters and escape sequences in
+// pasted text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PasteGuard {
+    // Send the text as is
+    Off,
+    // Drop them
+    #[default]
+    Strip,
+    // Replace them with caret notation (ESC becomes "^["), so the user can
+    // see what was hidden in the text
+    Escape,
+}
+
+// When and how hard a dropped session is reconnected automatically
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub enabled: bool,
+    pub max_attempts: u32,
+    // Delay before the first attempt, doubled after every failure up to the max
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    // The connection closed. A shell that simply exited leaves the transport
+    // up and is never reconnected.
+    pub on_eof: bool,
+    pub on_read_error: bool,
+    // The dead session sweeper found the server no longer answering
+    pub on_keepalive_failure: bool,
+    // The computer woke from sleep and the server no longer answers
+    pub on_resume: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            enabled: false,
+            max_attempts: 5,
+            base_delay_ms: 1000,
+            max_delay_ms: 30_000,
+            on_eof: true,
+            on_read_error: true,
+            on_keepalive_failure: true,
+            on_resume: true,
+        }
+    }
+}
+
+// Which address family to try first when a host has both A and AAAA records
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AddressFamily {
+    #[default]
+    Any,
+    PreferIpv4,
+    PreferIpv6,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileSortKey {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    // By extension
+    Type,
+}
+
+// How the file browser lists a directory. Directories always come first,
+// each group sorted by `sort_by`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileListOptions {
+    pub show_hidden: bool,
+    pub sort_by: FileSortKey,
+    pub sort_desc: bool,
+}
+
+impl Default for FileListOptions {
+    fn default() -> Self {
+        FileListOptions {
+            show_hidden: true,
+            sort_by: FileSortKey::default(),
+            sort_desc: false,
+        }
+    }
+}
+
+// SFTP transfer buffer bounds. libssh2 splits each buffer into ~30KB requests
+// and pipelines them, so larger buffers mostly help on high-latency links;
+// past a few MB there is nothing left to gain but memory use.
+pub const DEFAULT_SFTP_BUFFER_SIZE: usize = 32 * 1024;
+pub const MIN_SFTP_BUFFER_SIZE: usize = 4 * 1024;
+pub const MAX_SFTP_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+pub fn clamp_sftp_buffer_size(size: usize) -> usize {
+    size.clamp(MIN_SFTP_BUFFER_SIZE, MAX_SFTP_BUFFER_SIZE)
+}
+
+// Backend settings persisted in settings.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub hash_known_hosts: HashKnownHosts,
+    // Sessions can override this with their own mode
+    pub host_key_trust: HostKeyTrust,
+    pub sftp_buffer_size: usize,
+    pub address_family: AddressFamily,
+    // chmod private keys (and ~/.ssh) that are too permissive before using them
+    pub fix_key_permissions: bool,
+    // Wipe cached passwords whenever the window loses focus
+    pub clear_secrets_when_unfocused: bool,
+    // Overwrite downloaded temp files before deleting them
+    pub wipe_temp_files: bool,
+    // Where session logs go; None means the app data directory
+    pub session_log_dir: Option<String>,
+    // Session log rotation: newest N files and total size in MB, 0 for no limit
+    pub session_log_keep: usize,
+    pub session_log_max_mb: u64,
+    // Keep file paths out of the transfer history
+    pub transfer_history_omit_paths: bool,
+    // Lets transfers run a local command when they finish. Off by default:
+    // whatever calls the transfer commands gets to run any program.
+    pub transfer_hooks_enabled: bool,
+    // Also run the command after a failed transfer, with %s set to "failed"
+    pub transfer_hooks_on_failure: bool,
+    pub output_pause_mode: OutputPauseMode,
+    // Output buffered while paused; the default is about what the terminal's
+    // 10000 line scrollback can hold, more could never be scrolled back to
+    pub output_pause_buffer_kb: usize,
+    // Output longer than this goes to the terminal as several events, so a
+    // megabyte-long line (or a resume flush) doesn't stall the renderer. 0
+    // sends it whole. Applies to sessions connected after a change.
+    pub output_chunk_kb: usize,
+    // Emit `sudo_prompt` when the terminal shows sudo's password prompt. The
+    // event alone changes nothing; filling the password in is up to the UI.
+    // Applies to sessions connected after a change.
+    pub detect_sudo_prompts: bool,
+    // Emit `shell_cwd_changed` whenever the shell reports a new working
+    // directory (OSC 7), for a file browser that follows the terminal
+    pub sftp_follow_shell_cwd: bool,
+    // How often the dead session sweeper runs, 0 turns it off
+    pub session_sweep_interval_secs: u64,
+    // A session silent for this long is probed and reaped if the server
+    // doesn't answer. Sessions whose reader thread died are always reaped.
+    pub dead_session_after_secs: u64,
+    // Sessions can override this with their own policy
+    pub reconnect: ReconnectPolicy,
+    pub paste_guard: PasteGuard,
+    // Sessions connected at once when opening a whole group
+    pub group_connect_limit: usize,
+    // Used by the directory listings unless they're given their own
+    pub file_list: FileListOptions,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            hash_known_hosts: HashKnownHosts::default(),
+            host_key_trust: HostKeyTrust::default(),
+            sftp_buffer_size: DEFAULT_SFTP_BUFFER_SIZE,
+            address_family: AddressFamily::default(),
+            fix_key_permissions: false,
+            clear_secrets_when_unfocused: false,
+            wipe_temp_files: false,
+            session_log_dir: None,
+            session_log_keep: 50,
+            session_log_max_mb: 500,
+            transfer_history_omit_paths: false,
+            transfer_hooks_enabled: false,
+            transfer_hooks_on_failure: false,
+            output_pause_mode: OutputPauseMode::default(),
+            output_pause_buffer_kb: 2048,
+            output_chunk_kb: 64,
+            detect_sudo_prompts: true,
+            sftp_follow_shell_cwd: false,
+            session_sweep_interval_secs: 60,
+            dead_session_after_secs: 30 * 60,
+            reconnect: ReconnectPolicy::default(),
+            paste_guard: PasteGuard::default(),
+            group_connect_limit: 4,
+            file_list: FileListOptions::default(),
+        }
+    }
+}
+
+const SETTINGS_KEY: &str = "app_settings";
+
+pub fn load(app: &AppHandle) -> AppSettings {
+    let stored = app.store("settings.json").ok().and_then(|store| store.get(SETTINGS_KEY));
+    let settings: AppSettings = stored
+        .as_ref()
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default();
+
+    // Settings saved before host_key_trust existed get the default (Ask)
+    // written out, so it shows as a choice rather than a missing value
+    if stored.is_some_and(|value| value.get("host_key_trust").is_none()) {
+        if let Err(e) = save(app, &settings) {
+            warn!("Failed to migrate the host key trust setting: {}", e);
+        }
+    }
+
+    crate::net::set_address_family(settings.address_family);
+    crate::host_keys::set_defaults(settings.host_key_trust, settings.hash_known_hosts);
+    crate::temp_files::set_wipe(settings.wipe_temp_files);
+    settings
+}
+
+fn save(app: &AppHandle, settings: &AppSettings) -> Result<(), String> {
+    let store = app.store("settings.json").map_err(|e| e.to_string())?;
+    store.set(SETTINGS_KEY, serde_json::to_value(settings).map_err(|e| e.to_string())?);
+    store.save().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
+    let settings = state.settings.lock().map_err(|e| e.to_string())?;
+    Ok(settings.clone())
+}
+
+#[tauri::command]
+pub async fn update_app_settings(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: AppSettings,
+) -> Result<AppSettings, String> {
+    apply(&app, &state, settings)
+}
+
+#[tauri::command]
+pub async fn get_file_list_options(state: State<'_, AppState>) -> Result<FileListOptions, String> {
+    Ok(state.settings.lock().map_err(|e| e.to_string())?.file_list)
+}
+
+// Remembers how the file browser should list directories from now on
+#[tauri::command]
+pub async fn set_file_list_options(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    options: FileListOptions,
+) -> Result<FileListOptions, String> {
+    let mut settings = state.settings.lock().map_err(|e| e.to_string())?.clone();
+    settings.file_list = options;
+    apply(&app, &state, settings).map(|settings| settings.file_list)
+}
+
+// Saves the settings and puts them into effect
+pub(crate) fn apply(app: &AppHandle, state: &AppState, mut settings: AppSettings) -> Result<AppSettings, String> {
+    settings.sftp_buffer_size = clamp_sftp_buffer_size(settings.sftp_buffer_size);
+    save(app, &settings)?;
+    crate::net::set_address_family(settings.address_family);
+    crate::host_keys::set_defaults(settings.host_key_trust, settings.hash_known_hosts);
+    crate::temp_files::set_wipe(settings.wipe_temp_files);
+
+    let mut current = state.settings.lock().map_err(|e| e.to_string())?;
+    *current = settings.clone();
+    Ok(settings)
+}
@@ -0,0 +1,899 @@
+use log::{error, info};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use libssh2_sys::{
+    LIBSSH2_FX_FAILURE, LIBSSH2_FX_NO_SPACE_ON_FILESYSTEM, LIBSSH2_FX_NO_SUCH_FILE, LIBSSH2_FX_NO_SUCH_PATH,
+    LIBSSH2_FX_PERMISSION_DENIED, LIBSSH2_FX_QUOTA_EXCEEDED, LIBSSH2_FX_WRITE_PROTECT,
+};
+use ssh2::{FileStat, OpenFlags, OpenType, Sftp};
+use std::collections::HashMap;
+use std::ffi::c_int;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, State};
+use zeroize::Zeroizing;
+
+use crate::error::SshError;
+use crate::exec::{run_command, shell_quote};
+use crate::settings::{
+    clamp_sftp_buffer_size, FileListOptions, FileSortKey, DEFAULT_SFTP_BUFFER_SIZE, MAX_SFTP_BUFFER_SIZE,
+    MIN_SFTP_BUFFER_SIZE,
+};
+use crate::temp_files::TempFileRegistry;
+use crate::transfer_history::{self, TransferDirection, TransferRecord};
+use crate::remote::open_session;
+use crate::xattrs::selinux_context;
+use crate::{AppState, FileItem, LinkTargetType, Session};
+
+// Files larger than this need an explicit confirmation before being opened locally
+const OPEN_WITH_CONFIRM_SIZE: u64 = 50 * 1024 * 1024;
+
+// Thin wrapper around an authenticated SFTP channel for a stored session
+pub struct SftpClient {
+    session: ssh2::Session,
+    sftp: Sftp,
+    buffer_size: usize,
+}
+
+// Like io::copy but with a caller chosen buffer, which for SFTP decides how
+// many requests libssh2 keeps in flight
+pub fn copy_with_buffer(reader: &mut impl Read, writer: &mut impl Write, buffer_size: usize) -> std::io::Result<u64> {
+    let mut buffer = vec![0u8; clamp_sftp_buffer_size(buffer_size)];
+    let mut total = 0u64;
+
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buffer[..n])?;
+        total += n as u64;
+    }
+
+    writer.flush()?;
+    Ok(total)
+}
+
+impl SftpClient {
+    pub fn connect(session: &Session, password: Option<&str>) -> Result<Self, SshError> {
+        Self::from_session(open_session(session, password)?)
+    }
+
+    // Fails when the server has the SFTP subsystem disabled
+    pub fn from_session(sess: ssh2::Session) -> Result<Self, SshError> {
+        let sftp = sess.sftp()?;
+        Ok(SftpClient { session: sess, sftp, buffer_size: DEFAULT_SFTP_BUFFER_SIZE })
+    }
+
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = clamp_sftp_buffer_size(buffer_size);
+        self
+    }
+
+    pub fn stat(&self, remote_path: &str) -> Result<FileStat, SshError> {
+        Ok(self.sftp.stat(Path::new(remote_path))?)
+    }
+
+    pub fn set_mode(&self, remote_path: &str, mode: u32) -> Result<(), SshError> {
+        let stat = FileStat { size: None, uid: None, gid: None, perm: Some(mode), atime: None, mtime: None };
+        Ok(self.sftp.setstat(Path::new(remote_path), stat)?)
+    }
+
+    // Entries of a directory, without `.` and `..`
+    pub fn read_dir(&self, remote_dir: &str) -> Result<Vec<(PathBuf, FileStat)>, SshError> {
+        Ok(self.sftp.readdir(Path::new(remote_dir))?)
+    }
+
+    pub fn remove_file(&self, remote_path: &str) -> Result<(), SshError> {
+        Ok(self.sftp.unlink(Path::new(remote_path))?)
+    }
+
+    // Canonical absolute form of a path, with `.`, `..` and symlinks resolved
+    // by the server. SFTP v3 servers don't expand `~` (OpenSSH only does with
+    // an extension libssh2 doesn't speak), so `~` and `~/...` are resolved
+    // against the login directory, which realpath(".") returns.
+    pub fn realpath(&self, remote_path: &str) -> Result<String, SshError> {
+        let path = match remote_path.strip_prefix('~') {
+            Some("") => ".".to_string(),
+            Some(rest) if rest.starts_with('/') => format!(".{}", rest),
+            _ => remote_path.to_string(),
+        };
+        let resolved = self.sftp.realpath(Path::new(&path))?;
+        Ok(resolved.to_string_lossy().to_string())
+    }
+
+    // Reads a whole (small) remote file into memory, refusing anything over
+    // `max_bytes` rather than truncating it
+    pub fn read_file(&self, remote_path: &str, max_bytes: u64) -> Result<Vec<u8>, SshError> {
+        let mut file = self.sftp.open(Path::new(remote_path))?;
+        let mut data = Vec::new();
+        Read::take(&mut file, max_bytes + 1).read_to_end(&mut data)?;
+
+        if data.len() as u64 > max_bytes {
+            return Err(SshError::Other(format!("{} is larger than {} bytes", remote_path, max_bytes)));
+        }
+        Ok(data)
+    }
+
+    pub fn download_file(&self, remote_path: &str, local_path: &Path) -> Result<u64, SshError> {
+        let mut remote_file = self.sftp.open(Path::new(remote_path))?;
+        let mut local_file = File::create(local_path)?;
+
+        Ok(copy_with_buffer(&mut remote_file, &mut local_file, self.buffer_size)?)
+    }
+
+    pub fn upload_file(&self, local_path: &Path, remote_path: &str) -> Result<u64, SshError> {
+        let mut local_file = File::open(local_path)?;
+        let needed = local_file.metadata()?.len();
+
+        let mut remote_file = self.sftp.create(Path::new(remote_path)).map_err(|e| {
+            let status = match e.code() {
+                ssh2::ErrorCode::SFTP(code) => Some(code),
+                _ => None,
+            };
+            self.upload_error(remote_path, needed, status, e.into())
+        })?;
+
+        match copy_with_buffer(&mut local_file, &mut remote_file, self.buffer_size) {
+            Ok(bytes) => Ok(bytes),
+            Err(e) => {
+                let error = self.upload_error(remote_path, needed, write_status(&e), e);
+                // Don't leave a truncated copy behind
+                drop(remote_file);
+                let _ = self.sftp.unlink(Path::new(remote_path));
+                Err(error)
+            }
+        }
+    }
+
+    // Recognizes a write that failed because the server is out of space.
+    // OpenSSH's sftp-server speaks SFTP v3, which has no status for that and
+    // sends a generic failure, so free space is checked with df instead.
+    fn upload_error(&self, remote_path: &str, needed: u64, status: Option<c_int>, e: std::io::Error) -> SshError {
+        let dir = Path::new(remote_path).parent()
+            .map(|parent| parent.to_string_lossy().to_string())
+            .filter(|parent| !parent.is_empty())
+            .unwrap_or_else(|| ".".to_string());
+
+        let available_bytes = match status {
+            Some(LIBSSH2_FX_NO_SPACE_ON_FILESYSTEM | LIBSSH2_FX_QUOTA_EXCEEDED) => available_space(&self.session, &dir),
+            Some(LIBSSH2_FX_FAILURE) => match available_space(&self.session, &dir) {
+                Some(available) if available < needed => Some(available),
+                _ => return SshError::Io(e),
+            },
+            _ => return SshError::Io(e),
+        };
+
+        error!("Upload of {} failed, the server is out of space", remote_path);
+        SshError::RemoteDiskFull { path: remote_path.to_string(), available_bytes }
+    }
+
+    // libssh2 doesn't implement the hardlink@openssh.com extension, so the link
+    // is made with `ln` over an exec channel, which SFTP-only accounts refuse
+    pub fn hard_link(&self, target: &str, link_path: &str) -> Result<(), SshError> {
+        let output = run_command(&self.session, &format!("ln -- {} {}", shell_quote(target), shell_quote(link_path)))
+            .map_err(|_| SshError::Sftp("Hard links are not supported on this server".to_string()))?;
+
+        if output.exit_status != 0 {
+            return Err(SshError::Sftp(format!("Failed to create hard link: {}", output.stderr.trim())));
+        }
+        Ok(())
+    }
+}
+
+// The mode uploads are given regardless of the account's umask, e.g. 0o644
+// for web content. Permission bits only, setuid/setgid/sticky included.
+pub fn check_upload_mode(mode: Option<u32>) -> Result<Option<u32>, SshError> {
+    match mode {
+        Some(mode) if mode > 0o7777 => Err(SshError::Other(format!("{:o} is not a valid file mode", mode))),
+        mode => Ok(mode),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WriteAccess {
+    Writable,
+    NotWritable { reason: String },
+    // Nothing at that path, so there's nowhere to upload to yet
+    Missing,
+    NotADirectory,
+}
+
+fn sftp_status(e: &ssh2::Error) -> Option<c_int> {
+    match e.code() {
+        ssh2::ErrorCode::SFTP(code) => Some(code),
+        _ => None,
+    }
+}
+
+impl SftpClient {
+    // Whether files can be created in `dir`, found out by creating (and
+    // removing) an empty file there. Permission bits alone can't tell: ACLs,
+    // read-only mounts and quotas all get the final say.
+    pub fn check_writable(&self, dir: &str) -> Result<WriteAccess, SshError> {
+        let stat = match self.sftp.stat(Path::new(dir)) {
+            Ok(stat) => stat,
+            Err(e) if matches!(sftp_status(&e), Some(LIBSSH2_FX_NO_SUCH_FILE | LIBSSH2_FX_NO_SUCH_PATH)) => {
+                return Ok(WriteAccess::Missing);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !stat.is_dir() {
+            return Ok(WriteAccess::NotADirectory);
+        }
+
+        let probe = format!("{}/.termnest-write-test-{}", dir.trim_end_matches('/'), uuid::Uuid::new_v4());
+        let flags = OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::EXCLUSIVE;
+        match self.sftp.open_mode(Path::new(&probe), flags, 0o600, OpenType::File) {
+            Ok(file) => {
+                drop(file);
+                if let Err(e) = self.sftp.unlink(Path::new(&probe)) {
+                    error!("Failed to remove write test file {}: {}", probe, e);
+                }
+                Ok(WriteAccess::Writable)
+            }
+            Err(e) => {
+                let reason = match sftp_status(&e) {
+                    Some(LIBSSH2_FX_PERMISSION_DENIED) => match (stat.perm, stat.uid) {
+                        (Some(perm), Some(uid)) => {
+                            format!("Permission denied (mode {:o}, owned by uid {})", perm & 0o7777, uid)
+                        }
+                        _ => "Permission denied".to_string(),
+                    },
+                    Some(LIBSSH2_FX_WRITE_PROTECT) => "The file system is read-only".to_string(),
+                    Some(LIBSSH2_FX_NO_SPACE_ON_FILESYSTEM) => "The file system is full".to_string(),
+                    Some(LIBSSH2_FX_QUOTA_EXCEEDED) => "Disk quota exceeded".to_string(),
+                    _ => e.message().to_string(),
+                };
+                Ok(WriteAccess::NotWritable { reason })
+            }
+        }
+    }
+}
+
+// ssh2 turns SFTP errors from file writes into io::Errors holding only the
+// status message, so the status is recovered from that
+fn write_status(e: &std::io::Error) -> Option<c_int> {
+    match e.to_string().as_str() {
+        "no space on filesystem" => Some(LIBSSH2_FX_NO_SPACE_ON_FILESYSTEM),
+        "quota exceeded" => Some(LIBSSH2_FX_QUOTA_EXCEEDED),
+        "failure" => Some(LIBSSH2_FX_FAILURE),
+        _ => None,
+    }
+}
+
+// Free space in bytes on the filesystem holding `dir`, for the connecting
+// user. df -P output is the same on GNU, BSD and BusyBox.
+pub fn available_space(sess: &ssh2::Session, dir: &str) -> Option<u64> {
+    let output = run_command(sess, &format!("df -Pk {}", shell_quote(dir))).ok()?;
+    let line = output.stdout.lines().nth(1)?;
+    let available_kb: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(available_kb * 1024)
+}
+
+// What a directory entry's SFTP attributes leave out
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShellDetails {
+    pub nlink: Option<u64>,
+    pub selinux_context: Option<String>,
+}
+
+// SFTP v3 attributes carry neither the link count nor the SELinux label, so
+// ask the shell for them. Best effort: servers without exec access or
+// GNU/BusyBox stat just get none.
+fn shell_details(sess: &ssh2::Session, dir: &str) -> HashMap<String, ShellDetails> {
+    let command = format!(
+        "cd {} && find . -mindepth 1 -maxdepth 1 -exec stat -c '%h %C %n' {{}} + 2>/dev/null",
+        shell_quote(dir)
+    );
+
+    let Ok(output) = run_command(sess, &command) else {
+        return HashMap::new();
+    };
+
+    output.stdout
+        .lines()
+        .filter_map(|line| {
+            let (count, rest) = line.split_once(' ')?;
+            // A stat that doesn't know %C may leave it out altogether
+            let (context, name) = match rest.strip_prefix("./") {
+                Some(name) => (None, name),
+                None => {
+                    let (context, name) = rest.split_once(' ')?;
+                    (selinux_context(context), name.strip_prefix("./")?)
+                }
+            };
+            let details = ShellDetails { nlink: count.parse().ok(), selinux_context: context };
+            Some((name.to_string(), details))
+        })
+        .collect()
+}
+
+// st_mode as `ls -l` shows it, e.g. "drwxr-xr-x"
+fn format_permissions(mode: u32) -> String {
+    let file_type = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+
+    let mut out = String::with_capacity(10);
+    out.push(file_type);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        out.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    out
+}
+
+fn format_mtime(stat: &FileStat) -> String {
+    match stat.mtime {
+        Some(mtime) => chrono::DateTime::from_timestamp(mtime as i64, 0)
+            .unwrap_or_else(chrono::Utc::now)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+fn link_target_type(target: Option<&FileStat>) -> LinkTargetType {
+    match target {
+        Some(stat) if stat.is_dir() => LinkTargetType::Directory,
+        Some(stat) if stat.is_file() => LinkTargetType::File,
+        Some(_) => LinkTargetType::Other,
+        None => LinkTargetType::Missing,
+    }
+}
+
+// Lists a remote directory. readdir attributes are lstat results, so a symlink
+// always shows up as a link here; its target is then resolved with stat. With
+// `follow_symlinks` the entry reports the target's type, size and mtime,
+// otherwise it reports the link itself.
+pub fn read_directory(
+    sftp: &Sftp,
+    path: &str,
+    follow_symlinks: bool,
+) -> Result<Vec<FileItem>, String> {
+    let dir_entries = sftp.readdir(Path::new(path))
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    let mut files = Vec::new();
+
+    for (path_buf, lstat) in dir_entries {
+        let name = path_buf.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let is_symlink = lstat.file_type().is_symlink();
+        let (link_target, target_stat) = if is_symlink {
+            let target = sftp.readlink(&path_buf)
+                .ok()
+                .map(|target| target.to_string_lossy().to_string());
+            (target, sftp.stat(&path_buf).ok())
+        } else {
+            (None, None)
+        };
+
+        // A dangling link has nothing to follow, so it is reported as itself
+        let stat = match &target_stat {
+            Some(target) if follow_symlinks => target,
+            _ => &lstat,
+        };
+        let is_directory = stat.is_dir();
+
+        files.push(FileItem {
+            path: path_buf.to_str().unwrap_or("").to_string(),
+            size: if is_directory { 0 } else { stat.size.unwrap_or(0) },
+            is_directory,
+            modified: format_mtime(stat),
+            is_symlink,
+            link_target,
+            link_target_type: is_symlink.then(|| link_target_type(target_stat.as_ref())),
+            nlink: None,
+            permissions: lstat.perm.map(format_permissions),
+            selinux_context: None,
+            name,
+        });
+    }
+
+    if let Some(parent) = parent_entry(path) {
+        files.insert(0, parent);
+    }
+
+    Ok(files)
+}
+
+fn extension(name: &str) -> String {
+    match name.rsplit_once('.') {
+        // A dotfile's leading dot doesn't start an extension
+        Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+fn compare_names(a: &str, b: &str) -> std::cmp::Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b))
+}
+
+// Drops hidden entries if asked to and sorts the rest: ".." first, then the
+// directories, then everything else, each by the chosen key. Entries that tie
+// fall back to their names, which never reverse.
+pub fn apply_list_options(files: &mut Vec<FileItem>, options: &FileListOptions) {
+    if !options.show_hidden {
+        files.retain(|file| file.name == ".." || !file.name.starts_with('.'));
+    }
+
+    files.sort_by(|a, b| {
+        let by_key = match options.sort_by {
+            FileSortKey::Name => compare_names(&a.name, &b.name),
+            FileSortKey::Size => a.size.cmp(&b.size),
+            // Formatted as "%Y-%m-%d %H:%M", which sorts as text; unknown
+            // times count as the oldest
+            FileSortKey::Modified => {
+                let modified = |file: &FileItem| if file.modified == "unknown" { String::new() } else { file.modified.clone() };
+                modified(a).cmp(&modified(b))
+            }
+            FileSortKey::Type => extension(&a.name).cmp(&extension(&b.name)),
+        };
+        let by_key = if options.sort_desc { by_key.reverse() } else { by_key };
+
+        (b.name == "..")
+            .cmp(&(a.name == ".."))
+            .then_with(|| b.is_directory.cmp(&a.is_directory))
+            .then(by_key)
+            .then_with(|| compare_names(&a.name, &b.name))
+    });
+}
+
+// The ".." entry shown at the top of every directory except the root
+pub fn parent_entry(path: &str) -> Option<FileItem> {
+    if path == "/" || path.is_empty() {
+        return None;
+    }
+
+    let parent_path = Path::new(path)
+        .parent()
+        .and_then(|p| p.to_str())
+        .unwrap_or("/")
+        .to_string();
+
+    Some(FileItem {
+        name: "..".to_string(),
+        path: parent_path,
+        size: 0,
+        is_directory: true,
+        modified: "".to_string(),
+        is_symlink: false,
+        link_target: None,
+        link_target_type: None,
+        nlink: None,
+        permissions: None,
+        selinux_context: None,
+    })
+}
+
+// libssh2 caps a single SFTP read or write request at this size
+const SFTP_REQUEST_SIZE: usize = 30000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferParameters {
+    // Buffer used for downloads and uploads, after clamping
+    pub buffer_size: usize,
+    pub min_buffer_size: usize,
+    pub max_buffer_size: usize,
+    pub sftp_request_size: usize,
+    // Roughly how many requests libssh2 pipelines per buffer
+    pub requests_in_flight: usize,
+    // Initial windows of a session channel opened on this connection. ssh2
+    // doesn't expose the SFTP subsystem's channel, so these are what a new
+    // channel gets from the server rather than what the SFTP one agreed on.
+    pub session_channel_local_window: u32,
+    pub session_channel_remote_window: u32,
+}
+
+impl SftpClient {
+    pub fn transfer_parameters(&self) -> Result<TransferParameters, SshError> {
+        let mut channel = self.session.channel_session()?;
+        let session_channel_local_window = channel.read_window().window_size_initial;
+        let session_channel_remote_window = channel.write_window().window_size_initial;
+        let _ = channel.close();
+
+        Ok(TransferParameters {
+            buffer_size: self.buffer_size,
+            min_buffer_size: MIN_SFTP_BUFFER_SIZE,
+            max_buffer_size: MAX_SFTP_BUFFER_SIZE,
+            sftp_request_size: SFTP_REQUEST_SIZE,
+            requests_in_flight: self.buffer_size.div_ceil(SFTP_REQUEST_SIZE),
+            session_channel_local_window,
+            session_channel_remote_window,
+        })
+    }
+}
+
+#[tauri::command]
+pub async fn get_transfer_parameters(
+    state: State<'_, AppState>,
+    session_id: String,
+    password: Option<String>,
+) -> Result<TransferParameters, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let buffer_size = state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?.sftp_buffer_size;
+
+    SftpClient::connect(&session, password.as_deref())?
+        .with_buffer_size(buffer_size)
+        .transfer_parameters()
+}
+
+// Resolves a path entered in the file browser; "~" gives the user's home
+#[tauri::command]
+pub async fn realpath(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+) -> Result<String, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    SftpClient::connect(&session, password.as_deref())?.realpath(&path)
+}
+
+// Free space on the server where `path` lives, None when df isn't available
+#[tauri::command]
+pub async fn get_remote_free_space(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+) -> Result<Option<u64>, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let sess = open_session(&session, password.as_deref())?;
+    Ok(available_space(&sess, &path))
+}
+
+// Lets the UI warn before an upload or edit that would fail on the last step
+#[tauri::command]
+pub async fn check_writable(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+) -> Result<WriteAccess, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let client = SftpClient::connect(&session, password.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || client.check_writable(&path))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn create_hard_link(
+    state: State<'_, AppState>,
+    session_id: String,
+    target: String,
+    link_path: String,
+    password: Option<String>,
+) -> Result<(), SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let client = SftpClient::connect(&session, password.as_deref())?;
+
+    // Fail early with a clear message rather than whatever ln says
+    client.stat(&target)?;
+    client.hard_link(&target, &link_path)
+}
+
+// Link counts and SELinux labels for the entries of a directory, keyed by
+// name. Listings leave these out since finding them takes an exec channel,
+// so the UI asks for them only when it's going to show them.
+#[tauri::command]
+pub async fn get_directory_details(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+) -> Result<HashMap<String, ShellDetails>, String> {
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+    Ok(shell_details(&sess, &path))
+}
+
+// How file contents are compressed during upload_file_compressed and
+// download_file_compressed. Regular SFTP transfers never compress.
+//
+// - Gzip: compressed only on the wire. The server runs `gzip` over an exec
+//   channel (instead of SFTP), so both sides end up with the same bytes as
+//   the original. Needs gzip and shell access on the server.
+// - StoreGzipped: the copy that lands on disk is the gzip file itself, saved
+//   as `<destination>.gz`. Uploads go over plain SFTP; downloads still need
+//   gzip on the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferCompression {
+    Gzip,
+    StoreGzipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompressedTransfer {
+    // Where the file was written, including the added `.gz` for StoreGzipped
+    pub destination: String,
+    pub original_bytes: u64,
+    pub transferred_bytes: u64,
+}
+
+// Counts the bytes passing through a reader or writer
+struct Counted<T> {
+    inner: T,
+    count: u64,
+}
+
+impl<T: Read> Read for Counted<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+impl<T: Write> Write for Counted<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Closes an exec channel used for a gzip pipe and turns a failed exit into an error
+fn finish_gzip_channel(mut channel: ssh2::Channel) -> Result<(), SshError> {
+    let mut stderr = String::new();
+    let _ = channel.stderr().read_to_string(&mut stderr);
+    channel.wait_close()?;
+
+    match channel.exit_status()? {
+        0 => Ok(()),
+        127 => Err(SshError::Sftp("gzip is not available on the server".to_string())),
+        status => Err(SshError::Sftp(format!("gzip exited with status {}: {}", status, stderr.trim()))),
+    }
+}
+
+impl SftpClient {
+    pub fn upload_file_compressed(
+        &self,
+        local_path: &Path,
+        remote_path: &str,
+        compression: TransferCompression,
+    ) -> Result<CompressedTransfer, SshError> {
+        let mut local_file = Counted { inner: File::open(local_path)?, count: 0 };
+
+        let (destination, transferred_bytes) = match compression {
+            TransferCompression::Gzip => {
+                let mut channel = self.session.channel_session()?;
+                channel.exec(&format!("gzip -dc > {}", shell_quote(remote_path)))?;
+
+                let mut encoder = GzEncoder::new(Counted { inner: &mut channel, count: 0 }, Compression::default());
+                copy_with_buffer(&mut local_file, &mut encoder, self.buffer_size)?;
+                let sent = encoder.finish()?.count;
+
+                channel.send_eof()?;
+                finish_gzip_channel(channel)?;
+                (remote_path.to_string(), sent)
+            }
+            TransferCompression::StoreGzipped => {
+                let destination = format!("{}.gz", remote_path);
+                let remote_file = self.sftp.create(Path::new(&destination))?;
+
+                let mut encoder = GzEncoder::new(Counted { inner: remote_file, count: 0 }, Compression::default());
+                copy_with_buffer(&mut local_file, &mut encoder, self.buffer_size)?;
+                let sent = encoder.finish()?.count;
+                (destination, sent)
+            }
+        };
+
+        Ok(CompressedTransfer { destination, original_bytes: local_file.count, transferred_bytes })
+    }
+
+    pub fn download_file_compressed(
+        &self,
+        remote_path: &str,
+        local_path: &Path,
+        compression: TransferCompression,
+    ) -> Result<CompressedTransfer, SshError> {
+        let mut channel = self.session.channel_session()?;
+        channel.exec(&format!("gzip -c < {}", shell_quote(remote_path)))?;
+
+        let (destination, original_bytes, transferred_bytes) = match compression {
+            TransferCompression::Gzip => {
+                let mut local_file = File::create(local_path)?;
+                let mut decoder = GzDecoder::new(Counted { inner: &mut channel, count: 0 });
+                let written = copy_with_buffer(&mut decoder, &mut local_file, self.buffer_size)?;
+                let received = decoder.into_inner().count;
+                (local_path.to_path_buf(), written, received)
+            }
+            TransferCompression::StoreGzipped => {
+                let mut destination = local_path.as_os_str().to_owned();
+                destination.push(".gz");
+                let destination = PathBuf::from(destination);
+
+                let mut local_file = File::create(&destination)?;
+                let received = copy_with_buffer(&mut channel, &mut local_file, self.buffer_size)?;
+                // The uncompressed size would need a second pass over the file
+                let original = self.stat(remote_path).ok().and_then(|stat| stat.size).unwrap_or(0);
+                (destination, original, received)
+            }
+        };
+
+        finish_gzip_channel(channel)?;
+        Ok(CompressedTransfer {
+            destination: destination.to_string_lossy().to_string(),
+            original_bytes,
+            transferred_bytes,
+        })
+    }
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_file_compressed(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    local_path: String,
+    remote_path: String,
+    compression: TransferCompression,
+    password: Option<String>,
+    upload_mode: Option<u32>,
+) -> Result<CompressedTransfer, SshError> {
+    let upload_mode = check_upload_mode(upload_mode)?;
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let buffer_size = state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?.sftp_buffer_size;
+
+    let client = SftpClient::connect(&session, password.as_deref())?.with_buffer_size(buffer_size);
+    let started = Instant::now();
+    let transfer = client.upload_file_compressed(Path::new(&local_path), &remote_path, compression)?;
+    if let Some(mode) = upload_mode {
+        client.set_mode(&transfer.destination, mode)?;
+    }
+    let record = TransferRecord::new(&session, TransferDirection::Upload, transfer.transferred_bytes, started.elapsed());
+    transfer_history::record(&app, record.with_paths(&local_path, &transfer.destination));
+    info!(
+        "Uploaded {} to {} ({} bytes, {} on the wire)",
+        local_path, transfer.destination, transfer.original_bytes, transfer.transferred_bytes
+    );
+    Ok(transfer)
+}
+
+#[tauri::command]
+pub async fn download_file_compressed(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    remote_path: String,
+    local_path: String,
+    compression: TransferCompression,
+    password: Option<String>,
+) -> Result<CompressedTransfer, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+    let buffer_size = state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?.sftp_buffer_size;
+
+    let client = SftpClient::connect(&session, password.as_deref())?.with_buffer_size(buffer_size);
+    let started = Instant::now();
+    let transfer = client.download_file_compressed(&remote_path, Path::new(&local_path), compression)?;
+    let record = TransferRecord::new(&session, TransferDirection::Download, transfer.transferred_bytes, started.elapsed());
+    transfer_history::record(&app, record.with_paths(&transfer.destination, &remote_path));
+    info!(
+        "Downloaded {} to {} ({} bytes, {} on the wire)",
+        remote_path, transfer.destination, transfer.original_bytes, transfer.transferred_bytes
+    );
+    Ok(transfer)
+}
+
+// Polls a local temp copy and re-uploads it whenever its modification time changes
+fn watch_and_reupload(
+    session: Session,
+    password: Option<Zeroizing<String>>,
+    local_path: PathBuf,
+    remote_path: String,
+    buffer_size: usize,
+    shutdown: Arc<AtomicBool>,
+) {
+    thread::spawn(move || {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last_modified: Option<SystemTime> = modified(&local_path);
+
+        while !shutdown.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_secs(1));
+
+            let current = modified(&local_path);
+            if current.is_none() || current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            let result = SftpClient::connect(&session, password.as_deref().map(String::as_str))
+                .and_then(|client| client.with_buffer_size(buffer_size).upload_file(&local_path, &remote_path));
+
+            match result {
+                Ok(bytes) => info!("Re-uploaded {} ({} bytes) to {}", local_path.display(), bytes, remote_path),
+                Err(e) => error!("Failed to re-upload {}: {}", local_path.display(), e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn open_remote_with_default_app(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+    watch_changes: Option<bool>,
+    confirm_large: Option<bool>,
+) -> Result<String, String> {
+    let session = state.get_session(&session_id)?;
+    let buffer_size = state.settings.lock().map_err(|e| e.to_string())?.sftp_buffer_size;
+
+    let client = SftpClient::connect(&session, password.as_deref())?.with_buffer_size(buffer_size);
+
+    let stat = client.stat(&path)?;
+    if stat.is_dir() {
+        return Err(format!("{} is a directory", path));
+    }
+
+    let size = stat.size.unwrap_or(0);
+    if size > OPEN_WITH_CONFIRM_SIZE && !confirm_large.unwrap_or(false) {
+        return Err(format!(
+            "File is {} bytes, which exceeds the {} byte auto-open limit; confirm to download it anyway",
+            size, OPEN_WITH_CONFIRM_SIZE
+        ));
+    }
+
+    let file_name = Path::new(&path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("remote-file")
+        .to_string();
+
+    let local_path = TempFileRegistry::allocate_path(&file_name)?;
+    client.download_file(&path, &local_path)?;
+
+    tauri_plugin_opener::open_path(&local_path, None::<&str>)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    let holds_secret = password.is_some();
+    let watcher_shutdown = if watch_changes.unwrap_or(false) {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let password = password.map(Zeroizing::new);
+        watch_and_reupload(session, password, local_path.clone(), path, buffer_size, shutdown.clone());
+        Some(shutdown)
+    } else {
+        None
+    };
+
+    state.temp_files.register(&session_id, local_path.clone(), watcher_shutdown, holds_secret);
+
+    Ok(local_path.to_string_lossy().to_string())
+}
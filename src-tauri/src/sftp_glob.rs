@@ -0,0 +1,208 @@
+use log::info;
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::error::SshError;
+use crate::sftp::SftpClient;
+use crate::transfers::{self, BatchTransferResult, ConflictPolicy};
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobMatch {
+    pub path: String,
+    pub name: String,
+    pub size: Option<u64>,
+    pub is_directory: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedDelete {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GlobDeleteResult {
+    pub matched: Vec<GlobMatch>,
+    pub deleted: Vec<String>,
+    // Matched directories, which are never deleted (like `rm` without -r)
+    pub skipped: Vec<String>,
+    pub failed: Vec<FailedDelete>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobDownloadResult {
+    pub matched: Vec<GlobMatch>,
+    // None for a dry run
+    pub transfer: Option<BatchTransferResult>,
+}
+
+// Whether `pattern[at]` matches `c`, and where the pattern continues if so.
+// Handles `?`, `[...]` classes (with ranges and `!`/`^` negation) and `\`
+// escapes; a `[` without a closing `]` is an ordinary character.
+fn token_match(pattern: &[char], at: usize, c: char) -> Option<usize> {
+    match pattern[at] {
+        '?' => Some(at + 1),
+        '[' => {
+            let mut i = at + 1;
+            let negated = matches!(pattern.get(i), Some('!' | '^'));
+            if negated {
+                i += 1;
+            }
+
+            let class_start = i;
+            let mut matched = false;
+            // A `]` right at the start is part of the class
+            while i < pattern.len() && (pattern[i] != ']' || i == class_start) {
+                if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|&end| end != ']') {
+                    matched |= (pattern[i]..=pattern[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    matched |= pattern[i] == c;
+                    i += 1;
+                }
+            }
+
+            if i >= pattern.len() {
+                return (c == '[').then_some(at + 1);
+            }
+            (matched != negated).then_some(i + 1)
+        }
+        '\\' if at + 1 < pattern.len() => (pattern[at + 1] == c).then_some(at + 2),
+        literal => (literal == c).then_some(at + 1),
+    }
+}
+
+// Shell-style match of a whole file name. Like the shell, wildcards don't
+// match a leading dot, so `*` leaves hidden files alone.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    if name.first() == Some(&'.') && pattern.first() != Some(&'.') {
+        return false;
+    }
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it's currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    loop {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+            continue;
+        }
+        if n == name.len() {
+            break;
+        }
+        if let Some(next) = (p < pattern.len()).then(|| token_match(&pattern, p, name[n])).flatten() {
+            p = next;
+            n += 1;
+            continue;
+        }
+        match backtrack {
+            // Let the `*` swallow one more character and try again
+            Some((star, matched_to)) => {
+                p = star + 1;
+                n = matched_to + 1;
+                backtrack = Some((star, matched_to + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Lists `dir` and keeps the entries whose name matches, sorted by name
+fn expand(client: &SftpClient, dir: &str, pattern: &str) -> Result<Vec<GlobMatch>, SshError> {
+    if pattern.is_empty() || pattern.contains('/') {
+        return Err(SshError::Other(format!("Invalid pattern: {:?} (it applies to names in one directory)", pattern)));
+    }
+
+    let mut matches: Vec<GlobMatch> = client.read_dir(dir)?
+        .into_iter()
+        .filter_map(|(path, stat)| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            glob_match(pattern, &name).then(|| GlobMatch {
+                path: format!("{}/{}", dir.trim_end_matches('/'), name),
+                name,
+                size: stat.size,
+                is_directory: stat.is_dir(),
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(matches)
+}
+
+// Deletes the files in `dir` matching `pattern` (`*`, `?`, `[...]`). Run it
+// with dry_run first to show the user what would go.
+#[tauri::command]
+pub async fn delete_remote_glob(
+    state: State<'_, AppState>,
+    session_id: String,
+    dir: String,
+    pattern: String,
+    dry_run: Option<bool>,
+    password: Option<String>,
+) -> Result<GlobDeleteResult, SshError> {
+    let (_, client) = transfers::connect(&state, &session_id, password.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut result = GlobDeleteResult { matched: expand(&client, &dir, &pattern)?, ..Default::default() };
+        if dry_run.unwrap_or(false) {
+            return Ok(result);
+        }
+
+        for entry in &result.matched {
+            if entry.is_directory {
+                result.skipped.push(entry.path.clone());
+                continue;
+            }
+            match client.remove_file(&entry.path) {
+                Ok(()) => result.deleted.push(entry.path.clone()),
+                Err(e) => result.failed.push(FailedDelete { path: entry.path.clone(), error: e.to_string() }),
+            }
+        }
+
+        info!("Deleted {} of {} file(s) matching {} in {}", result.deleted.len(), result.matched.len(), pattern, dir);
+        Ok(result)
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+// Downloads the files in `dir` matching `pattern` into `local_dir`, handling
+// name clashes like download_files. Directories are left out.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_remote_glob(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    dir: String,
+    pattern: String,
+    local_dir: String,
+    policy: Option<ConflictPolicy>,
+    dry_run: Option<bool>,
+    password: Option<String>,
+) -> Result<GlobDownloadResult, SshError> {
+    let (session, client) = transfers::connect(&state, &session_id, password.as_deref())?;
+    let policy = policy.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let matched: Vec<GlobMatch> = expand(&client, &dir, &pattern)?
+            .into_iter()
+            .filter(|entry| !entry.is_directory)
+            .collect();
+        if dry_run.unwrap_or(false) {
+            return Ok(GlobDownloadResult { matched, transfer: None });
+        }
+
+        let remote_paths = matched.iter().map(|entry| entry.path.clone()).collect();
+        let transfer = transfers::download_batch(&app_handle, &session, &client, remote_paths, &local_dir, policy);
+        Ok(GlobDownloadResult { matched, transfer: Some(transfer) })
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))?
+}
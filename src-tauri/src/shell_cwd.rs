@@ -0,0 +1,151 @@
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::remote_user::RemoteUserCache;
+use crate::ssh_new::SshManager;
+
+// OSC 7, which shells set up for it (fish, zsh and bash with vte.sh or a
+// PROMPT_COMMAND) send with every prompt: ESC ] 7 ; file://host/path BEL
+const OSC7_START: &[u8] = b"\x1b]7;";
+// A longer sequence is not a path anyone's shell sent
+const MAX_OSC7_LEN: usize = 4096;
+
+// Decodes the path of a file:// URL, ignoring the host
+fn parse_file_url(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+// End of the OSC body starting at `from`, and where the terminator (BEL or
+// ESC \) ends
+fn osc_end(data: &[u8], from: usize) -> Option<(usize, usize)> {
+    (from..data.len()).find_map(|i| match data[i] {
+        0x07 => Some((i, i + 1)),
+        0x1b if data.get(i + 1) == Some(&b'\\') => Some((i, i + 2)),
+        _ => None,
+    })
+}
+
+// Follows the shell's working directory through the OSC 7 sequences in its
+// output. A sequence split across reads is put back together.
+pub struct CwdWatch {
+    carry: Mutex<Vec<u8>>,
+    cwd: Mutex<Option<String>>,
+}
+
+impl CwdWatch {
+    pub fn new() -> Self {
+        CwdWatch {
+            carry: Mutex::new(Vec::new()),
+            cwd: Mutex::new(None),
+        }
+    }
+
+    pub fn cwd(&self) -> Option<String> {
+        self.cwd.lock().unwrap().clone()
+    }
+
+    // Returns the new directory when the output changed it
+    pub fn feed(&self, data: &[u8]) -> Option<String> {
+        let mut carry = self.carry.lock().unwrap();
+        let joined;
+        let data = if carry.is_empty() {
+            data
+        } else {
+            carry.extend_from_slice(data);
+            joined = std::mem::take(&mut *carry);
+            &joined[..]
+        };
+
+        let mut latest = None;
+        let mut pos = 0;
+        while let Some(start) = data[pos..].windows(OSC7_START.len()).position(|w| w == OSC7_START).map(|i| pos + i) {
+            let body = start + OSC7_START.len();
+            match osc_end(data, body) {
+                Some((end, next)) => {
+                    latest = std::str::from_utf8(&data[body..end]).ok().and_then(parse_file_url).or(latest);
+                    pos = next;
+                }
+                None => {
+                    if data.len() - start <= MAX_OSC7_LEN {
+                        *carry = data[start..].to_vec();
+                    }
+                    pos = data.len();
+                    break;
+                }
+            }
+        }
+        // The output may stop in the middle of the introducer itself
+        if carry.is_empty() {
+            let tail = &data[pos..];
+            let partial = (1..OSC7_START.len())
+                .rev()
+                .find(|len| tail.len() >= *len && tail.ends_with(&OSC7_START[..*len]));
+            if let Some(len) = partial {
+                *carry = tail[tail.len() - len..].to_vec();
+            }
+        }
+
+        let latest = latest?;
+        let mut cwd = self.cwd.lock().unwrap();
+        if cwd.as_deref() == Some(latest.as_str()) {
+            return None;
+        }
+        *cwd = Some(latest.clone());
+        Some(latest)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SftpStartSource {
+    // Reported by the shell with OSC 7
+    ShellCwd,
+    Home,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SftpStart {
+    pub path: String,
+    pub source: SftpStartSource,
+}
+
+// Where the file browser should open: the shell's working directory when
+// the shell reports it, otherwise the account's home once it has been looked
+// up. None leaves it to the browser's own default.
+#[tauri::command]
+pub async fn get_suggested_sftp_start(
+    manager: State<'_, Arc<SshManager>>,
+    users: State<'_, RemoteUserCache>,
+    session_id: String,
+) -> Result<Option<SftpStart>, String> {
+    if let Some(path) = manager.shell_cwd(&session_id) {
+        return Ok(Some(SftpStart { path, source: SftpStartSource::ShellCwd }));
+    }
+    Ok(users
+        .get(&session_id)
+        .and_then(|info| info.home)
+        .map(|path| SftpStart { path, source: SftpStartSource::Home }))
+}
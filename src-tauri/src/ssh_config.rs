@@ -0,0 +1,665 @@
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use crate::ssh_new::{AuthMethod as SshAuthMethod, SshConfig};
+use crate::{get_ssh_dir, save_sessions_to_store, AppState, AuthMethod, Protocol, Session};
+
+// Nested Includes deeper than this are assumed to be a loop
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// One condition of a `Match` line. Supported: all, host, originalhost, user,
+// localuser, final and canonical (both only true in the final pass), each
+// optionally negated with `!`. Anything else (exec, tagged, ...) can't be
+// evaluated here and never matches, so options behind it are skipped.
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    All,
+    Host(String),
+    OriginalHost(String),
+    User(String),
+    LocalUser(String),
+    Final,
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Criteria {
+    // Options before the first Host or Match line apply to every host
+    Global,
+    Host(Vec<String>),
+    Match(Vec<(bool, Condition)>),
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+    criteria: Criteria,
+    // Lowercased keyword and its arguments, in file order
+    options: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpenSshConfig {
+    blocks: Vec<Block>,
+}
+
+// A host alias resolved against the whole config the way ssh would
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResolvedHost {
+    pub alias: String,
+    pub host_name: String,
+    pub port: u16,
+    pub user: Option<String>,
+    pub identity_file: Option<String>,
+    // PKCS11Provider, the module of a smartcard or token holding the keys
+    pub pkcs11_provider: Option<String>,
+    pub proxy_jump: Option<String>,
+    pub identities_only: bool,
+    pub bind_address: Option<String>,
+    // Match conditions we skipped because they can't be evaluated here
+    pub unsupported_conditions: Vec<String>,
+}
+
+// Glob match supporting `*` and `?`, case-insensitively like ssh does for hosts
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// A pattern list as used by Host (separate arguments) and Match (comma
+// separated): some positive pattern must match and no negated one may
+fn match_pattern_list<'a>(patterns: impl IntoIterator<Item = &'a str>, text: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if glob_match(negated, text) => return false,
+            Some(_) => {}
+            None => matched |= glob_match(pattern, text),
+        }
+    }
+    matched
+}
+
+// Splits a config line into words, honoring double quotes
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_word = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_word = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_word {
+                    words.push(std::mem::take(&mut current));
+                    has_word = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_word = true;
+            }
+        }
+    }
+    if has_word {
+        words.push(current);
+    }
+    words
+}
+
+fn parse_match(args: &[String]) -> Vec<(bool, Condition)> {
+    let mut conditions = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let (negated, keyword) = match arg.strip_prefix('!') {
+            Some(keyword) => (true, keyword.to_lowercase()),
+            None => (false, arg.to_lowercase()),
+        };
+
+        let condition = match keyword.as_str() {
+            "all" => Condition::All,
+            "final" | "canonical" => Condition::Final,
+            "host" | "originalhost" | "user" | "localuser" => {
+                let value = args.next().cloned().unwrap_or_default();
+                match keyword.as_str() {
+                    "host" => Condition::Host(value),
+                    "originalhost" => Condition::OriginalHost(value),
+                    "user" => Condition::User(value),
+                    _ => Condition::LocalUser(value),
+                }
+            }
+            other => {
+                // exec, tagged, localnetwork... all take an argument
+                args.next();
+                Condition::Unsupported(other.to_string())
+            }
+        };
+        conditions.push((negated, condition));
+    }
+    conditions
+}
+
+fn home_dir() -> Option<PathBuf> {
+    get_ssh_dir().and_then(|dir| dir.parent().map(Path::to_path_buf))
+}
+
+fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// Include paths are relative to ~/.ssh and may use `*` in the file name
+fn include_paths(pattern: &str) -> Vec<PathBuf> {
+    let path = expand_tilde(pattern);
+    let path = match (path.is_relative(), get_ssh_dir()) {
+        (true, Some(ssh_dir)) => ssh_dir.join(path),
+        _ => path,
+    };
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        return vec![path];
+    }
+
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|p| p.file_name().is_some_and(|n| glob_match(&name, &n.to_string_lossy())))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+impl OpenSshConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut config = OpenSshConfig::default();
+        config.read_file(path, Criteria::Global, 0)?;
+        Ok(config)
+    }
+
+    fn read_file(&mut self, path: &Path, criteria: Criteria, depth: usize) -> Result<(), String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.read_str(&content, criteria, depth);
+        Ok(())
+    }
+
+    fn read_str(&mut self, content: &str, criteria: Criteria, depth: usize) {
+        self.blocks.push(Block { criteria, options: Vec::new() });
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // "Key value", "Key=value" and "Key = value" are all valid
+            let (keyword, rest) = match line.find(|c: char| c.is_whitespace() || c == '=') {
+                Some(end) => (&line[..end], line[end..].trim_start_matches(|c: char| c.is_whitespace() || c == '=')),
+                None => (line, ""),
+            };
+            let keyword = keyword.to_lowercase();
+            let args = split_words(rest);
+
+            match keyword.as_str() {
+                "host" => self.blocks.push(Block { criteria: Criteria::Host(args), options: Vec::new() }),
+                "match" => self.blocks.push(Block { criteria: Criteria::Match(parse_match(&args)), options: Vec::new() }),
+                "include" if depth < MAX_INCLUDE_DEPTH => {
+                    // Included files are read in the context of the current block,
+                    // and the block continues after the Include line
+                    let current = self.blocks.last().map(|b| b.criteria.clone()).unwrap_or(Criteria::Global);
+                    for path in args.iter().flat_map(|pattern| include_paths(pattern)) {
+                        if let Err(e) = self.read_file(&path, current.clone(), depth + 1) {
+                            warn!("{}", e);
+                        }
+                    }
+                    self.blocks.push(Block { criteria: current, options: Vec::new() });
+                }
+                "include" => warn!("Ignoring Include nested more than {} levels deep", MAX_INCLUDE_DEPTH),
+                _ => {
+                    if let Some(block) = self.blocks.last_mut() {
+                        block.options.push((keyword, args));
+                    }
+                }
+            }
+        }
+    }
+
+    // Whether a Host block other than a catch-all `Host *` applies to the alias
+    pub fn knows(&self, alias: &str) -> bool {
+        self.blocks.iter().any(|block| match &block.criteria {
+            Criteria::Host(patterns) => {
+                patterns.iter().any(|pattern| pattern != "*")
+                    && match_pattern_list(patterns.iter().map(String::as_str), alias)
+            }
+            _ => false,
+        })
+    }
+
+    // Concrete aliases from Host lines, skipping wildcard patterns
+    pub fn host_aliases(&self) -> Vec<String> {
+        let mut aliases = Vec::new();
+        for block in &self.blocks {
+            if let Criteria::Host(patterns) = &block.criteria {
+                for pattern in patterns {
+                    if !pattern.contains(['*', '?', '!']) && !aliases.contains(pattern) {
+                        aliases.push(pattern.clone());
+                    }
+                }
+            }
+        }
+        aliases
+    }
+
+    fn has_final_pass(&self) -> bool {
+        self.blocks.iter().any(|block| match &block.criteria {
+            Criteria::Match(conditions) => conditions.iter().any(|(_, c)| *c == Condition::Final),
+            _ => false,
+        })
+    }
+
+    // Resolves an alias like `ssh -G` would: the first value obtained for an
+    // option wins, Host lines match the alias as typed and `Match host`
+    // matches the HostName picked so far. When any block uses `Match final`
+    // the config is evaluated a second time with final conditions true.
+    pub fn resolve(&self, alias: &str) -> ResolvedHost {
+        let local_user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+        let mut options: HashMap<String, Vec<String>> = HashMap::new();
+        let mut unsupported = Vec::new();
+
+        let passes: &[bool] = if self.has_final_pass() { &[false, true] } else { &[false] };
+        for &final_pass in passes {
+            for block in &self.blocks {
+                let applies = match &block.criteria {
+                    Criteria::Global => true,
+                    Criteria::Host(patterns) => match_pattern_list(patterns.iter().map(String::as_str), alias),
+                    Criteria::Match(conditions) => conditions.iter().all(|(negated, condition)| {
+                        let host = options.get("hostname").and_then(|v| v.first()).map(String::as_str).unwrap_or(alias);
+                        let user = options.get("user").and_then(|v| v.first()).map(String::as_str).unwrap_or(&local_user);
+                        let result = match condition {
+                            Condition::All => true,
+                            Condition::Host(list) => match_pattern_list(list.split(','), host),
+                            Condition::OriginalHost(list) => match_pattern_list(list.split(','), alias),
+                            Condition::User(list) => match_pattern_list(list.split(','), user),
+                            Condition::LocalUser(list) => match_pattern_list(list.split(','), &local_user),
+                            Condition::Final => final_pass,
+                            Condition::Unsupported(keyword) => {
+                                if !unsupported.contains(keyword) {
+                                    unsupported.push(keyword.clone());
+                                }
+                                return false;
+                            }
+                        };
+                        result != *negated
+                    }),
+                };
+
+                if applies {
+                    for (keyword, args) in &block.options {
+                        options.entry(keyword.clone()).or_insert_with(|| args.clone());
+                    }
+                }
+            }
+        }
+
+        let first = |key: &str| options.get(key).and_then(|v| v.first()).cloned();
+        let host_name = first("hostname").map(|h| h.replace("%h", alias)).unwrap_or_else(|| alias.to_string());
+        let user = first("user");
+        let identity_file = first("identityfile").map(|path| {
+            let path = path
+                .replace("%h", &host_name)
+                .replace("%r", user.as_deref().unwrap_or(&local_user))
+                .replace("%u", &local_user);
+            expand_tilde(&path).to_string_lossy().to_string()
+        });
+
+        ResolvedHost {
+            alias: alias.to_string(),
+            port: first("port").and_then(|p| p.parse().ok()).unwrap_or(22),
+            proxy_jump: first("proxyjump").filter(|jump| jump != "none"),
+            bind_address: first("bindaddress"),
+            identities_only: first("identitiesonly").is_some_and(|value| value.eq_ignore_ascii_case("yes")),
+            host_name,
+            user,
+            identity_file,
+            pkcs11_provider: first("pkcs11provider")
+                .filter(|provider| provider != "none")
+                .map(|provider| expand_tilde(&provider).to_string_lossy().to_string()),
+            unsupported_conditions: unsupported,
+        }
+    }
+}
+
+// One hop of a ProxyJump list, [user@]host[:port] or an ssh:// URI. Returns
+// the user, host and port.
+fn parse_jump(hop: &str) -> Option<(Option<String>, String, Option<u16>)> {
+    let hop = hop.trim();
+    let hop = hop.strip_prefix("ssh://").unwrap_or(hop);
+    let (user, rest) = match hop.rsplit_once('@') {
+        Some((user, rest)) => (Some(user.to_string()), rest),
+        None => (None, hop),
+    };
+    let (host, port) = match rest.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, after) = bracketed.split_once(']')?;
+            (host, after.strip_prefix(':'))
+        }
+        // A bare IPv6 address has several colons and no port
+        None => match rest.split_once(':') {
+            Some((host, port)) if !port.contains(':') => (host, Some(port)),
+            _ => (rest, None),
+        },
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let port = port.map(str::parse::<u16>).transpose().ok()?;
+    Some((user, host.to_string(), port))
+}
+
+// The jump hosts of a session's ProxyJump. Like ssh, each hop is resolved
+// through ~/.ssh/config, so it can be an alias with its own user, port and
+// key, and without a user it logs in as the local one. Hops authenticate
+// with their IdentityFile, PKCS11Provider or else ssh-agent; the first one
+// goes out through the session's HTTP proxy and bind address.
+pub fn jump_hosts(session: &Session) -> Vec<SshConfig> {
+    let Some(spec) = session.proxy_jump.as_deref().filter(|spec| !spec.trim().is_empty()) else {
+        return Vec::new();
+    };
+    let config = default_config_path().and_then(|path| OpenSshConfig::load(&path)).unwrap_or_default();
+    let local_user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+
+    let mut hops = Vec::new();
+    for hop in spec.split(',') {
+        let Some((user, alias, port)) = parse_jump(hop) else {
+            warn!("Skipping invalid ProxyJump hop {:?} of {}", hop, session.name);
+            continue;
+        };
+        let host = config.resolve(&alias);
+        let first = hops.is_empty();
+        hops.push(SshConfig {
+            host: host.host_name,
+            port: port.unwrap_or(host.port),
+            username: user.or(host.user).unwrap_or_else(|| local_user.clone()),
+            auth_method: match (host.identity_file, host.pkcs11_provider) {
+                (Some(private_key_path), _) => SshAuthMethod::PublicKey { private_key_path },
+                (None, Some(provider_path)) => SshAuthMethod::Pkcs11 { provider_path },
+                (None, None) => SshAuthMethod::Agent,
+            },
+            http_proxy: if first { session.http_proxy.clone() } else { None },
+            env_file: None,
+            allow_loopback: false,
+            terminal_modes: Default::default(),
+            reconnect: None,
+            strict_identities: host.identities_only,
+            bind_address: if first { session.bind_address.clone().or(host.bind_address) } else { None },
+            host_key_trust: session.host_key_trust,
+            sudo_autofill: false,
+            jump_hosts: Vec::new(),
+            keepalive_interval_secs: session.keepalive_interval_secs,
+            connect_timeout_secs: session.connect_timeout_secs,
+        });
+    }
+    hops
+}
+
+fn default_config_path() -> Result<PathBuf, String> {
+    get_ssh_dir()
+        .map(|dir| dir.join("config"))
+        .ok_or_else(|| "Could not determine the home directory".to_string())
+}
+
+// Resolves every concrete Host alias in an ssh config (~/.ssh/config by
+// default) without creating anything, so the user can pick what to import
+#[tauri::command]
+pub async fn preview_ssh_config_import(path: Option<String>) -> Result<Vec<ResolvedHost>, String> {
+    let path = path.map(PathBuf::from).map(Ok).unwrap_or_else(default_config_path)?;
+    let config = OpenSshConfig::load(&path)?;
+
+    Ok(config.host_aliases().iter().map(|alias| config.resolve(alias)).collect())
+}
+
+// Creates sessions for the given aliases (all of them when None). Aliases
+// that already exist as a session name are skipped.
+#[tauri::command]
+pub async fn import_ssh_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+    aliases: Option<Vec<String>>,
+) -> Result<Vec<Session>, String> {
+    let path = path.map(PathBuf::from).map(Ok).unwrap_or_else(default_config_path)?;
+    let config = OpenSshConfig::load(&path)?;
+    let aliases = aliases.unwrap_or_else(|| config.host_aliases());
+    let local_user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+
+    let mut imported = Vec::new();
+    {
+        let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+
+        for alias in aliases {
+            if sessions.values().any(|s| s.name == alias) {
+                continue;
+            }
+
+            let host = config.resolve(&alias);
+
+            let session = Session {
+                id: Uuid::new_v4().to_string(),
+                name: alias,
+                host: host.host_name,
+                port: host.port,
+                username: host.user.unwrap_or_else(|| local_user.clone()),
+                auth_method: match (host.identity_file, host.pkcs11_provider) {
+                    (Some(key_path), _) => AuthMethod::PublicKey { key_path },
+                    (None, Some(provider_path)) => AuthMethod::Pkcs11 { provider_path },
+                    (None, None) => AuthMethod::Agent,
+                },
+                protocol: Protocol::SSH,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                last_used: None,
+                group: None,
+                tags: Vec::new(),
+                color: None,
+                store_password_in_keychain: false,
+                profiles: Vec::new(),
+                http_proxy: None,
+                env_file: None,
+                allow_loopback: false,
+                terminal_modes: Default::default(),
+                reconnect: None,
+                strict_identities: host.identities_only,
+                bind_address: host.bind_address,
+                keymap: Default::default(),
+                host_key_trust: None,
+                sudo_autofill: false,
+                proxy_jump: host.proxy_jump,
+                keepalive_interval_secs: None,
+                ssh_options: Vec::new(),
+                connect_timeout_secs: None,
+            };
+            sessions.insert(session.id.clone(), session.clone());
+            imported.push(session);
+        }
+    }
+
+    info!("Imported {} session(s) from {}", imported.len(), path.display());
+    save_sessions_to_store(app, state).await?;
+    Ok(imported)
+}
+
+// What an alias typed into the connection form stands for, so the form can
+// fill in the real host, port, user and key. None when no Host block (other
+// than `Host *`) names it, or there's no config file.
+#[tauri::command]
+pub async fn resolve_host_alias(alias: String, path: Option<String>) -> Result<Option<ResolvedHost>, String> {
+    let path = path.map(PathBuf::from).map(Ok).unwrap_or_else(default_config_path)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let config = OpenSshConfig::load(&path)?;
+    let alias = alias.trim();
+    Ok(config.knows(alias).then(|| config.resolve(alias)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> OpenSshConfig {
+        let mut config = OpenSshConfig::default();
+        config.read_str(text, Criteria::Global, 0);
+        config
+    }
+
+    #[test]
+    fn parses_match_conditions() {
+        let args: Vec<String> = ["host", "*.example.com", "!user", "root", "exec", "true", "all"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(
+            parse_match(&args),
+            vec![
+                (false, Condition::Host("*.example.com".to_string())),
+                (true, Condition::User("root".to_string())),
+                (false, Condition::Unsupported("exec".to_string())),
+                (false, Condition::All),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_first_value_obtained_wins() {
+        let config = parse(
+            "Port 2022\n\
+             Host web\n  HostName web.example.com\n  Port 2200\n\
+             Host *\n  Port 22\n  User fallback\n\
+             Match host *.example.com\n  User deploy\n",
+        );
+        let web = config.resolve("web");
+        assert_eq!(web.host_name, "web.example.com");
+        // Options before any Host line come first of all
+        assert_eq!(web.port, 2022);
+        assert_eq!(web.user.as_deref(), Some("fallback"));
+    }
+
+    #[test]
+    fn match_host_uses_the_host_name_and_originalhost_the_alias() {
+        let config = parse(
+            "Host web\n  HostName web.example.com\n\
+             Match originalhost web.example.com\n  User by-original\n\
+             Match host web\n  User by-alias\n\
+             Match host *.example.com\n  User deploy\n",
+        );
+        assert_eq!(config.resolve("web").user.as_deref(), Some("deploy"));
+        assert_eq!(config.resolve("web.example.com").user.as_deref(), Some("by-original"));
+    }
+
+    #[test]
+    fn match_user_sees_the_user_picked_so_far() {
+        let config = parse(
+            "Host db\n  User postgres\n\
+             Match user root\n  Port 2201\n\
+             Match user postgres,admin\n  Port 2202\n",
+        );
+        assert_eq!(config.resolve("db").port, 2202);
+    }
+
+    #[test]
+    fn match_all_applies_everywhere() {
+        let config = parse("Match all\n  User everyone\n");
+        assert_eq!(config.resolve("anything").user.as_deref(), Some("everyone"));
+    }
+
+    #[test]
+    fn negated_criteria() {
+        let config = parse(
+            "Match !host *.internal\n  ProxyJump bastion\n\
+             Match host *.example.com !originalhost db\n  User web\n\
+             Match host *.example.com\n  User other\n",
+        );
+        assert_eq!(config.resolve("app.internal").proxy_jump, None);
+        assert_eq!(config.resolve("www.example.com").proxy_jump.as_deref(), Some("bastion"));
+        assert_eq!(config.resolve("www.example.com").user.as_deref(), Some("web"));
+
+        let config = parse(
+            "Host db\n  HostName db.example.com\n\
+             Match host *.example.com !originalhost db\n  User web\n\
+             Match host *.example.com\n  User other\n",
+        );
+        assert_eq!(config.resolve("db").user.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn negated_patterns_in_a_list() {
+        let config = parse(
+            "Host *.example.com !db.example.com\n  User web\n\
+             Match host *.example.org,!db.example.org\n  User web\n\
+             Host *\n  User other\n",
+        );
+        assert_eq!(config.resolve("www.example.com").user.as_deref(), Some("web"));
+        assert_eq!(config.resolve("db.example.com").user.as_deref(), Some("other"));
+        assert_eq!(config.resolve("www.example.org").user.as_deref(), Some("web"));
+        assert_eq!(config.resolve("db.example.org").user.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn final_blocks_apply_in_a_second_pass() {
+        let config = parse(
+            "Match final host *.example.com\n  User late\n\
+             Host web\n  HostName web.example.com\n\
+             Host *\n  Port 2200\n",
+        );
+        let web = config.resolve("web");
+        assert_eq!(web.user.as_deref(), Some("late"));
+        assert_eq!(web.port, 2200);
+    }
+
+    #[test]
+    fn unsupported_conditions_never_match_even_negated() {
+        let config = parse(
+            "Match exec \"test -f /tmp/x\"\n  User exec\n\
+             Match !tagged work\n  User tagged\n",
+        );
+        let resolved = config.resolve("web");
+        assert_eq!(resolved.user, None);
+        assert_eq!(resolved.unsupported_conditions, vec!["exec".to_string(), "tagged".to_string()]);
+    }
+}
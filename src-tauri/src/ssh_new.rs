@@ -1,15 +1,33 @@
-use anyhow::{anyhow, Result};
+use log::{debug, error, info, trace, warn};
 use serde::{Deserialize, Serialize};
-use ssh2::{Channel, Session};
+use ssh2::{Channel, PtyModeOpcode, PtyModes, Session, Sftp};
 use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::net::TcpStream;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use tauri::{AppHandle, Emitter};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::mpsc;
+use zeroize::Zeroize;
+
+use crate::disconnect::{self, DisconnectReason};
+use crate::error::SshError;
+use crate::forwarding::{self, Forward, ForwardInfo, ForwardKind, ForwardSpec, SessionCommand};
+use crate::jump::JumpChain;
+use crate::keyboard_interactive::{InteractivePrompter, MFA_APPROVAL_TIMEOUT};
+use crate::keys::KeyManager;
+use crate::latency::{LatencyMonitor, LatencySample, DEFAULT_LATENCY_INTERVAL};
+use crate::local_terminal::LocalTerminalManager;
+use crate::macros::MacroRecorder;
+use crate::net::ProxyConfig;
+use crate::session_logs::SessionLogger;
+use crate::reconnect::{self, ReconnectTrigger};
+use crate::settings::{HostKeyTrust, OutputPauseMode, ReconnectPolicy};
+use crate::shell_cwd::CwdWatch;
+
+type Result<T, E = SshError> = std::result::Result<T, E>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SshConfig {
@@ -17,6 +35,139 @@ pub struct SshConfig {
     pub port: u16,
     pub username: String,
     pub auth_method: AuthMethod,
+    #[serde(default)]
+    pub http_proxy: Option<ProxyConfig>,
+    // Local dotenv file exported into the remote shell, re-read on every connect
+    #[serde(default)]
+    pub env_file: Option<String>,
+    // Don't warn when the host is this machine (e.g. testing a local sshd)
+    #[serde(default)]
+    pub allow_loopback: bool,
+    #[serde(default)]
+    pub terminal_modes: TerminalModes,
+    // Overrides the global reconnect policy for this session
+    #[serde(default)]
+    pub reconnect: Option<ReconnectPolicy>,
+    // Never fall back to another way of authenticating when the configured
+    // one fails. See authenticate.
+    #[serde(default)]
+    pub strict_identities: bool,
+    // Local address the connection leaves from
+    #[serde(default)]
+    pub bind_address: Option<String>,
+    // None follows the global setting
+    #[serde(default)]
+    pub host_key_trust: Option<HostKeyTrust>,
+    // answer_sudo_prompt may type the cached password into sudo's prompt
+    #[serde(default)]
+    pub sudo_autofill: bool,
+    // Bastions to go through first, in order, each with its own credentials.
+    // See jump::connect_via_jump.
+    #[serde(default)]
+    pub jump_hosts: Vec<SshConfig>,
+    // None sends keepalives every DEFAULT_KEEPALIVE_INTERVAL seconds, 0 sends
+    // none at all
+    #[serde(default)]
+    pub keepalive_interval_secs: Option<u64>,
+    // Limit for the TCP connect to each address and for the SSH handshake.
+    // None (or 0) uses net::CONNECT_TIMEOUT.
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+}
+
+// What the Backspace key sends, which the remote tty must agree on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EraseChar {
+    // ^? (0x7f), what xterm.js sends by default
+    Delete,
+    // ^H (0x08), for hosts and programs that expect it
+    Backspace,
+}
+
+// Terminal modes sent with the PTY request itself, so they are in place
+// before the shell starts instead of racing it with an `stty` afterwards.
+// Anything left unset keeps the server's default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalModes {
+    pub echo: Option<bool>,
+    pub erase: Option<EraseChar>,
+    // Translate CR to NL on input, which line-based programs rely on
+    pub icrnl: Option<bool>,
+    pub cols: Option<u32>,
+    pub rows: Option<u32>,
+}
+
+impl TerminalModes {
+    fn size(&self) -> (u32, u32) {
+        (self.cols.unwrap_or(80), self.rows.unwrap_or(24))
+    }
+
+    fn pty_modes(&self) -> Option<PtyModes> {
+        if self.echo.is_none() && self.erase.is_none() && self.icrnl.is_none() {
+            return None;
+        }
+
+        let mut modes = PtyModes::new();
+        if let Some(echo) = self.echo {
+            modes.set_boolean(PtyModeOpcode::ECHO, echo);
+        }
+        if let Some(erase) = self.erase {
+            let c = match erase {
+                EraseChar::Delete => '\x7f',
+                EraseChar::Backspace => '\x08',
+            };
+            modes.set_character(PtyModeOpcode::VERASE, Some(c));
+        }
+        if let Some(icrnl) = self.icrnl {
+            modes.set_boolean(PtyModeOpcode::ICRNL, icrnl);
+        }
+        Some(modes)
+    }
+}
+
+impl SshConfig {
+    // Wipes a password kept for reconnects; the next reconnect has to ask again
+    fn clear_secrets(&mut self) -> bool {
+        let mut cleared = false;
+        for hop in &mut self.jump_hosts {
+            cleared |= hop.clear_secrets();
+        }
+        match &mut self.auth_method {
+            AuthMethod::Password { password } if !password.is_empty() => {
+                password.zeroize();
+                true
+            }
+            _ => cleared,
+        }
+    }
+
+    pub(crate) fn connect_timeout(&self) -> Duration {
+        self.connect_timeout_secs
+            .filter(|secs| *secs > 0)
+            .map_or(crate::net::CONNECT_TIMEOUT, Duration::from_secs)
+    }
+
+    fn can_autofill_sudo(&self) -> bool {
+        self.sudo_autofill
+            && match &self.auth_method {
+                AuthMethod::Password { password } => !password.is_empty(),
+                AuthMethod::Keychain { .. } => true,
+                _ => false,
+            }
+    }
+
+    // The password sudo prompts are answered with when the session allows it
+    fn sudo_password(&self) -> Result<String> {
+        if !self.sudo_autofill {
+            return Err(SshError::Other("Filling in sudo passwords is not enabled for this session".to_string()));
+        }
+        match &self.auth_method {
+            AuthMethod::Password { password } if !password.is_empty() => Ok(password.clone()),
+            AuthMethod::Keychain { session_id } => crate::keychain::require_password(session_id).map_err(SshError::Other),
+            _ => Err(SshError::Other("No password is cached for this session".to_string())),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +175,10 @@ pub enum AuthMethod {
     Password { password: String },
     PublicKey { private_key_path: String },
     Agent,
+    // Password auth with the password looked up in the OS keychain
+    Keychain { session_id: String },
+    // Keys on a smartcard or token, through its PKCS#11 module (see pkcs11.rs)
+    Pkcs11 { provider_path: String },
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -38,11 +193,598 @@ struct ConnectionStatusEvent {
     session_id: String,
     status: String,
     message: Option<String>,
+    // Set when the server sent a DISCONNECT explaining why it closed the connection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disconnect: Option<DisconnectReason>,
+}
+
+// Per-connection traffic counters shared with the reader and writer threads
+#[derive(Default)]
+pub struct ConnectionStats {
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    // Unix time in milliseconds of the last output from the shell, 0 if none yet
+    last_output_ms: AtomicU64,
+}
+
+impl ConnectionStats {
+    fn record_output(&self, bytes: usize) {
+        self.bytes_received.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.last_output_ms.store(chrono::Utc::now().timestamp_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn last_output_secs_ago(&self) -> Option<u64> {
+        match self.last_output_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some((chrono::Utc::now().timestamp_millis() as u64).saturating_sub(ms) / 1000),
+        }
+    }
+}
+
+// Enough for sudo's prompt with a long username, and any escape sequences
+// in front of it
+const MAX_PROMPT_LINE: usize = 512;
+
+#[derive(Clone, Serialize)]
+struct ShellCwdEvent {
+    session_id: String,
+    path: String,
+}
+
+#[derive(Clone, Serialize)]
+struct SudoPromptEvent {
+    session_id: String,
+    // Who sudo asks the password of
+    user: String,
+    prompt: String,
+    // Whether answer_sudo_prompt can fill in the cached password
+    can_autofill: bool,
+}
+
+// The user in sudo's "[sudo] password for alice: " prompt, when that's what
+// `line` ends with
+fn sudo_prompt_user(line: &str) -> Option<String> {
+    let (_, rest) = line.trim_end().rsplit_once("[sudo] password for ")?;
+    let user = rest.strip_suffix(':')?;
+    (!user.is_empty() && !user.contains(char::is_whitespace)).then(|| user.to_string())
+}
+
+// Watches the output for sudo's password prompt. The prompt is the last,
+// unfinished line of the output while sudo waits; anything the server sends
+// after it (the newline once the password is entered, "Sorry, try again")
+// means it's no longer waiting.
+struct SudoPromptWatch {
+    enabled: bool,
+    line: Mutex<String>,
+    // Set while the prompt is waiting, so the password is only ever typed
+    // into sudo's non-echoing prompt and never into the shell
+    pending: AtomicBool,
+}
+
+impl SudoPromptWatch {
+    fn new(enabled: bool) -> Self {
+        SudoPromptWatch {
+            enabled,
+            line: Mutex::new(String::new()),
+            pending: AtomicBool::new(false),
+        }
+    }
+
+    // Returns the prompt and user when the output has just come to a prompt
+    fn feed(&self, data: &[u8]) -> Option<(String, String)> {
+        if !self.enabled {
+            return None;
+        }
+        let text = String::from_utf8_lossy(data);
+        let mut line = self.line.lock().unwrap();
+        match text.rfind(['\n', '\r']) {
+            Some(end) => {
+                line.clear();
+                line.push_str(&text[end + 1..]);
+            }
+            None => line.push_str(&text),
+        }
+        if line.len() > MAX_PROMPT_LINE {
+            let mut start = line.len() - MAX_PROMPT_LINE;
+            while !line.is_char_boundary(start) {
+                start += 1;
+            }
+            line.drain(..start);
+        }
+
+        let user = sudo_prompt_user(&line);
+        let was_pending = self.pending.swap(user.is_some(), Ordering::SeqCst);
+        match user {
+            // Without whatever escape sequences came before it
+            Some(user) if !was_pending => Some((format!("[sudo] password for {}:", user), user)),
+            _ => None,
+        }
+    }
+
+    fn take_pending(&self) -> bool {
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+}
+
+struct PausedOutput {
+    buffer: Vec<u8>,
+    // The reader stops taking data from the channel once this much is held
+    limit: usize,
+}
+
+// An escape sequence starting further back than this is left to be cut (OSC 52
+// clipboard data can run to megabytes); xterm's parser copes either way, the
+// session log and anything matching on the text are better off with whole ones
+const MAX_KEPT_ESCAPE: usize = 256;
+
+// Length of the escape sequence at the start of `seq`, which starts with ESC,
+// or None if it hasn't ended yet
+fn escape_len(seq: &[u8]) -> Option<usize> {
+    match seq.get(1)? {
+        // CSI ends with a byte in @..~
+        b'[' => seq[2..].iter().position(|b| (0x40..=0x7e).contains(b)).map(|i| i + 3),
+        // OSC, DCS, APC, PM end with BEL or ST (ESC \)
+        b']' | b'P' | b'_' | b'^' => seq[2..].iter().enumerate().find_map(|(i, b)| match b {
+            0x07 => Some(i + 3),
+            0x1b if seq.get(i + 3) == Some(&b'\\') => Some(i + 4),
+            _ => None,
+        }),
+        // Anything else is ESC, intermediates (space../) and a final byte
+        _ => seq[1..].iter().position(|b| (0x30..=0x7e).contains(b)).map(|i| i + 2),
+    }
+}
+
+// Where to end a piece of at most `limit` bytes: after its last newline if it
+// has one, otherwise at the limit, moved back off a UTF-8 continuation byte
+// and out of an unfinished escape sequence
+fn chunk_end(data: &[u8], limit: usize) -> usize {
+    if let Some(newline) = data[..limit].iter().rposition(|b| *b == b'\n') {
+        return newline + 1;
+    }
+
+    let mut end = limit;
+    while end > 0 && data[end] & 0xc0 == 0x80 {
+        end -= 1;
+    }
+    // Walked forwards so the ESC of an ST isn't taken for a sequence's start
+    let mut from = end.saturating_sub(MAX_KEPT_ESCAPE);
+    while let Some(esc) = data[from..end].iter().position(|b| *b == 0x1b).map(|i| from + i) {
+        match escape_len(&data[esc..end]) {
+            Some(len) => from = esc + len,
+            None => {
+                end = esc;
+                break;
+            }
+        }
+    }
+    if end > 0 {
+        return end;
+    }
+
+    // The first character or escape is longer than the limit, so the piece is
+    // that one whole (an escape that never ends takes the rest of the data)
+    if data[0] == 0x1b {
+        return escape_len(data).unwrap_or(data.len());
+    }
+    let mut end = 1;
+    while end < data.len() && end < 4 && data[end] & 0xc0 == 0x80 {
+        end += 1;
+    }
+    end
+}
+
+// Sits between the reader thread and the terminal-data events so output can
+// be held back while the user reads. Delivery and the flush on resume both
+// go through the lock, which keeps the output in order.
+struct OutputGate {
+    paused: Mutex<Option<PausedOutput>>,
+    // The start of a UTF-8 character whose remaining bytes haven't been read
+    // yet, held back so it isn't turned into replacement characters
+    partial_char: Mutex<Vec<u8>>,
+    // Longest terminal-data event, 0 for no limit. Purely about IPC: a long
+    // run of output is sent as several events, never changed.
+    chunk_bytes: usize,
+}
+
+impl OutputGate {
+    fn new(chunk_bytes: usize) -> Self {
+        OutputGate {
+            paused: Mutex::new(None),
+            partial_char: Mutex::new(Vec::new()),
+            chunk_bytes,
+        }
+    }
+
+    fn emit(&self, app_handle: &AppHandle, session_id: &str, data: &[u8]) {
+        let mut partial = self.partial_char.lock().unwrap();
+        let mut data = if partial.is_empty() {
+            data.to_vec()
+        } else {
+            let mut joined = std::mem::take(&mut *partial);
+            joined.extend_from_slice(data);
+            joined
+        };
+
+        // An error without a length is a character cut off by the end
+        if let Err(e) = std::str::from_utf8(&data) {
+            if e.error_len().is_none() {
+                *partial = data.split_off(e.valid_up_to());
+            }
+        }
+
+        let mut rest = &data[..];
+        while self.chunk_bytes > 0 && rest.len() > self.chunk_bytes {
+            let (chunk, tail) = rest.split_at(chunk_end(rest, self.chunk_bytes));
+            emit_terminal_data(app_handle, session_id, String::from_utf8_lossy(chunk).to_string());
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            emit_terminal_data(app_handle, session_id, String::from_utf8_lossy(rest).to_string());
+        }
+    }
+
+    fn accepts_reads(&self) -> bool {
+        match &*self.paused.lock().unwrap() {
+            Some(paused) => paused.buffer.len() < paused.limit,
+            None => true,
+        }
+    }
+
+    fn deliver(&self, app_handle: &AppHandle, session_id: &str, data: &[u8]) {
+        match &mut *self.paused.lock().unwrap() {
+            Some(paused) => paused.buffer.extend_from_slice(data),
+            None => self.emit(app_handle, session_id, data),
+        }
+    }
+
+    // Returns false if output was already paused
+    fn pause(&self, limit: usize) -> bool {
+        let mut paused = self.paused.lock().unwrap();
+        if paused.is_some() {
+            return false;
+        }
+        *paused = Some(PausedOutput { buffer: Vec::new(), limit });
+        true
+    }
+
+    // Flushes what was held back and returns its size
+    fn resume(&self, app_handle: &AppHandle, session_id: &str) -> usize {
+        let mut paused = self.paused.lock().unwrap();
+        let Some(output) = paused.take() else {
+            return 0;
+        };
+        if !output.buffer.is_empty() {
+            self.emit(app_handle, session_id, &output.buffer);
+        }
+        output.buffer.len()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.lock().unwrap().is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveConnectionInfo {
+    pub session_id: String,
+    pub host: String,
+    pub port: u16,
+    pub remote_addr: String,
+    pub username: String,
+    pub connected_at: String,
+    pub uptime_secs: u64,
+    pub status: String,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub open_channels: usize,
+    pub sftp_active: bool,
+    pub forwards_active: usize,
+    // Lets the UI notice a session that has gone quiet
+    pub last_output_secs_ago: Option<u64>,
+    pub output_paused: bool,
+}
+
+// How a host's open tabs map onto SSH transports. Connections aren't
+// multiplexed: every tab opens its own, so `transports` always equals `tabs`
+// and closing a tab only ever drops that tab's connection.
+#[derive(Debug, Clone, Serialize)]
+pub struct MultiplexingInfo {
+    pub host: String,
+    pub tabs: usize,
+    pub transports: usize,
+    pub shared: bool,
+    // Shell, SFTP and forwarded channels over all of them
+    pub total_channels: usize,
+    // Transports whose shell is still running
+    pub healthy: usize,
+    pub session_ids: Vec<String>,
+}
+
+// Ids of the shell and SFTP channels in list_session_channels; forwards go
+// by their own id
+const SHELL_CHANNEL_ID: &str = "shell";
+const SFTP_CHANNEL_ID: &str = "sftp";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelKind {
+    Shell,
+    Sftp,
+    // A forward's listener, together with the direct-tcpip (local, dynamic)
+    // or forwarded-tcpip (remote) channels it has open
+    Forward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelState {
+    Open,
+    // The shell exited, the connection is only waiting to be closed or reaped
+    Closed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionChannel {
+    pub id: String,
+    pub kind: ChannelKind,
+    pub state: ChannelState,
+    pub forward: Option<ForwardInfo>,
+}
+
+// Result of probe_channel. A responsive server with a long silence usually means
+// the remote process is hung rather than the connection being dead.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelProbe {
+    pub session_id: String,
+    pub responsive: bool,
+    pub round_trip_ms: Option<u64>,
+    pub last_output_secs_ago: Option<u64>,
+    pub error: Option<String>,
+}
+
+// How long the server gets to answer a probe before the session is deemed unresponsive
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Opening (and immediately closing) a channel needs a full round trip to the
+// server, which makes it a cheap liveness check that doesn't touch the shell
+pub(crate) fn probe_session(session: &Session) -> Result<Duration, String> {
+    let started = Instant::now();
+
+    loop {
+        match session.channel_session() {
+            Ok(mut channel) => {
+                let elapsed = started.elapsed();
+                let _ = channel.close();
+                return Ok(elapsed);
+            }
+            Err(e) if forwarding::is_would_block(&e) => {
+                if started.elapsed() > PROBE_TIMEOUT {
+                    return Err("No response from the server".to_string());
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+const SFTP_OPEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+// Starts the SFTP subsystem on the shell's non-blocking session
+fn open_sftp_channel(session: &Session) -> Result<Sftp> {
+    let started = Instant::now();
+
+    loop {
+        match session.sftp() {
+            Ok(sftp) => return Ok(sftp),
+            Err(e) if forwarding::is_would_block(&e) => {
+                if started.elapsed() > SFTP_OPEN_TIMEOUT {
+                    return Err(SshError::Timeout);
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+// Only a warning: connecting to a local sshd or a tunnel endpoint is often
+// intended, and allow_loopback silences it
+#[derive(Clone, serde::Serialize)]
+struct LoopbackWarningEvent {
+    session_id: String,
+    host: String,
+    address: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct WriteFailedEvent {
+    session_id: String,
+    error: String,
+    dropped_bytes: usize,
+}
+
+// How long a window change request may keep hitting a busy transport
+const RESIZE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long a write may make no progress before the connection is considered dead
+const WRITE_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// How long reads may keep coming back empty without the channel being at EOF
+// before the connection is given up on. A key re-exchange takes a round trip
+// or two, so this only trips when something is really wrong.
+const READ_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Debug aid: the transport is busy with something other than channel data,
+// which on a long-lived session is almost always a key re-exchange. Sent as
+// `rekeying` when that starts and `rekeyed` once channel data flows again.
+#[derive(Clone, serde::Serialize)]
+struct RekeyingEvent {
+    session_id: String,
+}
+
+// Writes the whole buffer to a non-blocking channel. WouldBlock just means the
+// transport is busy (window full, rekey in progress), so the lock is released
+// and the write retried; only real errors or a long stall are fatal. A write
+// of 0 bytes (older libssh2 with an exhausted window) is a stall too.
+fn write_to_channel(channel: &Mutex<Channel>, data: &[u8]) -> std::io::Result<()> {
+    let mut written = 0;
+    let mut last_progress = Instant::now();
+
+    while written < data.len() {
+        let result = {
+            let mut channel = channel.lock().unwrap();
+            channel.write(&data[written..])
+        };
+
+        match result {
+            Ok(n) if n > 0 => {
+                written += n;
+                last_progress = Instant::now();
+            }
+            Ok(_) => {
+                if last_progress.elapsed() > WRITE_STALL_TIMEOUT {
+                    return Err(std::io::ErrorKind::WriteZero.into());
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted) => {
+                if last_progress.elapsed() > WRITE_STALL_TIMEOUT {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "channel stopped accepting input",
+                    ));
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    loop {
+        let result = {
+            let mut channel = channel.lock().unwrap();
+            channel.flush()
+        };
+        match result {
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(1)),
+            other => return other,
+        }
+    }
+}
+
+fn emit_write_failed(app_handle: &AppHandle, session_id: &str, error: &std::io::Error, dropped_bytes: usize) {
+    if let Err(e) = app_handle.emit("write_failed", &WriteFailedEvent {
+        session_id: session_id.to_string(),
+        error: error.to_string(),
+        dropped_bytes,
+    }) {
+        error!("Failed to emit write failure: {}", e);
+    }
+
+    emit_connection_status(
+        app_handle,
+        session_id,
+        "disconnected",
+        &format!("Input is no longer reaching the server: {}", error),
+    );
+}
+
+pub(crate) fn emit_terminal_data(app_handle: &AppHandle, session_id: &str, data: String) {
+    if let Some(logger) = app_handle.try_state::<SessionLogger>() {
+        logger.write(session_id, &data);
+    }
+
+    let event = TerminalEvent {
+        session_id: session_id.to_string(),
+        event_type: "data".to_string(),
+        data,
+    };
+
+    if let Err(e) = app_handle.emit("terminal-data", &event) {
+        error!("Failed to emit terminal data: {}", e);
+    }
+}
+
+pub(crate) fn emit_connection_status(app_handle: &AppHandle, session_id: &str, status: &str, message: &str) {
+    if let Err(e) = app_handle.emit("connection_status", &ConnectionStatusEvent {
+        session_id: session_id.to_string(),
+        status: status.to_string(),
+        message: Some(message.to_string()),
+        disconnect: None,
+    }) {
+        error!("Failed to emit connection status: {}", e);
+    }
+}
+
+// Tells the UI the remote end went away, with the server's reason when it gave one
+// Returns the message and reason it reported, which the reconnect logic keeps
+fn emit_remote_disconnect(app_handle: &AppHandle, session_id: &str, session: &Session, fallback: String) -> (String, Option<DisconnectReason>) {
+    let reason = disconnect::take(session);
+    let message = reason.as_ref().map(DisconnectReason::describe).unwrap_or(fallback);
+    info!("SSH session {} ended: {}", session_id, message);
+
+    if let Err(e) = app_handle.emit("connection_status", &ConnectionStatusEvent {
+        session_id: session_id.to_string(),
+        status: "disconnected".to_string(),
+        message: Some(message.clone()),
+        disconnect: reason.clone(),
+    }) {
+        error!("Failed to emit connection status: {}", e);
+    }
+    (message, reason)
+}
+
+const DEFAULT_KEEPALIVE_INTERVAL: u64 = 30;
+
+// Sends a keepalive every `interval_secs` so firewalls and NAT boxes don't
+// drop an idle terminal. No reply is asked for; a dead server is the
+// sweeper's to notice.
+fn spawn_keepalive(
+    session_id: String,
+    session: Session,
+    interval_secs: u64,
+    shutdown: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    session.set_keepalive(false, u32::try_from(interval_secs).unwrap_or(u32::MAX));
+    thread::spawn(move || {
+        let mut next = Instant::now();
+        while !shutdown.load(Ordering::Relaxed) {
+            if Instant::now() >= next {
+                // libssh2 skips the keepalive itself when the socket is busy
+                let wait = match session.keepalive_send() {
+                    Ok(secs) => u64::from(secs.max(1)),
+                    Err(e) => {
+                        debug!("Keepalive on {} failed: {}", session_id, e);
+                        interval_secs
+                    }
+                };
+                next = Instant::now() + Duration::from_secs(wait);
+            }
+            // Small steps so closing doesn't wait a whole interval
+            thread::sleep(Duration::from_millis(50));
+        }
+        trace!("SSH keepalive thread for {} exiting", session_id);
+    })
 }
 
 // Separate reader and writer handles to avoid mutex contention
 pub struct SshConnection {
     session_id: String,
+    // Kept so the session can be restarted without prompting again
+    config: SshConfig,
+    // The resolved address we actually connected to
+    remote_addr: SocketAddr,
+    // The terminal's size on the client; restarts and reconnects use it
+    pty_size: Mutex<(u32, u32)>,
+    // Columns the remote PTY was temporarily widened to, see widen_pty
+    widened_cols: Mutex<Option<u32>>,
+    connected_at: chrono::DateTime<chrono::Utc>,
+    started: Instant,
+    stats: Arc<ConnectionStats>,
+    output: Arc<OutputGate>,
+    sudo_prompt: Arc<SudoPromptWatch>,
+    cwd: Arc<CwdWatch>,
     input_tx: mpsc::UnboundedSender<String>,
     reader_shutdown: Arc<AtomicBool>,
     writer_shutdown: Arc<AtomicBool>,
@@ -51,12 +793,43 @@ pub struct SshConnection {
     writer_handle: Option<thread::JoinHandle<()>>,
     input_handle: Option<thread::JoinHandle<()>>,
     channel: Arc<Mutex<Channel>>,
+    session: Session,
+    forwards: HashMap<String, Forward>,
+    latency: Option<LatencyMonitor>,
+    // SFTP channel on this connection, opened on demand with open_sftp and
+    // closed with close_sftp independently of the shell
+    sftp: Option<Sftp>,
+    // Sessions to the jump hosts this connection goes through
+    jump: Option<JumpChain>,
+    // Stops along with the reader
+    keepalive_handle: Option<thread::JoinHandle<()>>,
+    // Drops the server's disconnect reason with the connection if nobody took it
+    disconnect_watch: Option<disconnect::Watch>,
+}
+
+pub struct ReapedSession {
+    pub session_id: String,
+    pub reason: String,
+    // The server stopped answering, as opposed to a reader that had exited
+    pub unresponsive: bool,
+    pub config: SshConfig,
+    pub pty_size: (u32, u32),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionCommandOutput {
+    pub message: String,
+    pub forward: Option<ForwardInfo>,
 }
 
 impl SshConnection {
     pub fn new(
         session_id: String,
+        config: &SshConfig,
+        remote_addr: SocketAddr,
+        session: Session,
         channel: Channel,
+        pty_size: (u32, u32),
         app_handle: AppHandle,
     ) -> Result<Self> {
         let (writer_tx, mut writer_rx) = mpsc::unbounded_channel::<Vec<u8>>();
@@ -65,6 +838,16 @@ impl SshConnection {
         let reader_shutdown = Arc::new(AtomicBool::new(false));
         let writer_shutdown = Arc::new(AtomicBool::new(false));
         let input_shutdown = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(ConnectionStats::default());
+        let (chunk_kb, detect_sudo_prompts) = app_handle.state::<crate::AppState>()
+            .settings
+            .lock()
+            .map(|settings| (settings.output_chunk_kb, settings.detect_sudo_prompts))
+            .unwrap_or((0, false));
+        let output = Arc::new(OutputGate::new(chunk_kb * 1024));
+        let sudo_prompt = Arc::new(SudoPromptWatch::new(detect_sudo_prompts));
+        let can_autofill = config.can_autofill_sudo();
+        let cwd = Arc::new(CwdWatch::new());
 
         // Use Arc<Mutex<Channel>> to share the channel safely between threads
         let shared_channel = Arc::new(Mutex::new(channel));
@@ -74,11 +857,27 @@ impl SshConnection {
         let reader_shutdown_clone = reader_shutdown.clone();
         let session_id_clone = session_id.clone();
         let app_handle_clone = app_handle.clone();
+        let reader_stats = stats.clone();
+        let reader_session = session.clone();
+        let reader_output = output.clone();
+        let reader_sudo_prompt = sudo_prompt.clone();
+        let reader_cwd = cwd.clone();
         
         let reader_handle = thread::spawn(move || {
             let mut buffer = [0u8; 4096];
+            // Since when reads have come back empty without EOF (in a row),
+            // and whether that's been reported since output last flowed
+            let mut stalled_since: Option<Instant> = None;
+            let mut rekey_reported = false;
             
             while !reader_shutdown_clone.load(Ordering::Relaxed) {
+                // Leaving the channel unread while paused is what applies flow
+                // control. A dropped connection is only noticed after resume.
+                if !reader_output.accepts_reads() {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+
                 let read_result = {
                     let mut channel = reader_channel.lock().unwrap();
                     channel.read(&mut buffer)
@@ -86,72 +885,129 @@ impl SshConnection {
                 
                 match read_result {
                     Ok(0) => {
+                        // libssh2 also returns 0 when draining the transport
+                        // ended on something other than channel data, which
+                        // is what a key re-exchange looks like from here. Only
+                        // a channel at EOF has really been closed.
+                        let at_eof = reader_channel.lock().unwrap().eof();
+                        let stalled_for = stalled_since.get_or_insert_with(Instant::now).elapsed();
+                        if !at_eof && stalled_for < READ_STALL_TIMEOUT {
+                            if !rekey_reported {
+                                debug!("Read on {} returned no data without EOF, transport busy (rekeying?)", session_id_clone);
+                                let _ = app_handle_clone.emit("rekeying", &RekeyingEvent {
+                                    session_id: session_id_clone.clone(),
+                                });
+                                rekey_reported = true;
+                            }
+                            thread::sleep(Duration::from_millis(1));
+                            continue;
+                        }
+
                         // EOF - connection closed
-                        println!("SSH connection {} closed", session_id_clone);
+                        debug!("SSH connection {} closed", session_id_clone);
+                        let (message, reason) = emit_remote_disconnect(&app_handle_clone, &session_id_clone, &reader_session, "Connection closed".to_string());
+                        reconnect::on_disconnect(&app_handle_clone, &session_id_clone, ReconnectTrigger::Eof, message, reason);
                         break;
                     }
                     Ok(n) => {
-                        let data = String::from_utf8_lossy(&buffer[..n]).to_string();
-                        
-                        let event = TerminalEvent {
-                            session_id: session_id_clone.clone(),
-                            event_type: "data".to_string(),
-                            data,
-                        };
-                        
-                        if let Err(e) = app_handle_clone.emit("terminal-data", &event) {
-                            eprintln!("Failed to emit terminal data: {}", e);
+                        if rekey_reported {
+                            debug!("Output on {} resumed", session_id_clone);
+                            let _ = app_handle_clone.emit("rekeyed", &RekeyingEvent {
+                                session_id: session_id_clone.clone(),
+                            });
+                        }
+                        stalled_since = None;
+                        rekey_reported = false;
+                        reader_stats.record_output(n);
+                        reader_output.deliver(&app_handle_clone, &session_id_clone, &buffer[..n]);
+                        if let Some((prompt, user)) = reader_sudo_prompt.feed(&buffer[..n]) {
+                            let _ = app_handle_clone.emit("sudo_prompt", &SudoPromptEvent {
+                                session_id: session_id_clone.clone(),
+                                user,
+                                prompt,
+                                can_autofill,
+                            });
+                        }
+                        if let Some(path) = reader_cwd.feed(&buffer[..n]) {
+                            let follow = app_handle_clone.state::<crate::AppState>()
+                                .settings
+                                .lock()
+                                .is_ok_and(|settings| settings.sftp_follow_shell_cwd);
+                            if follow {
+                                let _ = app_handle_clone.emit("shell_cwd_changed", &ShellCwdEvent {
+                                    session_id: session_id_clone.clone(),
+                                    path,
+                                });
+                            }
                         }
                     }
                     Err(e) => {
-                        if e.kind() == std::io::ErrorKind::WouldBlock {
+                        if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted) {
+                            stalled_since = None;
                             // Non-blocking read with no data, sleep briefly
                             // Use shorter sleep for better responsiveness to initial output
                             thread::sleep(Duration::from_millis(1));
                             continue;
                         }
-                        eprintln!("SSH read error: {}", e);
+                        error!("SSH read error: {}", e);
+                        let (message, reason) = emit_remote_disconnect(&app_handle_clone, &session_id_clone, &reader_session, format!("Connection lost: {}", e));
+                        reconnect::on_disconnect(&app_handle_clone, &session_id_clone, ReconnectTrigger::ReadError, message, reason);
                         break;
                     }
                 }
             }
             
-            println!("SSH reader thread for {} exiting", session_id_clone);
+            trace!("SSH reader thread for {} exiting", session_id_clone);
         });
         
         // Writer thread
         let writer_channel = shared_channel.clone();
         let writer_shutdown_clone = writer_shutdown.clone();
         let session_id_writer = session_id.clone();
+        let writer_stats = stats.clone();
+        let writer_app_handle = app_handle.clone();
         
         let writer_handle = thread::spawn(move || {
-            while !writer_shutdown_clone.load(Ordering::Relaxed) {
+            loop {
+                let shutting_down = writer_shutdown_clone.load(Ordering::Relaxed);
+
                 match writer_rx.try_recv() {
                     Ok(data) => {
-                        let write_result = {
-                            let mut channel = writer_channel.lock().unwrap();
-                            channel.write_all(&data).and_then(|_| channel.flush())
-                        };
-                        
-                        if let Err(e) = write_result {
-                            eprintln!("SSH write error for {}: {}", session_id_writer, e);
+                        if let Err(e) = write_to_channel(&writer_channel, &data) {
+                            error!("SSH write error for {}: {}", session_id_writer, e);
+
+                            // Everything still queued is lost too, so tell the UI
+                            let mut dropped_bytes = data.len();
+                            while let Ok(pending) = writer_rx.try_recv() {
+                                dropped_bytes += pending.len();
+                            }
+                            emit_write_failed(&writer_app_handle, &session_id_writer, &e, dropped_bytes);
                             break;
                         }
+                        writer_stats.bytes_sent.fetch_add(data.len() as u64, Ordering::Relaxed);
                     }
                     Err(mpsc::error::TryRecvError::Empty) => {
-                        // No data available, sleep briefly and check shutdown
+                        // The queue is drained, so a requested shutdown can finish now
+                        if shutting_down {
+                            break;
+                        }
                         thread::sleep(Duration::from_millis(10));
                     }
                     Err(mpsc::error::TryRecvError::Disconnected) => {
-                        println!("Writer channel disconnected for {}", session_id_writer);
+                        debug!("Writer channel disconnected for {}", session_id_writer);
                         break;
                     }
                 }
             }
             
-            println!("SSH writer thread for {} exiting", session_id_writer);
+            trace!("SSH writer thread for {} exiting", session_id_writer);
         });
 
+        let keepalive_handle = match config.keepalive_interval_secs.unwrap_or(DEFAULT_KEEPALIVE_INTERVAL) {
+            0 => None,
+            secs => Some(spawn_keepalive(session_id.clone(), session.clone(), secs, reader_shutdown.clone())),
+        };
+
         // ---- Input buffering and debouncing thread ----
         let input_writer_tx = writer_tx.clone();
         let input_shutdown_clone = input_shutdown.clone();
@@ -179,7 +1035,7 @@ impl SshConnection {
                     // Write to the SSH writer
                     let bytes = buffer.clone().into_bytes();
                     if let Err(e) = input_writer_tx.send(bytes) {
-                        eprintln!("[SSH] Failed to send buffered input: {e}");
+                        error!("Failed to send buffered input: {e}");
                     }
                     buffer.clear();
                     last_flush = now;
@@ -194,6 +1050,16 @@ impl SshConnection {
         
         Ok(SshConnection {
             session_id,
+            config: config.clone(),
+            remote_addr,
+            pty_size: Mutex::new(pty_size),
+            widened_cols: Mutex::new(None),
+            connected_at: chrono::Utc::now(),
+            started: Instant::now(),
+            stats,
+            output,
+            sudo_prompt,
+            cwd,
             input_tx,
             reader_shutdown,
             writer_shutdown,
@@ -201,69 +1067,447 @@ impl SshConnection {
             reader_handle: Some(reader_handle),
             writer_handle: Some(writer_handle),
             input_handle: Some(input_handle),
+            keepalive_handle,
             channel: shared_channel,
+            session,
+            forwards: HashMap::new(),
+            latency: None,
+            sftp: None,
+            jump: None,
+            disconnect_watch: None,
         })
     }
     
     pub fn send_input(&self, input: &str) -> Result<()> {
         self.input_tx
             .send(input.to_string())
-            .map_err(|e| anyhow!("Failed to send input for buffering: {}", e))?;
+            .map_err(|_| SshError::ChannelClosed)?;
         // let data = input.as_bytes().to_vec();
         // self.writer_tx.send(data)
-            // .map_err(|e| anyhow!("Failed to send input: {}", e))?;
+            // .map_err(|e| SshError::Other(format!("Failed to send input: {}", e)))?;
         Ok(())
     }
 
-    pub fn resize_pty(&self, cols: u32, rows: u32) -> Result<()> {
-        let mut channel = self.channel.lock().unwrap();
-        channel.request_pty_size(cols, rows, None, None)?;
-        Ok(())
+    pub fn info(&self) -> ActiveConnectionInfo {
+        // The reader thread exits as soon as the channel hits EOF or an error
+        let alive = self.reader_handle.as_ref().is_some_and(|handle| !handle.is_finished());
+
+        ActiveConnectionInfo {
+            session_id: self.session_id.clone(),
+            host: self.config.host.clone(),
+            port: self.config.port,
+            remote_addr: self.remote_addr.to_string(),
+            username: self.config.username.clone(),
+            connected_at: self.connected_at.to_rfc3339(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            status: if alive { "connected" } else { "closed" }.to_string(),
+            bytes_received: self.stats.bytes_received.load(Ordering::Relaxed),
+            bytes_sent: self.stats.bytes_sent.load(Ordering::Relaxed),
+            open_channels: usize::from(alive)
+                + usize::from(self.sftp.is_some())
+                + self.forwards.values().map(|f| f.info().active_channels).sum::<usize>(),
+            sftp_active: self.sftp.is_some(),
+            forwards_active: self.forwards.len(),
+            last_output_secs_ago: self.stats.last_output_secs_ago(),
+            output_paused: self.output.is_paused(),
+        }
     }
-    
-    pub fn close(&mut self) {
-        println!("Closing SSH connection {}", self.session_id);
-        
-        // Signal threads to shutdown
-        self.reader_shutdown.store(true, Ordering::Relaxed);
-        self.writer_shutdown.store(true, Ordering::Relaxed);
-        self.input_shutdown.store(true, Ordering::Relaxed);
-        
-        // Wait for threads to finish
-        if let Some(handle) = self.reader_handle.take() {
-            if let Err(e) = handle.join() {
-                eprintln!("Reader thread join error: {:?}", e);
+
+    // Runtime command console, the equivalent of OpenSSH's `~C` escape
+    pub fn run_session_command(&mut self, command: &str) -> Result<SessionCommandOutput> {
+        match forwarding::parse_session_command(command).map_err(SshError::Other)? {
+            SessionCommand::Forward(spec) => {
+                let info = self.add_forward(spec)?;
+                Ok(SessionCommandOutput {
+                    message: format!("{:?} forward on {}:{} started", info.kind, info.bind_address, info.bind_port),
+                    forward: Some(info),
+                })
             }
-        }
-        
-        if let Some(handle) = self.writer_handle.take() {
-            if let Err(e) = handle.join() {
-                eprintln!("Writer thread join error: {:?}", e);
+            SessionCommand::Cancel { kind, bind_port } => {
+                let id = self.forwards.values()
+                    .find(|forward| forward.matches(kind, bind_port))
+                    .map(|forward| forward.id().to_string())
+                    .ok_or_else(|| SshError::Other(format!("No {:?} forward on port {}", kind, bind_port)))?;
+                self.stop_forward(&id)
             }
+            SessionCommand::KillForward(id) => self.stop_forward(&id),
         }
+    }
 
-        if let Some(handle) = self.input_handle.take() {
-            if let Err(e) = handle.join() {
-                eprintln!("Input thread join error: {:?}", e);
-            }
+    // Exec channels aren't listed: commands run on connections of their own
+    fn channels(&self) -> Vec<SessionChannel> {
+        let alive = self.reader_handle.as_ref().is_some_and(|handle| !handle.is_finished());
+        let mut channels = vec![SessionChannel {
+            id: SHELL_CHANNEL_ID.to_string(),
+            kind: ChannelKind::Shell,
+            state: if alive { ChannelState::Open } else { ChannelState::Closed },
+            forward: None,
+        }];
+        if self.sftp.is_some() {
+            channels.push(SessionChannel {
+                id: SFTP_CHANNEL_ID.to_string(),
+                kind: ChannelKind::Sftp,
+                state: ChannelState::Open,
+                forward: None,
+            });
         }
+        let mut forwards: Vec<ForwardInfo> = self.forwards.values().map(Forward::info).collect();
+        forwards.sort_by_key(|forward| (forward.bind_port, forward.id.clone()));
+        channels.extend(forwards.into_iter().map(|forward| SessionChannel {
+            id: forward.id.clone(),
+            kind: ChannelKind::Forward,
+            state: ChannelState::Open,
+            forward: Some(forward),
+        }));
+        channels
     }
-}
 
-impl Drop for SshConnection {
-    fn drop(&mut self) {
-        self.close();
+    fn add_forward(&mut self, spec: ForwardSpec) -> Result<ForwardInfo> {
+        let forward = forwarding::start_forward(&self.session, spec).map_err(SshError::Other)?;
+        let info = forward.info();
+        self.forwards.insert(forward.id().to_string(), forward);
+        Ok(info)
     }
-}
 
-pub struct SshManager {
-    connections: Arc<Mutex<HashMap<String, SshConnection>>>,
-}
+    fn stop_forward(&mut self, id: &str) -> Result<SessionCommandOutput> {
+        let mut forward = self.forwards.remove(id)
+            .ok_or_else(|| SshError::Other(format!("Forward not found: {}", id)))?;
+        forward.stop();
+        Ok(SessionCommandOutput {
+            message: format!("Forward {} stopped", id),
+            forward: Some(forward.info()),
+        })
+    }
 
-impl SshManager {
-    pub fn new() -> Self {
-        SshManager {
-            connections: Arc::new(Mutex::new(HashMap::new())),
+    // The channel is non-blocking, so a window change request that hits a
+    // busy transport has to be repeated until it's actually been sent
+    fn request_pty_size(&self, cols: u32, rows: u32) -> Result<()> {
+        let started = Instant::now();
+        loop {
+            let result = self.channel.lock().unwrap().request_pty_size(cols, rows, None, None);
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if forwarding::is_would_block(&e) => {
+                    if started.elapsed() > RESIZE_TIMEOUT {
+                        return Err(SshError::Timeout);
+                    }
+                    thread::sleep(Duration::from_millis(5));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    // A resize from the client also ends a temporary widening: the size the
+    // user just chose wins
+    pub fn resize_pty(&self, cols: u32, rows: u32) -> Result<()> {
+        self.request_pty_size(cols, rows)?;
+        *self.pty_size.lock().unwrap() = (cols, rows);
+        *self.widened_cols.lock().unwrap() = None;
+        Ok(())
+    }
+
+    // Makes the remote PTY `cols` wide, keeping the client's row count, so
+    // programs that fit their output to the terminal (ps, docker ps) don't
+    // truncate it. Returns the client size that restore_pty goes back to.
+    pub fn widen_pty(&self, cols: u32) -> Result<(u32, u32)> {
+        let (client_cols, rows) = *self.pty_size.lock().unwrap();
+        self.request_pty_size(cols, rows)?;
+        *self.widened_cols.lock().unwrap() = Some(cols);
+        Ok((client_cols, rows))
+    }
+
+    // Returns the PTY to the client's size; None if it wasn't widened
+    pub fn restore_pty(&self) -> Result<Option<(u32, u32)>> {
+        if self.widened_cols.lock().unwrap().is_none() {
+            return Ok(None);
+        }
+        let (cols, rows) = *self.pty_size.lock().unwrap();
+        self.request_pty_size(cols, rows)?;
+        *self.widened_cols.lock().unwrap() = None;
+        Ok(Some((cols, rows)))
+    }
+    
+    pub fn close(&mut self) {
+        debug!("Closing SSH connection {}", self.session_id);
+
+        if let Some(mut monitor) = self.latency.take() {
+            monitor.stop();
+        }
+
+        // Dropping it shuts the subsystem down before the transport goes away
+        self.sftp = None;
+
+        for (_, mut forward) in self.forwards.drain() {
+            forward.stop();
+        }
+        
+        // Shut down in pipeline order so buffered input is flushed: the input
+        // thread hands its remainder to the writer, which drains its queue
+        // before exiting, and only then is the reader stopped
+        self.input_shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.input_handle.take() {
+            if let Err(e) = handle.join() {
+                error!("Input thread join error: {:?}", e);
+            }
+        }
+
+        self.writer_shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.writer_handle.take() {
+            if let Err(e) = handle.join() {
+                error!("Writer thread join error: {:?}", e);
+            }
+        }
+
+        self.reader_shutdown.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.reader_handle.take() {
+            if let Err(e) = handle.join() {
+                error!("Reader thread join error: {:?}", e);
+            }
+        }
+        if let Some(handle) = self.keepalive_handle.take() {
+            if let Err(e) = handle.join() {
+                error!("Keepalive thread join error: {:?}", e);
+            }
+        }
+
+        // Last, since the connection's own traffic runs through it
+        self.jump = None;
+    }
+}
+
+impl Drop for SshConnection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// A forward that couldn't be started, e.g. because its port is taken
+#[derive(Clone, serde::Serialize)]
+struct ForwardErrorEvent {
+    session_id: String,
+    kind: ForwardKind,
+    bind_address: String,
+    bind_port: u16,
+    message: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct InsecureKeyPermissionsEvent {
+    session_id: String,
+    #[serde(flatten)]
+    issue: crate::keys::InsecurePermissions,
+}
+
+// A too-open key file is a classic first-time setup problem, so tell the UI
+// (or fix it, if the user opted in) instead of leaving an opaque auth failure
+fn preflight_key_permissions(session_id: &str, key_path: &str, app_handle: &AppHandle) {
+    let fix = app_handle.state::<crate::AppState>()
+        .settings
+        .lock()
+        .map(|settings| settings.fix_key_permissions)
+        .unwrap_or(false);
+
+    for issue in crate::keys::check_key_permissions(std::path::Path::new(key_path), fix) {
+        if issue.fixed {
+            info!("Changed mode of {} from {} to {}", issue.path, issue.mode, issue.expected);
+        } else {
+            warn!("{} has insecure permissions {} (expected {})", issue.path, issue.mode, issue.expected);
+        }
+
+        if let Err(e) = app_handle.emit("insecure_key_permissions", &InsecureKeyPermissionsEvent {
+            session_id: session_id.to_string(),
+            issue,
+        }) {
+            error!("Failed to emit key permission warning: {}", e);
+        }
+    }
+}
+
+fn offers(methods: &str, method: &str) -> bool {
+    methods.split(',').any(|offered| offered == method)
+}
+
+// Runs keyboard-interactive with room for an out-of-band second factor: the
+// server may not answer until a push is approved on the user's phone
+fn keyboard_interactive(session: &Session, username: &str, prompter: &mut InteractivePrompter) -> Result<()> {
+    debug!("Authenticating with keyboard-interactive for user: {}", username);
+    session.set_timeout(MFA_APPROVAL_TIMEOUT.as_millis() as u32);
+    let result = session.userauth_keyboard_interactive(username, prompter);
+    session.set_timeout(0);
+    Ok(result?)
+}
+
+// Methods still to do after one succeeded partially, e.g. a key on a server
+// with AuthenticationMethods publickey,password
+#[derive(Clone, serde::Serialize)]
+struct AuthPartialSuccessEvent {
+    session_id: String,
+    remaining: Vec<String>,
+}
+
+// Bounds the chain of methods a server can ask for
+const MAX_AUTH_ROUNDS: usize = 5;
+
+// `agent_key` limits agent authentication to that one public key blob
+pub(crate) fn authenticate(session: &Session, config: &SshConfig, agent_key: Option<&[u8]>, app_handle: &AppHandle, session_id: &str) -> Result<()> {
+    // Asking for the methods tries "none" first, which some servers accept
+    let methods = session.auth_methods(&config.username).unwrap_or_default().to_string();
+    if session.authenticated() {
+        return Ok(());
+    }
+
+    // Servers that only do keyboard-interactive get the password through it
+    let password_over_kbd = !offers(&methods, "password") && offers(&methods, "keyboard-interactive");
+    let mut kbd_password = None;
+    let first_result = first_method(session, config, agent_key, password_over_kbd.then_some(&mut kbd_password), app_handle, session_id);
+    let mut result = Ok(());
+    let mut prompter = InteractivePrompter::new(app_handle, session_id, kbd_password.as_deref());
+
+    // A second factor shows up as partial success: the method list shrinks to
+    // what's left to do. Each step that succeeds partially leads to the next,
+    // until the server is satisfied or a step fails outright.
+    let mut previous = methods.clone();
+    let mut any_partial_success = false;
+    for round in 0..MAX_AUTH_ROUNDS {
+        if session.authenticated() {
+            break;
+        }
+        let remaining = session.auth_methods(&config.username).map(str::to_string).unwrap_or_else(|_| previous.clone());
+        let partial_success = remaining != previous;
+        if partial_success {
+            info!("Partial authentication success for {}, remaining: {}", session_id, remaining);
+            let _ = app_handle.emit("auth_partial_success", &AuthPartialSuccessEvent {
+                session_id: session_id.to_string(),
+                remaining: remaining.split(',').map(str::to_string).collect(),
+            });
+            any_partial_success = true;
+        }
+
+        // After the configured method, keyboard-interactive is also tried as a
+        // fallback. Strict mode still answers a second factor the server asks
+        // for, but a rejected key or password doesn't get to try it instead:
+        // every extra method offered tells the server (and anyone running it)
+        // more about the user than they chose to share. Later rounds only go
+        // on after a partial success.
+        let go_on = match round {
+            0 => !config.strict_identities || password_over_kbd || partial_success,
+            _ => partial_success,
+        };
+        if !go_on {
+            break;
+        }
+        previous = remaining.clone();
+
+        result = if offers(&remaining, "keyboard-interactive") {
+            keyboard_interactive(session, &config.username, &mut prompter)
+        } else if partial_success && offers(&remaining, "password") {
+            match prompter.ask_password(&config.username) {
+                Some(mut password) => {
+                    debug!("Continuing with password authentication for user: {}", config.username);
+                    let password_result = session.userauth_password(&config.username, &password);
+                    password.zeroize();
+                    password_result.map_err(SshError::from)
+                }
+                None => Err(SshError::Auth("The server also requires a password".to_string())),
+            }
+        } else if partial_success {
+            Err(SshError::Auth(format!("The server also requires authentication by {}, which isn't supported", remaining)))
+        } else {
+            break;
+        };
+    }
+    kbd_password.zeroize();
+
+    if !session.authenticated() {
+        // After a partial success the step that failed last is the one to
+        // report, otherwise it's the configured method
+        if !any_partial_success {
+            first_result?;
+        }
+        result?;
+        return Err(SshError::Auth("server rejected the credentials".to_string()));
+    }
+
+    Ok(())
+}
+
+// Tries the configured method. Passwords go into `kbd_password` instead when
+// it's given, for a keyboard-interactive round to answer with.
+fn first_method(
+    session: &Session,
+    config: &SshConfig,
+    agent_key: Option<&[u8]>,
+    kbd_password: Option<&mut Option<String>>,
+    app_handle: &AppHandle,
+    session_id: &str,
+) -> Result<()> {
+    match &config.auth_method {
+        AuthMethod::Password { password } => match kbd_password {
+            Some(slot) => *slot = Some(password.clone()),
+            None => {
+                debug!("Authenticating with password for user: {}", config.username);
+                session.userauth_password(&config.username, password)?;
+            }
+        },
+        AuthMethod::Keychain { session_id } => {
+            let password = crate::keychain::require_password(session_id).map_err(SshError::Auth)?;
+            match kbd_password {
+                Some(slot) => *slot = Some(password.to_string()),
+                None => {
+                    debug!("Authenticating with keychain password for user: {}", config.username);
+                    session.userauth_password(&config.username, &password)?;
+                }
+            }
+        }
+        AuthMethod::PublicKey { private_key_path } => {
+            debug!("Authenticating with public key: {}", private_key_path);
+            let private_key_path = std::path::Path::new(private_key_path);
+            session.userauth_pubkey_file(&config.username, None, private_key_path, None)?;
+        }
+        AuthMethod::Pkcs11 { provider_path } => {
+            debug!("Authenticating with PKCS#11 provider: {}", provider_path);
+            let prompter = InteractivePrompter::new(app_handle, session_id, None);
+            crate::pkcs11::authenticate(session, &config.username, provider_path, || {
+                prompter.ask_secret(&format!("Unlock {} to add its keys to ssh-agent", provider_path), "PIN: ")
+            })?;
+        }
+        AuthMethod::Agent => {
+            debug!("Authenticating with SSH agent for user: {}", config.username);
+            let mut agent = session.agent()?;
+            agent.connect()?;
+            agent.list_identities()?;
+            
+            let identities = agent.identities()?;
+            let mut authenticated = false;
+            
+            for identity in identities {
+                if agent_key.is_some_and(|key| key != identity.blob()) {
+                    continue;
+                }
+                if agent.userauth(&config.username, &identity).is_ok() {
+                    authenticated = true;
+                    break;
+                }
+            }
+            
+            if !authenticated {
+                return Err(SshError::Auth("SSH agent has no suitable identity".to_string()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub struct SshManager {
+    connections: Arc<Mutex<HashMap<String, SshConnection>>>,
+}
+
+impl SshManager {
+    pub fn new() -> Self {
+        SshManager {
+            connections: Arc::new(Mutex::new(HashMap::new())),
         }
     }
     
@@ -273,58 +1517,127 @@ impl SshManager {
         config: SshConfig,
         app_handle: AppHandle,
     ) -> Result<()> {
-        println!("Connecting to SSH host: {}@{}:{}", config.username, config.host, config.port);
+        let pty_size = config.terminal_modes.size();
+        self.connect_with_size(session_id, config, pty_size, app_handle)
+    }
+
+    fn connect_with_size(
+        &self,
+        session_id: String,
+        config: SshConfig,
+        pty_size: (u32, u32),
+        app_handle: AppHandle,
+    ) -> Result<()> {
+        info!("Connecting to SSH host: {}@{}:{}", config.username, config.host, config.port);
         
         // Establish TCP connection
-        let tcp_stream = TcpStream::connect(format!("{}:{}", config.host, config.port))?;
+        let (tcp_stream, remote_addr, jump) = if config.jump_hosts.is_empty() {
+            let (tcp_stream, remote_addr) = crate::net::connect_with_timeout(
+                &config.host,
+                config.port,
+                config.http_proxy.as_ref(),
+                config.bind_address.as_deref(),
+                config.connect_timeout(),
+            )?;
+            // Through a proxy the stream ends at the proxy, not the target
+            if !config.allow_loopback && config.http_proxy.is_none() && crate::net::is_self_connection(&tcp_stream) {
+                warn!("{} resolves to this machine ({})", config.host, remote_addr.ip());
+                let event = LoopbackWarningEvent {
+                    session_id: session_id.clone(),
+                    host: config.host.clone(),
+                    address: remote_addr.ip().to_string(),
+                };
+                if let Err(e) = app_handle.emit("loopback_warning", &event) {
+                    error!("Failed to emit loopback warning: {}", e);
+                }
+            }
+            (tcp_stream, remote_addr, None)
+        } else {
+            let (tcp_stream, remote_addr, jump) = crate::jump::connect_via_jump(&config, &app_handle, &session_id)?;
+            (tcp_stream, remote_addr, Some(jump))
+        };
         
         // Create SSH session
         let mut session = Session::new()?;
         session.set_tcp_stream(tcp_stream);
+        let disconnect_watch = disconnect::watch(&session);
+        // A server that accepts the connection but never answers would
+        // otherwise hang here; the limit is lifted again for everything after
+        session.set_timeout(config.connect_timeout().as_millis() as u32);
         session.handshake()?;
+        session.set_timeout(0);
+        crate::host_keys::verify(&session, &config.host, config.port, config.host_key_trust)?;
         
-        // Authenticate based on auth method
-        match &config.auth_method {
-            AuthMethod::Password { password } => {
-                println!("Authenticating with password for user: {}", config.username);
-                session.userauth_password(&config.username, password)?;
-            }
-            AuthMethod::PublicKey { private_key_path } => {
-                println!("Authenticating with public key: {}", private_key_path);
-                let private_key_path = std::path::Path::new(private_key_path);
-                session.userauth_pubkey_file(&config.username, None, private_key_path, None)?;
+        let mut security_key = false;
+        if let AuthMethod::PublicKey { private_key_path } = &config.auth_method {
+            preflight_key_permissions(&session_id, private_key_path, &app_handle);
+            security_key = KeyManager::analyze_key(std::path::Path::new(private_key_path))
+                .is_ok_and(|key| key.is_security_key);
+        }
+
+        // libssh2 can't drive a FIDO2 token itself (ssh2 doesn't expose its sk
+        // signing callback), so security keys go through ssh-agent, which talks
+        // to the token. The UI is told to ask the user for a touch meanwhile.
+        let auth_result = if security_key {
+            if let Err(e) = app_handle.emit("security_key_touch_required", &ConnectionStatusEvent {
+                session_id: session_id.clone(),
+                status: "authenticating".to_string(),
+                message: Some("Touch your security key to continue".to_string()),
+                disconnect: None,
+            }) {
+                error!("Failed to emit security key prompt: {}", e);
             }
-            AuthMethod::Agent => {
-                println!("Authenticating with SSH agent for user: {}", config.username);
-                let mut agent = session.agent()?;
-                agent.connect()?;
-                agent.list_identities()?;
-                
-                let identities = agent.identities()?;
-                let mut authenticated = false;
-                
-                for identity in identities {
-                    if agent.userauth(&config.username, &identity).is_ok() {
-                        authenticated = true;
-                        break;
-                    }
-                }
-                
-                if !authenticated {
-                    return Err(anyhow!("SSH agent authentication failed - no suitable identity found"));
+
+            // Strict mode offers the agent only this key, not everything loaded in it
+            let agent_key = match &config.auth_method {
+                AuthMethod::PublicKey { private_key_path } if config.strict_identities => {
+                    Some(KeyManager::public_key_blob(std::path::Path::new(private_key_path)).ok_or_else(|| SshError::Auth(format!(
+                        "Can't read the public half of {} to pick it out of ssh-agent",
+                        private_key_path
+                    )))?)
                 }
-            }
-        }
-        
-        if !session.authenticated() {
-            return Err(anyhow!("SSH authentication failed"));
-        }
+                _ => None,
+            };
+            let agent_config = SshConfig { auth_method: AuthMethod::Agent, ..config.clone() };
+            authenticate(&session, &agent_config, agent_key.as_deref(), &app_handle, &session_id).map_err(|e| SshError::Auth(format!(
+                "{}. Security keys must be loaded into ssh-agent (ssh-add), or use the system ssh client",
+                e
+            )))
+        } else {
+            authenticate(&session, &config, None, &app_handle, &session_id)
+        };
+
+        // If the server kicks us out (e.g. too many authentication failures)
+        // report its reason instead
+        auth_result.map_err(|e| match disconnect::take(&session) {
+            Some(reason) => SshError::Auth(reason.describe()),
+            None => e,
+        })?;
         
-        println!("SSH authentication successful for {}", session_id);
+        debug!("SSH authentication successful for {}", session_id);
         
         // Open channel and request PTY
         let mut channel = session.channel_session()?;
-        channel.request_pty("xterm-256color", None, Some((80, 24, 0, 0)))?;
+
+        // Variables go over the wire in plain text like anything else typed.
+        // Servers only accept setenv for names listed in AcceptEnv; the rest
+        // are exported from the shell once it's up.
+        let mut exports = Vec::new();
+        if let Some(env_file) = &config.env_file {
+            match crate::dotenv::load_dotenv(env_file) {
+                Ok(vars) => {
+                    exports = vars.into_iter()
+                        .filter(|(key, value)| channel.setenv(key, value).is_err())
+                        .collect();
+                }
+                Err(e) => {
+                    warn!("Not applying environment file: {}", e);
+                    emit_connection_status(&app_handle, &session_id, "connecting", &format!("Environment file skipped: {}", e));
+                }
+            }
+        }
+
+        channel.request_pty("xterm-256color", config.terminal_modes.pty_modes(), Some((pty_size.0, pty_size.1, 0, 0)))?;
         
         // Set up the shell - this is crucial for interactive terminal
         channel.shell()?;
@@ -332,10 +1645,15 @@ impl SshManager {
         // Important: Set the channel to non-blocking mode to prevent deadlocks
         session.set_blocking(false);
         
-        println!("SSH channel established for {}", session_id);
+        debug!("SSH channel established for {}", session_id);
         
         // Create connection wrapper
-        let connection = SshConnection::new(session_id.clone(), channel, app_handle.clone())?;
+        let mut connection = SshConnection::new(session_id.clone(), &config, remote_addr, session, channel, pty_size, app_handle.clone())?;
+        connection.jump = jump;
+        connection.disconnect_watch = Some(disconnect_watch);
+        if !exports.is_empty() {
+            connection.send_input(&crate::dotenv::export_script(&exports))?;
+        }
         
         // Give the shell a moment to initialize and send initial output
         std::thread::sleep(std::time::Duration::from_millis(200));
@@ -347,15 +1665,9 @@ impl SshManager {
         }
         
         // Emit connected status
-        if let Err(e) = app_handle.emit("connection_status", &ConnectionStatusEvent {
-            session_id: session_id.clone(),
-            status: "connected".to_string(),
-            message: Some("Connection established".to_string()),
-        }) {
-            eprintln!("Failed to emit connection status: {}", e);
-        }
+        emit_connection_status(&app_handle, &session_id, "connected", "Connection established");
         
-        println!("SSH connection {} ready", session_id);
+        info!("SSH connection {} ready", session_id);
         Ok(())
     }
     
@@ -366,8 +1678,80 @@ impl SshManager {
             connection.send_input(input)?;
             Ok(())
         } else {
-            Err(anyhow!("Session not found: {}", session_id))
+            Err(SshError::SessionNotFound(session_id.to_string()))
+        }
+    }
+
+    // Types the password into sudo's prompt, the given one or the session's
+    // cached one. Refused unless the prompt is waiting right now, since
+    // anywhere else the password would be echoed.
+    pub fn answer_sudo_prompt(&self, session_id: &str, password: Option<String>) -> Result<()> {
+        let connections = self.connections.lock().unwrap();
+        let connection = connections.get(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        if !connection.sudo_prompt.take_pending() {
+            return Err(SshError::Other("sudo is not waiting for a password".to_string()));
+        }
+
+        let mut password = match password {
+            Some(password) => password,
+            None => connection.config.sudo_password()?,
+        };
+        password.push('\r');
+        let result = connection.send_input(&password);
+        password.zeroize();
+        result
+    }
+
+    // Types Ctrl-D into the shell, which logs out the way `exit` would (with
+    // logout scripts and session accounting), then waits up to `timeout` for
+    // the channel to close. Returns the shell's exit status, or None if it
+    // didn't end in time. The connection is left for the caller to close.
+    pub fn send_eof(&self, session_id: &str, timeout: Duration, app_handle: &AppHandle) -> Result<Option<i32>> {
+        let channel = {
+            let connections = self.connections.lock().unwrap();
+            let connection = connections.get(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+            // A paused reader would never see the channel close
+            connection.output.resume(app_handle, session_id);
+            connection.send_input("\x04")?;
+            connection.channel.clone()
+        };
+
+        // The reader thread exits once the channel reaches EOF
+        let started = Instant::now();
+        loop {
+            let finished = {
+                let connections = self.connections.lock().unwrap();
+                connections.get(session_id)
+                    .and_then(|connection| connection.reader_handle.as_ref())
+                    .is_none_or(|handle| handle.is_finished())
+            };
+            if finished {
+                break;
+            }
+            if started.elapsed() >= timeout {
+                return Ok(None);
+            }
+            thread::sleep(Duration::from_millis(50));
         }
+
+        let exit_status = channel.lock().unwrap().exit_status()?;
+        Ok(Some(exit_status))
+    }
+
+    pub fn widen_terminal(&self, session_id: &str, cols: u32) -> Result<(u32, u32)> {
+        let connections = self.connections.lock().unwrap();
+        connections.get(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?
+            .widen_pty(cols)
+    }
+
+    pub fn restore_terminal_width(&self, session_id: &str) -> Result<Option<(u32, u32)>> {
+        let connections = self.connections.lock().unwrap();
+        connections.get(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?
+            .restore_pty()
     }
 
     pub fn resize_terminal(&self, session_id: &str, cols: u32, rows: u32) -> Result<()> {
@@ -377,28 +1761,404 @@ impl SshManager {
             connection.resize_pty(cols, rows)?;
             Ok(())
         } else {
-            Err(anyhow!("Session not found: {}", session_id))
+            Err(SshError::SessionNotFound(session_id.to_string()))
         }
     }
     
 
     
     pub fn disconnect(&self, session_id: &str) -> Result<()> {
-        let mut connections = self.connections.lock().unwrap();
-        
-        if let Some(mut connection) = connections.remove(session_id) {
+        // Closing joins the connection's threads, which can take a probe
+        // timeout, so it happens with the map unlocked
+        let connection = self.connections.lock().unwrap().remove(session_id);
+
+        if let Some(mut connection) = connection {
             connection.close();
-            println!("Disconnected SSH session: {}", session_id);
+            info!("Disconnected SSH session: {}", session_id);
             Ok(())
         } else {
-            Err(anyhow!("Session not found: {}", session_id))
+            Err(SshError::SessionNotFound(session_id.to_string()))
         }
     }
     
+    // Tears the session down and reconnects with the same config and terminal
+    // size. The old connection is removed from the map and fully closed (all
+    // threads joined) before the new one is created, so two readers never
+    // race on the same session id.
+    pub fn restart(&self, session_id: &str, app_handle: AppHandle) -> Result<()> {
+        let mut connection = {
+            let mut connections = self.connections.lock().unwrap();
+            let connection = connections.get(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+
+            // Keep the session alive rather than tear it down and fail to log back in
+            if matches!(&connection.config.auth_method, AuthMethod::Password { password } if password.is_empty()) {
+                return Err(SshError::Auth("The cached password was cleared, reconnect to enter it again".to_string()));
+            }
+            connections.remove(session_id).unwrap()
+        };
+
+        emit_connection_status(&app_handle, session_id, "connecting", "Restarting session");
+
+        let config = connection.config.clone();
+        let pty_size = *connection.pty_size.lock().unwrap();
+        connection.close();
+        drop(connection);
+
+        info!("Restarting SSH session {}", session_id);
+        if let Err(e) = self.connect_with_size(session_id.to_string(), config, pty_size, app_handle.clone()) {
+            emit_connection_status(&app_handle, session_id, "disconnected", &format!("Restart failed: {}", e));
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Returns how many cached passwords were wiped
+    pub fn clear_cached_secrets(&self) -> usize {
+        let mut connections = self.connections.lock().unwrap();
+        connections.values_mut().map(|c| c.config.clear_secrets()).filter(|&cleared| cleared).count()
+    }
+
     pub fn list_sessions(&self) -> Vec<String> {
         let connections = self.connections.lock().unwrap();
         connections.keys().cloned().collect()
     }
+
+    pub fn pause_output(&self, session_id: &str, mode: OutputPauseMode, buffer_bytes: usize) -> Result<bool> {
+        let connections = self.connections.lock().unwrap();
+        let connection = connections.get(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+
+        let limit = match mode {
+            OutputPauseMode::Buffer => buffer_bytes,
+            OutputPauseMode::FlowControl => 0,
+        };
+        Ok(connection.output.pause(limit))
+    }
+
+    pub fn resume_output(&self, session_id: &str, app_handle: &AppHandle) -> Result<usize> {
+        let output = {
+            let connections = self.connections.lock().unwrap();
+            connections.get(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?
+                .output
+                .clone()
+        };
+        Ok(output.resume(app_handle, session_id))
+    }
+
+    pub fn session_command(&self, session_id: &str, command: &str) -> Result<SessionCommandOutput> {
+        let mut connections = self.connections.lock().unwrap();
+
+        if let Some(connection) = connections.get_mut(session_id) {
+            connection.run_session_command(command)
+        } else {
+            Err(SshError::SessionNotFound(session_id.to_string()))
+        }
+    }
+
+    // Runs a command on an exec channel of the connected session, next to the
+    // shell, so looking something up doesn't cost another login
+    pub fn exec(&self, session_id: &str, command: &str, timeout: Duration) -> Result<crate::exec::ExecOutput> {
+        let session = {
+            let connections = self.connections.lock().unwrap();
+            connections.get(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?
+                .session
+                .clone()
+        };
+        crate::exec::run_command_nonblocking(&session, command, timeout).map_err(SshError::Other)
+    }
+
+    pub fn probe_channel(&self, session_id: &str) -> Result<ChannelProbe> {
+        // Probe with a clone of the session so the map isn't locked while we wait
+        let (session, stats) = {
+            let connections = self.connections.lock().unwrap();
+            let connection = connections.get(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+            (connection.session.clone(), connection.stats.clone())
+        };
+
+        let result = probe_session(&session);
+        if let Err(e) = &result {
+            info!("Probe of SSH session {} failed: {}", session_id, e);
+        }
+
+        Ok(ChannelProbe {
+            session_id: session_id.to_string(),
+            responsive: result.is_ok(),
+            round_trip_ms: result.as_ref().ok().map(|elapsed| elapsed.as_millis() as u64),
+            last_output_secs_ago: stats.last_output_secs_ago(),
+            error: result.err(),
+        })
+    }
+
+    pub fn start_latency_monitor(&self, session_id: &str, interval: Duration, app_handle: AppHandle) -> Result<()> {
+        let mut connections = self.connections.lock().unwrap();
+        let connection = connections.get_mut(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+
+        if connection.latency.is_none() {
+            let monitor = LatencyMonitor::start(session_id.to_string(), connection.session.clone(), interval, app_handle);
+            connection.latency = Some(monitor);
+        }
+        Ok(())
+    }
+
+    pub fn stop_latency_monitor(&self, session_id: &str) -> Result<()> {
+        // Joining the monitor thread may take a probe timeout, so do it unlocked
+        let monitor = {
+            let mut connections = self.connections.lock().unwrap();
+            connections.get_mut(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?
+                .latency
+                .take()
+        };
+        drop(monitor);
+        Ok(())
+    }
+
+    pub fn latency_history(&self, session_id: &str) -> Result<Vec<LatencySample>> {
+        let connections = self.connections.lock().unwrap();
+        let connection = connections.get(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        Ok(connection.latency.as_ref().map(LatencyMonitor::history).unwrap_or_default())
+    }
+
+    // Returns false when the channel was already open
+    pub fn open_sftp(&self, session_id: &str) -> Result<bool> {
+        let session = {
+            let connections = self.connections.lock().unwrap();
+            let connection = connections.get(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+            if connection.sftp.is_some() {
+                return Ok(false);
+            }
+            connection.session.clone()
+        };
+
+        // Open unlocked, the server may take a while to start the subsystem
+        let sftp = open_sftp_channel(&session)?;
+
+        let mut connections = self.connections.lock().unwrap();
+        let connection = connections.get_mut(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        if connection.sftp.is_some() {
+            return Ok(false);
+        }
+        connection.sftp = Some(sftp);
+        info!("Opened SFTP channel on SSH session {}", session_id);
+        Ok(true)
+    }
+
+    // Returns false when there was no channel to close
+    pub fn close_sftp(&self, session_id: &str) -> Result<bool> {
+        let sftp = {
+            let mut connections = self.connections.lock().unwrap();
+            connections.get_mut(session_id)
+                .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?
+                .sftp
+                .take()
+        };
+
+        let was_open = sftp.is_some();
+        drop(sftp);
+        if was_open {
+            info!("Closed SFTP channel on SSH session {}", session_id);
+        }
+        Ok(was_open)
+    }
+
+    // The shell's working directory, if it reports it
+    pub fn shell_cwd(&self, session_id: &str) -> Option<String> {
+        self.connections.lock().unwrap().get(session_id)?.cwd.cwd()
+    }
+
+    // Like `ssh -L`: connections to bind_address:local_port are carried to
+    // remote_host:remote_port from the server's side. Port 0 picks a free one.
+    pub fn start_local_forward(
+        &self,
+        session_id: &str,
+        bind_address: &str,
+        local_port: u16,
+        remote_host: &str,
+        remote_port: u16,
+    ) -> Result<ForwardInfo> {
+        if remote_host.trim().is_empty() || remote_port == 0 {
+            return Err(SshError::Other("A local forward needs a remote host and port".to_string()));
+        }
+        let mut connections = self.connections.lock().unwrap();
+        let connection = connections.get_mut(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        connection.add_forward(ForwardSpec {
+            kind: ForwardKind::Local,
+            bind_address: bind_address.to_string(),
+            bind_port: local_port,
+            target_host: Some(remote_host.trim().to_string()),
+            target_port: Some(remote_port),
+        })
+    }
+
+    // Like `ssh -D`: a SOCKS5 proxy on bind_address:port whose connections
+    // leave from the server
+    pub fn start_dynamic_forward(&self, session_id: &str, bind_address: &str, port: u16) -> Result<ForwardInfo> {
+        let mut connections = self.connections.lock().unwrap();
+        let connection = connections.get_mut(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        connection.add_forward(ForwardSpec {
+            kind: ForwardKind::Dynamic,
+            bind_address: bind_address.to_string(),
+            bind_port: port,
+            target_host: None,
+            target_port: None,
+        })
+    }
+
+    pub fn stop_forward(&self, session_id: &str, forward_id: &str) -> Result<ForwardInfo> {
+        let mut connections = self.connections.lock().unwrap();
+        let connection = connections.get_mut(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        let output = connection.stop_forward(forward_id)?;
+        output.forward.ok_or_else(|| SshError::Other(format!("Forward not found: {}", forward_id)))
+    }
+
+    pub fn list_channels(&self, session_id: &str) -> Result<Vec<SessionChannel>> {
+        let connections = self.connections.lock().unwrap();
+        let connection = connections.get(session_id)
+            .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+        Ok(connection.channels())
+    }
+
+    // Closes the SFTP channel or a forward, leaving the rest of the connection
+    // alone. The shell is the session itself and goes with a disconnect.
+    pub fn close_channel(&self, session_id: &str, channel_id: &str) -> Result<()> {
+        match channel_id {
+            SHELL_CHANNEL_ID => Err(SshError::Other("The shell can only be closed by disconnecting the session".to_string())),
+            SFTP_CHANNEL_ID => match self.close_sftp(session_id)? {
+                true => Ok(()),
+                false => Err(SshError::Other("The SFTP channel is not open".to_string())),
+            },
+            id => {
+                let mut connections = self.connections.lock().unwrap();
+                let connection = connections.get_mut(session_id)
+                    .ok_or_else(|| SshError::SessionNotFound(session_id.to_string()))?;
+                let output = connection.stop_forward(id)?;
+                info!("{} on SSH session {}", output.message, session_id);
+                Ok(())
+            }
+        }
+    }
+
+    // Removes connections whose reader thread has exited, and those silent for
+    // `dead_after` that no longer answer a probe. The caller tells the UI.
+    pub fn reap_dead_sessions(&self, dead_after: Duration) -> Vec<ReapedSession> {
+        let mut dead = Vec::new();
+        let mut quiet = Vec::new();
+        {
+            let connections = self.connections.lock().unwrap();
+            for (session_id, connection) in connections.iter() {
+                let reader_alive = connection.reader_handle.as_ref().is_some_and(|handle| !handle.is_finished());
+                let silent_for = connection.stats.last_output_secs_ago()
+                    .map(Duration::from_secs)
+                    .unwrap_or_else(|| connection.started.elapsed());
+
+                if !reader_alive {
+                    dead.push((session_id.clone(), "The session's reader stopped".to_string(), false));
+                } else if silent_for >= dead_after {
+                    quiet.push((session_id.clone(), connection.session.clone(), silent_for));
+                }
+            }
+        }
+
+        // Silence alone is normal for an idle shell, so only reap when the
+        // server doesn't answer either. Probing happens with the map unlocked.
+        for (session_id, session, silent_for) in quiet {
+            if let Err(e) = probe_session(&session) {
+                let reason = format!("No output for {} minutes and the server stopped answering: {}", silent_for.as_secs() / 60, e);
+                dead.push((session_id, reason, true));
+            }
+        }
+
+        self.remove_reaped(dead)
+    }
+
+    // After the computer wakes from sleep every connection is probed, since
+    // how long it has been silent says nothing then. The probes run side by
+    // side so a dozen dead sessions don't take a dozen probe timeouts.
+    pub fn reap_after_resume(&self) -> Vec<ReapedSession> {
+        let sessions: Vec<(String, Session, bool)> = {
+            let connections = self.connections.lock().unwrap();
+            connections.iter()
+                .map(|(session_id, connection)| {
+                    let reader_alive = connection.reader_handle.as_ref().is_some_and(|handle| !handle.is_finished());
+                    (session_id.clone(), connection.session.clone(), reader_alive)
+                })
+                .collect()
+        };
+
+        let dead = thread::scope(|scope| {
+            let probes: Vec<_> = sessions
+                .into_iter()
+                .map(|(session_id, session, reader_alive)| {
+                    scope.spawn(move || {
+                        if !reader_alive {
+                            return Some((session_id, "The session's reader stopped".to_string(), false));
+                        }
+                        probe_session(&session).err().map(|e| {
+                            (session_id, format!("The server stopped answering after the computer woke up: {}", e), true)
+                        })
+                    })
+                })
+                .collect();
+            probes.into_iter().filter_map(|probe| probe.join().ok().flatten()).collect()
+        });
+
+        self.remove_reaped(dead)
+    }
+
+    // Closes and removes the connections found dead: (session id, reason,
+    // whether the server stopped answering)
+    fn remove_reaped(&self, dead: Vec<(String, String, bool)>) -> Vec<ReapedSession> {
+        let mut reaped = Vec::new();
+        for (session_id, reason, unresponsive) in dead {
+            let connection = self.connections.lock().unwrap().remove(&session_id);
+            if let Some(mut connection) = connection {
+                connection.close();
+                warn!("Reaped dead SSH session {}: {}", session_id, reason);
+                reaped.push(ReapedSession {
+                    pty_size: *connection.pty_size.lock().unwrap(),
+                    config: connection.config.clone(),
+                    session_id,
+                    reason,
+                    unresponsive,
+                });
+            }
+        }
+        reaped
+    }
+
+    // What a reconnect needs from a session that has stopped working: its
+    // config, terminal size and the libssh2 session to probe
+    pub(crate) fn reconnect_info(&self, session_id: &str) -> Option<(SshConfig, (u32, u32), Session)> {
+        let connections = self.connections.lock().unwrap();
+        let connection = connections.get(session_id)?;
+        let pty_size = *connection.pty_size.lock().unwrap();
+        Some((connection.config.clone(), pty_size, connection.session.clone()))
+    }
+
+    // Replaces whatever is left of the session with a fresh connection
+    pub(crate) fn reconnect(&self, session_id: &str, config: SshConfig, pty_size: (u32, u32), app_handle: AppHandle) -> Result<()> {
+        let old = self.connections.lock().unwrap().remove(session_id);
+        if let Some(mut old) = old {
+            old.close();
+        }
+        self.connect_with_size(session_id.to_string(), config, pty_size, app_handle)
+    }
+
+    pub fn list_active_connections(&self) -> Vec<ActiveConnectionInfo> {
+        let connections = self.connections.lock().unwrap();
+        connections.values().map(SshConnection::info).collect()
+    }
 }
 
 // Tauri commands
@@ -408,10 +2168,8 @@ pub async fn ssh_connect(
     config: SshConfig,
     app_handle: AppHandle,
     state: tauri::State<'_, Arc<SshManager>>,
-) -> Result<(), String> {
-    state
-        .connect(session_id, config, app_handle)
-        .map_err(|e| format!("Connection failed: {}", e))
+) -> Result<()> {
+    state.connect(session_id, config, app_handle)
 }
 
 #[tauri::command]
@@ -421,13 +2179,11 @@ pub async fn ssh_connect_with_password(
     password: String,
     app_handle: AppHandle,
     state: tauri::State<'_, Arc<SshManager>>,
-) -> Result<(), String> {
+) -> Result<()> {
     // Update config with password
     config.auth_method = AuthMethod::Password { password };
     
-    state
-        .connect(session_id, config, app_handle)
-        .map_err(|e| format!("Connection failed: {}", e))
+    state.connect(session_id, config, app_handle)
 }
 
 #[tauri::command]
@@ -435,10 +2191,16 @@ pub async fn ssh_send_input(
     session_id: String,
     input: String,
     state: tauri::State<'_, Arc<SshManager>>,
-) -> Result<(), String> {
-    state
-        .send_input(&session_id, &input)
-        .map_err(|e| format!("Send input failed: {}", e))
+    local: tauri::State<'_, LocalTerminalManager>,
+    recorder: tauri::State<'_, MacroRecorder>,
+) -> Result<()> {
+    recorder.record(&session_id, &input);
+
+    // Sessions running through the system ssh client live in a local pty
+    if local.contains(&session_id) {
+        return local.write(&session_id, &input);
+    }
+    state.send_input(&session_id, &input)
 }
 
 #[tauri::command]
@@ -447,20 +2209,164 @@ pub async fn ssh_resize_terminal(
     cols: u32,
     rows: u32,
     state: tauri::State<'_, Arc<SshManager>>,
-) -> Result<(), String> {
-    state
-        .resize_terminal(&session_id, cols, rows)
-        .map_err(|e| format!("Resize failed: {}", e))
+    local: tauri::State<'_, LocalTerminalManager>,
+) -> Result<()> {
+    if local.contains(&session_id) {
+        return local.resize(&session_id, cols, rows);
+    }
+    state.resize_terminal(&session_id, cols, rows)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WidenedTerminal {
+    pub cols: u32,
+    // The client size the terminal goes back to
+    pub original_cols: u32,
+    pub rows: u32,
+}
+
+// Columns used when widen_terminal isn't given a width
+const DEFAULT_WIDE_COLS: u32 = 200;
+const MAX_WIDE_COLS: u32 = 1000;
+
+// Widens the remote PTY for wide output, optionally typing `command` into the
+// shell right after. The UI calls restore_terminal_width when done (or just
+// resizes, which also ends it). If the command can't be sent, the width is
+// restored straight away.
+#[tauri::command]
+pub async fn widen_terminal(
+    session_id: String,
+    cols: Option<u32>,
+    command: Option<String>,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<WidenedTerminal> {
+    let cols = cols.unwrap_or(DEFAULT_WIDE_COLS).clamp(1, MAX_WIDE_COLS);
+    let (original_cols, rows) = state.widen_terminal(&session_id, cols)?;
+
+    if let Some(command) = command.filter(|command| !command.trim().is_empty()) {
+        if let Err(e) = state.send_input(&session_id, &format!("{}\r", command.trim_end())) {
+            if let Err(restore_error) = state.restore_terminal_width(&session_id) {
+                warn!("Failed to restore the terminal width of {}: {}", session_id, restore_error);
+            }
+            return Err(e);
+        }
+    }
+
+    Ok(WidenedTerminal { cols, original_cols, rows })
+}
+
+// Undoes widen_terminal; returns the restored (cols, rows), or None if the
+// terminal wasn't widened
+#[tauri::command]
+pub async fn restore_terminal_width(
+    session_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<Option<(u32, u32)>> {
+    state.restore_terminal_width(&session_id)
 }
 
 #[tauri::command]
 pub async fn ssh_disconnect(
     session_id: String,
+    app_handle: AppHandle,
+    state: tauri::State<'_, Arc<SshManager>>,
+    local: tauri::State<'_, LocalTerminalManager>,
+) -> Result<()> {
+    if local.contains(&session_id) {
+        return local.close(&session_id);
+    }
+    app_handle.state::<reconnect::Reconnector>().cancel(&session_id);
+    state.disconnect(&session_id)?;
+
+    // Remove any local copies of remote files opened during this session
+    app_handle.state::<crate::AppState>().temp_files.cleanup_session(&session_id);
+    app_handle.state::<crate::metrics::MetricsMonitors>().cancel(&session_id);
+    Ok(())
+}
+
+// How long a graceful log out waits for the shell to exit by default, and at most
+const DEFAULT_LOGOUT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_LOGOUT_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LogoutOutcome {
+    // The shell exited on its own
+    Exited { exit_status: i32 },
+    // It was still running at the timeout (a stopped job, a program that
+    // ignores EOF) and the connection was closed anyway
+    ForcedDisconnect,
+}
+
+#[derive(Clone, Serialize)]
+struct LogoutEvent {
+    session_id: String,
+    #[serde(flatten)]
+    outcome: LogoutOutcome,
+}
+
+// Answers a `sudo_prompt` event. Without a password the session's cached one
+// is used, if the session has sudo_autofill on. Neither is logged.
+#[tauri::command]
+pub async fn answer_sudo_prompt(
+    session_id: String,
+    password: Option<String>,
     state: tauri::State<'_, Arc<SshManager>>,
-) -> Result<(), String> {
-    state
-        .disconnect(&session_id)
-        .map_err(|e| format!("Disconnect failed: {}", e))
+) -> Result<()> {
+    state.answer_sudo_prompt(&session_id, password)
+}
+
+// Graceful counterpart to disconnect: logs out by sending EOF (Ctrl-D) to the
+// shell and only closes the connection once it has exited, or after
+// `timeout_ms` if it doesn't. Emits `session_logged_out` with the outcome.
+#[tauri::command]
+pub async fn send_eof(
+    session_id: String,
+    timeout_ms: Option<u64>,
+    app_handle: AppHandle,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<LogoutOutcome> {
+    let timeout = timeout_ms.map(Duration::from_millis).unwrap_or(DEFAULT_LOGOUT_TIMEOUT).min(MAX_LOGOUT_TIMEOUT);
+    let manager = state.inner().clone();
+    let handle = app_handle.clone();
+    let id = session_id.clone();
+    let exit_status = tauri::async_runtime::spawn_blocking(move || manager.send_eof(&id, timeout, &handle))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))??;
+
+    let outcome = match exit_status {
+        Some(exit_status) => {
+            info!("Shell of {} logged out with status {}", session_id, exit_status);
+            LogoutOutcome::Exited { exit_status }
+        }
+        None => {
+            warn!("Shell of {} didn't exit within {:?}, disconnecting", session_id, timeout);
+            LogoutOutcome::ForcedDisconnect
+        }
+    };
+
+    // Tidies up the same way a disconnect does. The sweeper may have got to a
+    // connection whose shell exited first, which leaves nothing to close.
+    if let Err(e) = crate::close_session(&app_handle.state::<crate::AppState>(), &app_handle, &session_id) {
+        debug!("Closing {} after logout: {}", session_id, e);
+    }
+
+    if let Err(e) = app_handle.emit("session_logged_out", &LogoutEvent {
+        session_id: session_id.clone(),
+        outcome: outcome.clone(),
+    }) {
+        error!("Failed to emit logout event: {}", e);
+    }
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub async fn restart_session(
+    session_id: String,
+    app_handle: AppHandle,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<()> {
+    state.restart(&session_id, app_handle)
 }
 
 #[tauri::command]
@@ -469,3 +2375,282 @@ pub async fn ssh_list_sessions(
 ) -> Result<Vec<String>, String> {
     Ok(state.list_sessions())
 }
+
+// Scroll lock: stops terminal-data events for the session without sending
+// anything (like XOFF) to the remote. How output is held back follows the
+// output_pause_mode setting.
+#[tauri::command]
+pub async fn pause_output(
+    session_id: String,
+    app_handle: AppHandle,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<bool> {
+    let (mode, buffer_kb) = {
+        let app_state = app_handle.state::<crate::AppState>();
+        let settings = app_state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?;
+        (settings.output_pause_mode, settings.output_pause_buffer_kb)
+    };
+    state.pause_output(&session_id, mode, buffer_kb * 1024)
+}
+
+// Emits everything held back since pause_output, then carries on live.
+// Returns the number of bytes flushed.
+#[tauri::command]
+pub async fn resume_output(
+    session_id: String,
+    app_handle: AppHandle,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<usize> {
+    state.resume_output(&session_id, &app_handle)
+}
+
+#[tauri::command]
+pub async fn session_command(
+    session_id: String,
+    cmd: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<SessionCommandOutput> {
+    state.session_command(&session_id, &cmd)
+}
+
+#[tauri::command]
+pub async fn probe_channel(
+    session_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<ChannelProbe> {
+    let manager = state.inner().clone();
+
+    // The probe can wait for several seconds, keep it off the async runtime
+    tokio::task::spawn_blocking(move || manager.probe_channel(&session_id))
+        .await
+        .map_err(|e| SshError::Other(format!("Probe failed: {}", e)))?
+}
+
+#[tauri::command]
+pub async fn list_active_connections(
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<Vec<ActiveConnectionInfo>, String> {
+    Ok(state.list_active_connections())
+}
+
+// For "5 tabs, 5 connections to prod-db". `port` narrows it to one port of
+// the host.
+#[tauri::command]
+pub async fn get_multiplexing_info(
+    host: String,
+    port: Option<u16>,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<MultiplexingInfo, String> {
+    let mut connections: Vec<ActiveConnectionInfo> = state
+        .list_active_connections()
+        .into_iter()
+        .filter(|info| info.host.eq_ignore_ascii_case(&host) && port.is_none_or(|port| info.port == port))
+        .collect();
+    connections.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+
+    Ok(MultiplexingInfo {
+        host,
+        tabs: connections.len(),
+        transports: connections.len(),
+        shared: false,
+        total_channels: connections.iter().map(|info| info.open_channels).sum(),
+        healthy: connections.iter().filter(|info| info.status == "connected").count(),
+        session_ids: connections.into_iter().map(|info| info.session_id).collect(),
+    })
+}
+
+// Starts emitting `latency` events for the session every `interval_ms`
+// (two seconds by default)
+#[tauri::command]
+pub async fn start_latency_monitor(
+    session_id: String,
+    interval_ms: Option<u64>,
+    app_handle: AppHandle,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<()> {
+    let interval = interval_ms.map(Duration::from_millis).unwrap_or(DEFAULT_LATENCY_INTERVAL);
+    state.start_latency_monitor(&session_id, interval, app_handle)
+}
+
+#[tauri::command]
+pub async fn stop_latency_monitor(
+    session_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<()> {
+    let manager = state.inner().clone();
+    tokio::task::spawn_blocking(move || manager.stop_latency_monitor(&session_id))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+#[tauri::command]
+pub async fn get_latency_history(
+    session_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<Vec<LatencySample>> {
+    state.latency_history(&session_id)
+}
+
+// Opens the SFTP subsystem on the already connected session, leaving the
+// shell alone. Does nothing if it's already open.
+#[tauri::command]
+pub async fn open_sftp(
+    session_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<bool> {
+    let manager = state.inner().clone();
+    tokio::task::spawn_blocking(move || manager.open_sftp(&session_id))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+// The connection's shell, SFTP channel and forwards, for a "3 forwards
+// open" view and for closing one of them with close_channel
+#[tauri::command]
+pub async fn list_session_channels(
+    session_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<Vec<SessionChannel>> {
+    state.list_channels(&session_id)
+}
+
+// Stopping a forward waits for its thread, so this runs off the async runtime
+#[tauri::command]
+pub async fn close_channel(
+    session_id: String,
+    channel_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<()> {
+    let manager = state.inner().clone();
+    tokio::task::spawn_blocking(move || manager.close_channel(&session_id, &channel_id))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+// Frees the SFTP channel once file browsing is done; the terminal stays
+// connected and open_sftp can bring the channel back later
+#[tauri::command]
+pub async fn close_sftp(
+    session_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<bool> {
+    let manager = state.inner().clone();
+    tokio::task::spawn_blocking(move || manager.close_sftp(&session_id))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+// Forwards a local port through the session, bound to localhost unless
+// `bind_address` says otherwise. The returned info's id stops it with
+// stop_local_forward; disconnecting the session stops it too.
+#[tauri::command]
+pub async fn start_local_forward(
+    session_id: String,
+    local_port: u16,
+    remote_host: String,
+    remote_port: u16,
+    bind_address: Option<String>,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<ForwardInfo> {
+    let bind_address = bind_address.unwrap_or_else(|| "127.0.0.1".to_string());
+    state.start_local_forward(&session_id, &bind_address, local_port, &remote_host, remote_port)
+}
+
+// Stopping a forward waits for its thread, so this runs off the async runtime
+#[tauri::command]
+pub async fn stop_local_forward(
+    session_id: String,
+    forward_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<ForwardInfo> {
+    let manager = state.inner().clone();
+    tokio::task::spawn_blocking(move || manager.stop_forward(&session_id, &forward_id))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+// Starts a SOCKS5 proxy through the session, on localhost:1080 unless told
+// otherwise. A failure to start, like the port being taken, is also sent as
+// a `forward_error` event.
+#[tauri::command]
+pub async fn start_socks_proxy(
+    session_id: String,
+    bind_port: Option<u16>,
+    bind_address: Option<String>,
+    app_handle: AppHandle,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<ForwardInfo> {
+    let bind_address = bind_address.unwrap_or_else(|| "127.0.0.1".to_string());
+    let bind_port = bind_port.unwrap_or(1080);
+    state.start_dynamic_forward(&session_id, &bind_address, bind_port).inspect_err(|e| {
+        let _ = app_handle.emit("forward_error", &ForwardErrorEvent {
+            session_id: session_id.clone(),
+            kind: ForwardKind::Dynamic,
+            bind_address: bind_address.clone(),
+            bind_port,
+            message: e.to_string(),
+        });
+    })
+}
+
+#[tauri::command]
+pub async fn stop_socks_proxy(
+    session_id: String,
+    forward_id: String,
+    state: tauri::State<'_, Arc<SshManager>>,
+) -> Result<ForwardInfo> {
+    let manager = state.inner().clone();
+    tokio::task::spawn_blocking(move || manager.stop_forward(&session_id, &forward_id))
+        .await
+        .map_err(|e| SshError::Other(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_end_prefers_the_last_newline() {
+        assert_eq!(chunk_end(b"one\ntwo\nthree", 10), 8);
+        assert_eq!(chunk_end(b"abcdefgh", 4), 4);
+    }
+
+    #[test]
+    fn chunk_end_keeps_multibyte_characters_whole() {
+        // "é" is two bytes and "€" three, both straddling the limit
+        let data = "aé".as_bytes();
+        assert_eq!(chunk_end(data, 2), 1);
+        let data = "ab€cd".as_bytes();
+        for limit in 3..5 {
+            assert_eq!(chunk_end(data, limit), 2, "limit {} cut the character", limit);
+        }
+        assert_eq!(chunk_end(data, 5), 5);
+    }
+
+    #[test]
+    fn chunk_end_keeps_escapes_whole() {
+        for (data, limit, end) in [
+            (&b"ab\x1b[31mcd"[..], 6, 2),
+            (b"ab\x1b[31mcd", 4, 2),
+            (b"ab\x1b[31mcd", 7, 7),
+            (b"ab\x1b]0;title\x07cd", 10, 2),
+            (b"ab\x1b]0;title\x1b\\cd", 12, 2),
+            (b"ab\x1b]0;title\x1b\\cd", 13, 13),
+            (b"ab\x1b(Bcd", 4, 2),
+        ] {
+            assert_eq!(chunk_end(data, limit), end, "{:?} at {}", data, limit);
+        }
+    }
+
+    #[test]
+    fn chunk_end_takes_a_whole_first_character_or_escape_past_the_limit() {
+        assert_eq!(chunk_end("€abc".as_bytes(), 1), 3);
+        assert_eq!(chunk_end("€abc".as_bytes(), 2), 3);
+        assert_eq!(chunk_end(b"\x1b[38;5;196mab", 4), 11);
+        assert_eq!(chunk_end(b"\x1b]0;a long title\x07ab", 4), 17);
+        // One that never ends goes out as it is
+        assert_eq!(chunk_end(b"\x1b]0;unterminated", 4), 16);
+        // Stray continuation bytes don't run on past a character's length
+        assert_eq!(chunk_end(b"\x80\x80\x80\x80\x80\x80", 2), 4);
+    }
+}
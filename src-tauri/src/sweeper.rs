@@ -0,0 +1,125 @@
+use log::{error, info};
+use serde::Serialize;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::metrics::MetricsMonitors;
+use crate::reconnect::{self, ReconnectTrigger, RecentDrops};
+use crate::session_logs::SessionLogger;
+use crate::ssh_new::{emit_connection_status, ReapedSession, SshManager};
+use crate::{AppState, ConnectionStatus};
+
+// How often a disabled sweeper looks at the settings again
+const DISABLED_RECHECK: Duration = Duration::from_secs(30);
+// The resume watcher wakes up this often; when the wall clock has moved on
+// by much more than that in between, the computer was asleep
+const RESUME_POLL: Duration = Duration::from_secs(5);
+const RESUME_MIN_GAP: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Serialize)]
+struct SystemResumedEvent {
+    slept_secs: u64,
+    // Sessions found dead and closed; those whose policy allows it are being
+    // reconnected
+    reaped: Vec<String>,
+}
+
+// Tells the UI about each reaped session and starts reconnecting the ones
+// whose server stopped answering, each on its own thread since a reconnect
+// waits out its backoff
+fn handle_reaped(app_handle: &AppHandle, reaped: Vec<ReapedSession>, trigger: ReconnectTrigger) {
+    for reaped in reaped {
+        let session_id = reaped.session_id;
+        let state = app_handle.state::<AppState>();
+        state.temp_files.cleanup_session(&session_id);
+        app_handle.state::<MetricsMonitors>().cancel(&session_id);
+        if let Ok(mut connections) = state.active_connections.lock() {
+            connections.insert(session_id.clone(), ConnectionStatus::Disconnected);
+        }
+        if let Some(logger) = app_handle.try_state::<SessionLogger>() {
+            logger.stop(&session_id);
+        }
+        emit_connection_status(app_handle, &session_id, "disconnected", &reaped.reason);
+
+        if reaped.unresponsive {
+            app_handle.state::<RecentDrops>().record(
+                &session_id,
+                trigger,
+                reaped.reason,
+                None,
+                &reaped.config,
+                reaped.pty_size,
+            );
+            let app_handle = app_handle.clone();
+            let spawned = thread::Builder::new().name(format!("reconnect-{}", session_id)).spawn(move || {
+                reconnect::start(&app_handle, &session_id, trigger, reaped.config, reaped.pty_size);
+            });
+            if let Err(e) = spawned {
+                error!("Failed to start a reconnect thread: {}", e);
+            }
+        }
+    }
+}
+
+// Background thread that reaps connections left behind when a session ended
+// without disconnect being called (a reader thread that hit an error, a
+// server that vanished without closing the TCP connection). Each reaped
+// session gets a `connection_status` "disconnected" event. Settings are
+// re-read every round, so changes apply without a restart.
+pub fn start(app_handle: AppHandle, manager: Arc<SshManager>) {
+    let spawned = thread::Builder::new().name("session-sweeper".to_string()).spawn(move || loop {
+        let (interval, dead_after) = {
+            let state = app_handle.state::<AppState>();
+            let settings = state.settings.lock().unwrap();
+            (settings.session_sweep_interval_secs, settings.dead_session_after_secs)
+        };
+
+        if interval == 0 {
+            thread::sleep(DISABLED_RECHECK);
+            continue;
+        }
+        thread::sleep(Duration::from_secs(interval));
+
+        let reaped = manager.reap_dead_sessions(Duration::from_secs(dead_after));
+        handle_reaped(&app_handle, reaped, ReconnectTrigger::KeepaliveFailure);
+    });
+
+    if let Err(e) = spawned {
+        error!("Failed to start the session sweeper: {}", e);
+    }
+}
+
+// Background thread that notices the computer waking from sleep, when every
+// connection has usually died without either side knowing. It checks them
+// all right away instead of waiting for the sweeper or the next keystroke,
+// then emits `system_resumed`.
+pub fn watch_for_resume(app_handle: AppHandle, manager: Arc<SshManager>) {
+    let spawned = thread::Builder::new().name("resume-watcher".to_string()).spawn(move || {
+        let mut last_tick = SystemTime::now();
+        loop {
+            thread::sleep(RESUME_POLL);
+            let now = SystemTime::now();
+            // A clock set backwards isn't a resume
+            let gap = now.duration_since(last_tick).unwrap_or_default();
+            last_tick = now;
+            if gap < RESUME_POLL + RESUME_MIN_GAP {
+                continue;
+            }
+
+            info!("The computer was asleep for about {}s, checking sessions", gap.as_secs());
+            let reaped = manager.reap_after_resume();
+            let event = SystemResumedEvent {
+                slept_secs: gap.saturating_sub(RESUME_POLL).as_secs(),
+                reaped: reaped.iter().map(|reaped| reaped.session_id.clone()).collect(),
+            };
+            let _ = app_handle.emit("system_resumed", &event);
+            handle_reaped(&app_handle, reaped, ReconnectTrigger::Resume);
+        }
+    });
+
+    if let Err(e) = spawned {
+        error!("Failed to start the resume watcher: {}", e);
+    }
+}
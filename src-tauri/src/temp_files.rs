@@ -0,0 +1,168 @@
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::AppState;
+
+// Mirrors AppSettings::wipe_temp_files so cleanup doesn't need the app state
+static WIPE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_wipe(enabled: bool) {
+    WIPE.store(enabled, Ordering::Relaxed);
+}
+
+// A local copy of a remote file, optionally watched for changes
+struct TrackedTempFile {
+    path: PathBuf,
+    watcher_shutdown: Option<Arc<AtomicBool>>,
+    // The watcher keeps a password around to re-upload with
+    holds_secret: bool,
+}
+
+// Keeps track of the temp files created for each session so they can be
+// removed when the session is closed
+pub struct TempFileRegistry {
+    files: Mutex<HashMap<String, Vec<TrackedTempFile>>>,
+}
+
+impl TempFileRegistry {
+    pub fn new() -> Self {
+        TempFileRegistry {
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Creates a unique directory for a single temp file so that files with the
+    // same name from different hosts never clobber each other
+    pub fn allocate_path(file_name: &str) -> Result<PathBuf, String> {
+        let dir = std::env::temp_dir()
+            .join("termnest")
+            .join(uuid::Uuid::new_v4().to_string());
+
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+
+        Ok(dir.join(file_name))
+    }
+
+    pub fn register(
+        &self,
+        session_id: &str,
+        path: PathBuf,
+        watcher_shutdown: Option<Arc<AtomicBool>>,
+        holds_secret: bool,
+    ) {
+        let mut files = self.files.lock().unwrap();
+        files
+            .entry(session_id.to_string())
+            .or_default()
+            .push(TrackedTempFile { path, watcher_shutdown, holds_secret });
+    }
+
+    // Stops the watchers that hold a password; they wipe it on exit. The temp
+    // files stay until the session closes, they just stop syncing back.
+    pub fn stop_secret_watchers(&self) -> usize {
+        let mut files = self.files.lock().unwrap();
+        let mut stopped = 0;
+
+        for file in files.values_mut().flatten().filter(|file| file.holds_secret) {
+            if let Some(shutdown) = &file.watcher_shutdown {
+                shutdown.store(true, Ordering::Relaxed);
+                stopped += 1;
+            }
+            file.holds_secret = false;
+        }
+        stopped
+    }
+
+    pub fn cleanup_session(&self, session_id: &str) {
+        let tracked = {
+            let mut files = self.files.lock().unwrap();
+            files.remove(session_id).unwrap_or_default()
+        };
+        tracked.into_iter().for_each(discard);
+    }
+
+    // Every session's files, for when the app exits
+    pub fn cleanup_all(&self) {
+        let tracked: Vec<TrackedTempFile> = {
+            let mut files = self.files.lock().unwrap();
+            files.drain().flat_map(|(_, tracked)| tracked).collect()
+        };
+        if !tracked.is_empty() {
+            info!("Removing {} temp files", tracked.len());
+        }
+        tracked.into_iter().for_each(discard);
+    }
+
+    // Removes one tracked file, returning false when it isn't one of ours
+    pub fn remove(&self, path: &Path) -> bool {
+        let file = {
+            let mut files = self.files.lock().unwrap();
+            files.values_mut().find_map(|tracked| {
+                let index = tracked.iter().position(|file| file.path == path)?;
+                Some(tracked.remove(index))
+            })
+        };
+        file.map(discard).is_some()
+    }
+}
+
+fn discard(file: TrackedTempFile) {
+    if let Some(shutdown) = &file.watcher_shutdown {
+        shutdown.store(true, Ordering::Relaxed);
+    }
+    remove_temp_file(&file.path);
+}
+
+// Zeroes the file in place so its contents aren't left in free disk space.
+// SSDs and copy-on-write filesystems may still keep the old blocks around;
+// this only rules out simply undeleting the file.
+fn overwrite(path: &Path) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; 64 * 1024];
+    let mut remaining = file.metadata()?.len();
+    while remaining > 0 {
+        let n = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()
+}
+
+fn remove_temp_file(path: &Path) {
+    if WIPE.load(Ordering::Relaxed) {
+        match overwrite(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to overwrite temp file {}: {}", path.display(), e),
+        }
+    }
+
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove temp file {}: {}", path.display(), e);
+        }
+    }
+
+    // The parent is the per-file directory created by allocate_path
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::remove_dir(parent);
+    }
+}
+
+// Deletes a temp file the app downloaded (see open_with), overwriting it first
+// when wipe_temp_files is on, instead of leaving it until the session closes.
+// Only files the app is tracking can be removed this way.
+#[tauri::command]
+pub async fn secure_delete_temp(state: State<'_, AppState>, path: String) -> Result<(), String> {
+    if !state.temp_files.remove(Path::new(&path)) {
+        return Err(format!("{} is not a temp file of this app", path));
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use tauri_plugin_store::StoreExt;
+use uuid::Uuid;
+
+use crate::{save_sessions_to_store, AppState, AuthMethod, Protocol, Session};
+
+// A reusable blueprint holding everything about a session except its name and host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTemplate {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub port: u16,
+    pub username: String,
+    pub auth_method: AuthMethod,
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+pub(crate) fn load_templates(app: &AppHandle) -> Result<Vec<SessionTemplate>, String> {
+    let store = app.store("templates.json").map_err(|e| e.to_string())?;
+
+    match store.get("templates") {
+        Some(value) => serde_json::from_value(value).map_err(|e| e.to_string()),
+        None => Ok(vec![]),
+    }
+}
+
+pub(crate) fn save_templates(app: &AppHandle, templates: &[SessionTemplate]) -> Result<(), String> {
+    let store = app.store("templates.json").map_err(|e| e.to_string())?;
+    store.set("templates", serde_json::to_value(templates).map_err(|e| e.to_string())?);
+    store.save().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_templates(app: AppHandle) -> Result<Vec<SessionTemplate>, String> {
+    load_templates(&app)
+}
+
+// Creates the template when its id is empty or unknown, otherwise replaces it
+#[tauri::command]
+pub async fn save_template(app: AppHandle, mut template: SessionTemplate) -> Result<SessionTemplate, String> {
+    let mut templates = load_templates(&app)?;
+
+    if template.id.is_empty() {
+        template.id = Uuid::new_v4().to_string();
+    }
+
+    match templates.iter_mut().find(|t| t.id == template.id) {
+        Some(existing) => *existing = template.clone(),
+        None => templates.push(template.clone()),
+    }
+
+    save_templates(&app, &templates)?;
+    Ok(template)
+}
+
+#[tauri::command]
+pub async fn delete_template(app: AppHandle, template_id: String) -> Result<(), String> {
+    let mut templates = load_templates(&app)?;
+    templates.retain(|t| t.id != template_id);
+    save_templates(&app, &templates)
+}
+
+#[tauri::command]
+pub async fn create_session_from_template(
+    state: State<'_, AppState>,
+    app: AppHandle,
+    template_id: String,
+    name: String,
+    host: String,
+) -> Result<Session, String> {
+    let template = load_templates(&app)?
+        .into_iter()
+        .find(|t| t.id == template_id)
+        .ok_or_else(|| "Template not found".to_string())?;
+
+    let session = Session {
+        id: Uuid::new_v4().to_string(),
+        name,
+        host,
+        port: template.port,
+        username: template.username,
+        auth_method: template.auth_method,
+        protocol: template.protocol,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        last_used: None,
+        group: template.group,
+        tags: template.tags,
+        color: template.color,
+        store_password_in_keychain: false,
+        profiles: Vec::new(),
+        http_proxy: None,
+        env_file: None,
+        allow_loopback: false,
+        terminal_modes: Default::default(),
+        reconnect: None,
+        strict_identities: false,
+        bind_address: None,
+        keymap: Default::default(),
+        host_key_trust: None,
+        sudo_autofill: false,
+        proxy_jump: None,
+        keepalive_interval_secs: None,
+        ssh_options: Vec::new(),
+        connect_timeout_secs: None,
+    };
+
+    {
+        let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+        sessions.insert(session.id.clone(), session.clone());
+    }
+
+    save_sessions_to_store(app, state).await?;
+    Ok(session)
+}
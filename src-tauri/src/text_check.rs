@@ -0,0 +1,205 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::SshError;
+use crate::sftp::SftpClient;
+use crate::AppState;
+
+// The file is read whole, and config files and scripts are far smaller
+const MAX_CHECK_FILE_SIZE: u64 = 2 * 1024 * 1024;
+// Line numbers listed per finding; the counts cover the whole file
+const MAX_LISTED_LINES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    // Not valid UTF-8 and no sign of UTF-16: most likely a legacy 8-bit
+    // encoding, of which Latin-1 is the usual one
+    Latin1,
+    Binary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEndings {
+    // A single line without an ending
+    None,
+    Lf,
+    Crlf,
+    // Old Mac style, a lone CR
+    Cr,
+    Mixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Indentation {
+    None,
+    Tabs,
+    Spaces,
+    // Some lines indented with tabs and others with spaces, or both on one line
+    Mixed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextFileCheck {
+    pub size: u64,
+    pub encoding: TextEncoding,
+    pub bom: bool,
+    pub line_endings: LineEndings,
+    pub lf_count: usize,
+    pub crlf_count: usize,
+    pub cr_count: usize,
+    pub line_count: usize,
+    pub final_newline: bool,
+    // 1-based line numbers, at most MAX_LISTED_LINES of them
+    pub trailing_whitespace_lines: Vec<usize>,
+    pub trailing_whitespace_count: usize,
+    pub indentation: Indentation,
+    pub tab_indented_lines: usize,
+    pub space_indented_lines: usize,
+    pub mixed_indent_lines: Vec<usize>,
+}
+
+// The encoding, whether it has a BOM, and the text without the BOM
+fn decode(data: &[u8]) -> (TextEncoding, bool, Option<String>) {
+    let utf16 = |data: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = data.chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    if let Some(rest) = data.strip_prefix(b"\xef\xbb\xbf") {
+        return (TextEncoding::Utf8, true, Some(String::from_utf8_lossy(rest).into_owned()));
+    }
+    if let Some(rest) = data.strip_prefix(b"\xff\xfe") {
+        return (TextEncoding::Utf16Le, true, Some(utf16(rest, u16::from_le_bytes)));
+    }
+    if let Some(rest) = data.strip_prefix(b"\xfe\xff") {
+        return (TextEncoding::Utf16Be, true, Some(utf16(rest, u16::from_be_bytes)));
+    }
+
+    let sample = &data[..data.len().min(8000)];
+    if !sample.contains(&0) {
+        return match std::str::from_utf8(data) {
+            Ok(text) => (TextEncoding::Utf8, false, Some(text.to_string())),
+            Err(_) => (TextEncoding::Latin1, false, Some(data.iter().map(|&b| b as char).collect())),
+        };
+    }
+
+    // ASCII-range text in UTF-16 has a NUL in every other byte
+    let zeros_at = |parity: usize| sample.iter().skip(parity).step_by(2).filter(|&&b| b == 0).count();
+    let half = sample.len() / 2;
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if half > 0 && odd * 10 >= half * 9 && even * 10 < half {
+        (TextEncoding::Utf16Le, false, Some(utf16(data, u16::from_le_bytes)))
+    } else if half > 0 && even * 10 >= half * 9 && odd * 10 < half {
+        (TextEncoding::Utf16Be, false, Some(utf16(data, u16::from_be_bytes)))
+    } else {
+        (TextEncoding::Binary, false, None)
+    }
+}
+
+fn listed(lines: &mut Vec<usize>, line: usize) {
+    if lines.len() < MAX_LISTED_LINES {
+        lines.push(line);
+    }
+}
+
+fn check_text(data: &[u8]) -> TextFileCheck {
+    let (encoding, bom, text) = decode(data);
+    let mut check = TextFileCheck {
+        size: data.len() as u64,
+        encoding,
+        bom,
+        line_endings: LineEndings::None,
+        lf_count: 0,
+        crlf_count: 0,
+        cr_count: 0,
+        line_count: 0,
+        final_newline: false,
+        trailing_whitespace_lines: Vec::new(),
+        trailing_whitespace_count: 0,
+        indentation: Indentation::None,
+        tab_indented_lines: 0,
+        space_indented_lines: 0,
+        mixed_indent_lines: Vec::new(),
+    };
+    let Some(text) = text else {
+        return check;
+    };
+
+    let mut rest = text.as_str();
+    while !rest.is_empty() {
+        check.line_count += 1;
+        let (line, next) = match rest.find(['\n', '\r']) {
+            Some(end) if rest[end..].starts_with("\r\n") => {
+                check.crlf_count += 1;
+                (&rest[..end], &rest[end + 2..])
+            }
+            Some(end) => {
+                if rest.as_bytes()[end] == b'\n' {
+                    check.lf_count += 1;
+                } else {
+                    check.cr_count += 1;
+                }
+                (&rest[..end], &rest[end + 1..])
+            }
+            None => (rest, ""),
+        };
+        check.final_newline = line.len() < rest.len();
+
+        let number = check.line_count;
+        if line.ends_with([' ', '\t']) {
+            check.trailing_whitespace_count += 1;
+            listed(&mut check.trailing_whitespace_lines, number);
+        }
+        let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        match (indent.contains('\t'), indent.contains(' ')) {
+            (true, true) => listed(&mut check.mixed_indent_lines, number),
+            (true, false) => check.tab_indented_lines += 1,
+            (false, true) => check.space_indented_lines += 1,
+            (false, false) => {}
+        }
+        rest = next;
+    }
+
+    let endings = [check.lf_count, check.crlf_count, check.cr_count];
+    check.line_endings = match endings.iter().filter(|&&count| count > 0).count() {
+        0 => LineEndings::None,
+        1 if check.lf_count > 0 => LineEndings::Lf,
+        1 if check.crlf_count > 0 => LineEndings::Crlf,
+        1 => LineEndings::Cr,
+        _ => LineEndings::Mixed,
+    };
+    check.indentation = match (check.tab_indented_lines, check.space_indented_lines, check.mixed_indent_lines.is_empty()) {
+        (0, 0, true) => Indentation::None,
+        (_, 0, true) => Indentation::Tabs,
+        (0, _, true) => Indentation::Spaces,
+        _ => Indentation::Mixed,
+    };
+    check
+}
+
+// Reports what commonly breaks scripts and parsers in a remote text file:
+// its encoding and BOM, CRLF or mixed line endings, trailing whitespace and
+// tabs mixed with spaces. Only reads the file.
+#[tauri::command]
+pub async fn analyze_text_file(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+) -> Result<TextFileCheck, SshError> {
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = SftpClient::connect(&session, password.as_deref())?;
+        let data = client.read_file(&path, MAX_CHECK_FILE_SIZE)?;
+        Ok(check_text(&data))
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))?
+}
@@ -0,0 +1,128 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::{ImageFormat, ImageReader};
+use serde::Serialize;
+use std::io::Cursor;
+use tauri::State;
+
+use crate::error::SshError;
+use crate::sftp::SftpClient;
+use crate::AppState;
+
+const DEFAULT_MAX_BYTES: u64 = 5 * 1024 * 1024;
+// The whole file is held in memory and sent over IPC as base64
+const MAX_MAX_BYTES: u64 = 20 * 1024 * 1024;
+// PNG and JPEG images larger than this either way are scaled down to fit
+const THUMBNAIL_SIZE: u32 = 512;
+
+// SVG is left out on purpose: it's a document that can carry scripts and
+// external references, not just pixels
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "cur"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteThumbnail {
+    // data:<mime>;base64,...
+    pub data_url: String,
+    pub mime: String,
+    // Of the image sent, when it was decoded (PNG and JPEG)
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    // Size of the remote file
+    pub size: u64,
+    pub scaled: bool,
+}
+
+// The MIME type the file's magic bytes say it is
+fn sniff_image(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else if data.starts_with(b"\x00\x00\x01\x00") || data.starts_with(b"\x00\x00\x02\x00") {
+        Some("image/x-icon")
+    } else {
+        None
+    }
+}
+
+fn has_image_extension(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.')
+        .is_some_and(|(_, extension)| IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+// Scales a PNG or JPEG down to THUMBNAIL_SIZE, keeping its format. Returns
+// the new bytes, if it was scaled, and the dimensions sent.
+fn downscale(data: Vec<u8>, format: ImageFormat) -> Result<(Vec<u8>, bool, u32, u32), SshError> {
+    let image = ImageReader::with_format(Cursor::new(&data), format)
+        .decode()
+        .map_err(|e| SshError::Other(format!("Failed to decode the image: {}", e)))?;
+    if image.width() <= THUMBNAIL_SIZE && image.height() <= THUMBNAIL_SIZE {
+        return Ok((data, false, image.width(), image.height()));
+    }
+
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut encoded = Cursor::new(Vec::new());
+    // JPEG has no alpha channel to encode
+    let result = match format {
+        ImageFormat::Jpeg => thumbnail.to_rgb8().write_to(&mut encoded, format),
+        _ => thumbnail.write_to(&mut encoded, format),
+    };
+    result.map_err(|e| SshError::Other(format!("Failed to encode the thumbnail: {}", e)))?;
+    Ok((encoded.into_inner(), true, thumbnail.width(), thumbnail.height()))
+}
+
+// Reads a remote image for the file browser's preview, refusing anything
+// that isn't one or is over `max_bytes` (5MB by default). Large PNGs and
+// JPEGs are scaled down before being sent.
+#[tauri::command]
+pub async fn get_remote_thumbnail(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    max_bytes: Option<u64>,
+    password: Option<String>,
+) -> Result<RemoteThumbnail, SshError> {
+    if !has_image_extension(&path) {
+        return Err(SshError::Other(format!("{} is not an image", path)));
+    }
+    let max_bytes = max_bytes.unwrap_or(DEFAULT_MAX_BYTES).min(MAX_MAX_BYTES);
+    let session = state.get_session(&session_id).map_err(|_| SshError::SessionNotFound(session_id.clone()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = SftpClient::connect(&session, password.as_deref())?;
+        // Checked up front so an oversized file isn't read at all
+        let size = client.stat(&path)?.size.unwrap_or(0);
+        if size > max_bytes {
+            return Err(SshError::Other(format!("{} is too large to preview ({} bytes)", path, size)));
+        }
+        let data = client.read_file(&path, max_bytes)?;
+        let mime = sniff_image(&data).ok_or_else(|| SshError::Other(format!("{} is not an image", path)))?;
+
+        let (data, scaled, dimensions) = match mime {
+            "image/png" | "image/jpeg" => {
+                let format = if mime == "image/png" { ImageFormat::Png } else { ImageFormat::Jpeg };
+                let (data, scaled, width, height) = downscale(data, format)?;
+                (data, scaled, Some((width, height)))
+            }
+            _ => (data, false, None),
+        };
+
+        Ok(RemoteThumbnail {
+            data_url: format!("data:{};base64,{}", mime, BASE64.encode(&data)),
+            mime: mime.to_string(),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            size,
+            scaled,
+        })
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))?
+}
@@ -0,0 +1,181 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::{AppState, Session};
+
+const HISTORY_KEY: &str = "transfers";
+// Oldest records are dropped past this
+const MAX_HISTORY: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
+// One completed transfer, kept for charting throughput after the fact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRecord {
+    pub at: String,
+    pub session_id: String,
+    pub host: String,
+    pub direction: TransferDirection,
+    pub bytes: u64,
+    pub duration_ms: u64,
+    pub bytes_per_sec: u64,
+    // Left out when transfer_history_omit_paths is on
+    pub local_path: Option<String>,
+    pub remote_path: Option<String>,
+}
+
+impl TransferRecord {
+    pub fn new(session: &Session, direction: TransferDirection, bytes: u64, elapsed: Duration) -> Self {
+        TransferRecord {
+            at: chrono::Utc::now().to_rfc3339(),
+            session_id: session.id.clone(),
+            host: session.host.clone(),
+            direction,
+            bytes,
+            duration_ms: elapsed.as_millis() as u64,
+            bytes_per_sec: (bytes as f64 / elapsed.as_secs_f64().max(0.001)) as u64,
+            local_path: None,
+            remote_path: None,
+        }
+    }
+
+    pub fn with_paths(mut self, local_path: &str, remote_path: &str) -> Self {
+        self.local_path = Some(local_path.to_string());
+        self.remote_path = Some(remote_path.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransferHistoryFilter {
+    pub session_id: Option<String>,
+    pub host: Option<String>,
+    pub direction: Option<TransferDirection>,
+    // RFC 3339; only records at or after this time
+    pub since: Option<String>,
+    // Newest N matching records
+    pub limit: Option<usize>,
+}
+
+impl TransferHistoryFilter {
+    fn matches(&self, record: &TransferRecord) -> bool {
+        self.session_id.as_ref().is_none_or(|id| *id == record.session_id)
+            && self.host.as_ref().is_none_or(|host| host.eq_ignore_ascii_case(&record.host))
+            && self.direction.is_none_or(|direction| direction == record.direction)
+            && self.since.as_ref().is_none_or(|since| {
+                // Records are all written as UTC, so compare parsed times
+                match (chrono::DateTime::parse_from_rfc3339(since), chrono::DateTime::parse_from_rfc3339(&record.at)) {
+                    (Ok(since), Ok(at)) => at >= since,
+                    _ => true,
+                }
+            })
+    }
+}
+
+// Completed transfers, oldest first, mirrored to transfer_history.json
+pub struct TransferHistory {
+    records: Mutex<Option<VecDeque<TransferRecord>>>,
+}
+
+impl TransferHistory {
+    pub fn new() -> Self {
+        TransferHistory {
+            records: Mutex::new(None),
+        }
+    }
+
+    // Runs `f` on the history, reading it from the store on first use
+    fn with_records<T>(&self, app: &AppHandle, f: impl FnOnce(&mut VecDeque<TransferRecord>) -> T) -> Result<T, String> {
+        let mut records = self.records.lock().map_err(|e| e.to_string())?;
+        let records = match &mut *records {
+            Some(records) => records,
+            None => records.insert(load(app)?),
+        };
+        Ok(f(records))
+    }
+
+    // Records matching the filter, oldest first
+    pub fn matching(&self, app: &AppHandle, filter: &TransferHistoryFilter) -> Result<Vec<TransferRecord>, String> {
+        self.with_records(app, |records| {
+            let mut matching: Vec<TransferRecord> = records.iter().filter(|r| filter.matches(r)).cloned().collect();
+            if let Some(limit) = filter.limit {
+                matching.drain(..matching.len().saturating_sub(limit));
+            }
+            matching
+        })
+    }
+}
+
+fn load(app: &AppHandle) -> Result<VecDeque<TransferRecord>, String> {
+    let store = app.store("transfer_history.json").map_err(|e| e.to_string())?;
+    Ok(store.get(HISTORY_KEY)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+fn save(app: &AppHandle, records: &VecDeque<TransferRecord>) -> Result<(), String> {
+    let store = app.store("transfer_history.json").map_err(|e| e.to_string())?;
+    store.set(HISTORY_KEY, serde_json::to_value(records).map_err(|e| e.to_string())?);
+    store.save().map_err(|e| e.to_string())
+}
+
+// Appends finished transfers. Batches should be recorded in one call since
+// every call rewrites the history file.
+pub fn record_all(app: &AppHandle, new_records: Vec<TransferRecord>) {
+    if new_records.is_empty() {
+        return;
+    }
+
+    let omit_paths = app.state::<AppState>().settings.lock()
+        .map(|settings| settings.transfer_history_omit_paths)
+        .unwrap_or(true);
+
+    let result = app.state::<TransferHistory>().with_records(app, |records| {
+        for mut record in new_records {
+            if omit_paths {
+                record.local_path = None;
+                record.remote_path = None;
+            }
+            records.push_back(record);
+        }
+        while records.len() > MAX_HISTORY {
+            records.pop_front();
+        }
+        save(app, records)
+    });
+
+    if let Err(e) = result.and_then(|saved| saved) {
+        warn!("Failed to save transfer history: {}", e);
+    }
+}
+
+pub fn record(app: &AppHandle, record: TransferRecord) {
+    record_all(app, vec![record]);
+}
+
+// Completed transfers matching the filter, oldest first
+#[tauri::command]
+pub async fn get_transfer_history(
+    app: AppHandle,
+    history: State<'_, TransferHistory>,
+    filter: Option<TransferHistoryFilter>,
+) -> Result<Vec<TransferRecord>, String> {
+    history.matching(&app, &filter.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn clear_transfer_history(app: AppHandle, history: State<'_, TransferHistory>) -> Result<(), String> {
+    history.with_records(&app, |records| {
+        records.clear();
+        save(&app, records)
+    })?
+}
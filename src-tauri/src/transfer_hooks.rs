@@ -0,0 +1,181 @@
+use log::{info, warn};
+use serde::Serialize;
+use std::process::Command;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::AppState;
+
+// stdout and stderr each, past this the event only carries the start
+const MAX_HOOK_OUTPUT: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    Success,
+    Failed,
+}
+
+impl TransferStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            TransferStatus::Success => "success",
+            TransferStatus::Failed => "failed",
+        }
+    }
+}
+
+// A finished transfer to run the hook for
+pub struct HookTransfer {
+    pub local_path: String,
+    pub remote_path: String,
+    pub status: TransferStatus,
+}
+
+// One per hook run. `error` is set when the command couldn't be started or
+// exited with a non-zero status; the transfer itself is reported by
+// `transfer_status` and is not affected either way.
+#[derive(Clone, Serialize)]
+struct TransferHookEvent {
+    session_id: String,
+    command: String,
+    local_path: String,
+    remote_path: String,
+    transfer_status: TransferStatus,
+    exit_code: Option<i32>,
+    stdout: String,
+    stderr: String,
+    error: Option<String>,
+}
+
+// Rejects a hook up front when hooks are turned off, before anything is
+// transferred. An empty command counts as none.
+pub fn check(state: &AppState, command: Option<String>) -> Result<Option<String>, String> {
+    let Some(command) = command.filter(|command| !command.trim().is_empty()) else {
+        return Ok(None);
+    };
+    if !state.settings.lock().map_err(|e| e.to_string())?.transfer_hooks_enabled {
+        return Err("Running a command after transfers is turned off in the settings".to_string());
+    }
+    Ok(Some(command))
+}
+
+#[cfg(unix)]
+fn quote(value: &str) -> String {
+    crate::exec::shell_quote(value)
+}
+
+#[cfg(not(unix))]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+// %f is the local path and %r the remote one, both already quoted for the
+// shell; %s is "success" or "failed" and %% a literal %
+fn expand(template: &str, transfer: &HookTransfer) -> String {
+    let mut command = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            command.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => command.push_str(&quote(&transfer.local_path)),
+            Some('r') => command.push_str(&quote(&transfer.remote_path)),
+            Some('s') => command.push_str(transfer.status.as_str()),
+            Some('%') => command.push('%'),
+            Some(other) => {
+                command.push('%');
+                command.push(other);
+            }
+            None => command.push('%'),
+        }
+    }
+    command
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn truncated(output: &[u8]) -> String {
+    let text = String::from_utf8_lossy(output);
+    if text.len() <= MAX_HOOK_OUTPUT {
+        return text.into_owned();
+    }
+    let mut end = MAX_HOOK_OUTPUT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
+
+fn run_one(session_id: &str, template: &str, transfer: HookTransfer) -> TransferHookEvent {
+    let command = expand(template, &transfer);
+    let (exit_code, stdout, stderr, error) = match shell_command(&command).output() {
+        Ok(output) => {
+            let error = (!output.status.success()).then(|| match output.status.code() {
+                Some(code) => format!("The command exited with status {}", code),
+                None => "The command was killed by a signal".to_string(),
+            });
+            (output.status.code(), truncated(&output.stdout), truncated(&output.stderr), error)
+        }
+        Err(e) => (None, String::new(), String::new(), Some(format!("Failed to run the command: {}", e))),
+    };
+
+    TransferHookEvent {
+        session_id: session_id.to_string(),
+        command,
+        local_path: transfer.local_path,
+        remote_path: transfer.remote_path,
+        transfer_status: transfer.status,
+        exit_code,
+        stdout,
+        stderr,
+        error,
+    }
+}
+
+// Runs the hook for each transfer in the background, one after the other,
+// emitting a `transfer_hook` event for each. Failed transfers are skipped
+// unless transfer_hooks_on_failure is set.
+pub fn run(app: &AppHandle, session_id: &str, command: Option<&str>, transfers: Vec<HookTransfer>) {
+    let Some(template) = command else {
+        return;
+    };
+    let on_failure = app.state::<AppState>().settings.lock()
+        .map(|settings| settings.transfer_hooks_on_failure)
+        .unwrap_or(false);
+    let transfers: Vec<HookTransfer> = transfers
+        .into_iter()
+        .filter(|transfer| on_failure || transfer.status == TransferStatus::Success)
+        .collect();
+    if transfers.is_empty() {
+        return;
+    }
+
+    let app = app.clone();
+    let session_id = session_id.to_string();
+    let template = template.to_string();
+    thread::spawn(move || {
+        for transfer in transfers {
+            let event = run_one(&session_id, &template, transfer);
+            match &event.error {
+                Some(error) => warn!("Transfer hook `{}` failed: {}", event.command, error),
+                None => info!("Ran transfer hook `{}`", event.command),
+            }
+            let _ = app.emit("transfer_hook", &event);
+        }
+    });
+}
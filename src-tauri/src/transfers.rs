@@ -0,0 +1,614 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::error::SshError;
+use crate::sftp::{check_upload_mode, SftpClient};
+use crate::transfer_history::{self, TransferDirection, TransferRecord};
+use crate::transfer_hooks::{self, HookTransfer, TransferStatus};
+use crate::{AppState, Session};
+
+// Give up on an unanswered conflict prompt eventually and skip the file
+const CONFLICT_ANSWER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// What to do when the destination of a batch transfer already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictPolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    // Save as "name (n).ext" next to the existing file
+    Rename,
+    // Overwrite only when the source was modified more recently
+    NewerWins,
+    // Emit a `sftp_conflict` event and wait for resolve_transfer_conflict
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictDecision {
+    Overwrite,
+    Skip,
+    Rename,
+}
+
+struct ConflictAnswer {
+    decision: ConflictDecision,
+    apply_to_all: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictEvent {
+    pub conflict_id: String,
+    pub source: String,
+    pub destination: String,
+    pub source_size: Option<u64>,
+    pub source_mtime: Option<u64>,
+    pub destination_size: Option<u64>,
+    pub destination_mtime: Option<u64>,
+}
+
+// Conflict prompts waiting for the user, keyed by conflict id
+pub struct ConflictResolver {
+    pending: Mutex<HashMap<String, mpsc::Sender<ConflictAnswer>>>,
+}
+
+impl ConflictResolver {
+    pub fn new() -> Self {
+        ConflictResolver {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Blocks until the frontend answers; only call from a blocking task
+    fn ask(&self, app_handle: &AppHandle, event: ConflictEvent) -> ConflictAnswer {
+        self.ask_with(event, |event| {
+            let _ = app_handle.emit("sftp_conflict", event);
+        })
+    }
+
+    // `notify` tells whoever answers about the prompt, once it's pending
+    fn ask_with(&self, mut event: ConflictEvent, notify: impl FnOnce(&ConflictEvent)) -> ConflictAnswer {
+        let (tx, rx) = mpsc::channel();
+        event.conflict_id = Uuid::new_v4().to_string();
+        self.pending.lock().unwrap().insert(event.conflict_id.clone(), tx);
+
+        notify(&event);
+        let answer = rx.recv_timeout(CONFLICT_ANSWER_TIMEOUT).unwrap_or_else(|_| {
+            warn!("No answer for conflict on {}, skipping it", event.destination);
+            ConflictAnswer { decision: ConflictDecision::Skip, apply_to_all: false }
+        });
+
+        self.pending.lock().unwrap().remove(&event.conflict_id);
+        answer
+    }
+
+    fn answer(&self, conflict_id: &str, answer: ConflictAnswer) -> Result<(), String> {
+        let sender = self.pending.lock().unwrap()
+            .remove(conflict_id)
+            .ok_or_else(|| "This conflict is no longer pending".to_string())?;
+
+        sender.send(answer).map_err(|_| "The transfer was cancelled".to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferredFile {
+    pub source: String,
+    pub destination: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedTransfer {
+    pub source: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchTransferResult {
+    pub transferred: Vec<TransferredFile>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<FailedTransfer>,
+}
+
+// Size and mtime (seconds) of one side of a transfer
+#[derive(Debug, Clone, Copy)]
+struct FileInfo {
+    size: Option<u64>,
+    mtime: Option<u64>,
+}
+
+enum Plan {
+    Transfer(String),
+    Skip,
+}
+
+// "notes.txt" -> "notes (1).txt", splitting at the last dot like most file
+// managers do
+fn numbered_name(name: &str, n: u32) -> String {
+    match name.rfind('.') {
+        // A leading dot is a hidden file, not an extension
+        Some(dot) if dot > 0 => format!("{} ({}){}", &name[..dot], n, &name[dot..]),
+        _ => format!("{} ({})", name, n),
+    }
+}
+
+fn file_name(path: &str) -> Result<String, String> {
+    Path::new(path.trim_end_matches('/'))
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("{} has no file name", path))
+}
+
+fn join_remote(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+fn local_info(path: &Path) -> Option<FileInfo> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata.modified().ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs());
+    Some(FileInfo { size: Some(metadata.len()), mtime })
+}
+
+fn remote_info(client: &SftpClient, path: &str) -> Option<FileInfo> {
+    client.stat(path).ok().map(|stat| FileInfo { size: stat.size, mtime: stat.mtime })
+}
+
+struct ConflictContext<'a> {
+    // Prompts for an Ask decision
+    ask: Box<dyn Fn(ConflictEvent) -> ConflictAnswer + 'a>,
+    policy: ConflictPolicy,
+    // Set once the user picks "apply to all" in an Ask prompt
+    remembered: Option<ConflictDecision>,
+}
+
+impl<'a> ConflictContext<'a> {
+    fn new(app_handle: &'a AppHandle, policy: ConflictPolicy) -> Self {
+        ConflictContext {
+            ask: Box::new(move |event| app_handle.state::<ConflictResolver>().ask(app_handle, event)),
+            policy,
+            remembered: None,
+        }
+    }
+
+    // Applies the policy to one file. `name_for` builds the destination path
+    // for a file name and `info_of` stats a destination, None if it's free.
+    fn plan(
+        &mut self,
+        source: &str,
+        source_info: Option<FileInfo>,
+        name: &str,
+        name_for: impl Fn(&str) -> String,
+        info_of: impl Fn(&str) -> Option<FileInfo>,
+    ) -> Plan {
+        let destination = name_for(name);
+        let Some(existing) = info_of(&destination) else {
+            return Plan::Transfer(destination);
+        };
+
+        let decision = match (self.remembered, self.policy) {
+            (Some(decision), _) => decision,
+            (None, ConflictPolicy::Overwrite) => ConflictDecision::Overwrite,
+            (None, ConflictPolicy::Skip) => ConflictDecision::Skip,
+            (None, ConflictPolicy::Rename) => ConflictDecision::Rename,
+            (None, ConflictPolicy::NewerWins) => {
+                let source_mtime = source_info.and_then(|info| info.mtime);
+                match (source_mtime, existing.mtime) {
+                    (Some(source), Some(existing)) if source > existing => ConflictDecision::Overwrite,
+                    _ => ConflictDecision::Skip,
+                }
+            }
+            (None, ConflictPolicy::Ask) => {
+                let event = ConflictEvent {
+                    conflict_id: String::new(),
+                    source: source.to_string(),
+                    destination: destination.clone(),
+                    source_size: source_info.and_then(|info| info.size),
+                    source_mtime: source_info.and_then(|info| info.mtime),
+                    destination_size: existing.size,
+                    destination_mtime: existing.mtime,
+                };
+                let answer = (self.ask)(event);
+                if answer.apply_to_all {
+                    self.remembered = Some(answer.decision);
+                }
+                answer.decision
+            }
+        };
+
+        match decision {
+            ConflictDecision::Overwrite => Plan::Transfer(destination),
+            ConflictDecision::Skip => Plan::Skip,
+            ConflictDecision::Rename => {
+                let free = (1..)
+                    .map(|n| name_for(&numbered_name(name, n)))
+                    .find(|candidate| info_of(candidate).is_none())
+                    .unwrap_or(destination);
+                Plan::Transfer(free)
+            }
+        }
+    }
+}
+
+pub(crate) fn connect(state: &State<'_, AppState>, session_id: &str, password: Option<&str>) -> Result<(Session, SftpClient), SshError> {
+    let session = state.get_session(session_id).map_err(|_| SshError::SessionNotFound(session_id.to_string()))?;
+    let buffer_size = state.settings.lock().map_err(|e| SshError::Other(e.to_string()))?.sftp_buffer_size;
+
+    let client = SftpClient::connect(&session, password)?.with_buffer_size(buffer_size);
+    Ok((session, client))
+}
+
+// History entries for the files a batch moved
+fn history_records(session: &Session, direction: TransferDirection, transferred: &[(TransferredFile, Duration)]) -> Vec<TransferRecord> {
+    transferred
+        .iter()
+        .map(|(file, elapsed)| {
+            let record = TransferRecord::new(session, direction, file.bytes, *elapsed);
+            match direction {
+                TransferDirection::Upload => record.with_paths(&file.source, &file.destination),
+                TransferDirection::Download => record.with_paths(&file.destination, &file.source),
+            }
+        })
+        .collect()
+}
+
+// What the completion hook runs for: every file sent or failed, not the
+// skipped ones. `target` is where a failed file was headed.
+fn hook_transfers(result: &BatchTransferResult, direction: TransferDirection, target: impl Fn(&str) -> String) -> Vec<HookTransfer> {
+    let transferred = result.transferred.iter().map(|file| (&file.source, file.destination.clone(), TransferStatus::Success));
+    let failed = result.failed.iter().map(|file| (&file.source, target(&file.source), TransferStatus::Failed));
+    transferred
+        .chain(failed)
+        .map(|(source, destination, status)| match direction {
+            TransferDirection::Upload => HookTransfer { local_path: source.clone(), remote_path: destination, status },
+            TransferDirection::Download => HookTransfer { local_path: destination, remote_path: source.clone(), status },
+        })
+        .collect()
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_files(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    local_paths: Vec<String>,
+    remote_dir: String,
+    policy: Option<ConflictPolicy>,
+    password: Option<String>,
+    upload_mode: Option<u32>,
+    on_complete_command: Option<String>,
+) -> Result<BatchTransferResult, SshError> {
+    let upload_mode = check_upload_mode(upload_mode)?;
+    let on_complete_command = transfer_hooks::check(&state, on_complete_command).map_err(SshError::Other)?;
+    let (session, client) = connect(&state, &session_id, password.as_deref())?;
+    let policy = policy.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut context = ConflictContext::new(&app_handle, policy);
+        let mut result = BatchTransferResult::default();
+        let mut timings = Vec::new();
+
+        for local_path in local_paths {
+            let name = match file_name(&local_path) {
+                Ok(name) => name,
+                Err(error) => {
+                    result.failed.push(FailedTransfer { source: local_path, error });
+                    continue;
+                }
+            };
+
+            let source_info = local_info(Path::new(&local_path));
+            let plan = context.plan(
+                &local_path,
+                source_info,
+                &name,
+                |name| join_remote(&remote_dir, name),
+                |path| remote_info(&client, path),
+            );
+
+            match plan {
+                Plan::Skip => result.skipped.push(local_path),
+                Plan::Transfer(destination) => {
+                    let started = Instant::now();
+                    let uploaded = client.upload_file(Path::new(&local_path), &destination).and_then(|bytes| {
+                        // An overwritten file keeps its old mode unless told otherwise
+                        match upload_mode {
+                            Some(mode) => client.set_mode(&destination, mode).map(|_| bytes),
+                            None => Ok(bytes),
+                        }
+                    });
+                    match uploaded {
+                        Ok(bytes) => {
+                            let file = TransferredFile { source: local_path, destination, bytes };
+                            timings.push((file.clone(), started.elapsed()));
+                            result.transferred.push(file);
+                        }
+                        Err(e) => result.failed.push(FailedTransfer { source: local_path, error: e.to_string() }),
+                    }
+                }
+            }
+        }
+
+        transfer_history::record_all(&app_handle, history_records(&session, TransferDirection::Upload, &timings));
+        let hooks = hook_transfers(&result, TransferDirection::Upload, |source| {
+            file_name(source).map_or_else(|_| remote_dir.clone(), |name| join_remote(&remote_dir, &name))
+        });
+        transfer_hooks::run(&app_handle, &session_id, on_complete_command.as_deref(), hooks);
+
+        info!(
+            "Batch upload to {}: {} sent, {} skipped, {} failed",
+            remote_dir, result.transferred.len(), result.skipped.len(), result.failed.len()
+        );
+        result
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))
+}
+
+// Downloads the files into `local_dir`, blocking until the batch is done
+pub(crate) fn download_batch(
+    app_handle: &AppHandle,
+    session: &Session,
+    client: &SftpClient,
+    remote_paths: Vec<String>,
+    local_dir: &str,
+    policy: ConflictPolicy,
+) -> BatchTransferResult {
+    let mut context = ConflictContext::new(app_handle, policy);
+    let mut result = BatchTransferResult::default();
+    let mut timings = Vec::new();
+    let local_dir = PathBuf::from(local_dir);
+
+    for remote_path in remote_paths {
+        let name = match file_name(&remote_path) {
+            Ok(name) => name,
+            Err(error) => {
+                result.failed.push(FailedTransfer { source: remote_path, error });
+                continue;
+            }
+        };
+
+        let source_info = remote_info(client, &remote_path);
+        let plan = context.plan(
+            &remote_path,
+            source_info,
+            &name,
+            |name| local_dir.join(name).to_string_lossy().to_string(),
+            |path| local_info(Path::new(path)),
+        );
+
+        match plan {
+            Plan::Skip => result.skipped.push(remote_path),
+            Plan::Transfer(destination) => {
+                let started = Instant::now();
+                match client.download_file(&remote_path, Path::new(&destination)) {
+                    Ok(bytes) => {
+                        let file = TransferredFile { source: remote_path, destination, bytes };
+                        timings.push((file.clone(), started.elapsed()));
+                        result.transferred.push(file);
+                    }
+                    Err(e) => result.failed.push(FailedTransfer { source: remote_path, error: e.to_string() }),
+                }
+            }
+        }
+    }
+
+    transfer_history::record_all(app_handle, history_records(session, TransferDirection::Download, &timings));
+
+    info!(
+        "Batch download to {}: {} received, {} skipped, {} failed",
+        local_dir.display(), result.transferred.len(), result.skipped.len(), result.failed.len()
+    );
+    result
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn download_files(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    session_id: String,
+    remote_paths: Vec<String>,
+    local_dir: String,
+    policy: Option<ConflictPolicy>,
+    password: Option<String>,
+    on_complete_command: Option<String>,
+) -> Result<BatchTransferResult, SshError> {
+    let on_complete_command = transfer_hooks::check(&state, on_complete_command).map_err(SshError::Other)?;
+    let (session, client) = connect(&state, &session_id, password.as_deref())?;
+    let policy = policy.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = download_batch(&app_handle, &session, &client, remote_paths, &local_dir, policy);
+        let hooks = hook_transfers(&result, TransferDirection::Download, |source| {
+            let local_dir = Path::new(&local_dir);
+            file_name(source).map_or_else(|_| local_dir.to_path_buf(), |name| local_dir.join(name)).to_string_lossy().to_string()
+        });
+        transfer_hooks::run(&app_handle, &session_id, on_complete_command.as_deref(), hooks);
+        result
+    })
+    .await
+    .map_err(|e| SshError::Other(e.to_string()))
+}
+
+// Answers a `sftp_conflict` event
+#[tauri::command]
+pub async fn resolve_transfer_conflict(
+    resolver: State<'_, ConflictResolver>,
+    conflict_id: String,
+    decision: ConflictDecision,
+    apply_to_all: Option<bool>,
+) -> Result<(), String> {
+    resolver.answer(&conflict_id, ConflictAnswer { decision, apply_to_all: apply_to_all.unwrap_or(false) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const OLD: FileInfo = FileInfo { size: Some(10), mtime: Some(1_000) };
+    const NEW: FileInfo = FileInfo { size: Some(20), mtime: Some(2_000) };
+
+    fn never_asked() -> Box<dyn Fn(ConflictEvent) -> ConflictAnswer> {
+        Box::new(|event| panic!("asked about {}", event.destination))
+    }
+
+    fn context(policy: ConflictPolicy) -> ConflictContext<'static> {
+        ConflictContext { ask: never_asked(), policy, remembered: None }
+    }
+
+    // Plans `name` for upload into /dest, where `existing` names are taken
+    fn plan(context: &mut ConflictContext, source_info: FileInfo, name: &str, existing: &[&str]) -> Option<String> {
+        let plan = context.plan(
+            "/src/file",
+            Some(source_info),
+            name,
+            |name| join_remote("/dest", name),
+            |path| existing.iter().any(|name| join_remote("/dest", name) == path).then_some(OLD),
+        );
+        match plan {
+            Plan::Transfer(destination) => Some(destination),
+            Plan::Skip => None,
+        }
+    }
+
+    #[test]
+    fn numbers_names_before_the_extension() {
+        assert_eq!(numbered_name("notes.txt", 1), "notes (1).txt");
+        assert_eq!(numbered_name("archive.tar.gz", 2), "archive.tar (2).gz");
+        assert_eq!(numbered_name("README", 3), "README (3)");
+        assert_eq!(numbered_name(".bashrc", 1), ".bashrc (1)");
+    }
+
+    #[test]
+    fn free_destinations_are_transferred_under_every_policy() {
+        for policy in [
+            ConflictPolicy::Overwrite,
+            ConflictPolicy::Skip,
+            ConflictPolicy::Rename,
+            ConflictPolicy::NewerWins,
+            ConflictPolicy::Ask,
+        ] {
+            let destination = plan(&mut context(policy), NEW, "a.txt", &["b.txt"]);
+            assert_eq!(destination.as_deref(), Some("/dest/a.txt"), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn overwrite_replaces_the_existing_file() {
+        let destination = plan(&mut context(ConflictPolicy::Overwrite), NEW, "a.txt", &["a.txt"]);
+        assert_eq!(destination.as_deref(), Some("/dest/a.txt"));
+    }
+
+    #[test]
+    fn skip_leaves_the_existing_file() {
+        assert_eq!(plan(&mut context(ConflictPolicy::Skip), NEW, "a.txt", &["a.txt"]), None);
+    }
+
+    #[test]
+    fn rename_picks_the_first_free_number() {
+        let mut context = context(ConflictPolicy::Rename);
+        let destination = plan(&mut context, NEW, "a.txt", &["a.txt"]);
+        assert_eq!(destination.as_deref(), Some("/dest/a (1).txt"));
+
+        let destination = plan(&mut context, NEW, "a.txt", &["a.txt", "a (1).txt", "a (2).txt"]);
+        assert_eq!(destination.as_deref(), Some("/dest/a (3).txt"));
+    }
+
+    #[test]
+    fn newer_wins_compares_modification_times() {
+        let mut context = context(ConflictPolicy::NewerWins);
+        assert_eq!(plan(&mut context, NEW, "a.txt", &["a.txt"]).as_deref(), Some("/dest/a.txt"));
+        assert_eq!(plan(&mut context, OLD, "a.txt", &["a.txt"]), None);
+
+        let unknown = FileInfo { size: Some(20), mtime: None };
+        assert_eq!(plan(&mut context, unknown, "a.txt", &["a.txt"]), None);
+    }
+
+    #[test]
+    fn ask_uses_the_answer_and_describes_both_files() {
+        let asked = RefCell::new(Vec::new());
+        let mut context = ConflictContext {
+            ask: Box::new(|event| {
+                asked.borrow_mut().push(event);
+                ConflictAnswer { decision: ConflictDecision::Rename, apply_to_all: false }
+            }),
+            policy: ConflictPolicy::Ask,
+            remembered: None,
+        };
+
+        let destination = plan(&mut context, NEW, "a.txt", &["a.txt"]);
+        assert_eq!(destination.as_deref(), Some("/dest/a (1).txt"));
+        assert_eq!(context.remembered, None);
+        drop(context);
+
+        let asked = asked.into_inner();
+        assert_eq!(asked.len(), 1);
+        assert_eq!(asked[0].source, "/src/file");
+        assert_eq!(asked[0].destination, "/dest/a.txt");
+        assert_eq!((asked[0].source_size, asked[0].source_mtime), (NEW.size, NEW.mtime));
+        assert_eq!((asked[0].destination_size, asked[0].destination_mtime), (OLD.size, OLD.mtime));
+    }
+
+    #[test]
+    fn ask_remembers_an_answer_applied_to_all() {
+        let asked = RefCell::new(0);
+        let mut context = ConflictContext {
+            ask: Box::new(|_| {
+                *asked.borrow_mut() += 1;
+                ConflictAnswer { decision: ConflictDecision::Skip, apply_to_all: true }
+            }),
+            policy: ConflictPolicy::Ask,
+            remembered: None,
+        };
+
+        assert_eq!(plan(&mut context, NEW, "a.txt", &["a.txt"]), None);
+        assert_eq!(plan(&mut context, NEW, "b.txt", &["b.txt"]), None);
+        assert_eq!(context.remembered, Some(ConflictDecision::Skip));
+        drop(context);
+        assert_eq!(asked.into_inner(), 1);
+    }
+
+    #[test]
+    fn resolver_hands_the_answer_to_the_waiting_transfer() {
+        let resolver = ConflictResolver::new();
+        let event = ConflictEvent {
+            conflict_id: String::new(),
+            source: "/src/a.txt".to_string(),
+            destination: "/dest/a.txt".to_string(),
+            source_size: None,
+            source_mtime: None,
+            destination_size: None,
+            destination_mtime: None,
+        };
+
+        std::thread::scope(|scope| {
+            let answer = resolver.ask_with(event, |event| {
+                let conflict_id = event.conflict_id.clone();
+                let resolver = &resolver;
+                scope.spawn(move || {
+                    resolver
+                        .answer(&conflict_id, ConflictAnswer { decision: ConflictDecision::Overwrite, apply_to_all: true })
+                        .unwrap();
+                });
+            });
+            assert_eq!(answer.decision, ConflictDecision::Overwrite);
+            assert!(answer.apply_to_all);
+        });
+
+        assert!(resolver.pending.lock().unwrap().is_empty());
+        let stale = ConflictAnswer { decision: ConflictDecision::Skip, apply_to_all: false };
+        assert!(resolver.answer("unknown", stale).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::exec::{run_command, shell_quote};
+use crate::remote::open_session;
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtendedAttribute {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSecurityInfo {
+    pub path: String,
+    pub xattrs: Vec<ExtendedAttribute>,
+    // False when getfattr isn't installed, so `xattrs` is empty for lack of a tool
+    pub xattrs_supported: bool,
+    // None when SELinux is disabled or neither stat nor ls can show it
+    pub selinux_context: Option<String>,
+    // Raw `getfacl` output, None when the acl tools are not installed
+    pub acl: Option<String>,
+}
+
+fn has_tool(sess: &ssh2::Session, tool: &str) -> Result<bool, String> {
+    let output = run_command(sess, &format!("command -v {} >/dev/null 2>&1", tool))?;
+    Ok(output.exit_status == 0)
+}
+
+fn require_tool(sess: &ssh2::Session, tool: &str, feature: &str) -> Result<(), String> {
+    if has_tool(sess, tool)? {
+        Ok(())
+    } else {
+        Err(format!("{} is not supported on this server ({} is not installed)", feature, tool))
+    }
+}
+
+// Parses `getfattr -d` output, which looks like `user.comment="some text"`
+fn parse_getfattr(output: &str) -> Vec<ExtendedAttribute> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .filter_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+            Some(ExtendedAttribute { name: name.to_string(), value: value.to_string() })
+        })
+        .collect()
+}
+
+// A context as `stat -c %C` or `ls -Z` shows it; `?` means there is none
+pub(crate) fn selinux_context(field: &str) -> Option<String> {
+    let context = field.trim();
+    if context == "?" || !context.contains(':') {
+        None
+    } else {
+        Some(context.to_string())
+    }
+}
+
+// `ls -Zd` prints the context before the file name
+fn parse_ls_context(output: &str) -> Option<String> {
+    output.split_whitespace().next().and_then(selinux_context)
+}
+
+fn validate_xattr_name(name: &str) -> Result<(), String> {
+    let valid = name.contains('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid extended attribute name: {}", name))
+    }
+}
+
+#[tauri::command]
+pub async fn get_xattrs(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    password: Option<String>,
+) -> Result<FileSecurityInfo, String> {
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+    let quoted = shell_quote(&path);
+
+    let xattrs_supported = has_tool(&sess, "getfattr")?;
+    let xattrs = if xattrs_supported {
+        let output = run_command(&sess, &format!("getfattr --absolute-names -d -m - -- {}", quoted))?;
+        if output.exit_status != 0 {
+            return Err(format!("getfattr failed: {}", output.stderr.trim()));
+        }
+        parse_getfattr(&output.stdout)
+    } else {
+        Vec::new()
+    };
+
+    // The SELinux context and ACL are best effort extras. BusyBox stat has no
+    // %C, and some ls builds have no -Z, so either may be missing.
+    let selinux_context = run_command(&sess, &format!("stat -c %C -- {}", quoted))
+        .ok()
+        .filter(|output| output.exit_status == 0)
+        .and_then(|output| selinux_context(&output.stdout))
+        .or_else(|| {
+            run_command(&sess, &format!("ls -Zd -- {}", quoted))
+                .ok()
+                .filter(|output| output.exit_status == 0)
+                .and_then(|output| parse_ls_context(&output.stdout))
+        });
+
+    let acl = if has_tool(&sess, "getfacl")? {
+        run_command(&sess, &format!("getfacl --absolute-names -p -- {}", quoted))
+            .ok()
+            .filter(|output| output.exit_status == 0)
+            .map(|output| output.stdout)
+    } else {
+        None
+    };
+
+    Ok(FileSecurityInfo { path, xattrs, xattrs_supported, selinux_context, acl })
+}
+
+#[tauri::command]
+pub async fn set_xattr(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    name: String,
+    value: String,
+    password: Option<String>,
+) -> Result<(), String> {
+    validate_xattr_name(&name)?;
+
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+
+    require_tool(&sess, "setfattr", "Extended attributes")?;
+
+    let command = format!(
+        "setfattr -n {} -v {} -- {}",
+        shell_quote(&name),
+        shell_quote(&value),
+        shell_quote(&path)
+    );
+    let output = run_command(&sess, &command)?;
+    if output.exit_status != 0 {
+        return Err(format!("setfattr failed: {}", output.stderr.trim()));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_selinux_context(
+    state: State<'_, AppState>,
+    session_id: String,
+    path: String,
+    context: String,
+    password: Option<String>,
+) -> Result<(), String> {
+    let session = state.get_session(&session_id)?;
+    let sess = open_session(&session, password.as_deref())?;
+
+    require_tool(&sess, "chcon", "SELinux contexts")?;
+
+    let output = run_command(&sess, &format!("chcon -- {} {}", shell_quote(&context), shell_quote(&path)))?;
+    if output.exit_status != 0 {
+        return Err(format!("chcon failed: {}", output.stderr.trim()));
+    }
+
+    Ok(())
+}
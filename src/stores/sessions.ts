@@ -13,12 +13,71 @@ export interface Session {
   protocol: Protocol
   created_at: string
   last_used?: string
+  group?: string | null
+  tags?: string[]
+  color?: string | null
+  store_password_in_keychain?: boolean
+  profiles?: SessionProfile[]
+  http_proxy?: ProxyConfig | null
+  env_file?: string | null
+  allow_loopback?: boolean
+  terminal_modes?: TerminalModes
+  reconnect?: ReconnectPolicy | null
+  strict_identities?: boolean
+  bind_address?: string | null
+  keymap?: Record<string, string>
+  host_key_trust?: 'Disk' | 'Memory' | 'Ask' | 'Off' | null
+  sudo_autofill?: boolean
+  proxy_jump?: string | null
+  keepalive_interval_secs?: number | null
+  ssh_options?: string[]
+  connect_timeout_secs?: number | null
+}
+
+export interface HostKeyEvent {
+  host: string
+  port: number
+  key_type: string
+  fingerprint: string
+}
+
+export interface ReconnectPolicy {
+  enabled: boolean
+  max_attempts: number
+  base_delay_ms: number
+  max_delay_ms: number
+  on_eof: boolean
+  on_read_error: boolean
+  on_keepalive_failure: boolean
+  on_resume: boolean
+}
+
+export interface TerminalModes {
+  echo?: boolean | null
+  erase?: 'Delete' | 'Backspace' | null
+  icrnl?: boolean | null
+  cols?: number | null
+  rows?: number | null
+}
+
+export interface ProxyConfig {
+  host: string
+  port: number
+  username?: string | null
+  password?: string | null
+}
+
+export interface SessionProfile {
+  name: string
+  username: string
+  auth_method: AuthMethod
 }
 
 export type AuthMethod = 
   | 'Password' 
   | { PublicKey: { key_path: string } }
   | 'Agent'
+  | { Pkcs11: { provider_path: string } }
 
 export type Protocol = 'SSH' | 'SFTP' | 'RDP' | 'VNC' | 'Telnet'
 
@@ -143,6 +202,7 @@ export const useSessionsStore = defineStore('sessions', () => {
           protocol: session.protocol,
           created_at: session.created_at,
           last_used: session.last_used,
+          ssh_options: session.ssh_options ?? [],
         }
       })
       const index = sessions.value.findIndex(s => s.id === session.id)
@@ -299,6 +359,26 @@ export const useSessionsStore = defineStore('sessions', () => {
     }).catch(err => {
       console.error('Store: Failed to set up connection_status listener:', err)
     })
+
+    // Unknown hosts are refused until their key is accepted; connecting
+    // again afterwards goes through
+    listen<HostKeyEvent>('host_key_unknown', async (event) => {
+      const { host, port, key_type, fingerprint } = event.payload
+      const accepted = window.confirm(
+        `The authenticity of ${host}:${port} can't be established.\n` +
+        `${key_type} key fingerprint is ${fingerprint}.\n\n` +
+        'Trust this key and remember it in known_hosts?'
+      )
+      if (accepted) {
+        try {
+          await invoke('accept_host_key', { host, port, fingerprint })
+        } catch (err) {
+          console.error('Store: Failed to accept host key:', err)
+        }
+      }
+    }).catch(err => {
+      console.error('Store: Failed to set up host_key_unknown listener:', err)
+    })
   }
 
   return {